@@ -0,0 +1,92 @@
+// Clipboard.rs - Sharing text with other programs through the system clipboard
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// A program that can copy to the clipboard and its counterpart for pasting
+struct Tool {
+    copy: &'static [&'static str],
+    paste: &'static [&'static str],
+}
+
+fn tools() -> Vec<Tool> {
+    // Work out which programs to try, in order of preference
+    let mut tools = vec![];
+    if cfg!(windows) {
+        // PowerShell is told to use UTF-8 so that text other than ASCII survives
+        tools.push(Tool {
+            copy: &[
+                "powershell",
+                "-NoProfile",
+                "-Command",
+                "[Console]::InputEncoding = [Text.Encoding]::UTF8; \
+                 Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+            ],
+            paste: &[
+                "powershell",
+                "-NoProfile",
+                "-Command",
+                "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+                 [Console]::Write((Get-Clipboard -Raw))",
+            ],
+        });
+    }
+    if cfg!(target_os = "macos") {
+        tools.push(Tool {
+            copy: &["pbcopy"],
+            paste: &["pbpaste"],
+        });
+    }
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(Tool {
+            copy: &["wl-copy"],
+            paste: &["wl-paste", "--no-newline"],
+        });
+    }
+    tools.push(Tool {
+        copy: &["xclip", "-selection", "clipboard"],
+        paste: &["xclip", "-selection", "clipboard", "-o"],
+    });
+    tools.push(Tool {
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+    });
+    tools
+}
+
+pub fn copy(text: &str) -> bool {
+    // Put text onto the clipboard, returning whether it worked
+    for tool in tools() {
+        let child = Command::new(tool.copy[0])
+            .args(&tool.copy[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(mut child) = child {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            return child.wait().is_ok_and(|status| status.success());
+        }
+    }
+    false
+}
+
+pub fn paste() -> Option<String> {
+    // Read the text currently on the clipboard
+    for tool in tools() {
+        let output = Command::new(tool.paste[0])
+            .args(&tool.paste[1..])
+            .stderr(Stdio::null())
+            .output();
+        if let Ok(output) = output {
+            return if output.status.success() {
+                Some(String::from_utf8_lossy(&output.stdout).to_string())
+            } else {
+                None
+            };
+        }
+    }
+    None
+}
@@ -0,0 +1,85 @@
+// Compress.rs - Reading and writing files kept compressed with gzip or zstd
+use crate::util::tool_error;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+// The ways a file can be compressed, each done by the program of the same name
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn detect(path: &str, bytes: &[u8]) -> Option<Self> {
+        // Work out how a file is compressed from its first bytes, or its name when it is empty
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if bytes.is_empty() {
+            Self::from_name(path)
+        } else {
+            None
+        }
+    }
+    pub fn from_name(path: &str) -> Option<Self> {
+        // How a file of this name is compressed, going by its extension
+        match Path::new(path).extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+    pub fn strip(path: &str) -> &str {
+        // The name of the file inside, which tells what type of file it is
+        match Self::from_name(path) {
+            Some(_) => path.rsplit_once('.').map_or(path, |(inside, _)| inside),
+            None => path,
+        }
+    }
+    fn program(self) -> &'static str {
+        // The program that does the compressing
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+    pub fn decompress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        // Get back the bytes a file holds
+        run(self.program(), &["-d", "-c", "-q"], bytes)
+    }
+    pub fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        // Squeeze bytes down the way the file was
+        run(self.program(), &["-c", "-q"], bytes)
+    }
+}
+
+fn run(program: &str, args: &[&str], input: &[u8]) -> io::Result<Vec<u8>> {
+    // Pass bytes through a program, failing with what it said when it didn't work
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| {
+            io::Error::new(error.kind(), format!("couldn't run {program}: {error}"))
+        })?;
+    // The input is written from another thread so a full pipe of output can't hold both up
+    let mut stdin = child.stdin.take();
+    let input = input.to_vec();
+    let writer = thread::spawn(move || stdin.as_mut().map_or(Ok(()), |s| s.write_all(&input)));
+    let mut output = vec![];
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_end(&mut output)?;
+    }
+    let result = child.wait_with_output()?;
+    let _ = writer.join();
+    if result.status.success() {
+        return Ok(output);
+    }
+    Err(io::Error::other(tool_error(program, &result)))
+}
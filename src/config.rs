@@ -1,10 +1,39 @@
 // Config.rs - In charge of storing configuration information
+use crate::scheme;
 use crossterm::style::{Color, SetBackgroundColor, SetForegroundColor};
 use regex::Regex;
 use ron::de::from_str;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// How many colours the terminal shows, 24 meaning any RGB colour
+static COLOURS: AtomicUsize = AtomicUsize::new(24);
+
+// The colours terminals with only 16 give, as xterm shows them
+const BASIC: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (128, 0, 0),
+    (0, 128, 0),
+    (128, 128, 0),
+    (0, 0, 128),
+    (128, 0, 128),
+    (0, 128, 128),
+    (192, 192, 192),
+    (128, 128, 128),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (0, 0, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// The levels each channel of the colour cube of 256 colour terminals steps through
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
 
 // Enum for determining what type of token it is
 #[derive(Clone)]
@@ -55,15 +84,48 @@ pub enum RawKey {
     Esc,
 }
 
+impl KeyBinding {
+    pub fn describe(self) -> String {
+        // Name a key the way it is written in the help, such as Ctrl+K
+        let raw = |key: RawKey| match key {
+            RawKey::Char(' ') => "Space".to_string(),
+            // Capitals are typed with shift, which sets them apart from the same letter without it
+            RawKey::Char(c) if c.is_uppercase() => format!("Shift+{c}"),
+            RawKey::Char(c) => c.to_uppercase().to_string(),
+            RawKey::BackTab => "Shift+Tab".to_string(),
+            RawKey::PageUp => "PgUp".to_string(),
+            RawKey::PageDown => "PgDn".to_string(),
+            key => format!("{key:?}"),
+        };
+        match self {
+            KeyBinding::Ctrl(key) => format!("Ctrl+{}", raw(key)),
+            KeyBinding::Alt(key) => format!("Alt+{}", raw(key)),
+            KeyBinding::Shift(key) => format!("Shift+{}", raw(key)),
+            KeyBinding::Raw(RawKey::Char(c)) => c.to_string(),
+            KeyBinding::Raw(key) => raw(key),
+            KeyBinding::F(n) => format!("F{n}"),
+            KeyBinding::Unsupported => "?".to_string(),
+        }
+    }
+}
+
 // Struct for storing and managing configuration
 #[derive(Debug, Deserialize, Clone)]
 pub struct Reader {
     pub general: General,
     pub theme: Theme,
+    #[serde(default)]
+    pub themes: HashMap<String, Theme>,
     pub macros: HashMap<String, Vec<String>>,
     pub highlights: HashMap<String, HashMap<String, (u8, u8, u8)>>,
     pub keys: HashMap<KeyBinding, Vec<String>>,
+    #[serde(default)]
+    pub chords: HashMap<Vec<KeyBinding>, Vec<String>>,
+    #[serde(default)]
+    pub abbreviations: HashMap<String, String>,
     pub languages: Vec<Language>,
+    #[serde(default)]
+    pub filetypes: HashMap<String, Filetype>,
 }
 
 impl Reader {
@@ -76,7 +138,7 @@ impl Reader {
             config.to_string()
         };
         // Attempt to read and parse the configuration file
-        if let Ok(file) = fs::read_to_string(config) {
+        let (mut reader, status) = if let Ok(file) = fs::read_to_string(&config) {
             let result: (Self, Status) = if let Ok(contents) = from_str(&file) {
                 (contents, Status::Success)
             } else if file.is_empty() {
@@ -88,18 +150,27 @@ impl Reader {
                 // Provide the syntax issue with the config file for debugging
                 (
                     from_str(&default()).unwrap(),
-                    Status::Parse(format!("{:?}", result)),
+                    Status::Parse(format!("{result:?}")),
                 )
             };
             result
         } else {
             // File wasn't able to be found
             (from_str(&default()).unwrap(), Status::File)
-        }
+        };
+        // Allow switching back to the main colour scheme by name
+        let theme = reader.theme.clone();
+        reader.themes.entry("default".to_string()).or_insert(theme);
+        // Take in the base16 schemes and VS Code themes kept next to the config file
+        scheme::import_dir(&mut reader, &Path::new(&config).with_file_name("themes"));
+        (reader, status)
     }
     pub fn get_syntax_regex(config: &Self, extension: &str) -> Vec<TokenType> {
         // Compile the regular expressions from their string format
         let mut result = vec![];
+        if !config.general.syntax_highlighting {
+            return result;
+        }
         for lang in &config.languages {
             // Locate the correct language for the extension
             if lang.extensions.contains(&extension.to_string()) {
@@ -110,12 +181,12 @@ impl Reader {
                     for expr in reg {
                         if expr.starts_with("(?ms)") || expr.starts_with("(?sm)") {
                             // Multiline regular expression
-                            if let Ok(regx) = Regex::new(&expr) {
+                            if let Ok(regx) = Regex::new(expr) {
                                 multi.push(regx);
                             }
                         } else {
                             // Single line regular expression
-                            if let Ok(regx) = Regex::new(&expr) {
+                            if let Ok(regx) = Regex::new(expr) {
                                 single.push(regx);
                             }
                         }
@@ -132,46 +203,402 @@ impl Reader {
                     "keywords".to_string(),
                     lang.keywords
                         .iter()
-                        .map(|x| Regex::new(&format!(r"\b({})\b", x)).unwrap())
+                        .map(|x| Regex::new(&format!(r"\b({x})\b")).unwrap())
                         .collect(),
                 ));
             }
         }
         result
     }
+    pub fn get_pairs(config: &Self, extension: &str) -> Vec<(char, char)> {
+        // Work out which pairs of characters are closed automatically
+        if !config.general.auto_pairs {
+            return vec![];
+        }
+        let pairs = config
+            .languages
+            .iter()
+            .find(|lang| lang.extensions.contains(&extension.to_string()))
+            .map_or_else(pairs, |lang| lang.pairs.clone());
+        pairs
+            .iter()
+            .filter_map(|pair| {
+                let mut chars = pair.chars();
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(open), Some(close), None) => Some((open, close)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    pub fn get_language<'a>(config: &'a Self, extension: &str) -> Option<&'a Language> {
+        // Find the language that files with an extension are written in
+        config
+            .languages
+            .iter()
+            .find(|lang| lang.extensions.contains(&extension.to_string()))
+    }
     pub fn rgb_fg(colour: (u8, u8, u8)) -> SetForegroundColor {
         // Get the text ANSI code from an RGB value
-        SetForegroundColor(Color::Rgb {
-            r: colour.0,
-            g: colour.1,
-            b: colour.2,
-        })
+        SetForegroundColor(Reader::colour(colour))
     }
     pub fn rgb_bg(colour: (u8, u8, u8)) -> SetBackgroundColor {
         // Get the background ANSI code from an RGB value
-        SetBackgroundColor(Color::Rgb {
-            r: colour.0,
-            g: colour.1,
-            b: colour.2,
-        })
+        SetBackgroundColor(Reader::colour(colour))
+    }
+    pub fn set_colours(colours: usize) {
+        // Draw every colour from then on with what the terminal can show
+        COLOURS.store(colours, Ordering::Relaxed);
+    }
+    fn colour((r, g, b): (u8, u8, u8)) -> Color {
+        // Turn an RGB value into the closest colour the terminal shows
+        let distance = |other: (u8, u8, u8)| {
+            let channel = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            channel(r, other.0) + channel(g, other.1) + channel(b, other.2)
+        };
+        match COLOURS.load(Ordering::Relaxed) {
+            24 => Color::Rgb { r, g, b },
+            256 => {
+                // The nearest of the colour cube and the nearest grey, whichever is closer
+                let level = |value: u8| {
+                    (0..CUBE.len())
+                        .min_by_key(|&index| (i32::from(CUBE[index]) - i32::from(value)).abs())
+                        .unwrap_or(0)
+                };
+                let (red, green, blue) = (level(r), level(g), level(b));
+                let cube = (CUBE[red], CUBE[green], CUBE[blue]);
+                let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+                let step = (average.saturating_sub(8) / 10).min(23) as u8;
+                let shade = 8 + step * 10;
+                if distance((shade, shade, shade)) < distance(cube) {
+                    Color::AnsiValue(232 + step)
+                } else {
+                    Color::AnsiValue((16 + 36 * red + 6 * green + blue) as u8)
+                }
+            }
+            _ => {
+                let index = (0..BASIC.len())
+                    .min_by_key(|&index| distance(BASIC[index]))
+                    .unwrap_or(0);
+                Color::AnsiValue(index as u8)
+            }
+        }
     }
 }
 
 // Struct for storing the general configuration
 #[derive(Debug, Deserialize, Clone)]
+#[allow(clippy::struct_excessive_bools)] // The config file is a flat list of switches
 pub struct General {
     pub line_number_padding_right: usize,
     pub line_number_padding_left: usize,
+    #[serde(default = "enabled")]
+    pub line_numbers: bool,
+    #[serde(default)]
+    pub relative_line_numbers: bool,
     pub tab_width: usize,
     pub undo_period: u64,
+    #[serde(default = "undo_history")]
+    pub undo_history: usize,
+    #[serde(default = "large_file_size")]
+    pub large_file_size: u64,
     pub status_left: String,
+    #[serde(default)]
+    pub status_center: String,
     pub status_right: String,
     pub tab: String,
     pub wrap_cursor: bool,
+    #[serde(default = "enabled")]
+    pub trim_trailing_whitespace: bool,
+    #[serde(default = "enabled")]
+    pub ensure_final_newline: bool,
+    #[serde(default)]
+    pub soft_wrap: bool,
+    #[serde(default)]
+    pub highlight_line: bool,
+    #[serde(default)]
+    pub indent_guides: bool,
+    #[serde(default)]
+    pub show_whitespace: bool,
+    #[serde(default)]
+    pub backup: bool,
+    #[serde(default)]
+    pub format_on_save: bool,
+    #[serde(default)]
+    pub autosave: u64,
+    #[serde(default)]
+    pub scroll_off: usize,
+    #[serde(default = "enabled")]
+    pub swap_files: bool,
+    #[serde(default = "enabled")]
+    pub syntax_highlighting: bool,
+    #[serde(default)]
+    pub modal: bool,
+    #[serde(default = "enabled")]
+    pub cursor_shapes: bool,
+    #[serde(default = "enabled")]
+    pub scrollbar: bool,
+    #[serde(default = "enabled")]
+    pub auto_indent: bool,
+    #[serde(default = "enabled")]
+    pub expand_tabs: bool,
+    #[serde(default = "enabled")]
+    pub auto_pairs: bool,
+    #[serde(default)]
+    pub lsp: bool,
+    #[serde(default = "enabled")]
+    pub word_completion: bool,
+    #[serde(default)]
+    pub spell_check: bool,
+    #[serde(default = "dictionary")]
+    pub dictionary: String,
+    #[serde(default = "enabled")]
+    pub git_gutter: bool,
+    #[serde(default)]
+    pub blame: bool,
+    #[serde(default)]
+    pub restore_session: bool,
+    #[serde(default = "chord_timeout")]
+    pub chord_timeout: u64,
+    #[serde(default = "message_timeout")]
+    pub message_timeout: u64,
+    #[serde(default = "enabled")]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub smart_case: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    #[serde(default)]
+    pub rulers: Vec<usize>,
+}
+
+impl General {
+    #[allow(clippy::too_many_lines)] // One arm per setting, each parsing its own value
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<String, String> {
+        // Change an option while the editor runs, describing its new value
+        let flag = match name {
+            "relative_line_numbers" => &mut self.relative_line_numbers,
+            "wrap_cursor" => &mut self.wrap_cursor,
+            "trim_trailing_whitespace" => &mut self.trim_trailing_whitespace,
+            "ensure_final_newline" => &mut self.ensure_final_newline,
+            "highlight_line" => &mut self.highlight_line,
+            "indent_guides" => &mut self.indent_guides,
+            "show_whitespace" => &mut self.show_whitespace,
+            "backup" => &mut self.backup,
+            "format_on_save" => &mut self.format_on_save,
+            "swap_files" => &mut self.swap_files,
+            "syntax_highlighting" => &mut self.syntax_highlighting,
+            "modal" => &mut self.modal,
+            "cursor_shapes" => &mut self.cursor_shapes,
+            "scrollbar" => &mut self.scrollbar,
+            "auto_indent" => &mut self.auto_indent,
+            "auto_pairs" => &mut self.auto_pairs,
+            "lsp" => &mut self.lsp,
+            "word_completion" => &mut self.word_completion,
+            "spell_check" => &mut self.spell_check,
+            "git_gutter" => &mut self.git_gutter,
+            "blame" => &mut self.blame,
+            "restore_session" => &mut self.restore_session,
+            "expand_tabs" => &mut self.expand_tabs,
+            "case_sensitive" => &mut self.case_sensitive,
+            "smart_case" => &mut self.smart_case,
+            "whole_word" => &mut self.whole_word,
+            "autosave" => {
+                // Autosave can be switched off with 0
+                self.autosave = value
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| "autosave needs a number of seconds".to_string())?;
+                return Ok(if self.autosave == 0 {
+                    "autosave is now off".to_string()
+                } else {
+                    format!("autosave is now after {} seconds", self.autosave)
+                });
+            }
+            "message_timeout" => {
+                // Messages stay until something replaces them with 0
+                self.message_timeout = value
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| "message_timeout needs a number of seconds".to_string())?;
+                return Ok(if self.message_timeout == 0 {
+                    "messages now stay up".to_string()
+                } else {
+                    format!("messages now go after {} seconds", self.message_timeout)
+                });
+            }
+            "rulers" => {
+                // Rulers are given as columns split by commas, and none switches them off
+                let value = value.unwrap_or("");
+                self.rulers = value
+                    .split(',')
+                    .filter(|column| !column.trim().is_empty())
+                    .map(|column| column.trim().parse::<usize>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("rulers can't be set to {value}"))?;
+                return Ok(if self.rulers.is_empty() {
+                    "rulers are now off".to_string()
+                } else {
+                    format!("rulers are now at {value}")
+                });
+            }
+            "undo_history" => {
+                // The history of files isn't kept between sessions with 0
+                self.undo_history = value
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .ok_or_else(|| "undo_history needs a number of changes".to_string())?;
+                return Ok(if self.undo_history == 0 {
+                    "undo history is now forgotten on close".to_string()
+                } else {
+                    format!("undo history now keeps {} changes", self.undo_history)
+                });
+            }
+            "large_file_size" => {
+                // Files are always read in whole with 0
+                self.large_file_size = value
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| "large_file_size needs a number of megabytes".to_string())?;
+                return Ok(if self.large_file_size == 0 {
+                    "files are now always read in whole".to_string()
+                } else {
+                    format!(
+                        "files over {} megabytes are now read a part at a time",
+                        self.large_file_size
+                    )
+                });
+            }
+            "scroll_off" => {
+                // No lines are kept in view around the cursor with 0
+                self.scroll_off = value
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .ok_or_else(|| "scroll_off needs a number of lines".to_string())?;
+                return Ok(format!("scroll_off is now {}", self.scroll_off));
+            }
+            "tab_width" | "undo_period" | "chord_timeout" => {
+                let number = match value.map(str::parse::<u64>) {
+                    Some(Ok(number)) if number > 0 => number,
+                    _ => return Err(format!("{name} needs a number above 0")),
+                };
+                match name {
+                    "tab_width" => self.tab_width = number as usize,
+                    "undo_period" => self.undo_period = number,
+                    _ => self.chord_timeout = number,
+                }
+                return Ok(format!("{name} is now {number}"));
+            }
+            _ => return Err(format!("Unknown option: {name}")),
+        };
+        // Switches are flipped when no value is given
+        *flag = match value {
+            None => !*flag,
+            Some("true" | "on") => true,
+            Some("false" | "off") => false,
+            Some(value) => return Err(format!("{name} can't be set to {value}")),
+        };
+        Ok(format!(
+            "{} is now {}",
+            name,
+            if *flag { "on" } else { "off" }
+        ))
+    }
+}
+
+fn enabled() -> bool {
+    // Default value for options that are switched on
+    true
+}
+
+fn chord_timeout() -> u64 {
+    // Default milliseconds to wait for the next key of a chord
+    1000
+}
+
+fn undo_history() -> usize {
+    // Default number of changes of each file kept between sessions
+    1000
+}
+
+fn large_file_size() -> u64 {
+    // Default megabytes above which files are read a part at a time
+    100
+}
+
+fn message_timeout() -> u64 {
+    // Default seconds a message stays on the command line
+    5
+}
+
+fn dictionary() -> String {
+    // Default list of words for spell checking
+    "/usr/share/dict/words".to_string()
+}
+
+fn line_highlight_bg() -> (u8, u8, u8) {
+    // Default background colour of the current line
+    (52, 52, 77)
+}
+
+fn selection_bg() -> (u8, u8, u8) {
+    // Default background colour of selected text
+    (78, 78, 120)
+}
+
+fn bracket_bg() -> (u8, u8, u8) {
+    // Default background colour of a pair of matching brackets
+    (90, 90, 140)
+}
+
+fn git_added_fg() -> (u8, u8, u8) {
+    // Default colour of the marks beside added lines
+    (35, 240, 144)
+}
+
+fn git_modified_fg() -> (u8, u8, u8) {
+    // Default colour of the marks beside changed lines
+    (208, 164, 79)
+}
+
+fn git_removed_fg() -> (u8, u8, u8) {
+    // Default colour of the marks where lines were removed
+    (224, 113, 113)
+}
+
+fn bookmark_fg() -> (u8, u8, u8) {
+    // Default colour of the letters of marks beside their lines
+    (130, 170, 255)
+}
+
+fn diff_added_bg() -> (u8, u8, u8) {
+    // Default background of lines only the second of two compared files has
+    (36, 66, 48)
+}
+
+fn diff_removed_bg() -> (u8, u8, u8) {
+    // Default background of lines only the first of two compared files has
+    (78, 40, 46)
+}
+
+fn diff_modified_bg() -> (u8, u8, u8) {
+    // Default background of lines that differ between two compared files
+    (52, 56, 92)
+}
+
+fn guide_fg() -> (u8, u8, u8) {
+    // Default colour of the indentation guides
+    (58, 58, 84)
+}
+
+fn whitespace_fg() -> (u8, u8, u8) {
+    // Default colour of tabs and trailing spaces when they are shown
+    (90, 90, 130)
+}
+
+fn ruler_bg() -> (u8, u8, u8) {
+    // Default background of the columns marked by rulers
+    (49, 49, 72)
 }
 
 // Struct for storing theme information
 #[derive(Debug, Deserialize, Clone)]
+#[allow(clippy::struct_field_names)] // Named after the config file key
 pub struct Theme {
     pub transparent_editor: bool,
     pub editor_bg: (u8, u8, u8),
@@ -180,6 +607,32 @@ pub struct Theme {
     pub status_fg: (u8, u8, u8),
     pub line_number_fg: (u8, u8, u8),
     pub line_number_bg: (u8, u8, u8),
+    #[serde(default = "line_highlight_bg")]
+    pub line_highlight_bg: (u8, u8, u8),
+    #[serde(default = "selection_bg")]
+    pub selection_bg: (u8, u8, u8),
+    #[serde(default = "bracket_bg")]
+    pub bracket_bg: (u8, u8, u8),
+    #[serde(default = "git_added_fg")]
+    pub git_added_fg: (u8, u8, u8),
+    #[serde(default = "git_modified_fg")]
+    pub git_modified_fg: (u8, u8, u8),
+    #[serde(default = "git_removed_fg")]
+    pub git_removed_fg: (u8, u8, u8),
+    #[serde(default = "bookmark_fg")]
+    pub bookmark_fg: (u8, u8, u8),
+    #[serde(default = "diff_added_bg")]
+    pub diff_added_bg: (u8, u8, u8),
+    #[serde(default = "diff_removed_bg")]
+    pub diff_removed_bg: (u8, u8, u8),
+    #[serde(default = "diff_modified_bg")]
+    pub diff_modified_bg: (u8, u8, u8),
+    #[serde(default = "guide_fg")]
+    pub guide_fg: (u8, u8, u8),
+    #[serde(default = "whitespace_fg")]
+    pub whitespace_fg: (u8, u8, u8),
+    #[serde(default = "ruler_bg")]
+    pub ruler_bg: (u8, u8, u8),
     pub inactive_tab_fg: (u8, u8, u8),
     pub inactive_tab_bg: (u8, u8, u8),
     pub active_tab_fg: (u8, u8, u8),
@@ -191,7 +644,27 @@ pub struct Theme {
     pub fallback: bool,
 }
 
+// Struct for storing the settings that differ for one type of file
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Filetype {
+    #[serde(default)]
+    pub tab_width: Option<usize>,
+    #[serde(default)]
+    pub expand_tabs: Option<bool>,
+    #[serde(default)]
+    pub soft_wrap: Option<bool>,
+    #[serde(default)]
+    pub trim_trailing_whitespace: Option<bool>,
+    #[serde(default)]
+    pub ensure_final_newline: Option<bool>,
+    #[serde(default)]
+    pub formatter: Option<String>,
+    #[serde(default)]
+    pub format_on_save: Option<bool>,
+}
+
 // Struct for storing language information
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct Language {
     pub name: String,
@@ -199,6 +672,22 @@ pub struct Language {
     pub extensions: Vec<String>,
     pub keywords: Vec<String>,
     pub definitions: HashMap<String, Vec<String>>,
+    #[serde(default = "pairs")]
+    pub pairs: Vec<String>,
+    #[serde(default)]
+    pub server: String,
+    #[serde(default)]
+    pub build: String,
+    #[serde(default)]
+    pub snippets: HashMap<String, String>,
+}
+
+fn pairs() -> Vec<String> {
+    // Default pairs of characters that are closed automatically
+    ["()", "[]", "{}", "\"\""]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
 }
 
 // Default configuration format
@@ -206,5 +695,5 @@ pub struct Language {
 // (| )//[a-zA-Z0-9 ]+ on https://www.regextester.com/
 // https://codebeautify.org/text-minifier
 fn default() -> String {
-"/*\n    My very own (awesome) Ox configuration file!\n    \n    Ox uses RON. RON is an object notation similar to JSON.\n    It makes it easy and quick for Ox to parse.\n\n    Config name: NAME\n    Author:      AUTHOR\n    YEAR:        YEAR\n*/\n\n// General settings for Ox\n(\n    general: General(\n        line_number_padding_right: 2, // Line number padding on the right\n        line_number_padding_left:  1, // Line number padding on the left\n        tab_width:                 4, // The amount of spaces for a tab\n        undo_period:               5, // Seconds of inactivity for undo\n        wrap_cursor:            true, // Determines wheter the cursor wraps around\n        // Values:\n        // %f - File name\n        // %F - File name with full path\n        // %I - Language specific icon with leading space\n        // %i - Language specific icon\n        // %n - Language name\n        // %l - Current line number in the document\n        // %L - Total number of lines in the document\n        // %x - X position of the cursor\n        // %y - Y position of the cursor\n        // %v - Version of the editor (e.g. 0.2.6)\n        // %d - Dirty file indicator text\n        // %D - Dirty file indicator icon\n        // %R - Read only file indicator\n        status_left:  \" %f%d %D \u{2502} %n %i\", // Left part of status line\n        status_right: \"\u{4e26} %l / %L \u{2502} \u{fae6}(%x, %y) \", // Right part of status line\n        tab: \"%I%f%d\", // Tab formatting\n    ),\n    // Custom defined macros\n    macros: {\n        // Macro to move a line up\n        \"move line up\": [\n            \"store line 1\", // Store current line in bank #1\n            \"delete 0\",     // Delete current line\n            \"move 1 up\",    // Move cursor up by 1\n            \"line above\",   // Insert an empty line above\n            \"move 1 up\",    // Move cursor up to the empty line\n            \"load line 1\",  // Load line in bank #1 over the empty line\n        ],\n        // Macro to move a line down\n        \"move line down\": [\n            \"store line 1\", // Store the current line in bank #1\n            \"delete 0\",     // Delete the current line\n            \"line below\",   // Create an empty line below\n            \"move 1 down\",  // Move cursor down to empty line\n            \"load line 1\",  // Overwrite empty line with line in bank #1\n        ],\n        // Macro to save with root permission\n        \"save #\": [\n            // SHCS: Shell with confirmation and substitution\n            // With substitution, `%C` becomes the current documents contents\n            // `%F` becomes the file path of the current document\n            \"shcs sudo cat > %F << EOF\\n%CEOF\", // \'%F\' is the current file name\n            \"is saved\", // Set the status of the file to saved\n        ],\n    },\n    // RGB values for the colours of Ox\n    theme: Theme(\n        transparent_editor: false,         // Makes editor background transparent\n        editor_bg:          (41, 41, 61), // The main background color\n        editor_fg:          (255, 255, 255), // The default text color\n        status_bg:          (59, 59, 84), // The background color of the status line\n        status_fg:          (35, 240, 144), // The text color of the status line\n        line_number_fg:     (73, 73, 110), // The text color of the line numbers\n        line_number_bg:     (49, 49, 73), // The background color of the line numbers\n        active_tab_fg:      (255, 255, 255), // The text color of the active tab\n        active_tab_bg:      (41, 41, 61), //  The background color of the active tab\n        inactive_tab_fg:    (255, 255, 255), // The text color of the inactive tab(s)\n        inactive_tab_bg:    (59, 59, 84), // The text color of the inactive tab(s)\n        warning_fg:         (208, 164, 79), // Text colour of the warning message\n        error_fg:           (224, 113, 113), // Text colour of the warning message\n        info_fg:            (255, 255, 255), // Text colour of the warning message\n        default_theme:    \"default\", // The default syntax highlights to use\n        fallback:         true, // Enables use of fallback themes (if detected)\n    ),\n    // Colours for the syntax highlighting\n    highlights: {\n        \"default\": {\n            \"comments\":   (113, 113, 169),\n            \"keywords\":   (134, 76, 232),\n            \"namespaces\": (134, 76, 232),\n            \"references\": (134, 76, 232),\n            \"strings\":    (39, 222, 145),\n            \"characters\": (40, 198, 232),\n            \"digits\":     (40, 198, 232),\n            \"booleans\":   (86, 217, 178),\n            \"functions\":  (47, 141, 252),\n            \"structs\":    (47, 141, 252),\n            \"macros\":     (223, 52, 249),\n            \"attributes\": (40, 198, 232),\n            \"headers\":    (47, 141, 252),\n            \"symbols\":    (47, 141, 252),\n            \"global\":     (86, 217, 178),\n            \"operators\":  (86, 217, 178),\n            \"regex\":      (40, 198, 232),\n            \"search_active\":   (41, 73, 131),\n            \"search_inactive\": (29, 52, 93),\n        },\n        \"alternative\": {\n            \"comments\":   (113, 113, 169),\n            \"keywords\":   (64, 86, 244),\n            \"namespaces\": (64, 86, 244),\n            \"references\": (64, 86, 244),\n            \"strings\":    (76, 224, 179),\n            \"characters\": (110, 94, 206),\n            \"digits\":     (4, 95, 204),\n            \"booleans\":   (76, 224, 179),\n            \"functions\":  (4, 95, 204),\n            \"structs\":    (4, 95, 204),\n            \"macros\":     (110, 94, 206),\n            \"attributes\": (4, 95, 204),\n            \"headers\":    (141, 129, 217),\n            \"symbols\":    (249, 233, 0),\n            \"global\":     (76, 224, 179),\n            \"operators\":  (76, 224, 179),\n            \"regex\":      (4, 95, 204),\n            \"search_active\":   (41, 73, 131),\n            \"search_inactive\": (29, 52, 93),\n        },\n    },\n    // Key bindings\n    keys: {\n        // Keybinding: [Oxa commands]\n        Ctrl(Char(\'q\')): [\"quit\"], // Quit current document\n        Ctrl(Char(\'s\')): [\"save\"], // Save current document\n        Alt(Char(\'s\')):  [\"save ?\"], // Save current document as\n        Ctrl(Char(\'w\')): [\"save *\"], // Save all open documents\n        Ctrl(Char(\'n\')): [\"new\"], // Create new document\n        Ctrl(Char(\'o\')): [\"open\"], // Open document\n        Ctrl(Left):      [\"prev\"], // Move to previous tab\n        Ctrl(Right):     [\"next\"], // Move to next tab\n        Ctrl(Char(\'z\')): [\"undo\"], // Undo last edit\n        Ctrl(Char(\'y\')): [\"redo\"], // Redo last edit\n        Ctrl(Char(\'f\')): [\"search\"], // Trigger search command\n        Ctrl(Char(\'r\')): [\"replace\"], // Trigger replace command\n        Ctrl(Char(\'a\')): [\"replace *\"], // Trigger replace all command\n        Ctrl(Up):        [\"move line up\"], // Move line up\n        Ctrl(Down):      [\"move line down\"], // Move line down\n        Ctrl(Delete):    [\"delete word left\"], // Delete word\n        Alt(Char(\'a\')):  [\"cmd\"], // Open the command line\n        // Show help message URL\n        F(1):   [\n            \"sh echo You can get help here:\",\n            \"shc echo https://github.com/curlpipe/ox/wiki\",\n        ]\n    },\n    // Language specific settings\n    languages: [\n        Language(\n            name: \"Rust\", // Name of the language\n            icon: \"\u{e7a8} \", // Icon for the language\n            extensions: [\"rs\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"as\", \"break\", \"const\", \"continue\", \"crate\", \"else\", \n                \"enum\", \"extern\", \"fn\", \"for\", \"if\", \"impl\", \"in\", \n                \"let\", \"loop\", \"match\", \"mod\", \"move\", \"mut\", \"pub\", \n                \"ref\", \"return\", \"self\", \"static\", \"struct\", \"super\", \n                \"trait\", \"type\", \"unsafe\", \"use\", \"where\", \"while\", \n                \"async\", \"await\", \"dyn\", \"abstract\", \"become\", \"box\", \n                \"do\", \"final\", \"macro\", \"override\", \"priv\", \"typeof\", \n                \"unsized\", \"virtual\", \"yield\", \"try\", \"\'static\",\n                \"u8\", \"u16\", \"u32\", \"u64\", \"u128\", \"usize\",\n                \"i8\", \"i16\", \"i32\", \"i64\", \"i128\", \"isize\",\n                \"f32\", \"f64\", \"String\", \"Vec\", \"str\", \"Some\", \"bool\",\n                \"None\", \"Box\", \"Result\", \"Option\", \"Ok\", \"Err\", \"Self\",\n                \"std\"\n            ],\n            // Syntax definitions\n            definitions: {\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"[^/](/)[^/]\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\?)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n                \"namespaces\": [\n                    r\"([a-z_][A-Za-z0-9_]*)::\",\n                ],\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*?\\\\*/)\",\n                ],\n                \"strings\":    [\n                    \"\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(r\\\".*?\\\")\",\n                    \"(?ms)(r#\\\".*?\\\"#)\",\n                    \"(?ms)(#\\\".*?\\\"#)\",\n                ],\n                \"characters\": [\n                    \"(\'.\')\", \n                    \"(\'\\\\\\\\.\')\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                    \"\\\\b(\\\\d+.\\\\d+(?:f32|f64))\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"functions\":  [\n                    \"fn\\\\s+([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    r\"\\.([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                    r\"([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                ],\n                \"structs\":    [\n                    \"(?:trait|enum|struct|impl)\\\\s+([A-Z][A-Za-z0-9_]*)\\\\s*\", \n                    \"impl(?:<.*?>|)\\\\s+([A-Z][A-Za-z0-9_]*)\",\n                    \"([A-Z][A-Za-z0-9_]*)::\",\n                    r\"([A-Z][A-Za-z0-9_]*)\\s*\\(\",\n                    \"impl.*for\\\\s+([A-Z][A-Za-z0-9_]*)\",\n                    r\"::\\s*([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                ],\n                \"macros\":     [\n                    \"\\\\b([a-z_][a-zA-Z0-9_]*!)\",\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\",\n                ],\n                \"attributes\": [\n                    \"(?ms)^\\\\s*(#(?:!|)\\\\[.*?\\\\])\",\n                ],\n                \"references\": [\n                    \"(&)\",\n                    \"&str\", \"&mut\", \"&self\", \n                    \"&i8\", \"&i16\", \"&i32\", \"&i64\", \"&i128\", \"&isize\",\n                    \"&u8\", \"&u16\", \"&u32\", \"&u64\", \"&u128\", \"&usize\",\n                    \"&f32\", \"&f64\",\n                ]\n            }\n        ),\n        Language(\n            name: \"Ruby\", // Name of the language\n            icon: \"\u{e739} \", // Icon for the language\n            extensions: [\"rb\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"__ENCODING__\", \"__LINE__\", \"__FILE__\", \"BEGIN\", \"END\", \n                \"alias\", \"and\", \"begin\", \"break\", \"case\", \"class\", \"def\", \n                \"defined?\", \"do\", \"else\", \"elsif\", \"end\", \"ensure\", \"print\",\n                \"for\", \"if\", \"in\", \"module\", \"next\", \"nil\", \"not\", \"or\", \"puts\",\n                \"redo\", \"rescue\", \"retry\", \"return\", \"self\", \"super\", \"then\", \n                \"undef\", \"unless\", \"until\", \"when\", \"while\", \"yield\", \"raise\",\n                \"include\", \"extend\", \"require\" \n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                    \"(?ms)(=begin.*=end)\", \n                ],\n                \"strings\":    [\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    r\"\\b(\\d+.\\d+|\\d+)\",\n                ],\n                \"booleans\":   [\n                    r\"\\b(true)\\b\", \n                    r\"\\b(false)\\b\",\n                ],\n                \"structs\":    [\n                    r\"class(\\s+[A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    r\"def\\s+([a-z_][A-Za-z0-9_\\\\?!]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"symbols\":    [\n                    r\"(:[^,\\)\\.\\s=]+)\",\n                ],\n                \"global\":     [\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\\s\",\n                ],\n                \"regex\": [\n                    r\"/.+/\"\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Crystal\", // Name of the language\n            icon: \"\u{e7a3} \", // Icon for the language\n            extensions: [\"cr\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"__ENCODING__\", \"__LINE__\", \"__FILE__\", \"BEGIN\", \"END\", \n                \"alias\", \"and\", \"begin\", \"break\", \"case\", \"class\", \"def\", \n                \"defined?\", \"do\", \"else\", \"elsif\", \"end\", \"ensure\", \"print\",\n                \"for\", \"if\", \"in\", \"module\", \"next\", \"nil\", \"not\", \"or\", \"puts\",\n                \"redo\", \"rescue\", \"retry\", \"return\", \"self\", \"super\", \"then\", \n                \"undef\", \"unless\", \"until\", \"when\", \"while\", \"yield\", \"raise\",\n                \"include\", \"extend\", \"Int32\", \"String\", \"getter\", \"setter\",\n                \"property\", \"Array\", \"Set\", \"Hash\", \"Range\", \"Proc\", \"typeof\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                    \"(?ms)(=begin.*=end)\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\".*?\\\")\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(\\\'.*?\\\')\",\n                ],\n                \"digits\":     [\n                    r\"\\b(\\d+.\\d+|\\d+)\",\n                    r\"(_i(?:8|16|32|64|128))\",\n                    r\"(_u(?:8|16|32|64|128))\",\n                    r\"(_f(?:8|16|32|64|128))\",\n                    \"0x[A-Fa-f0-9]{6}\"\n                ],\n                \"booleans\":   [\n                    r\"\\b(true)\\b\", \n                    r\"\\b(false)\\b\",\n                ],\n                \"structs\":    [\n                    r\"class(\\s+[A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    r\"def\\s+([a-z_][A-Za-z0-9_\\\\?!]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"symbols\":    [\n                    r\"(:[^,\\}\\)\\.\\s=]+)\",\n                ],\n                \"global\":     [\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\\s\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(\\?)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Python\", // Name of the language\n            icon: \"\u{e73c} \", // Icon for the language\n            extensions: [\"py\", \"pyw\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"and\", \"as\", \"assert\", \"break\", \"class\", \"continue\", \n                \"def\", \"del\", \"elif\", \"else\", \"except\", \"exec\", \n                \"finally\", \"for\", \"from\", \"global\", \"if\", \"import\", \n                \"in\", \"is\", \"lambda\", \"not\", \"or\", \"pass\", \"print\", \n                \"raise\", \"return\", \"try\", \"while\", \"with\", \"yield\",\n                \"str\", \"bool\", \"int\", \"tuple\", \"list\", \"dict\", \"tuple\",\n                \"len\", \"None\", \"input\", \"type\", \"set\", \"range\", \"enumerate\",\n                \"open\", \"iter\", \"min\", \"max\", \"dir\", \"self\", \"isinstance\", \n                \"help\", \"next\", \"super\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\"\\\"\\\".*?\\\"\\\"\\\")\",\n                    \"(?ms)(\\\'\\\'\\\'.*?\\\'\\\'\\\')\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(True)\\\\b\", \n                    \"\\\\b(False)\\\\b\",\n                ],\n                \"structs\":    [\n                    \"class\\\\s+([A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    \"def\\\\s+([a-z_][A-Za-z0-9_]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"attributes\": [\n                    \"@.*$\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\s//\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Javascript\", // Name of the language\n            icon: \"\u{e74e} \", // Icon for the language\n            extensions: [\"js\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"abstract\", \"arguments\", \"await\", \"boolean\", \"break\", \"byte\", \n                \"case\", \"catch\", \"char\", \"class\", \"const\", \"continue\", \"debugger\", \n                \"default\", \"delete\", \"do\", \"double\", \"else\", \"enum\", \"eval\", \n                \"export\", \"extends\", \"final\", \"finally\", \"float\", \"for\", \"of\",\n                \"function\", \"goto\", \"if\", \"implements\", \"import\", \"in\", \"instanceof\", \n                \"int\", \"interface\", \"let\", \"long\", \"native\", \"new\", \"null\", \"package\", \n                \"private\", \"protected\", \"public\", \"return\", \"short\", \"static\", \n                \"super\", \"switch\", \"synchronized\", \"this\", \"throw\", \"throws\", \n                \"transient\", \"try\", \"typeof\", \"var\", \"void\", \"volatile\", \"console\",\n                \"while\", \"with\", \"yield\", \"undefined\", \"NaN\", \"-Infinity\", \"Infinity\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*\\\\*/)$\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\"\\\"\\\".*?\\\"\\\"\\\")\",\n                    \"(?ms)(\\\'\\\'\\\'.*?\\\'\\\'\\\')\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"structs\":    [\n                    \"class\\\\s+([A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    \"function\\\\s+([a-z_][A-Za-z0-9_]*)\",\n                    \"\\\\b([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(<<)\",\n                    r\"(>>)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                ],\n            }\n        ),\n        Language(\n            name: \"C\", // Name of the language\n            icon: \"\u{e61e} \", // Icon for the language\n            extensions: [\"c\", \"h\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"auto\", \"break\", \"case\", \"char\", \"const\", \"continue\", \"default\", \n                \"do\", \"double\", \"else\", \"enum\", \"extern\", \"float\", \"for\", \"goto\", \n                \"if\", \"int\", \"long\", \"register\", \"return\", \"short\", \"signed\", \n                \"sizeof\", \"static\", \"struct\", \"switch\", \"typedef\", \"union\", \n                \"unsigned\", \"void\", \"volatile\", \"while\", \"printf\", \"fscanf\", \n                \"scanf\", \"fputsf\", \"exit\", \"stderr\", \"malloc\", \"calloc\", \"bool\",\n                \"realloc\", \"free\", \"strlen\", \"size_t\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*?\\\\*/)\",\n                ],\n                \"strings\":    [\n                    \"\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                ],\n                \"characters\": [\n                    \"(\'.\')\", \n                    \"(\'\\\\\\\\.\')\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                    \"\\\\b(\\\\d+.\\\\d+(?:f|))\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"functions\":  [\n                    \"(int|bool|void|char|double|long|short|size_t)\\\\s+([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    \"\\\\b([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                ],\n                \"structs\":    [\n                    \"struct\\\\s+([A-Za-z0-9_]*)\\\\s*\", \n                ],\n                \"attributes\": [\n                    \"^\\\\s*(#.*?)\\\\s\",\n                ],\n                \"headers\":    [\n                    \"(<.*?>)\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(<<)\",\n                    r\"(>>)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                ],\n            }\n        ),\n    ],\n)\n".to_string()
-}
\ No newline at end of file
+"/*\n    My very own (awesome) Ox configuration file!\n    \n    Ox uses RON. RON is an object notation similar to JSON.\n    It makes it easy and quick for Ox to parse.\n\n    Config name: NAME\n    Author:      AUTHOR\n    YEAR:        YEAR\n*/\n\n// General settings for Ox\n(\n    general: General(\n        line_number_padding_right: 2, // Line number padding on the right\n        line_number_padding_left:  1, // Line number padding on the left\n        line_numbers:           true, // Show line numbers down the left side\n        relative_line_numbers: false, // Number lines by their distance from the cursor\n        tab_width:                 4, // The amount of spaces for a tab\n        expand_tabs:            true, // Indent new files with spaces instead of tabs\n        undo_period:               5, // Seconds of inactivity for undo\n        undo_history:           1000, // Changes of each file kept to undo after reopening it, 0 is off\n        large_file_size:         100, // Megabytes above which files are read a part at a time as they are viewed, 0 is off\n        wrap_cursor:            true, // Determines wheter the cursor wraps around\n        trim_trailing_whitespace: true, // Remove trailing whitespace on save\n        ensure_final_newline:   true, // Make sure saved files end with a newline\n        soft_wrap:             false, // Wrap long lines instead of scrolling sideways\n        highlight_line:        false, // Highlight the line the cursor is on\n        indent_guides:         false, // Draw a faint line at each level of indentation\n        show_whitespace:       false, // Show tabs as \u{2192} and spaces at the end of lines as \u{b7}\n        backup:                false, // Keep the previous contents in file~ on save\n        format_on_save:        false, // Run the formatter of the file type before saving\n        autosave:                  0, // Seconds of inactivity before changed files are saved, 0 is off\n        scroll_off:                3, // Lines kept in view above and below the cursor while scrolling\n        swap_files:             true, // Keep unsaved changes in .file.ox-swap to recover after a crash\n        syntax_highlighting:    true, // Colour the code using the language definitions\n        modal:                 false, // Use vim style normal, insert and visual modes\n        cursor_shapes:          true, // Draw the cursor as a bar, block or underline for what typing does\n        scrollbar:              true, // Show where the view is in the file at the right edge, to drag with the mouse\n        auto_indent:            true, // Indent new lines to match the code around them\n        auto_pairs:             true, // Type the closing bracket or quote along with the opening one\n        lsp:                   false, // Start the language servers of the languages below\n        word_completion:        true, // Tab after a word offers the words in open documents that start with it\n        spell_check:           false, // Underline misspelt words in text, and in the comments and strings of code\n        dictionary: \"/usr/share/dict/words\", // The words spell checking knows, one on each line\n        git_gutter:             true, // Mark the lines changed since the version git has beside the line numbers\n        blame:                 false, // Show who last changed the line the cursor is on, when and why\n        restore_session:       false, // Reopen the files of the last session when Ox starts without any\n        chord_timeout:          1000, // Milliseconds to wait for the next key of a chord\n        message_timeout:           5, // Seconds before a message leaves the command line, 0 to keep it\n        case_sensitive:         true, // Tell capitals apart when searching\n        smart_case:            false, // Search ignoring case unless the search has capitals in it\n        whole_word:            false, // Only find searches that are whole words\n        rulers:                   [], // Columns to mark down the screen, such as [80, 100]\n        // Values:\n        // %f - File name\n        // %F - File name with full path\n        // %I - Language specific icon with leading space\n        // %i - Language specific icon\n        // %n - Language name\n        // %l - Current line number in the document\n        // %L - Total number of lines in the document\n        // %x - X position of the cursor\n        // %y - Y position of the cursor\n        // %v - Version of the editor (e.g. 0.2.6)\n        // %d - Dirty file indicator text\n        // %D - Dirty file indicator icon\n        // %R - Read only file indicator\n        // %e - Line ending of the file, LF or CRLF\n        // %c - Character encoding of the file\n        // %t - Number of the current tab\n        // %T - Total number of open tabs\n        // %m - Editing mode when modal editing is on\n        // %p - How far through the document the cursor is, as a percentage\n        // %g - Git branch of the file, with * when the repository has uncommitted changes\n        status_left:  \" %m%f%d%R %D \u{2502} %n %i \u{2502} %t/%T %g\", // Left part of status line\n        status_center: \"\", // Middle part of status line, centred between the other two\n        status_right: \"%c \u{2502} %e \u{2502} \u{4e26} %l / %L %p \u{2502} \u{fae6}(%x, %y) \", // Right part of status line\n        tab: \"%I%f%d\", // Tab formatting\n    ),\n    // Custom defined macros\n    macros: {\n        // Macro to open a new line below the cursor\n        \"open line below\": [\n            \"line below\",  // Insert an empty line below\n            \"move 1 down\", // Move cursor down to the empty line\n            \"move home\",   // Move cursor to the start of the line\n        ],\n        // Macro to open a new line above the cursor\n        \"open line above\": [\n            \"line above\",  // Insert an empty line above\n            \"move 1 up\",   // Move cursor up to the empty line\n            \"move home\",   // Move cursor to the start of the line\n        ],\n        // Macro to save with root permission\n        \"save #\": [\n            // SHCS: Shell with confirmation and substitution\n            // With substitution, `%C` becomes the current documents contents\n            // `%F` becomes the file path of the current document\n            \"shcs sudo cat > %F << EOF\\n%CEOF\", // \'%F\' is the current file name\n            \"is saved\", // Set the status of the file to saved\n        ],\n    },\n    // RGB values for the colours of Ox\n    theme: Theme(\n        transparent_editor: false,         // Makes editor background transparent\n        editor_bg:          (41, 41, 61), // The main background color\n        editor_fg:          (255, 255, 255), // The default text color\n        status_bg:          (59, 59, 84), // The background color of the status line\n        status_fg:          (35, 240, 144), // The text color of the status line\n        line_number_fg:     (73, 73, 110), // The text color of the line numbers\n        line_number_bg:     (49, 49, 73), // The background color of the line numbers\n        line_highlight_bg:  (52, 52, 77), // The background color of the current line\n        selection_bg:       (78, 78, 120), // The background color of selected text\n        bracket_bg:         (90, 90, 140), // The background color of matching brackets\n        git_added_fg:       (35, 240, 144), // The color of the marks beside added lines\n        git_modified_fg:    (208, 164, 79), // The color of the marks beside changed lines\n        git_removed_fg:     (224, 113, 113), // The color of the marks where lines were removed\n        bookmark_fg:        (130, 170, 255), // The color of the letters of marks beside their lines\n        diff_added_bg:      (36, 66, 48), // The background of lines only the second compared file has\n        diff_removed_bg:    (78, 40, 46), // The background of lines only the first compared file has\n        diff_modified_bg:   (52, 56, 92), // The background of lines that differ between compared files\n        guide_fg:           (58, 58, 84), // The color of the indentation guides\n        whitespace_fg:      (90, 90, 130), // The color of shown tabs and trailing spaces\n        ruler_bg:           (49, 49, 72), // The background color of the columns marked by rulers\n        active_tab_fg:      (255, 255, 255), // The text color of the active tab\n        active_tab_bg:      (41, 41, 61), //  The background color of the active tab\n        inactive_tab_fg:    (255, 255, 255), // The text color of the inactive tab(s)\n        inactive_tab_bg:    (59, 59, 84), // The text color of the inactive tab(s)\n        warning_fg:         (208, 164, 79), // Text colour of the warning message\n        error_fg:           (224, 113, 113), // Text colour of the warning message\n        info_fg:            (255, 255, 255), // Text colour of the warning message\n        default_theme:    \"default\", // The default syntax highlights to use\n        fallback:         true, // Enables use of fallback themes (if detected)\n    ),\n    // Other colour schemes, switch between them with `theme <name>`\n    themes: {\n        \"alternative\": Theme(\n            transparent_editor: false,\n            editor_bg:          (30, 30, 36),\n            editor_fg:          (220, 220, 220),\n            status_bg:          (48, 48, 58),\n            status_fg:          (76, 224, 179),\n            line_number_fg:     (90, 90, 110),\n            line_number_bg:     (36, 36, 44),\n            line_highlight_bg:  (40, 40, 48),\n            selection_bg:       (62, 62, 84),\n            bracket_bg:         (74, 74, 104),\n            active_tab_fg:      (220, 220, 220),\n            active_tab_bg:      (30, 30, 36),\n            inactive_tab_fg:    (160, 160, 170),\n            inactive_tab_bg:    (48, 48, 58),\n            warning_fg:         (249, 233, 0),\n            error_fg:           (224, 113, 113),\n            info_fg:            (220, 220, 220),\n            default_theme:    \"alternative\",\n            fallback:         true,\n        ),\n    },\n    // Colours for the syntax highlighting\n    highlights: {\n        \"default\": {\n            \"comments\":   (113, 113, 169),\n            \"keywords\":   (134, 76, 232),\n            \"namespaces\": (134, 76, 232),\n            \"references\": (134, 76, 232),\n            \"strings\":    (39, 222, 145),\n            \"characters\": (40, 198, 232),\n            \"digits\":     (40, 198, 232),\n            \"booleans\":   (86, 217, 178),\n            \"functions\":  (47, 141, 252),\n            \"structs\":    (47, 141, 252),\n            \"macros\":     (223, 52, 249),\n            \"attributes\": (40, 198, 232),\n            \"headers\":    (47, 141, 252),\n            \"symbols\":    (47, 141, 252),\n            \"global\":     (86, 217, 178),\n            \"operators\":  (86, 217, 178),\n            \"regex\":      (40, 198, 232),\n            \"search_active\":   (41, 73, 131),\n            \"search_inactive\": (29, 52, 93),\n        },\n        \"alternative\": {\n            \"comments\":   (113, 113, 169),\n            \"keywords\":   (64, 86, 244),\n            \"namespaces\": (64, 86, 244),\n            \"references\": (64, 86, 244),\n            \"strings\":    (76, 224, 179),\n            \"characters\": (110, 94, 206),\n            \"digits\":     (4, 95, 204),\n            \"booleans\":   (76, 224, 179),\n            \"functions\":  (4, 95, 204),\n            \"structs\":    (4, 95, 204),\n            \"macros\":     (110, 94, 206),\n            \"attributes\": (4, 95, 204),\n            \"headers\":    (141, 129, 217),\n            \"symbols\":    (249, 233, 0),\n            \"global\":     (76, 224, 179),\n            \"operators\":  (76, 224, 179),\n            \"regex\":      (4, 95, 204),\n            \"search_active\":   (41, 73, 131),\n            \"search_inactive\": (29, 52, 93),\n        },\n    },\n    // Key bindings\n    keys: {\n        // Keybinding: [Oxa commands]\n        Ctrl(Char(\'q\')): [\"quit\"], // Quit current document\n        Alt(Char(\'q\')):  [\"quit !\"], // Quit current document without saving\n        Ctrl(Char(\'s\')): [\"save\"], // Save current document\n        Alt(Char(\'s\')):  [\"save ?\"], // Save current document as\n        Ctrl(Char(\'w\')): [\"save *\"], // Save all open documents\n        Ctrl(Char(\'n\')): [\"new\"], // Create new document\n        Ctrl(Char(\'o\')): [\"open\"], // Open document\n        Ctrl(Left):      [\"move word left\"], // Move to the start of the previous word\n        Ctrl(Right):     [\"move word right\"], // Move to the end of the next word\n        Alt(PageUp):     [\"move paragraph up\"], // Move to the blank line before the paragraph\n        Alt(PageDown):   [\"move paragraph down\"], // Move to the blank line after the paragraph\n        Alt(Home):       [\"move block start\"], // Move to the first line of the indented block\n        Alt(End):        [\"move block end\"], // Move to the last line of the indented block\n        Ctrl(PageUp):    [\"prev\"], // Move to previous tab\n        Ctrl(PageDown):  [\"next\"], // Move to next tab\n        Ctrl(Char(\'z\')): [\"suspend\"], // Stop the editor and go back to the shell, fg to return\n        Alt(Char(\'z\')):  [\"undo\"], // Undo last edit\n        Ctrl(Char(\'y\')): [\"redo\"], // Redo last edit\n        Alt(Char(\'Z\')):  [\"undo tree\"], // Browse every state the document has been in\n        Ctrl(Char(\'f\')): [\"search\"], // Trigger search command\n        Ctrl(Char(\'r\')): [\"replace\"], // Trigger replace command\n        Ctrl(Char(\'a\')): [\"replace *\"], // Trigger replace all command\n        Ctrl(Up):        [\"scroll up\"], // Scroll the view up\n        Ctrl(Down):      [\"scroll down\"], // Scroll the view down\n        Alt(Up):         [\"move line up\"], // Move line up\n        Alt(Down):       [\"move line down\"], // Move line down\n        Alt(Char(\'j\')):  [\"open line below\"], // Open a new line below\n        Alt(Char(\'k\')):  [\"open line above\"], // Open a new line above\n        Ctrl(Char(\'d\')): [\"line duplicate\"], // Copy the current line below itself\n        Ctrl(Char(\'j\')): [\"line join\"], // Join the next line onto the current one\n        Alt(Char(\'d\')):  [\"line delete\"], // Delete the current line\n        Alt(Char(\'J\')):  [\"cursor below\"], // Add a cursor on the line below\n        Alt(Char(\'K\')):  [\"cursor above\"], // Add a cursor on the line above\n        Alt(Char(\'c\')):  [\"cursor next\"], // Add a cursor where the word at the cursor appears next\n        Ctrl(Backspace): [\"delete word left\"], // Delete the word before the cursor\n        Ctrl(Char(\'h\')): [\"delete word left\"], // Ctrl + Backspace in most terminals\n        Ctrl(Delete):    [\"delete word right\"], // Delete the word after the cursor\n        Ctrl(Char(\'k\')): [\"delete end\"], // Delete to the end of the line\n        Alt(Char(\'a\')):  [\"cmd\"], // Open the command line\n        Ctrl(Char(\'p\')): [\"cmd\"], // Open the command line\n        Alt(Char(\'w\')):  [\"wrap\"], // Toggle soft wrapping of long lines\n        Alt(Char(\'t\')):  [\"console\"], // Open a shell below the documents, or move focus to and from it\n        Alt(Char(\'P\')):  [\"preview\"], // Show or hide a formatted preview of a Markdown document\n        F(5):            [\"build\"], // Run the build command of the file\'s language\n        Alt(Char(\'e\')):  [\"problem next\"], // Go to the next problem the build found\n        Alt(Char(\'E\')):  [\"problem previous\"], // Go to the previous problem the build found\n        Alt(Char(\'L\')):  [\"problem list\"], // Show or hide the problems the build found\n        Alt(Char(\'G\')):  [\"grep\"], // Search every file under the working directory\n        Alt(Char(\'R\')):  [\"grep replace\"], // Replace in every file, going through the changes first\n        Alt(Char(\'N\')):  [\"messages\"], // Look back over the messages shown on the command line\n        Alt(Char(\'o\')):  [\"pane\"], // Switch between split panes\n        Ctrl(Char(\'b\')): [\"explorer\"], // Show, focus or hide the file tree\n        Ctrl(Char(\'t\')): [\"find\"], // Find a file to open by its path\n        Ctrl(Char(\'g\')): [\"goto ?\"], // Go to a line and column\n        Ctrl(Char(\'l\')): [\"center\"], // Scroll the line the cursor is on to the middle of the screen\n        Ctrl(Char(\'c\')): [\"copy\"], // Copy the selection or current line to the clipboard\n        Ctrl(Char(\'v\')): [\"paste\"], // Paste from the clipboard\n        Ctrl(Char(\'x\')): [\"cut\"], // Cut the selection to the clipboard\n        Alt(Char(\'y\')):  [\"registers\"], // Pick from what the registers hold to paste it\n        Alt(Char(\';\')):  [\"repeat\"], // Make the last edit again at the cursor\n        Alt(Char(\'=\')):  [\"increment\"], // Add one to the number under or after the cursor\n        Alt(Char(\'-\')):  [\"decrement\"], // Take one from the number under or after the cursor\n        Alt(Char(\'v\')):  [\"select\"], // Start or stop selecting text\n        Alt(Char(\'V\')):  [\"select block\"], // Start or stop selecting a block of columns\n        Alt(Char(\'m\')):  [\"bracket\"], // Jump to the matching bracket\n        Alt(Char(\'M\')):  [\"mark set\"], // Set a mark at the cursor, named by the next letter typed\n        Alt(Char(\'g\')):  [\"mark\"], // Go to the mark named by the next letter typed\n        Alt(Left):       [\"jump back\"], // Go back to where the last large jump was made from\n        Alt(Right):      [\"jump forward\"], // Go forward again through the jumps\n        Alt(Char(\'f\')):  [\"fold\"], // Fold away the block the cursor is in\n        Alt(Char(\'u\')):  [\"unfold\"], // Open the fold on the cursor\'s line\n        Alt(Char(\'F\')):  [\"fold all\"], // Fold away every outermost block\n        Alt(Char(\'U\')):  [\"unfold all\"], // Open every fold\n        Ctrl(Char(\'7\')): [\"comment\"], // Ctrl + / in most terminals, comment or uncomment lines\n        Alt(Char(\'/\')):  [\"comment\"], // Comment out the selected lines, or uncomment them\n        Alt(Char(\'r\')):  [\"record\"], // Start or stop recording keys\n        Alt(Char(\'p\')):  [\"replay\"], // Replay the recorded keys\n        Alt(Char(\'l\')):  [\"readonly\"], // Refuse or allow changes to the document\n        Ctrl(Char(\' \')): [\"complete\"], // Complete the word at the cursor with the language server\n        Alt(Char(\'n\')):  [\"words\"], // Complete the word at the cursor from the open documents\n        F(7):   [\"spell\"], // Suggest spellings for the word at the cursor\n        Alt(Char(\'.\')):  [\"hunk next\"], // Go to the next run of lines changed since git saw them\n        Alt(Char(\',\')):  [\"hunk prev\"], // Go to the previous run of changed lines\n        Alt(Char(\'b\')):  [\"blame\"], // Show or hide the blame of the line the cursor is on\n        F(12):  [\"tag\"], // Go to the definition of the word at the cursor from the tags file\n        Alt(Char(\'T\')):  [\"tag back\"], // Go back to where the definition was gone to from\n        F(1):   [\"help\"], // List every key and command\n    },\n    // Sequences of keys pressed one after the other\n    chords: {\n        // [Keybindings]: [Oxa commands]\n        [Ctrl(Char(\'e\')), Raw(Char(\'v\'))]: [\"vsplit\"], // Show the next tab beside this one\n        [Ctrl(Char(\'e\')), Raw(Char(\'h\'))]: [\"hsplit\"], // Show the next tab below this one\n        [Ctrl(Char(\'e\')), Raw(Char(\'u\'))]: [\"unsplit\"], // Go back to one pane\n        [Ctrl(Char(\'e\')), Raw(Char(\'d\'))]: [\"diff\"], // Compare the two panes\n        [Ctrl(Char(\'e\')), Raw(Char(\'p\'))]: [\"preview\"], // Preview a Markdown document\n    },\n    // Words replaced by what they stand for as a space or punctuation is typed after them\n    abbreviations: {\n        // [Abbreviation]: [Text it stands for]\n        // \"teh\": \"the\", // Fix a common typo\n        // \";sig\": \"Kind regards,\\nA. Person\", // Lines after the first line up with it\n    },\n    // Settings for types of file, named by their extension, that go over the general ones\n    filetypes: {\n        \"Makefile\": (expand_tabs: Some(false)), // Makefiles need tabs\n        \"go\": (expand_tabs: Some(false), formatter: Some(\"gofmt\")), // Go is formatted with tabs\n        // Formatters read the text and write it formatted, %F is the file\n        \"rs\": (formatter: Some(\"rustfmt --edition 2021\")),\n        \"py\": (formatter: Some(\"black --quiet -\")),\n        \"js\": (formatter: Some(\"prettier --stdin-filepath %F\")),\n    },\n    // Language specific settings\n    languages: [\n        Language(\n            name: \"Rust\", // Name of the language\n            icon: \"\u{e7a8} \", // Icon for the language\n            extensions: [\"rs\"], // Extensions of the language\n            server: \"rust-analyzer\", // Language server for diagnostics and completion\n            build: \"cargo build\", // Command run by the build command, %F is the file\n            // Snippets expanded by Tab, which then moves on through $1, $2 and so on to $0\n            snippets: {\n                \"fn\":    \"fn $1($2) {\\n\\t$0\\n}\",\n                \"if\":    \"if $1 {\\n\\t$0\\n}\",\n                \"for\":   \"for $1 in $2 {\\n\\t$0\\n}\",\n                \"match\": \"match $1 {\\n\\t$2 => $0,\\n}\",\n                \"test\":  \"#[test]\\nfn $1() {\\n\\t$0\\n}\",\n            },\n            // Keywords of the language\n            keywords: [\n                \"as\", \"break\", \"const\", \"continue\", \"crate\", \"else\", \n                \"enum\", \"extern\", \"fn\", \"for\", \"if\", \"impl\", \"in\", \n                \"let\", \"loop\", \"match\", \"mod\", \"move\", \"mut\", \"pub\", \n                \"ref\", \"return\", \"self\", \"static\", \"struct\", \"super\", \n                \"trait\", \"type\", \"unsafe\", \"use\", \"where\", \"while\", \n                \"async\", \"await\", \"dyn\", \"abstract\", \"become\", \"box\", \n                \"do\", \"final\", \"macro\", \"override\", \"priv\", \"typeof\", \n                \"unsized\", \"virtual\", \"yield\", \"try\", \"\'static\",\n                \"u8\", \"u16\", \"u32\", \"u64\", \"u128\", \"usize\",\n                \"i8\", \"i16\", \"i32\", \"i64\", \"i128\", \"isize\",\n                \"f32\", \"f64\", \"String\", \"Vec\", \"str\", \"Some\", \"bool\",\n                \"None\", \"Box\", \"Result\", \"Option\", \"Ok\", \"Err\", \"Self\",\n                \"std\"\n            ],\n            // Syntax definitions\n            definitions: {\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"[^/](/)[^/]\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\?)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n                \"namespaces\": [\n                    r\"([a-z_][A-Za-z0-9_]*)::\",\n                ],\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*?\\\\*/)\",\n                ],\n                \"strings\":    [\n                    \"\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(r\\\".*?\\\")\",\n                    \"(?ms)(r#\\\".*?\\\"#)\",\n                    \"(?ms)(#\\\".*?\\\"#)\",\n                ],\n                \"characters\": [\n                    \"(\'.\')\", \n                    \"(\'\\\\\\\\.\')\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                    \"\\\\b(\\\\d+.\\\\d+(?:f32|f64))\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"functions\":  [\n                    \"fn\\\\s+([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    r\"\\.([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                    r\"([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                ],\n                \"structs\":    [\n                    \"(?:trait|enum|struct|impl)\\\\s+([A-Z][A-Za-z0-9_]*)\\\\s*\", \n                    \"impl(?:<.*?>|)\\\\s+([A-Z][A-Za-z0-9_]*)\",\n                    \"([A-Z][A-Za-z0-9_]*)::\",\n                    r\"([A-Z][A-Za-z0-9_]*)\\s*\\(\",\n                    \"impl.*for\\\\s+([A-Z][A-Za-z0-9_]*)\",\n                    r\"::\\s*([a-z_][A-Za-z0-9_]*)\\s*\\(\",\n                ],\n                \"macros\":     [\n                    \"\\\\b([a-z_][a-zA-Z0-9_]*!)\",\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\",\n                ],\n                \"attributes\": [\n                    \"(?ms)^\\\\s*(#(?:!|)\\\\[.*?\\\\])\",\n                ],\n                \"references\": [\n                    \"(&)\",\n                    \"&str\", \"&mut\", \"&self\", \n                    \"&i8\", \"&i16\", \"&i32\", \"&i64\", \"&i128\", \"&isize\",\n                    \"&u8\", \"&u16\", \"&u32\", \"&u64\", \"&u128\", \"&usize\",\n                    \"&f32\", \"&f64\",\n                ]\n            }\n        ),\n        Language(\n            name: \"Ruby\", // Name of the language\n            icon: \"\u{e739} \", // Icon for the language\n            extensions: [\"rb\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"__ENCODING__\", \"__LINE__\", \"__FILE__\", \"BEGIN\", \"END\", \n                \"alias\", \"and\", \"begin\", \"break\", \"case\", \"class\", \"def\", \n                \"defined?\", \"do\", \"else\", \"elsif\", \"end\", \"ensure\", \"print\",\n                \"for\", \"if\", \"in\", \"module\", \"next\", \"nil\", \"not\", \"or\", \"puts\",\n                \"redo\", \"rescue\", \"retry\", \"return\", \"self\", \"super\", \"then\", \n                \"undef\", \"unless\", \"until\", \"when\", \"while\", \"yield\", \"raise\",\n                \"include\", \"extend\", \"require\" \n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                    \"(?ms)(=begin.*=end)\", \n                ],\n                \"strings\":    [\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    r\"\\b(\\d+.\\d+|\\d+)\",\n                ],\n                \"booleans\":   [\n                    r\"\\b(true)\\b\", \n                    r\"\\b(false)\\b\",\n                ],\n                \"structs\":    [\n                    r\"class(\\s+[A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    r\"def\\s+([a-z_][A-Za-z0-9_\\\\?!]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"symbols\":    [\n                    r\"(:[^,\\)\\.\\s=]+)\",\n                ],\n                \"global\":     [\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\\s\",\n                ],\n                \"regex\": [\n                    r\"/.+/\"\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Crystal\", // Name of the language\n            icon: \"\u{e7a3} \", // Icon for the language\n            extensions: [\"cr\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"__ENCODING__\", \"__LINE__\", \"__FILE__\", \"BEGIN\", \"END\", \n                \"alias\", \"and\", \"begin\", \"break\", \"case\", \"class\", \"def\", \n                \"defined?\", \"do\", \"else\", \"elsif\", \"end\", \"ensure\", \"print\",\n                \"for\", \"if\", \"in\", \"module\", \"next\", \"nil\", \"not\", \"or\", \"puts\",\n                \"redo\", \"rescue\", \"retry\", \"return\", \"self\", \"super\", \"then\", \n                \"undef\", \"unless\", \"until\", \"when\", \"while\", \"yield\", \"raise\",\n                \"include\", \"extend\", \"Int32\", \"String\", \"getter\", \"setter\",\n                \"property\", \"Array\", \"Set\", \"Hash\", \"Range\", \"Proc\", \"typeof\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                    \"(?ms)(=begin.*=end)\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\".*?\\\")\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(\\\'.*?\\\')\",\n                ],\n                \"digits\":     [\n                    r\"\\b(\\d+.\\d+|\\d+)\",\n                    r\"(_i(?:8|16|32|64|128))\",\n                    r\"(_u(?:8|16|32|64|128))\",\n                    r\"(_f(?:8|16|32|64|128))\",\n                    \"0x[A-Fa-f0-9]{6}\"\n                ],\n                \"booleans\":   [\n                    r\"\\b(true)\\b\", \n                    r\"\\b(false)\\b\",\n                ],\n                \"structs\":    [\n                    r\"class(\\s+[A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    r\"def\\s+([a-z_][A-Za-z0-9_\\\\?!]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"symbols\":    [\n                    r\"(:[^,\\}\\)\\.\\s=]+)\",\n                ],\n                \"global\":     [\n                    r\"(\\$[a-z_][A-Za-z0-9_]*)\\s\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(\\?)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Python\", // Name of the language\n            icon: \"\u{e73c} \", // Icon for the language\n            extensions: [\"py\", \"pyw\"], // Extensions of the language\n            server: \"pylsp\", // Language server for diagnostics and completion\n            build: \"python3 %F\", // Command run by the build command, %F is the file\n            // Snippets expanded by Tab, which then moves on through $1, $2 and so on to $0\n            snippets: {\n                \"def\":   \"def $1($2):\\n\\t$0\",\n                \"if\":    \"if $1:\\n\\t$0\",\n                \"for\":   \"for $1 in $2:\\n\\t$0\",\n                \"class\": \"class $1:\\n\\tdef __init__(self$2):\\n\\t\\t$0\",\n            },\n            pairs: [\"()\", \"[]\", \"{}\", \"\\\"\\\"\", \"\'\'\"], // Characters closed automatically\n            // Keywords of the language\n            keywords: [\n                \"and\", \"as\", \"assert\", \"break\", \"class\", \"continue\", \n                \"def\", \"del\", \"elif\", \"else\", \"except\", \"exec\", \n                \"finally\", \"for\", \"from\", \"global\", \"if\", \"import\", \n                \"in\", \"is\", \"lambda\", \"not\", \"or\", \"pass\", \"print\", \n                \"raise\", \"return\", \"try\", \"while\", \"with\", \"yield\",\n                \"str\", \"bool\", \"int\", \"tuple\", \"list\", \"dict\", \"tuple\",\n                \"len\", \"None\", \"input\", \"type\", \"set\", \"range\", \"enumerate\",\n                \"open\", \"iter\", \"min\", \"max\", \"dir\", \"self\", \"isinstance\", \n                \"help\", \"next\", \"super\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(#.*)$\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\"\\\"\\\".*?\\\"\\\"\\\")\",\n                    \"(?ms)(\\\'\\\'\\\'.*?\\\'\\\'\\\')\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(True)\\\\b\", \n                    \"\\\\b(False)\\\\b\",\n                ],\n                \"structs\":    [\n                    \"class\\\\s+([A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    \"def\\\\s+([a-z_][A-Za-z0-9_]*)\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                    \"\\\\b([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\\\\(\",\n                ],\n                \"attributes\": [\n                    \"@.*$\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(\\s//\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                ],\n            }\n        ),\n        Language(\n            name: \"Javascript\", // Name of the language\n            icon: \"\u{e74e} \", // Icon for the language\n            extensions: [\"js\"], // Extensions of the language\n            // Keywords of the language\n            keywords: [\n                \"abstract\", \"arguments\", \"await\", \"boolean\", \"break\", \"byte\", \n                \"case\", \"catch\", \"char\", \"class\", \"const\", \"continue\", \"debugger\", \n                \"default\", \"delete\", \"do\", \"double\", \"else\", \"enum\", \"eval\", \n                \"export\", \"extends\", \"final\", \"finally\", \"float\", \"for\", \"of\",\n                \"function\", \"goto\", \"if\", \"implements\", \"import\", \"in\", \"instanceof\", \n                \"int\", \"interface\", \"let\", \"long\", \"native\", \"new\", \"null\", \"package\", \n                \"private\", \"protected\", \"public\", \"return\", \"short\", \"static\", \n                \"super\", \"switch\", \"synchronized\", \"this\", \"throw\", \"throws\", \n                \"transient\", \"try\", \"typeof\", \"var\", \"void\", \"volatile\", \"console\",\n                \"while\", \"with\", \"yield\", \"undefined\", \"NaN\", \"-Infinity\", \"Infinity\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*\\\\*/)$\", \n                ],\n                \"strings\":    [\n                    \"(?ms)(\\\"\\\"\\\".*?\\\"\\\"\\\")\",\n                    \"(?ms)(\\\'\\\'\\\'.*?\\\'\\\'\\\')\",\n                    \"(?:f|r|)\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                    \"(?:f|r|)\\\'(?:[^\\\'\\\\\\\\]*(?:\\\\\\\\.[^\\\'\\\\\\\\]*)*)\\\'\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"structs\":    [\n                    \"class\\\\s+([A-Za-z0-9_]*)\",\n                ],\n                \"functions\":  [\n                    \"function\\\\s+([a-z_][A-Za-z0-9_]*)\",\n                    \"\\\\b([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    \"\\\\.([a-z_][A-Za-z0-9_\\\\?!]*)\\\\s*\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(<<)\",\n                    r\"(>>)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                ],\n            }\n        ),\n        Language(\n            name: \"C\", // Name of the language\n            icon: \"\u{e61e} \", // Icon for the language\n            extensions: [\"c\", \"h\"], // Extensions of the language\n            server: \"clangd\", // Language server for diagnostics and completion\n            build: \"make\", // Command run by the build command, %F is the file\n            // Snippets expanded by Tab, which then moves on through $1, $2 and so on to $0\n            snippets: {\n                \"main\": \"int main(int argc, char **argv) {\\n\\t$0\\n\\treturn 0;\\n}\",\n                \"if\":   \"if ($1) {\\n\\t$0\\n}\",\n                \"for\":  \"for ($1; $2; $3) {\\n\\t$0\\n}\",\n                \"inc\":  \"#include <$1>$0\",\n            },\n            // Keywords of the language\n            keywords: [\n                \"auto\", \"break\", \"case\", \"char\", \"const\", \"continue\", \"default\", \n                \"do\", \"double\", \"else\", \"enum\", \"extern\", \"float\", \"for\", \"goto\", \n                \"if\", \"int\", \"long\", \"register\", \"return\", \"short\", \"signed\", \n                \"sizeof\", \"static\", \"struct\", \"switch\", \"typedef\", \"union\", \n                \"unsigned\", \"void\", \"volatile\", \"while\", \"printf\", \"fscanf\", \n                \"scanf\", \"fputsf\", \"exit\", \"stderr\", \"malloc\", \"calloc\", \"bool\",\n                \"realloc\", \"free\", \"strlen\", \"size_t\",\n            ],\n            // Syntax definitions\n            definitions: {\n                \"comments\":   [\n                    \"(?m)(//.*)$\", \n                    \"(?ms)(/\\\\*.*?\\\\*/)\",\n                ],\n                \"strings\":    [\n                    \"\\\"(?:[^\\\"\\\\\\\\]*(?:\\\\\\\\.[^\\\"\\\\\\\\]*)*)\\\"\",\n                ],\n                \"characters\": [\n                    \"(\'.\')\", \n                    \"(\'\\\\\\\\.\')\",\n                ],\n                \"digits\":     [\n                    \"\\\\b(\\\\d+.\\\\d+|\\\\d+)\",\n                    \"\\\\b(\\\\d+.\\\\d+(?:f|))\",\n                ],\n                \"booleans\":   [\n                    \"\\\\b(true)\\\\b\", \n                    \"\\\\b(false)\\\\b\",\n                ],\n                \"functions\":  [\n                    \"(int|bool|void|char|double|long|short|size_t)\\\\s+([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                    \"\\\\b([a-z_][A-Za-z0-9_]*)\\\\s*\\\\(\",\n                ],\n                \"structs\":    [\n                    \"struct\\\\s+([A-Za-z0-9_]*)\\\\s*\", \n                ],\n                \"attributes\": [\n                    \"^\\\\s*(#.*?)\\\\s\",\n                ],\n                \"headers\":    [\n                    \"(<.*?>)\",\n                ],\n                \"operators\":  [\n                    r\"(=)\",\n                    r\"(\\+)\",\n                    r\"(\\-)\",\n                    r\"(\\*)\",\n                    r\"(\\s/\\s)\",\n                    r\"(%)\",\n                    r\"(\\+=)\",\n                    r\"(\\-=)\",\n                    r\"(\\*=)\",\n                    r\"(\\\\=)\",\n                    r\"(==)\",\n                    r\"(!=)\",\n                    r\"(>=)\",\n                    r\"(<=)\",\n                    r\"(<)\",\n                    r\"(>)\",\n                    r\"(<<)\",\n                    r\"(>>)\",\n                    r\"(\\&\\&)\",\n                    r\"(\\|\\|)\",\n                    r\"(!)\\S\",\n                ],\n            }\n        ),\n    ],\n)\n".to_string()
+}
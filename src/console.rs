@@ -0,0 +1,746 @@
+// Console.rs - A shell running in a pseudo terminal, shown in a pane below the documents
+use crate::config::{KeyBinding, RawKey, Reader};
+use crossterm::style::{Color, SetBackgroundColor, SetForegroundColor};
+use std::io;
+use unicode_width::UnicodeWidthChar;
+#[cfg(unix)]
+use {
+    std::env,
+    std::fs::File,
+    std::io::{ErrorKind, Read, Write},
+    std::os::unix::io::{AsRawFd, FromRawFd},
+    std::os::unix::process::CommandExt,
+    std::process::{Child, Command},
+    std::ptr,
+    std::sync::mpsc::{self, Receiver, TryRecvError},
+    std::thread,
+};
+
+// The colour of a character or the space behind it
+#[derive(Clone, Copy, PartialEq)]
+enum Colour {
+    Default,         // Whatever the editor's theme uses
+    Index(u8),       // One of the 256 palette colours
+    Rgb(u8, u8, u8), // An exact colour
+}
+
+// How a character is drawn
+#[derive(Clone, Copy, PartialEq)]
+struct Style {
+    fg: Colour,      // The colour of the character
+    bg: Colour,      // The colour behind it
+    bold: bool,      // Whether it is drawn bold
+    underline: bool, // Whether it is underlined
+    reverse: bool,   // Whether the two colours are swapped
+}
+
+// A spot on the screen, which is empty for the second half of a wide character
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    c: char,
+    style: Style,
+}
+
+// Where the parser is within a sequence of characters
+enum State {
+    Ground,      // Plain text
+    Escape,      // Just after an escape
+    Csi(String), // In a control sequence, with its parameters so far
+    Osc(bool),   // In an operating system command, and whether an escape came last
+    Charset,     // Choosing a character set, which is ignored
+}
+
+// The grid of characters a program running in the console has drawn
+#[cfg_attr(not(unix), allow(dead_code))]
+pub struct Screen {
+    width: usize,                 // How many columns there are
+    height: usize,                // How many rows there are
+    cells: Vec<Vec<Cell>>,        // What is in each spot
+    main: Option<Vec<Vec<Cell>>>, // The normal screen while the alternate one is shown
+    pub x: usize,                 // The column of the cursor, the width when a wrap is due
+    pub y: usize,                 // The row of the cursor
+    saved: (usize, usize, Style), // The cursor and style put aside for later
+    style: Style,                 // How characters are drawn right now
+    top: usize,                   // The first row that scrolls
+    bottom: usize,                // The last row that scrolls
+    state: State,                 // Where the parser is
+    pending: Vec<u8>,             // The start of a character that was cut off
+    pub shown: bool,              // Whether the program wants the cursor seen
+    application: bool,            // Whether the arrow keys send their application codes
+    replies: Vec<u8>,             // Answers to the questions the program asked
+}
+
+impl Style {
+    fn new() -> Self {
+        // The plain style of text
+        Self {
+            fg: Colour::Default,
+            bg: Colour::Default,
+            bold: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        // An empty screen with the cursor at the top left
+        let (width, height) = (width.max(1), height.max(1));
+        let blank = Cell {
+            c: ' ',
+            style: Style::new(),
+        };
+        Self {
+            width,
+            height,
+            cells: vec![vec![blank; width]; height],
+            main: None,
+            x: 0,
+            y: 0,
+            saved: (0, 0, Style::new()),
+            style: Style::new(),
+            top: 0,
+            bottom: height - 1,
+            state: State::Ground,
+            pending: vec![],
+            shown: true,
+            application: false,
+            replies: vec![],
+        }
+    }
+    pub fn resize(&mut self, width: usize, height: usize) {
+        // Change the size of the screen, keeping the cursor's line in view
+        let (width, height) = (width.max(1), height.max(1));
+        let blank = self.blank();
+        for cells in self.cells.iter_mut().chain(self.main.iter_mut().flatten()) {
+            cells.resize(width, blank);
+        }
+        while self.cells.len() > height && self.y > 0 {
+            self.cells.remove(0);
+            self.y -= 1;
+        }
+        self.cells.resize(height, vec![blank; width]);
+        if let Some(main) = &mut self.main {
+            main.resize(height, vec![blank; width]);
+        }
+        self.width = width;
+        self.height = height;
+        self.top = 0;
+        self.bottom = height - 1;
+        self.x = self.x.min(width - 1);
+        self.y = self.y.min(height - 1);
+    }
+    pub fn feed(&mut self, data: &[u8]) {
+        // Take in what the program wrote, which may end partway through a character
+        let mut bytes = std::mem::take(&mut self.pending);
+        bytes.extend_from_slice(data);
+        let mut start = 0;
+        loop {
+            match std::str::from_utf8(&bytes[start..]) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.input(c));
+                    break;
+                }
+                Err(error) => {
+                    let valid = start + error.valid_up_to();
+                    if let Ok(text) = std::str::from_utf8(&bytes[start..valid]) {
+                        text.chars().for_each(|c| self.input(c));
+                    }
+                    if let Some(length) = error.error_len() {
+                        self.input('\u{fffd}');
+                        start = valid + length;
+                    } else {
+                        self.pending = bytes[valid..].to_vec();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        // What should be written back to the program
+        std::mem::take(&mut self.replies)
+    }
+    pub fn lines(&self, fg: (u8, u8, u8), bg: (u8, u8, u8)) -> Vec<String> {
+        // Render each row, with the theme's colours where the program didn't pick any
+        self.cells
+            .iter()
+            .map(|cells| {
+                let mut line = String::new();
+                let mut last = None;
+                for cell in cells.iter().filter(|cell| cell.c != '\0') {
+                    if last != Some(cell.style) {
+                        line.push_str(&paint(cell.style, fg, bg));
+                        last = Some(cell.style);
+                    }
+                    line.push(cell.c);
+                }
+                line.push_str("\x1b[0m");
+                line
+            })
+            .collect()
+    }
+    pub fn encode(&self, key: KeyBinding) -> Option<Vec<u8>> {
+        // The bytes a terminal sends for a key
+        let arrow = |c: char| {
+            if self.application {
+                format!("\x1bO{c}")
+            } else {
+                format!("\x1b[{c}")
+            }
+        };
+        let special = |key: RawKey, modifier: u8| -> Option<String> {
+            let (code, tilde) = match key {
+                RawKey::Up => ('A', false),
+                RawKey::Down => ('B', false),
+                RawKey::Right => ('C', false),
+                RawKey::Left => ('D', false),
+                RawKey::Home => ('H', false),
+                RawKey::End => ('F', false),
+                RawKey::Insert => ('2', true),
+                RawKey::Delete => ('3', true),
+                RawKey::PageUp => ('5', true),
+                RawKey::PageDown => ('6', true),
+                _ => return None,
+            };
+            Some(match (tilde, modifier) {
+                (false, 1) => arrow(code),
+                (false, _) => format!("\x1b[1;{modifier}{code}"),
+                (true, 1) => format!("\x1b[{code}~"),
+                (true, _) => format!("\x1b[{code};{modifier}~"),
+            })
+        };
+        let text = match key {
+            KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => c.to_string(),
+            KeyBinding::Raw(RawKey::Enter) => "\r".to_string(),
+            KeyBinding::Raw(RawKey::Tab) => "\t".to_string(),
+            KeyBinding::Raw(RawKey::BackTab) | KeyBinding::Shift(RawKey::BackTab) => {
+                "\x1b[Z".to_string()
+            }
+            KeyBinding::Raw(RawKey::Backspace) => "\x7f".to_string(),
+            KeyBinding::Raw(RawKey::Esc) => "\x1b".to_string(),
+            KeyBinding::Raw(key) => special(key, 1)?,
+            KeyBinding::Shift(key) => special(key, 2)?,
+            KeyBinding::Ctrl(RawKey::Char(c)) => {
+                // Keys that crossterm reads as a digit are the ones sending these codes
+                let code = match c {
+                    'a'..='z' => c as u8 - b'a' + 1,
+                    '@' | ' ' | '2' => 0,
+                    '[' | '3' => 0x1b,
+                    '\\' | '4' => 0x1c,
+                    ']' | '5' => 0x1d,
+                    '^' | '6' => 0x1e,
+                    '_' | '/' | '7' => 0x1f,
+                    _ => return None,
+                };
+                (code as char).to_string()
+            }
+            KeyBinding::Ctrl(RawKey::Backspace) => "\x08".to_string(),
+            KeyBinding::Ctrl(key) => special(key, 5)?,
+            KeyBinding::Alt(RawKey::Char(c)) => format!("\x1b{c}"),
+            KeyBinding::Alt(RawKey::Backspace) => "\x1b\x7f".to_string(),
+            KeyBinding::Alt(RawKey::Enter) => "\x1b\r".to_string(),
+            KeyBinding::Alt(key) => special(key, 3)?,
+            KeyBinding::F(n @ 1..=4) => format!("\x1bO{}", (b'P' + n - 1) as char),
+            KeyBinding::F(n @ 5..=12) => {
+                format!("\x1b[{}~", [15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5])
+            }
+            _ => return None,
+        };
+        Some(text.into_bytes())
+    }
+    fn input(&mut self, c: char) {
+        // Act on the next character the program wrote
+        match &mut self.state {
+            State::Ground => match c {
+                '\x1b' => self.state = State::Escape,
+                '\r' => self.x = 0,
+                '\n' | '\x0b' | '\x0c' => self.line_feed(),
+                '\x08' => self.x = self.x.min(self.width - 1).saturating_sub(1),
+                '\t' => self.x = ((self.x / 8 + 1) * 8).min(self.width - 1),
+                c if c < ' ' || c == '\x7f' => (),
+                c => self.put(c),
+            },
+            State::Escape => {
+                self.state = State::Ground;
+                match c {
+                    '[' => self.state = State::Csi(String::new()),
+                    ']' => self.state = State::Osc(false),
+                    '(' | ')' | '*' | '+' => self.state = State::Charset,
+                    '7' => self.saved = (self.x, self.y, self.style),
+                    '8' => self.restore(),
+                    'D' => self.line_feed(),
+                    'E' => {
+                        self.x = 0;
+                        self.line_feed();
+                    }
+                    'M' => self.reverse_index(),
+                    'c' => *self = Screen::new(self.width, self.height),
+                    _ => (),
+                }
+            }
+            State::Csi(parameters) => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    let parameters = std::mem::take(parameters);
+                    self.state = State::Ground;
+                    self.control(&parameters, c);
+                } else if c == '\x1b' {
+                    self.state = State::Escape;
+                } else if parameters.len() < 64 {
+                    parameters.push(c);
+                }
+            }
+            State::Osc(escaped) => {
+                if c == '\x07' || (*escaped && c == '\\') {
+                    self.state = State::Ground;
+                } else {
+                    *escaped = c == '\x1b';
+                }
+            }
+            State::Charset => self.state = State::Ground,
+        }
+    }
+    #[allow(clippy::too_many_lines)] // One arm per escape sequence
+    fn control(&mut self, parameters: &str, code: char) {
+        // Carry out a control sequence
+        let private = parameters.starts_with(['?', '>', '=', '<']);
+        let numbers: Vec<usize> = parameters
+            .trim_start_matches(['?', '>', '=', '<'])
+            .split([';', ':'])
+            .map(|number| number.parse().unwrap_or(0))
+            .collect();
+        let first = numbers.first().copied().unwrap_or(0);
+        let arg = |index: usize| {
+            numbers
+                .get(index)
+                .copied()
+                .filter(|&number| number != 0)
+                .unwrap_or(1)
+        };
+        let (n, x) = (arg(0), self.x.min(self.width - 1));
+        let (last_column, last_row) = (self.width - 1, self.height - 1);
+        match code {
+            'A' => self.y = self.y.saturating_sub(n),
+            'B' => self.y = (self.y + n).min(last_row),
+            'C' => self.x = (x + n).min(last_column),
+            'D' => self.x = x.saturating_sub(n),
+            'E' => (self.x, self.y) = (0, (self.y + n).min(last_row)),
+            'F' => (self.x, self.y) = (0, self.y.saturating_sub(n)),
+            'G' | '`' => self.x = (n - 1).min(last_column),
+            'd' => self.y = (n - 1).min(last_row),
+            'H' | 'f' => {
+                self.y = (arg(0) - 1).min(last_row);
+                self.x = (arg(1) - 1).min(last_column);
+            }
+            'J' => {
+                let (above, below) = (0..self.y, self.y + 1..self.height);
+                match first {
+                    0 => {
+                        self.erase(self.y, x, self.width);
+                        below.for_each(|y| self.erase(y, 0, self.width));
+                    }
+                    1 => {
+                        self.erase(self.y, 0, x + 1);
+                        above.for_each(|y| self.erase(y, 0, self.width));
+                    }
+                    _ => (0..self.height).for_each(|y| self.erase(y, 0, self.width)),
+                }
+            }
+            'K' => match first {
+                0 => self.erase(self.y, x, self.width),
+                1 => self.erase(self.y, 0, x + 1),
+                _ => self.erase(self.y, 0, self.width),
+            },
+            'L' | 'M' if (self.top..=self.bottom).contains(&self.y) => {
+                let blank = vec![self.blank(); self.width];
+                for _ in 0..n.min(self.bottom - self.y + 1) {
+                    if code == 'L' {
+                        self.cells.remove(self.bottom);
+                        self.cells.insert(self.y, blank.clone());
+                    } else {
+                        self.cells.remove(self.y);
+                        self.cells.insert(self.bottom, blank.clone());
+                    }
+                }
+            }
+            '@' => {
+                let blank = self.blank();
+                let row = &mut self.cells[self.y];
+                for _ in 0..n.min(self.width - x) {
+                    row.insert(x, blank);
+                }
+                row.truncate(self.width);
+            }
+            'P' => {
+                let blank = self.blank();
+                let row = &mut self.cells[self.y];
+                for _ in 0..n.min(self.width - x) {
+                    row.remove(x);
+                    row.push(blank);
+                }
+            }
+            'X' => self.erase(self.y, x, (x + n).min(self.width)),
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'm' => self.paint(&numbers),
+            'r' => {
+                let (top, bottom) = (arg(0) - 1, numbers.get(1).copied().unwrap_or(0));
+                let bottom = if bottom == 0 { last_row } else { bottom - 1 };
+                if top < bottom && bottom <= last_row {
+                    (self.top, self.bottom) = (top, bottom);
+                    (self.x, self.y) = (0, 0);
+                }
+            }
+            's' if !private => self.saved = (self.x, self.y, self.style),
+            'u' if !private => self.restore(),
+            'h' | 'l' if private => {
+                let set = code == 'h';
+                for number in &numbers {
+                    match number {
+                        1 => self.application = set,
+                        25 => self.shown = set,
+                        47 | 1047 | 1049 => self.alternate(set),
+                        _ => (),
+                    }
+                }
+            }
+            'n' if first == 6 => {
+                let position = format!("\x1b[{};{}R", self.y + 1, x + 1);
+                self.replies.extend_from_slice(position.as_bytes());
+            }
+            'n' if first == 5 => self.replies.extend_from_slice(b"\x1b[0n"),
+            'c' if !private && first == 0 => self.replies.extend_from_slice(b"\x1b[?1;2c"),
+            _ => (),
+        }
+    }
+    fn paint(&mut self, numbers: &[usize]) {
+        // Change how the characters that follow are drawn
+        let mut index = 0;
+        while index < numbers.len() {
+            let number = numbers[index];
+            match number {
+                0 => self.style = Style::new(),
+                1 => self.style.bold = true,
+                4 => self.style.underline = true,
+                7 => self.style.reverse = true,
+                22 => self.style.bold = false,
+                24 => self.style.underline = false,
+                27 => self.style.reverse = false,
+                30..=37 => self.style.fg = Colour::Index((number - 30) as u8),
+                39 => self.style.fg = Colour::Default,
+                40..=47 => self.style.bg = Colour::Index((number - 40) as u8),
+                49 => self.style.bg = Colour::Default,
+                90..=97 => self.style.fg = Colour::Index((number - 90 + 8) as u8),
+                100..=107 => self.style.bg = Colour::Index((number - 100 + 8) as u8),
+                38 | 48 => {
+                    // Colours picked from the palette or given exactly
+                    let channel = |offset: usize| {
+                        numbers
+                            .get(index + offset)
+                            .map_or(0, |&value| value.min(255) as u8)
+                    };
+                    let (colour, length) = match numbers.get(index + 1) {
+                        Some(5) => (Colour::Index(channel(2)), 2),
+                        Some(2) => (Colour::Rgb(channel(2), channel(3), channel(4)), 4),
+                        _ => (Colour::Default, 0),
+                    };
+                    index += length;
+                    if number == 38 {
+                        self.style.fg = colour;
+                    } else {
+                        self.style.bg = colour;
+                    }
+                }
+                _ => (),
+            }
+            index += 1;
+        }
+    }
+    fn put(&mut self, c: char) {
+        // Write a character at the cursor, wrapping onto the next line when it is full
+        let width = c.width().unwrap_or(0);
+        if width == 0 || width > self.width {
+            return;
+        }
+        if self.x + width > self.width {
+            self.x = 0;
+            self.line_feed();
+        }
+        self.cells[self.y][self.x] = Cell {
+            c,
+            style: self.style,
+        };
+        if width == 2 {
+            self.cells[self.y][self.x + 1] = Cell {
+                c: '\0',
+                style: self.style,
+            };
+        }
+        self.x += width;
+    }
+    fn line_feed(&mut self) {
+        // Move down a line, scrolling when at the bottom
+        if self.y == self.bottom {
+            self.scroll_up(1);
+        } else if self.y + 1 < self.height {
+            self.y += 1;
+        }
+    }
+    fn reverse_index(&mut self) {
+        // Move up a line, scrolling when at the top
+        if self.y == self.top {
+            self.scroll_down(1);
+        } else {
+            self.y = self.y.saturating_sub(1);
+        }
+    }
+    fn scroll_up(&mut self, lines: usize) {
+        // Move the scrolling rows up, with blank ones coming in at the bottom
+        let blank = vec![self.blank(); self.width];
+        for _ in 0..lines.min(self.bottom - self.top + 1) {
+            self.cells.remove(self.top);
+            self.cells.insert(self.bottom, blank.clone());
+        }
+    }
+    fn scroll_down(&mut self, lines: usize) {
+        // Move the scrolling rows down, with blank ones coming in at the top
+        let blank = vec![self.blank(); self.width];
+        for _ in 0..lines.min(self.bottom - self.top + 1) {
+            self.cells.remove(self.bottom);
+            self.cells.insert(self.top, blank.clone());
+        }
+    }
+    fn erase(&mut self, y: usize, start: usize, end: usize) {
+        // Blank out some of a row
+        let blank = self.blank();
+        self.cells[y][start..end].fill(blank);
+    }
+    fn restore(&mut self) {
+        // Go back to the cursor and style that were saved
+        let (x, y, style) = self.saved;
+        self.x = x.min(self.width - 1);
+        self.y = y.min(self.height - 1);
+        self.style = style;
+    }
+    fn alternate(&mut self, on: bool) {
+        // Switch to a screen of its own for a full screen program, or back to the normal one
+        if on && self.main.is_none() {
+            self.saved = (self.x, self.y, self.style);
+            let blank = vec![vec![self.blank(); self.width]; self.height];
+            self.main = Some(std::mem::replace(&mut self.cells, blank));
+        } else if !on {
+            if let Some(main) = self.main.take() {
+                self.cells = main;
+                self.restore();
+            }
+        }
+    }
+    fn blank(&self) -> Cell {
+        // An empty spot, keeping the colour behind the text
+        Cell {
+            c: ' ',
+            style: Style {
+                bg: self.style.bg,
+                ..Style::new()
+            },
+        }
+    }
+}
+
+fn paint(style: Style, fg: (u8, u8, u8), bg: (u8, u8, u8)) -> String {
+    // The escape codes that draw characters in a style
+    let (fg, bg) = if style.reverse {
+        (colour_fg(style.bg, bg), colour_bg(style.fg, fg))
+    } else {
+        (colour_fg(style.fg, fg), colour_bg(style.bg, bg))
+    };
+    format!(
+        "\x1b[0m{}{}{}{}",
+        if style.bold { "\x1b[1m" } else { "" },
+        if style.underline { "\x1b[4m" } else { "" },
+        fg,
+        bg
+    )
+}
+
+fn colour_fg(colour: Colour, default: (u8, u8, u8)) -> SetForegroundColor {
+    // The escape code for the colour of characters
+    match colour {
+        Colour::Default => Reader::rgb_fg(default),
+        Colour::Index(index) => SetForegroundColor(Color::AnsiValue(index)),
+        Colour::Rgb(r, g, b) => Reader::rgb_fg((r, g, b)),
+    }
+}
+
+fn colour_bg(colour: Colour, default: (u8, u8, u8)) -> SetBackgroundColor {
+    // The escape code for the colour behind characters
+    match colour {
+        Colour::Default => Reader::rgb_bg(default),
+        Colour::Index(index) => SetBackgroundColor(Color::AnsiValue(index)),
+        Colour::Rgb(r, g, b) => Reader::rgb_bg((r, g, b)),
+    }
+}
+
+#[cfg(unix)]
+pub struct Console {
+    pub screen: Screen,          // What the shell has drawn
+    pub focus: bool,             // Whether keys go to the shell
+    pub alive: bool,             // Whether the shell is still running
+    master: File,                // Our end of the pseudo terminal
+    child: Child,                // The running shell
+    receiver: Receiver<Vec<u8>>, // What the shell wrote, read on another thread
+}
+
+#[cfg(unix)]
+impl Console {
+    pub fn open(width: usize, height: usize) -> io::Result<Self> {
+        // Start the user's shell in a pseudo terminal of a certain size
+        let (mut master, mut slave) = (0, 0);
+        let size = window(width, height);
+        let opened = unsafe {
+            libc::openpty(
+                ptr::addr_of_mut!(master),
+                ptr::addr_of_mut!(slave),
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::addr_of!(size),
+            )
+        };
+        if opened != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let mut command = Command::new(shell);
+        command
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave)
+            .env("TERM", "xterm-256color");
+        // The shell leads a session of its own, with the pseudo terminal as its terminal
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        // Our copies of the shell's end are let go, so reading stops once it exits
+        drop(command);
+        let mut reader = master.try_clone()?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut chunk = [0; 4096];
+            loop {
+                match reader.read(&mut chunk) {
+                    Ok(0) => return,
+                    Ok(read) => {
+                        if sender.send(chunk[..read].to_vec()).is_err() {
+                            return;
+                        }
+                    }
+                    Err(error) if error.kind() == ErrorKind::Interrupted => (),
+                    Err(_) => return,
+                }
+            }
+        });
+        Ok(Self {
+            screen: Screen::new(width, height),
+            focus: true,
+            alive: true,
+            master,
+            child,
+            receiver,
+        })
+    }
+    pub fn receive(&mut self) -> bool {
+        // Take in what the shell wrote since last time, returning whether there was anything
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(data) => {
+                    self.screen.feed(&data);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.alive = false;
+                    break;
+                }
+            }
+        }
+        let replies = self.screen.take_replies();
+        if !replies.is_empty() {
+            self.send(&replies);
+        }
+        changed
+    }
+    pub fn key(&mut self, key: KeyBinding) {
+        // Pass a key on to the shell
+        if let Some(bytes) = self.screen.encode(key) {
+            self.send(&bytes);
+        }
+    }
+    pub fn resize(&mut self, width: usize, height: usize) {
+        // Change the size of the pseudo terminal, letting the shell know
+        if (width.max(1), height.max(1)) == (self.screen.width, self.screen.height) {
+            return;
+        }
+        self.screen.resize(width, height);
+        let size = window(width, height);
+        unsafe {
+            libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size);
+        }
+    }
+    fn send(&mut self, bytes: &[u8]) {
+        // Write to the shell, which has stopped when that fails
+        self.alive &= self.master.write_all(bytes).is_ok();
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Console {
+    fn drop(&mut self) {
+        // Stop the shell along with the pane
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(unix)]
+fn window(width: usize, height: usize) -> libc::winsize {
+    // The size of a pseudo terminal in the form the system takes it
+    libc::winsize {
+        ws_row: height.max(1) as u16,
+        ws_col: width.max(1) as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+#[cfg(not(unix))]
+pub struct Console {
+    pub screen: Screen,
+    pub focus: bool,
+    pub alive: bool,
+}
+
+#[cfg(not(unix))]
+impl Console {
+    pub fn open(_: usize, _: usize) -> io::Result<Self> {
+        // Pseudo terminals like these are only found on Unix systems
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Running a shell in a pane needs a Unix system",
+        ))
+    }
+    pub fn receive(&mut self) -> bool {
+        false
+    }
+    pub fn key(&mut self, _: KeyBinding) {}
+    pub fn resize(&mut self, _: usize, _: usize) {}
+}
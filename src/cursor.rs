@@ -0,0 +1,202 @@
+// Cursor.rs - Positions, sizes and the ways the cursor moves, which the documents share
+// with the editor without needing the terminal
+// The motions work out where the cursor goes from the lines alone, leaving scrolling to
+// whatever shows them
+use crate::util::{word_end, word_start};
+use crate::Row;
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+// The lines at the top of the screen before the documents start
+pub const OFFSET: usize = 1;
+
+// For representing positions
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
+}
+
+// Enum for direction
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// The jumps the cursor makes to the ends of a line or by a screen
+#[derive(Clone, Copy, Debug)]
+pub enum Leap {
+    Home,
+    End,
+    PageUp,
+    PageDown,
+}
+
+// Struct to hold size
+#[derive(Clone, Copy)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+pub fn indentation_of(row: &Row) -> Option<usize> {
+    // How far a line is indented, or nothing for a blank line
+    let text = row.string.trim_start();
+    (!text.is_empty()).then_some(row.string.len() - text.len())
+}
+
+pub fn left(rows: &[Row], pos: Position, wrap: bool) -> Position {
+    // One character to the left, onto the end of the line above when wrapping at the start
+    if pos.x == 0 {
+        return match pos.y.checked_sub(1).filter(|_| wrap) {
+            Some(y) => end(rows, y),
+            None => pos,
+        };
+    }
+    let jump = rows[pos.y]
+        .ext_chars()
+        .get(pos.x - 1)
+        .map_or(1, |chr| UnicodeWidthStr::width(*chr));
+    Position {
+        x: pos.x.saturating_sub(jump),
+        y: pos.y,
+    }
+}
+
+pub fn right(rows: &[Row], pos: Position, wrap: bool) -> Position {
+    // One character to the right, onto the start of the line below when wrapping at the end
+    let row = &rows[pos.y];
+    if pos.x >= row.length() {
+        return if wrap && pos.y + 1 < rows.len() {
+            Position { x: 0, y: pos.y + 1 }
+        } else {
+            pos
+        };
+    }
+    let jump = row
+        .ext_chars()
+        .get(pos.x)
+        .map_or(1, |chr| UnicodeWidthStr::width(*chr));
+    Position {
+        x: pos.x + jump,
+        y: pos.y,
+    }
+}
+
+pub fn home(rows: &[Row], pos: Position) -> Position {
+    // Where the text of the line starts, or the very start when the cursor is there already
+    let line = &rows[pos.y].string;
+    let indent = line[..line.len() - line.trim_start().len()].width();
+    Position {
+        x: if pos.x == indent { 0 } else { indent },
+        y: pos.y,
+    }
+}
+
+pub fn end(rows: &[Row], y: usize) -> Position {
+    // The end of a line
+    Position {
+        x: rows[y].length(),
+        y,
+    }
+}
+
+pub fn word_before(rows: &[Row], pos: Position) -> Position {
+    // Where the word before a position starts, or the end of the line above at the start
+    if pos.x == 0 {
+        return match pos.y.checked_sub(1) {
+            Some(y) => end(rows, y),
+            None => pos,
+        };
+    }
+    let row = &rows[pos.y];
+    let boundaries = row.boundaries();
+    let index = boundaries.iter().take_while(|&&x| x < pos.x).count();
+    let start = word_start(&row.chars(), index);
+    Position {
+        x: boundaries
+            .get(start)
+            .copied()
+            .unwrap_or_else(|| row.length()),
+        y: pos.y,
+    }
+}
+
+pub fn word_after(rows: &[Row], pos: Position) -> Position {
+    // Where the word after a position ends, or the start of the line below at the end
+    let row = &rows[pos.y];
+    if pos.x >= row.length() {
+        return if pos.y + 1 < rows.len() {
+            Position { x: 0, y: pos.y + 1 }
+        } else {
+            pos
+        };
+    }
+    let boundaries = row.boundaries();
+    let index = boundaries.iter().take_while(|&&x| x < pos.x).count();
+    let end = word_end(&row.chars(), index);
+    Position {
+        x: boundaries.get(end).copied().unwrap_or_else(|| row.length()),
+        y: pos.y,
+    }
+}
+
+pub fn paragraph(rows: &[Row], y: usize, down: bool) -> Position {
+    // The blank line before or after the paragraph a line is in, as vim's { and } go to,
+    // or the first or last line when there isn't one
+    let blank = |line: &usize| indentation_of(&rows[*line]).is_none();
+    let last = rows.len().saturating_sub(1);
+    let target = if down {
+        (y..rows.len())
+            .skip_while(blank)
+            .find(blank)
+            .unwrap_or(last)
+    } else {
+        (0..=y).rev().skip_while(blank).find(blank).unwrap_or(0)
+    };
+    if down && !blank(&target) {
+        end(rows, target)
+    } else {
+        Position { x: 0, y: target }
+    }
+}
+
+pub fn block_edge(rows: &[Row], y: usize, end: bool) -> Option<Position> {
+    // The first or last line indented at least as far as a line, and from there the line the
+    // block hangs off or the one closing it, with nothing when there is only blank lines
+    // Blank lines go with the line of text below them
+    let depth = (y..rows.len()).find_map(|line| indentation_of(&rows[line]))?;
+    let inside = |line: usize| indentation_of(&rows[line]).is_none_or(|indent| indent >= depth);
+    let mut target = y;
+    if end {
+        while target + 1 < rows.len() && inside(target + 1) {
+            target += 1;
+        }
+    } else {
+        while target > 0 && inside(target - 1) {
+            target -= 1;
+        }
+    }
+    // The blank lines around the block aren't part of it
+    while target != y && indentation_of(&rows[target]).is_none() {
+        target = if end { target - 1 } else { target + 1 };
+    }
+    if target == y {
+        // Already at the edge, so go to the next line of text that is indented less
+        let outer =
+            |line: &usize| indentation_of(&rows[*line]).is_some_and(|indent| indent < depth);
+        target = if end {
+            (y + 1..rows.len()).find(outer)
+        } else {
+            (0..y).rev().find(outer)
+        }
+        .unwrap_or(y);
+    }
+    Some(Position {
+        x: indentation_of(&rows[target]).unwrap_or(0),
+        y: target,
+    })
+}
@@ -1,24 +1,52 @@
 // Document.rs - For managing external files
+use crate::compress::Compression;
 use crate::config::{Reader, Status, TokenType};
-use crate::editor::OFFSET;
-use crate::util::{line_offset, spaces_to_tabs, tabs_to_spaces};
-use crate::{log, Editor, Event, EventStack, Position, Row, Size, Variable, VERSION};
-use crossterm::event::KeyCode as Key;
+use crate::cursor::{self, indentation_of, Leap, OFFSET};
+use crate::editorconfig::{self, Settings};
+use crate::encoding::Encoding;
+use crate::filetype;
+use crate::git::{Branch, Change};
+use crate::lazy::{self, Lazy, WINDOW};
+use crate::lsp::Diagnostic;
+use crate::modeline;
+use crate::ssh;
+use crate::undo::will_edit;
+use crate::util::{fnv1a, line_column, line_offset, spaces_to_tabs, tabs_to_spaces, text_between};
+use crate::{log, Direction, Event, EventStack, Position, Row, Size, Variable, VERSION};
 use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::fs::OpenOptions;
-use std::io::Write;
-use std::path::Path;
-use std::{cmp, fs};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+use std::{cmp, fs, iter};
 use unicode_width::UnicodeWidthStr;
 
+// How many lines either way to look for a matching bracket
+const BRACKET_RANGE: usize = 1000;
+
+// How many jumps each document remembers
+const JUMPS: usize = 100;
+
+// How many messages each document remembers
+const MESSAGES: usize = 200;
+
+// How long a message keeps less serious ones waiting
+const HOLD: Duration = Duration::from_secs(2);
+
 // For holding the info in the command line
+#[derive(Clone)]
 pub struct CommandLine {
     pub msg: Type,
     pub text: String,
+    pub posted: Option<Instant>, // When a message was put up, which prompts and questions aren't
 }
 
-// Enum for the kinds of status messages
+// Enum for the kinds of status messages, from the most serious down
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Type {
     Error,
     Warning,
@@ -33,26 +61,57 @@ pub enum TabType {
 }
 
 // Document struct (class) to manage files and text
+#[allow(clippy::struct_excessive_bools)] // Independent per-document flags
 pub struct Document {
-    pub rows: Vec<Row>,         // For holding the contents of the document
-    pub path: String,           // For holding the path to the document
-    pub name: String,           // For holding the name of the document
-    pub dirty: bool,            // True if the current document has been edited
-    pub cmd_line: CommandLine,  // For holding the command line
-    pub line_offset: usize,     // For holding a line number offset
-    pub undo_stack: EventStack, // For holding the undo event stack
-    pub redo_stack: EventStack, // For holding the redo event stack
-    pub regex: Vec<TokenType>,  // For holding regular expressions
-    pub icon: String,           // For holding the icon of the document
-    pub kind: String,           // For holding the icon of the document
-    pub show_welcome: bool,     // Whether to show welcome in the document
-    pub cursor: Position,       // For holding the raw cursor location
-    pub offset: Position,       // For holding the offset on the X and Y axes
-    pub graphemes: usize,       // For holding the special grapheme cursor
-    pub tabs: TabType,          // For detecting if tabs are used over spaces
-    pub last_save_index: usize, // For holding the last save index
-    pub true_path: String,      // For holding the path that was provided as argument
-    pub read_only: bool,        // Boolean to determine if the document is read only
+    pub rows: Vec<Row>,                 // For holding the contents of the document
+    pub path: String,                   // For holding the path to the document
+    pub name: String,                   // For holding the name of the document
+    pub dirty: bool,                    // True if the current document has been edited
+    pub cmd_line: CommandLine,          // For holding the command line
+    pub line_offset: usize,             // For holding a line number offset
+    pub undo_stack: EventStack,         // For holding the undo event stack
+    pub regex: Vec<TokenType>,          // For holding regular expressions
+    pub icon: String,                   // For holding the icon of the document
+    pub kind: String,                   // For holding the icon of the document
+    pub filetype: String,               // The type of file, named by its usual extension
+    pub show_welcome: bool,             // Whether to show welcome in the document
+    pub cursor: Position,               // For holding the raw cursor location
+    pub offset: Position,               // For holding the offset on the X and Y axes
+    pub graphemes: usize,               // For holding the special grapheme cursor
+    pub tabs: TabType,                  // For detecting if tabs are used over spaces
+    pub last_save_index: usize,         // For holding the last save index
+    pub true_path: String,              // For holding the path that was provided as argument
+    pub read_only: bool,                // Boolean to determine if the document is read only
+    pub soft_wrap: bool,                // Whether long lines wrap onto the next screen row
+    pub scroll_off: usize,              // Lines to keep in view above and below the cursor
+    pub tab_width: usize,               // Columns a tab and a level of indentation take up
+    pub trim_whitespace: bool,          // Whether spaces at the ends of lines go on save
+    pub final_newline: bool,            // Whether saving adds a newline at the end
+    pub trailing_newline: bool,         // Whether the file ends with a newline
+    pub peek: Option<usize>,            // Top line of the view when scrolled away from the cursor
+    pub anchor: Option<Position>,       // Where the selection started, if there is one
+    pub cursors: Vec<Position>,         // Extra cursors that typing is repeated at
+    pub block: Option<Position>,        // The corner a block selection of columns started at
+    pub marks: HashMap<char, Position>, // Places named by a letter to come back to
+    pub jumps: Vec<Position>,           // Where large jumps were made from, oldest first
+    pub jump_index: usize,              // Which jump the cursor went back to, or how many there are
+    pub folds: Vec<(usize, usize)>, // Lines folded away under the one before, by that line and the last
+    pub swapped: bool,              // Whether a swap file holds the unsaved changes
+    pub crlf: bool,                 // Whether lines end with \r\n rather than \n
+    pub encoding: Encoding,         // The character encoding the file is saved in
+    pub edits: usize,               // How many changes there have been, for language servers
+    pub diagnostics: Vec<Diagnostic>, // Problems language servers found in the text
+    pub git_base: Option<Vec<String>>, // The lines git has for the file, if it tracks it
+    pub git_changes: HashMap<usize, Change>, // How each row differs from those lines
+    pub git_edits: Option<usize>,   // The edits the changes were worked out at, if they were
+    pub git_branch: Option<Branch>, // The branch of the repository the file is in, if it is
+    pub modified: Option<SystemTime>, // When the file was last changed, as of opening or saving it
+    pub kept: Option<SystemTime>,   // A newer version on disk that the text here was kept over
+    pub fingerprint: Option<u64>,   // A hash of the text on disk, when the history reaches it
+    pub pending: VecDeque<CommandLine>, // Messages waiting for a more serious one to go
+    pub messages: VecDeque<CommandLine>, // The messages shown so far, oldest first
+    pub lazy: Option<Lazy>, // The file a window of lines is held of, when it is too large to read in
+    pub compression: Option<Compression>, // How the file is compressed on disk, if it is
 }
 
 // Add methods to the document struct
@@ -63,142 +122,629 @@ impl Document {
             rows: vec![Row::from("")],
             name: String::from("[No name]"),
             dirty: false,
-            cmd_line: Document::config_to_commandline(&status),
+            cmd_line: Document::config_to_commandline(status),
             path: String::new(),
             line_offset: config.general.line_number_padding_right
                 + config.general.line_number_padding_left,
             undo_stack: EventStack::new(),
-            redo_stack: EventStack::new(),
-            regex: Reader::get_syntax_regex(&config, ""),
+            regex: Reader::get_syntax_regex(config, ""),
             icon: String::new(),
             kind: String::new(),
+            filetype: String::new(),
             show_welcome: true,
             graphemes: 0,
             cursor: Position { x: 0, y: OFFSET },
             offset: Position { x: 0, y: 0 },
-            tabs: TabType::Spaces,
+            tabs: Document::tab_type("", config),
             last_save_index: 0,
             true_path: String::new(),
             read_only,
+            soft_wrap: config.general.soft_wrap,
+            scroll_off: config.general.scroll_off,
+            tab_width: config.general.tab_width,
+            trim_whitespace: config.general.trim_trailing_whitespace,
+            final_newline: config.general.ensure_final_newline,
+            trailing_newline: false,
+            peek: None,
+            anchor: None,
+            cursors: vec![],
+            block: None,
+            marks: HashMap::new(),
+            jumps: vec![],
+            jump_index: 0,
+            folds: vec![],
+            swapped: false,
+            crlf: false,
+            encoding: Encoding::Utf8,
+            edits: 0,
+            diagnostics: vec![],
+            git_base: None,
+            git_changes: HashMap::new(),
+            git_branch: None,
+            git_edits: None,
+            modified: None,
+            fingerprint: None,
+            kept: None,
+            pending: VecDeque::new(),
+            messages: VecDeque::new(),
+            lazy: None,
+            compression: None,
+        }
+    }
+    fn file_path(path: &str) -> &str {
+        // The file a path names, leaving off the line and column after it
+        // Files on other machines keep the machine before the colon
+        if ssh::parse(path).is_some() {
+            path
+        } else {
+            path.split(':').next().unwrap()
+        }
+    }
+    pub fn from_text(config: &Reader, status: &Status, bytes: &[u8], read_only: bool) -> Self {
+        // Create an unnamed document holding some text, such as what was piped into Ox
+        let mut doc = Document::new(config, status, read_only);
+        doc.encoding = Encoding::detect(bytes).unwrap_or(Encoding::Utf8);
+        let text = doc.encoding.decode(bytes);
+        doc.tabs = Document::tab_type(&text, config);
+        doc.trailing_newline = text.ends_with('\n');
+        doc.crlf = text.matches("\r\n").count() * 2 > text.matches('\n').count();
+        let text = tabs_to_spaces(&text, config.general.tab_width);
+        let mut lines = Document::split_file(&text);
+        if lines.len() > 1 && lines.last() == Some(&"") {
+            lines.pop();
         }
+        doc.rows = lines.into_iter().map(Row::from).collect();
+        // The text only exists in the editor, so leaving without saving it is checked
+        doc.show_welcome = false;
+        doc.dirty = !text.is_empty();
+        doc
     }
-    pub fn open(config: &Reader, status: &Status, path: &str, read_only: bool) -> Option<Self> {
+    pub fn open(
+        config: &Reader,
+        status: &Status,
+        path: &str,
+        read_only: bool,
+    ) -> std::io::Result<Self> {
         // Create a new document from a path
-        let true_path = path.to_string();
-        let path = path.split(':').next().unwrap();
-        if let Ok(file) = fs::read_to_string(path) {
-            // File exists
-            let tabs = file.contains("\n\t");
-            let file = tabs_to_spaces(&file, config.general.tab_width);
-            let mut file = Document::split_file(&file);
-            // Handle newline on last line
-            if let Some(line) = file.iter().last() {
-                if line.is_empty() {
-                    let _ = file.pop();
-                }
-            }
-            // Handle empty document by automatically inserting a row
-            if file.is_empty() {
-                file.push("");
-            }
-            let ext = path.split('.').last().unwrap_or(&"");
-            Some(Self {
-                rows: file.iter().map(|row| Row::from(*row)).collect(),
-                name: Path::new(path)
-                    .file_name()
-                    .unwrap_or_else(|| OsStr::new(path))
-                    .to_str()
-                    .unwrap_or(&path)
-                    .to_string(),
-                dirty: false,
-                cmd_line: Document::config_to_commandline(&status),
-                path: path.to_string(),
-                line_offset: config.general.line_number_padding_right
-                    + config.general.line_number_padding_left,
-                undo_stack: EventStack::new(),
-                redo_stack: EventStack::new(),
-                regex: Reader::get_syntax_regex(&config, ext),
-                kind: Self::identify(path).0.to_string(),
-                icon: Self::identify(path).1.to_string(),
-                show_welcome: false,
-                graphemes: 0,
-                cursor: Position { x: 0, y: OFFSET },
-                offset: Position { x: 0, y: 0 },
-                tabs: if tabs { TabType::Tabs } else { TabType::Spaces },
-                last_save_index: 0,
-                true_path,
-                read_only,
-            })
+        if let Some((host, file)) = ssh::parse(path) {
+            // Files on other machines are read through ssh, and saved back the same way
+            let bytes = ssh::fetch(host, file)?;
+            let encoding = Encoding::detect(&bytes)
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Not a text file"))?;
+            let text = encoding.decode(&bytes);
+            return Ok(Document::build(
+                config, status, path, read_only, encoding, &text,
+            ));
+        }
+        let name = path.split(':').next().unwrap();
+        // Compressed files can't be read from the middle, so they are read in whole
+        let sample = if lazy::is_large(name, config.general.large_file_size) {
+            Some(lazy::sample(name)?).filter(|sample| Compression::detect(name, sample).is_none())
         } else {
-            // File doesn't exist
             None
+        };
+        if let Some(sample) = sample {
+            // Only UTF-8 can be read from anywhere in it, so other text is read in whole
+            match Encoding::detect(&sample) {
+                None => return Err(io::Error::new(ErrorKind::InvalidData, "Not a text file")),
+                Some(Encoding::Utf8) => {
+                    return Document::open_lazy(config, status, path, read_only)
+                }
+                Some(_) => (),
+            }
         }
+        Document::open_whole(config, status, path, read_only)
+    }
+    fn open_whole(
+        config: &Reader,
+        status: &Status,
+        path: &str,
+        read_only: bool,
+    ) -> std::io::Result<Self> {
+        // Create a new document from all of a file
+        let (encoding, file, compression) = Document::read(path.split(':').next().unwrap())?;
+        let mut doc = Document::build(config, status, path, read_only, encoding, &file);
+        doc.compression = compression;
+        Ok(doc)
+    }
+    fn open_lazy(
+        config: &Reader,
+        status: &Status,
+        path: &str,
+        read_only: bool,
+    ) -> std::io::Result<Self> {
+        // Create a new document from the start of a file, leaving the rest until it comes into view
+        let mut lazy = Lazy::new(path.split(':').next().unwrap())?;
+        let lines = lazy.read(0)?;
+        let mut doc = Document::build(
+            config,
+            status,
+            path,
+            read_only,
+            Encoding::Utf8,
+            &lines.join("\n"),
+        );
+        doc.trailing_newline = true;
+        doc.lazy = Some(lazy);
+        Ok(doc)
+    }
+    fn build(
+        config: &Reader,
+        status: &Status,
+        path: &str,
+        read_only: bool,
+        encoding: Encoding,
+        file: &str,
+    ) -> Self {
+        // Create a new document from the text of a file
+        let true_path = path.to_string();
+        let path = Document::file_path(path);
+        let tabs = Document::tab_type(file, config);
+        let trailing_newline = file.ends_with('\n');
+        // Keep whichever line ending most of the lines use
+        let crlf = file.matches("\r\n").count() * 2 > file.matches('\n').count();
+        // Compressed files are the type of the file inside them
+        let inside = Compression::strip(path);
+        let settings = Document::file_settings(config, inside, file);
+        let tab_width = settings.width().unwrap_or(config.general.tab_width);
+        let file = tabs_to_spaces(file, tab_width);
+        let mut file = Document::split_file(&file);
+        // Handle newline on last line
+        if let Some(line) = file.iter().last() {
+            if line.is_empty() {
+                let _ = file.pop();
+            }
+        }
+        // Handle empty document by automatically inserting a row
+        if file.is_empty() {
+            file.push("");
+        }
+        let ext = inside.split('.').next_back().unwrap_or("");
+        let mut doc = Self {
+            rows: file.iter().map(|row| Row::from(*row)).collect(),
+            name: Path::new(path)
+                .file_name()
+                .unwrap_or_else(|| OsStr::new(path))
+                .to_str()
+                .unwrap_or(path)
+                .to_string(),
+            dirty: false,
+            cmd_line: Document::config_to_commandline(status),
+            path: path.to_string(),
+            line_offset: config.general.line_number_padding_right
+                + config.general.line_number_padding_left,
+            undo_stack: EventStack::new(),
+            regex: Reader::get_syntax_regex(config, ext),
+            kind: Self::identify(inside).0.to_string(),
+            icon: Self::identify(inside).1.to_string(),
+            filetype: ext.to_string(),
+            show_welcome: false,
+            graphemes: 0,
+            cursor: Position { x: 0, y: OFFSET },
+            offset: Position { x: 0, y: 0 },
+            tabs,
+            last_save_index: 0,
+            true_path,
+            read_only,
+            soft_wrap: config.general.soft_wrap,
+            scroll_off: config.general.scroll_off,
+            tab_width: config.general.tab_width,
+            trim_whitespace: config.general.trim_trailing_whitespace,
+            final_newline: config.general.ensure_final_newline,
+            trailing_newline,
+            peek: None,
+            anchor: None,
+            cursors: vec![],
+            block: None,
+            marks: HashMap::new(),
+            jumps: vec![],
+            jump_index: 0,
+            folds: vec![],
+            swapped: false,
+            crlf,
+            encoding,
+            edits: 0,
+            diagnostics: vec![],
+            git_base: None,
+            git_changes: HashMap::new(),
+            git_branch: None,
+            git_edits: None,
+            modified: Document::disk_time(path),
+            kept: None,
+            fingerprint: None,
+            pending: VecDeque::new(),
+            messages: VecDeque::new(),
+            lazy: None,
+            compression: None,
+        };
+        doc.fingerprint = Some(doc.hash());
+        doc.configure(config, &settings);
+        doc
     }
     pub fn from(config: &Reader, status: &Status, path: &str, read_only: bool) -> Self {
         // Create a new document from a path with empty document on error
         let true_path = path.to_string();
-        let path = path.split(':').next().unwrap();
-        if let Some(doc) = Document::open(&config, &status, &true_path, read_only) {
-            log!("Opening file", "File was found");
-            doc
+        let path = Document::file_path(path);
+        match Document::open(config, status, &true_path, read_only) {
+            Ok(doc) => {
+                log!("Opening file", "File was found");
+                doc
+            }
+            Err(error) if error.kind() != ErrorKind::NotFound => {
+                // File exists but can't be read, don't let a save overwrite it
+                log!(
+                    error,
+                    "Opening file",
+                    format!("File couldn't be read: {}", error)
+                );
+                let mut doc = Document::new(config, status, read_only);
+                doc.show_welcome = false;
+                doc.set_command_line(format!("Couldn't open {path}: {error}"), Type::Error);
+                doc
+            }
+            Err(_) => {
+                // Create blank document
+                log!("Opening file", "File not found");
+                let ext = path.split('.').next_back().unwrap_or("");
+                let mut doc = Self {
+                    rows: vec![Row::from("")],
+                    name: path.to_string(),
+                    path: path.to_string(),
+                    dirty: false,
+                    cmd_line: Document::config_to_commandline(status),
+                    line_offset: config.general.line_number_padding_right
+                        + config.general.line_number_padding_left,
+                    undo_stack: EventStack::new(),
+                    regex: Reader::get_syntax_regex(config, ext),
+                    kind: Self::identify(path).0.to_string(),
+                    icon: Self::identify(path).1.to_string(),
+                    filetype: ext.to_string(),
+                    show_welcome: false,
+                    graphemes: 0,
+                    cursor: Position { x: 0, y: OFFSET },
+                    offset: Position { x: 0, y: 0 },
+                    tabs: Document::tab_type("", config),
+                    last_save_index: 0,
+                    true_path,
+                    read_only,
+                    soft_wrap: config.general.soft_wrap,
+                    scroll_off: config.general.scroll_off,
+                    tab_width: config.general.tab_width,
+                    trim_whitespace: config.general.trim_trailing_whitespace,
+                    final_newline: config.general.ensure_final_newline,
+                    trailing_newline: false,
+                    peek: None,
+                    anchor: None,
+                    cursors: vec![],
+                    block: None,
+                    marks: HashMap::new(),
+                    jumps: vec![],
+                    jump_index: 0,
+                    folds: vec![],
+                    swapped: false,
+                    crlf: false,
+                    encoding: Encoding::Utf8,
+                    edits: 0,
+                    diagnostics: vec![],
+                    git_base: None,
+                    git_changes: HashMap::new(),
+                    git_branch: None,
+                    git_edits: None,
+                    modified: None,
+                    fingerprint: None,
+                    kept: None,
+                    pending: VecDeque::new(),
+                    messages: VecDeque::new(),
+                    lazy: None,
+                    compression: Compression::from_name(path),
+                };
+                doc.configure(config, &Document::file_settings(config, path, ""));
+                doc
+            }
+        }
+    }
+    fn file_settings(config: &Reader, path: &str, text: &str) -> Settings {
+        // What the config says about the type of a file, then its .editorconfig files and modelines
+        let lines = Document::split_file(text);
+        let mut modelines = Settings::default();
+        modeline::apply(&mut modelines, &lines);
+        let filetype = match &modelines.filetype {
+            Some(name) => filetype::from_name(config, name),
+            None => filetype::detect(path, lines.first().unwrap_or(&"")),
+        };
+        let found = if path.is_empty() {
+            Settings::default()
         } else {
-            // Create blank document
-            log!("Opening file", "File not found");
-            let ext = path.split('.').last().unwrap_or(&"");
-            Self {
-                rows: vec![Row::from("")],
-                name: path.to_string(),
-                path: path.to_string(),
-                dirty: false,
-                cmd_line: Document::config_to_commandline(&status),
-                line_offset: config.general.line_number_padding_right
-                    + config.general.line_number_padding_left,
-                undo_stack: EventStack::new(),
-                redo_stack: EventStack::new(),
-                regex: Reader::get_syntax_regex(&config, ext),
-                kind: Self::identify(path).0.to_string(),
-                icon: Self::identify(path).1.to_string(),
-                show_welcome: false,
-                graphemes: 0,
-                cursor: Position { x: 0, y: OFFSET },
-                offset: Position { x: 0, y: 0 },
-                tabs: TabType::Spaces,
-                last_save_index: 0,
-                true_path,
-                read_only,
+            editorconfig::settings(Path::new(path))
+        };
+        let mut settings = modelines.or(found.or(filetype::settings(config, &filetype)));
+        settings.filetype = Some(filetype);
+        settings
+    }
+    pub fn settings(&self, config: &Reader) -> Settings {
+        // What the config, .editorconfig files and modelines say about the document as it is now
+        let text: Vec<&str> = self.rows.iter().map(|row| row.string.as_str()).collect();
+        Document::file_settings(config, &self.path, &text.join("\n"))
+    }
+    pub fn retype(&mut self, config: &Reader, filetype: String) {
+        // Treat the document as a different type of file
+        self.regex = Reader::get_syntax_regex(config, &filetype);
+        self.kind = Document::identify(&filetype).0.to_string();
+        self.icon = Document::identify(&filetype).1.to_string();
+        self.filetype = filetype;
+    }
+    pub fn configure(&mut self, config: &Reader, settings: &Settings) {
+        // Take the settings that can differ between documents from the config, then the file
+        self.tab_width = settings.width().unwrap_or(config.general.tab_width);
+        self.trim_whitespace = settings
+            .trim_trailing_whitespace
+            .unwrap_or(config.general.trim_trailing_whitespace);
+        self.final_newline = settings
+            .insert_final_newline
+            .unwrap_or(config.general.ensure_final_newline);
+        if let Some(tabs) = settings.tabs {
+            self.tabs = if tabs { TabType::Tabs } else { TabType::Spaces };
+        }
+        if let Some(crlf) = settings.crlf {
+            self.crlf = crlf;
+        }
+        if let Some(wrap) = settings.soft_wrap {
+            self.soft_wrap = wrap;
+        }
+        // Highlighting, comments and indentation all go by the type of file
+        let filetype = settings.filetype.clone().unwrap_or_else(|| {
+            let first = self.rows.first().map_or("", |row| row.string.as_str());
+            filetype::detect(&self.path, first)
+        });
+        if filetype != self.filetype {
+            self.retype(config, filetype);
+        }
+        // Text that was read in already only changes encoding when it reads the same in both
+        if let Some(charset) = settings.charset {
+            if self.encoding == Encoding::Utf8 && self.rows.iter().all(|row| row.string.is_ascii())
+            {
+                self.encoding = charset;
             }
         }
     }
+    fn read(path: &str) -> io::Result<(Encoding, String, Option<Compression>)> {
+        // Read a file in, working out how it is compressed and how its text is encoded
+        let mut bytes = fs::read(path)?;
+        let compression = Compression::detect(path, &bytes);
+        if let (Some(compression), false) = (compression, bytes.is_empty()) {
+            bytes = compression.decompress(&bytes)?;
+        }
+        let encoding = Encoding::detect(&bytes)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Not a text file"))?;
+        let file = encoding.decode(&bytes);
+        Ok((encoding, file, compression))
+    }
+    fn disk_time(path: &str) -> Option<SystemTime> {
+        // When a file was last changed on disk
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+    pub fn changed_on_disk(&self) -> Option<SystemTime> {
+        // When another program changed the file, if it has since it was opened or saved
+        if self.path.is_empty() {
+            return None;
+        }
+        Document::disk_time(&self.path).filter(|time| Some(*time) != self.modified)
+    }
+    pub fn reload(&mut self, config: &Reader) -> io::Result<()> {
+        // Replace the contents with what the file has on disk now, dropping any changes
+        if let Some(start) = self.lazy.as_ref().map(|lazy| lazy.start) {
+            // Large files read the lines held again, finding where lines start from scratch
+            let mut lazy = Lazy::new(&self.path)?;
+            lazy.start = start;
+            self.lazy = Some(lazy);
+            self.move_window(start);
+            self.edits += 1;
+            self.modified = Document::disk_time(&self.path);
+            self.kept = None;
+            return Ok(());
+        }
+        let (encoding, file, compression) = Document::read(&self.path)?;
+        self.compression = compression;
+        self.tabs = Document::tab_type(&file, config);
+        self.trailing_newline = file.ends_with('\n');
+        self.crlf = file.matches("\r\n").count() * 2 > file.matches('\n').count();
+        self.encoding = encoding;
+        let settings = Document::file_settings(config, &self.path, &file);
+        self.tab_width = settings.width().unwrap_or(config.general.tab_width);
+        let file = tabs_to_spaces(&file, self.tab_width);
+        let mut lines = Document::split_file(&file);
+        if lines.len() > 1 && lines.last() == Some(&"") {
+            lines.pop();
+        }
+        self.rows = lines.iter().map(|row| Row::from(*row)).collect();
+        self.undo_stack = EventStack::new();
+        self.last_save_index = self.undo_stack.state();
+        self.folds.clear();
+        self.cursors.clear();
+        self.anchor = None;
+        self.block = None;
+        self.dirty = false;
+        self.edits += 1;
+        self.git_edits = None;
+        self.modified = Document::disk_time(&self.path);
+        self.kept = None;
+        self.fingerprint = Some(self.hash());
+        self.remove_swap();
+        self.configure(config, &settings);
+        Ok(())
+    }
     pub fn split_file(contents: &str) -> Vec<&str> {
         // Detect DOS line ending
         let splitter = Regex::new("(?ms)(\r\n|\n)").unwrap();
         splitter.split(contents).collect()
     }
-    pub fn correct_path(&mut self, term: &Size) {
-        if self.true_path.contains(':') {
-            let split: Vec<&str> = self.true_path.split(':').collect();
-            let mut y = split.get(1).unwrap_or(&"").parse().unwrap_or(0);
-            let mut x = split.get(2).unwrap_or(&"").parse().unwrap_or(0);
-            if y >= self.rows.len() {
-                self.set_command_line(format!("Row {} out of scope", y), Type::Warning);
-                y = self.rows.len();
-            }
-            if x >= self.rows[y.saturating_sub(1)].length() {
-                self.set_command_line(format!("Column {} out of scope", x), Type::Warning);
-                x = self.rows[y.saturating_sub(1)].length();
+    pub fn matching_bracket(&self, pos: &Position) -> Option<(Position, Position)> {
+        // Pair up the bracket at a position with the one that opens or closes it
+        let bracket = self
+            .rows
+            .get(pos.y)?
+            .brackets()
+            .into_iter()
+            .find(|b| b.0 == pos.x)?
+            .1;
+        let (partner, forward) = match bracket {
+            '(' => (')', true),
+            '[' => (']', true),
+            '{' => ('}', true),
+            ')' => ('(', false),
+            ']' => ('[', false),
+            _ => ('{', false),
+        };
+        // Only look so far so that huge files stay quick to draw
+        let lines: Vec<usize> = if forward {
+            (pos.y..cmp::min(pos.y + BRACKET_RANGE, self.rows.len())).collect()
+        } else {
+            (pos.y.saturating_sub(BRACKET_RANGE)..=pos.y)
+                .rev()
+                .collect()
+        };
+        let mut depth = 0;
+        for y in lines {
+            let mut brackets = self.rows[y].brackets();
+            if !forward {
+                brackets.reverse();
             }
-            self.goto(
-                Position {
-                    x,
-                    y: y.saturating_sub(1),
-                },
-                term,
+            for (x, ch) in brackets {
+                if y == pos.y && (forward && x < pos.x || !forward && x > pos.x) {
+                    continue;
+                }
+                if ch == bracket {
+                    depth += 1;
+                } else if ch == partner {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((*pos, Position { x, y }));
+                    }
+                }
+            }
+        }
+        None
+    }
+    pub fn correct_path(&mut self, term: &Size) {
+        // Go to the line and column given after the file name, if any
+        let place = self
+            .true_path
+            .split_once(':')
+            .and_then(|(_, place)| line_column(place));
+        if let Some((line, column)) = place {
+            self.jump(line, column, term);
+        }
+    }
+    pub fn jump(&mut self, line: usize, column: Option<usize>, term: &Size) {
+        // Go to a line and column counted from 1, keeping the line in the middle of the view
+        let mut start = self.lazy.as_ref().map_or(0, |lazy| lazy.start);
+        let held = start..start + self.rows.len();
+        if self.lazy.is_some() && !held.contains(&line.saturating_sub(1)) {
+            // Large files read in the lines around it first
+            self.move_window(line.saturating_sub(WINDOW / 2));
+            start = self.lazy.as_ref().map_or(0, |lazy| lazy.start);
+        }
+        let line = line.saturating_sub(start);
+        let y = line.clamp(1, self.rows.len()) - 1;
+        let boundaries = self.rows[y].boundaries();
+        let column = column.unwrap_or(1);
+        let x = match column.checked_sub(1) {
+            Some(index) if index < boundaries.len() => boundaries[index],
+            _ => self.rows[y].length(),
+        };
+        if y + 1 != line || column == 0 || column > boundaries.len() + 1 {
+            self.set_command_line(
+                format!(
+                    "Out of range, went to {}:{}",
+                    y + 1 + start,
+                    column.clamp(1, boundaries.len() + 1)
+                ),
+                Type::Warning,
             );
         }
+        self.goto(Position { x, y }, term);
+        self.center(term);
+        self.recalculate_graphemes();
+    }
+    pub fn restore(&mut self, (x, y): (usize, usize), term: &Size) {
+        // Move to a remembered position, the file may have changed since
+        let y = cmp::min(y, self.rows.len().saturating_sub(1));
+        let x = cmp::min(x, self.rows[y].length());
+        self.goto(Position { x, y }, term);
+        self.recalculate_graphemes();
+        self.prevent_unicode_hell();
     }
     pub fn set_command_line(&mut self, text: String, msg: Type) {
-        // Function to update the command line
-        self.cmd_line = CommandLine { text, msg };
+        // Put up a message, which waits its turn if a more serious one is still showing
+        let line = CommandLine {
+            msg,
+            text,
+            posted: Some(Instant::now()),
+        };
+        if line.text.is_empty() {
+            // Clearing the line leaves serious messages up until they go
+            if self.cmd_line.posted.is_none() || self.cmd_line.msg == Type::Info {
+                self.cmd_line = line;
+            }
+            return;
+        }
+        if msg == Type::Error {
+            log!(error, "Message", line.text);
+        }
+        if self.messages.back().map(|last| (&last.text, last.msg)) != Some((&line.text, msg)) {
+            if self.messages.len() == MESSAGES {
+                self.messages.pop_front();
+            }
+            self.messages.push_back(line.clone());
+        }
+        let held = self
+            .cmd_line
+            .posted
+            .is_some_and(|posted| posted.elapsed() < HOLD);
+        if held && !self.cmd_line.text.is_empty() && msg > self.cmd_line.msg {
+            // Newer messages take the place of ones waiting that are no more serious
+            self.pending.retain(|waiting| waiting.msg < msg);
+            self.pending.push_back(line);
+        } else {
+            self.cmd_line = line;
+        }
+    }
+    pub fn set_prompt_line(&mut self, text: String, msg: Type) {
+        // Show a prompt or question, which stays up and isn't kept with the messages
+        self.cmd_line = CommandLine {
+            msg,
+            text,
+            posted: None,
+        };
+    }
+    pub fn message_left(&self, timeout: u64) -> Option<Duration> {
+        // How long the message showing has left, if it goes away at all
+        let posted = self.cmd_line.posted?;
+        let life = match (timeout, self.pending.is_empty()) {
+            (0, true) => return None,
+            (_, true) => Duration::from_secs(timeout),
+            // Messages waiting are put up as soon as the one showing stops holding them off
+            (0, false) => HOLD,
+            (_, false) => cmp::min(HOLD, Duration::from_secs(timeout)),
+        };
+        Some(life.saturating_sub(posted.elapsed()))
+    }
+    pub fn expire_message(&mut self, timeout: u64) -> bool {
+        // Take down a message that has had its time, putting up the next one waiting
+        if self.message_left(timeout) != Some(Duration::ZERO) || self.cmd_line.text.is_empty() {
+            return false;
+        }
+        self.cmd_line = if let Some(mut next) = self.pending.pop_front() {
+            next.posted = Some(Instant::now());
+            next
+        } else {
+            CommandLine {
+                msg: Type::Info,
+                text: String::new(),
+                posted: None,
+            }
+        };
+        true
     }
     pub fn mass_redraw(&mut self) {
         for i in &mut self.rows {
@@ -210,7 +756,7 @@ impl Document {
             text: match status {
                 Status::Success => "Welcome to Ox".to_string(),
                 Status::File => "Config file not found, using default values".to_string(),
-                Status::Parse(error) => format!("Failed to parse: {:?}", error),
+                Status::Parse(error) => format!("Failed to parse: {error:?}"),
                 Status::Empty => "Config file is empty, using defaults".to_string(),
             },
             msg: match status {
@@ -218,10 +764,17 @@ impl Document {
                 Status::File => Type::Warning,
                 Status::Parse(_) => Type::Error,
             },
+            posted: Some(Instant::now()),
         }
     }
     pub fn format(&self, template: &str) -> String {
         // Form data from a template
+        // Large files only know how many lines they have once the end has been read
+        let start = self.lazy.as_ref().map_or(0, |lazy| lazy.start);
+        let lines = match &self.lazy {
+            Some(lazy) if !lazy.end => "?".to_string(),
+            _ => (self.line_count() + start).to_string(),
+        };
         template
             .replace("%f", &self.name)
             .replace("%F", &self.path)
@@ -235,70 +788,85 @@ impl Document {
                 },
             )
             .replace("%n", &self.kind)
-            .replace(
-                "%l",
-                &format!("{}", self.cursor.y + self.offset.y.saturating_sub(OFFSET)),
-            )
-            .replace("%L", &format!("{}", self.rows.len()))
+            .replace("%l", &format!("{}", self.cursor.y + self.offset.y + start))
+            .replace("%L", &lines)
             .replace("%x", &format!("{}", self.cursor.x + self.offset.x))
-            .replace("%y", &format!("{}", self.cursor.y + self.offset.y))
+            .replace("%y", &format!("{}", self.cursor.y + self.offset.y + start))
             .replace("%v", VERSION)
             .replace("%d", if self.dirty { "[+]" } else { "" })
+            .replace("%R", if self.read_only { "[RO]" } else { "" })
+            .replace("%e", if self.crlf { "CRLF" } else { "LF" })
+            .replace("%c", self.encoding.name())
             .replace("%D", if self.dirty { "\u{fb12} " } else { "\u{f723} " })
+            .replace("%p", &format!("{}%", self.percentage()))
+            .replace(
+                "%g",
+                &self.git_branch.as_ref().map_or(String::new(), |branch| {
+                    format!(
+                        "\u{e0a0} {}{}",
+                        branch.name,
+                        if branch.dirty { "*" } else { "" }
+                    )
+                }),
+            )
+    }
+    fn percentage(&self) -> usize {
+        // How far through the document the cursor is, counting the last line as all the way
+        if let Some(lazy) = self.lazy.as_ref().filter(|lazy| !lazy.end) {
+            // Large files go by how far into the file the cursor's line starts
+            let y = self.cursor.y + self.offset.y - OFFSET;
+            let before: usize = self.rows[..y].iter().map(|row| row.string.len() + 1).sum();
+            let byte = lazy.byte + before as u64;
+            return usize::try_from(byte * 100 / lazy.length.max(1)).unwrap_or(100);
+        }
+        let start = self.lazy.as_ref().map_or(0, |lazy| lazy.start);
+        let line = self.cursor.y + self.offset.y + start;
+        let total = (self.line_count() + start).max(1);
+        line.min(total) * 100 / total
     }
-    pub fn move_cursor(&mut self, direction: Key, term: &Size, wrap: bool) {
+    pub fn move_cursor(&mut self, direction: Direction, term: &Size, wrap: bool) {
         // Move the cursor around the editor
         match direction {
-            Key::Down => {
-                // Move the cursor down
-                if self.cursor.y + self.offset.y + 1 - (OFFSET) < self.rows.len() {
+            Direction::Down | Direction::Up if self.soft_wrap => self.move_wrapped(direction, term),
+            Direction::Down => {
+                // Move the cursor down, over any folded lines
+                let y = self.cursor.y + self.offset.y - OFFSET;
+                if let Some(below) = self.shown_after(y) {
                     // If the proposed move is within the length of the document
-                    if self.cursor.y == term.height.saturating_sub(3) {
-                        self.offset.y = self.offset.y.saturating_add(1);
-                    } else {
-                        self.cursor.y = self.cursor.y.saturating_add(1);
-                    }
+                    self.scroll_to(below, term);
                     self.snap_cursor(term);
                     self.prevent_unicode_hell();
                     self.recalculate_graphemes();
                 }
             }
-            Key::Up => {
-                // Move the cursor up
-                if self.cursor.y - OFFSET == 0 {
-                    self.offset.y = self.offset.y.saturating_sub(1);
-                } else if self.cursor.y != OFFSET {
-                    self.cursor.y = self.cursor.y.saturating_sub(1);
-                }
+            Direction::Up => {
+                // Move the cursor up, over any folded lines
+                let y = self.cursor.y + self.offset.y - OFFSET;
+                self.scroll_to(self.shown_before(y).unwrap_or(y), term);
                 self.snap_cursor(term);
                 self.prevent_unicode_hell();
                 self.recalculate_graphemes();
             }
-            Key::Right => {
-                // Move the cursor right
-                let line = &self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-                // Check for line wrapping
-                if line.length() == self.cursor.x + self.offset.x
-                    && self.cursor.y + self.offset.y - OFFSET != self.rows.len().saturating_sub(1)
-                    && wrap
-                {
-                    self.move_cursor(Key::Down, term, wrap);
-                    self.leap_cursor(Key::Home, term);
+            Direction::Right => {
+                // Move the cursor right, onto the line below from the end when wrapping
+                let current = Position {
+                    x: self.cursor.x + self.offset.x,
+                    y: self.cursor.y + self.offset.y - OFFSET,
+                };
+                let target = cursor::right(&self.rows, current, wrap);
+                if target.y != current.y {
+                    self.move_cursor(Direction::Down, term, wrap);
+                    self.leap_cursor(Leap::Home, term);
                     return;
                 }
-                // Work out the width of the character to traverse
-                let mut jump = 1;
-                if let Some(chr) = line.ext_chars().get(self.cursor.x + self.offset.x) {
-                    jump = UnicodeWidthStr::width(*chr);
-                }
-                // Check the proposed move is within the current line length
-                if line.length() > self.cursor.x + self.offset.x {
+                let jump = target.x - current.x;
+                if jump > 0 {
                     // Check for normal width character
-                    let indicator1 =
-                        self.cursor.x == term.width.saturating_sub(self.line_offset + jump + 1);
+                    let indicator1 = self.cursor.x
+                        == self.width(term).saturating_sub(self.line_offset + jump + 1);
                     // Check for half broken unicode character
                     let indicator2 =
-                        self.cursor.x == term.width.saturating_sub(self.line_offset + jump);
+                        self.cursor.x == self.width(term).saturating_sub(self.line_offset + jump);
                     if indicator1 || indicator2 {
                         self.offset.x = self.offset.x.saturating_add(jump);
                     } else {
@@ -307,67 +875,115 @@ impl Document {
                     self.graphemes = self.graphemes.saturating_add(1);
                 }
             }
-            Key::Left => {
-                // Move the cursor left
-                let line = &self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-                if self.cursor.x + self.offset.x == 0
-                    && self.cursor.y + self.offset.y - OFFSET != 0
-                    && wrap
-                {
-                    self.move_cursor(Key::Up, term, wrap);
-                    self.leap_cursor(Key::End, term);
+            Direction::Left => {
+                // Move the cursor left, onto the line above from the start when wrapping
+                let current = Position {
+                    x: self.cursor.x + self.offset.x,
+                    y: self.cursor.y + self.offset.y - OFFSET,
+                };
+                let target = cursor::left(&self.rows, current, wrap);
+                if target.y != current.y {
+                    self.move_cursor(Direction::Up, term, wrap);
+                    self.leap_cursor(Leap::End, term);
                     return;
                 }
-                // Work out the width of the character to traverse
-                let mut jump = 1;
-                if let Some(chr) = line
-                    .ext_chars()
-                    .get((self.cursor.x + self.offset.x).saturating_sub(1))
-                {
-                    jump = UnicodeWidthStr::width(*chr);
-                }
-                if self.cursor.x == 0 {
-                    self.offset.x = self.offset.x.saturating_sub(jump);
-                } else {
-                    self.cursor.x = self.cursor.x.saturating_sub(jump);
+                let jump = current.x - target.x;
+                if jump > 0 {
+                    if self.cursor.x == 0 {
+                        self.offset.x = self.offset.x.saturating_sub(jump);
+                    } else {
+                        self.cursor.x = self.cursor.x.saturating_sub(jump);
+                    }
+                    self.graphemes = self.graphemes.saturating_sub(1);
                 }
-                self.graphemes = self.graphemes.saturating_sub(1);
             }
-            _ => (),
         }
     }
-    pub fn leap_cursor(&mut self, action: Key, term: &Size) {
+    fn move_wrapped(&mut self, direction: Direction, term: &Size) {
+        // Move the cursor up and down through screen rows of wrapped lines
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        let width = self.wrap_width(term);
+        let points = if let Some(row) = self.rows.get(y) {
+            row.wrap_points(width)
+        } else {
+            return;
+        };
+        let chunk = wrap_chunk(&points, self.cursor.x);
+        let column = self.cursor.x - points[chunk];
+        match (direction, self.shown_after(y), self.shown_before(y)) {
+            (Direction::Down, _, _) if chunk + 1 < points.len() => {
+                // Move to the next screen row of this line
+                let end = points.get(chunk + 2).map_or(usize::MAX, |p| p - 1);
+                self.cursor.x = cmp::min(points[chunk + 1] + column, end);
+                self.scroll_to(y, term);
+            }
+            (Direction::Down, Some(below), _) => {
+                // Move to the first screen row of the next line
+                self.cursor.x = column;
+                self.scroll_to(below, term);
+            }
+            (Direction::Up, _, _) if chunk > 0 => {
+                // Move to the previous screen row of this line
+                self.cursor.x = cmp::min(points[chunk - 1] + column, points[chunk] - 1);
+                self.scroll_to(y, term);
+            }
+            (Direction::Up, _, Some(above)) => {
+                // Move to the last screen row of the previous line
+                let points = self.rows[above].wrap_points(width);
+                self.cursor.x = points[points.len() - 1] + column;
+                self.scroll_to(above, term);
+            }
+            _ => return,
+        }
+        self.snap_cursor(term);
+        self.prevent_unicode_hell();
+        self.recalculate_graphemes();
+    }
+    pub fn leap_cursor(&mut self, action: Leap, term: &Size) {
         // Handle large cursor movements
         match action {
-            Key::PageUp => {
+            Leap::PageUp => {
                 // Move cursor to the top of the screen
-                self.cursor.y = OFFSET;
+                self.scroll_to(self.offset.y, term);
                 self.snap_cursor(term);
                 self.prevent_unicode_hell();
                 self.recalculate_graphemes();
             }
-            Key::PageDown => {
+            Leap::PageDown => {
                 // Move cursor to the bottom of the screen
-                self.cursor.y = cmp::min(
-                    self.rows.len().saturating_sub(1).saturating_add(OFFSET),
-                    term.height.saturating_sub(3) as usize,
-                );
+                let bottom = if self.folds.is_empty() {
+                    self.offset.y + term.height.saturating_sub(OFFSET + 3)
+                } else {
+                    self.layout(term).last().map_or(self.offset.y, |row| row.0)
+                };
+                self.scroll_to(cmp::min(bottom, self.rows.len().saturating_sub(1)), term);
                 self.snap_cursor(term);
                 self.prevent_unicode_hell();
                 self.recalculate_graphemes();
             }
-            Key::Home => {
-                // Move cursor to the start of the line
-                self.offset.x = 0;
-                self.cursor.x = 0;
-                self.graphemes = 0;
+            Leap::Home => {
+                // Move cursor to where the text of the line starts, or to the very start
+                // when it is there already
+                let current = Position {
+                    x: self.cursor.x + self.offset.x,
+                    y: self.cursor.y + self.offset.y - OFFSET,
+                };
+                let Position { x, y } = cursor::home(&self.rows, current);
+                if x == 0 {
+                    self.offset.x = 0;
+                    self.cursor.x = 0;
+                    self.graphemes = 0;
+                } else {
+                    self.goto(Position { x, y }, term);
+                    self.recalculate_graphemes();
+                }
             }
-            Key::End => {
+            Leap::End => {
                 // Move cursor to the end of the line
-                let cursor = self.cursor;
-                let offset = self.offset;
-                let line = self.rows[cursor.y + offset.y - OFFSET].clone();
-                if line.length() >= term.width.saturating_sub(self.line_offset) {
+                let width = self.width(term);
+                let line = &self.rows[self.cursor.y + self.offset.y - OFFSET];
+                // The last column is kept free for the cursor, as when moving right
+                if line.length() >= width.saturating_sub(self.line_offset + 1) {
                     // Work out the width of the character to traverse
                     let mut jump = 1;
                     if let Some(chr) = line.ext_chars().get(line.length()) {
@@ -376,23 +992,485 @@ impl Document {
                     self.offset.x = line
                         .length()
                         .saturating_add(jump + self.line_offset + 1)
-                        .saturating_sub(term.width as usize);
-                    self.cursor.x = term.width.saturating_sub(jump + self.line_offset + 1);
+                        .saturating_sub(width);
+                    self.cursor.x = width.saturating_sub(jump + self.line_offset + 1);
                 } else {
                     self.cursor.x = line.length();
                 }
                 self.graphemes = line.chars().len();
             }
-            _ => (),
         }
     }
+    fn width(&self, term: &Size) -> usize {
+        // Width to scroll horizontally within, wrapped lines never need to scroll
+        if self.soft_wrap {
+            usize::MAX / 2
+        } else {
+            term.width
+        }
+    }
+    fn wrap_width(&self, term: &Size) -> usize {
+        // Number of columns a wrapped line can occupy on each screen row
+        cmp::max(term.width.saturating_sub(self.line_offset), 1)
+    }
+    pub fn layout(&self, term: &Size) -> Vec<(usize, usize, usize, bool)> {
+        // Work out which part of which line goes on each screen row
+        let height = term.height.saturating_sub(OFFSET + 2);
+        if !self.soft_wrap {
+            return (self.top()..self.rows.len())
+                .filter(|&y| !self.hidden(y))
+                .take(height)
+                .map(|y| (y, self.offset.x, term.width, true))
+                .collect();
+        }
+        let width = self.wrap_width(term);
+        let mut layout = vec![];
+        for (y, row) in self.rows.iter().enumerate().skip(self.top()) {
+            if self.hidden(y) {
+                continue;
+            }
+            for (chunk, start) in row.wrap_points(width).into_iter().enumerate() {
+                if layout.len() == height {
+                    return layout;
+                }
+                layout.push((y, start, self.line_offset + width, chunk == 0));
+            }
+        }
+        layout
+    }
+    pub fn top(&self) -> usize {
+        // The first line in view, which differs from the offset while peeking
+        self.peek.unwrap_or(self.offset.y)
+    }
+    pub fn screen_cursor(&self, term: &Size) -> Option<Position> {
+        // Find where the cursor appears on the screen, if it is in view
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        if y < self.top() {
+            return None;
+        }
+        let cursor = self.cursor_from(self.top(), term);
+        if cursor.y > term.height.saturating_sub(3) {
+            None
+        } else {
+            Some(cursor)
+        }
+    }
+    fn cursor_from(&self, top: usize, term: &Size) -> Position {
+        // Work out the screen position of the cursor when the view starts at a line
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        if !self.soft_wrap && self.folds.is_empty() {
+            return Position {
+                x: self.cursor.x.saturating_add(self.line_offset),
+                y: OFFSET + y - top,
+            };
+        }
+        let width = self.wrap_width(term);
+        let points = self.points(y, width);
+        let chunk = wrap_chunk(&points, self.cursor.x);
+        let above: usize = (top..y).map(|line| self.screen_rows(line, width)).sum();
+        Position {
+            x: self.cursor.x - points[chunk] + self.line_offset,
+            y: OFFSET + above + chunk,
+        }
+    }
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        // Find the start and end of the selection, in the order they appear
+        let anchor = self.anchor?;
+        let y = cmp::min(anchor.y, self.rows.len().saturating_sub(1));
+        let anchor = Position {
+            x: cmp::min(anchor.x, self.rows[y].length()),
+            y,
+        };
+        let cursor = Position {
+            x: self.cursor.x + self.offset.x,
+            y: self.cursor.y + self.offset.y - OFFSET,
+        };
+        let (start, end) = if (anchor.y, anchor.x) <= (cursor.y, cursor.x) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        if start == end {
+            None
+        } else {
+            Some((start, end))
+        }
+    }
+    pub fn selected_text(&self) -> Option<(Position, String)> {
+        // Get the selected text along with where it starts
+        let (start, end) = self.selection()?;
+        Some((start, text_between(&self.rows, start, end)))
+    }
+    pub fn click(&mut self, row: usize, column: usize, term: &Size) {
+        // Move the cursor to the character shown at a spot in the text area
+        let (y, x) = match self.layout(term).get(row) {
+            Some(&(y, start, _, _)) => (y, start + column.saturating_sub(self.line_offset)),
+            // Clicking below the last line goes to the end of the document
+            None => (self.rows.len().saturating_sub(1), usize::MAX),
+        };
+        // Keep the view where it is, even while peeking
+        self.offset.y = self.top();
+        self.peek = None;
+        let x = cmp::min(x, self.rows[y].length());
+        self.cursor.y = y - self.offset.y + OFFSET;
+        self.cursor.x = x - self.offset.x;
+        self.recalculate_graphemes();
+        self.prevent_unicode_hell();
+    }
+    pub fn scroll(&mut self, direction: Direction) {
+        // Move the view up or down by a line without moving the cursor
+        let top = match direction {
+            Direction::Up => self.top().saturating_sub(1),
+            Direction::Down => cmp::min(self.top() + 1, self.rows.len().saturating_sub(1)),
+            _ => return,
+        };
+        self.peek_at(top);
+    }
+    pub fn page_in(&mut self, term: &Size) -> bool {
+        // Read the lines around the view of a large file once it nears the edge of the window held
+        let (start, end) = if let Some(lazy) = &self.lazy {
+            (lazy.start, lazy.end)
+        } else {
+            return false;
+        };
+        let (top, height) = (self.top(), term.height.saturating_sub(OFFSET + 2));
+        let margin = WINDOW / 4;
+        if (top < margin && start > 0) || (top + height + margin > self.rows.len() && !end) {
+            self.move_window((start + top).saturating_sub(WINDOW / 2));
+            return true;
+        }
+        false
+    }
+    fn move_window(&mut self, start: usize) {
+        // Hold the lines of a large file from a line on, keeping the view and cursor where they were
+        let Some(lazy) = &mut self.lazy else {
+            return;
+        };
+        let before = lazy.start;
+        let lines = match lazy.read(start) {
+            Ok(lines) => lines,
+            Err(error) => {
+                let message = format!("Couldn't read more of {}: {}", self.name, error);
+                self.set_command_line(message, Type::Error);
+                return;
+            }
+        };
+        let after = lazy.start;
+        let (top, cursor) = (self.top(), self.cursor.y + self.offset.y - OFFSET);
+        self.rows = lines
+            .iter()
+            .map(|line| Row::from(tabs_to_spaces(line, self.tab_width).as_str()))
+            .collect();
+        // Lines keep their place in the file, and those that are no longer held are let go
+        let len = self.rows.len();
+        let moved = |y: usize| (y + before).checked_sub(after).filter(|y| *y < len);
+        let top = moved(top).unwrap_or(0);
+        if let Some(cursor) = moved(cursor) {
+            self.offset.y = moved(self.offset.y).map_or(cursor, |y| cmp::min(y, cursor));
+            self.cursor.y = cursor - self.offset.y + OFFSET;
+        } else {
+            // A cursor left behind goes to the top of the view
+            self.offset = Position { x: 0, y: top };
+            self.cursor = Position { x: 0, y: OFFSET };
+            self.graphemes = 0;
+        }
+        self.peek = (top != self.offset.y).then_some(top);
+        self.anchor = self
+            .anchor
+            .and_then(|pos| moved(pos.y).map(|y| Position { y, ..pos }));
+        self.block = self
+            .block
+            .and_then(|pos| moved(pos.y).map(|y| Position { y, ..pos }));
+        self.cursors = self
+            .cursors
+            .iter()
+            .filter_map(|pos| moved(pos.y).map(|y| Position { y, ..*pos }))
+            .collect();
+        self.jumps = self
+            .jumps
+            .iter()
+            .filter_map(|pos| moved(pos.y).map(|y| Position { y, ..*pos }))
+            .collect();
+        self.jump_index = self.jumps.len();
+        self.marks = self
+            .marks
+            .iter()
+            .filter_map(|(letter, pos)| moved(pos.y).map(|y| (*letter, Position { y, ..*pos })))
+            .collect();
+        self.folds.clear();
+        self.mass_redraw();
+    }
+    pub fn load_whole(&mut self, config: &Reader, status: &Status) -> io::Result<usize> {
+        // Read in all of a large file, returning how far down the lines held have moved
+        let start = if let Some(lazy) = &self.lazy {
+            lazy.start
+        } else {
+            return Ok(0);
+        };
+        let mut whole = Document::open_whole(config, status, &self.path, self.read_only)?;
+        let down = |pos: &Position| Position {
+            x: pos.x,
+            y: pos.y + start,
+        };
+        whole.cursor = self.cursor;
+        whole.offset = down(&self.offset);
+        whole.graphemes = self.graphemes;
+        whole.peek = self.peek.map(|y| y + start);
+        whole.anchor = self.anchor.as_ref().map(down);
+        whole.block = self.block.as_ref().map(down);
+        whole.cursors = self.cursors.iter().map(down).collect();
+        whole.jumps = self.jumps.iter().map(down).collect();
+        whole.jump_index = self.jump_index;
+        whole.marks = self.marks.iter().map(|(l, pos)| (*l, down(pos))).collect();
+        whole.cmd_line = self.cmd_line.clone();
+        whole.true_path = std::mem::take(&mut self.true_path);
+        *self = whole;
+        Ok(start)
+    }
+    pub fn peek_at(&mut self, top: usize) {
+        // Show the document from a line without moving the cursor
+        let top = cmp::min(top, self.rows.len().saturating_sub(1));
+        self.peek = if top == self.offset.y {
+            None
+        } else {
+            Some(top)
+        };
+    }
+    fn scroll_to(&mut self, y: usize, term: &Size) {
+        // Put the cursor on a line, scrolling just enough to keep lines around it in view
+        let y = if self.hidden(y) {
+            self.shown_before(y).unwrap_or(0)
+        } else {
+            y
+        };
+        let height = cmp::max(term.height.saturating_sub(OFFSET + 2), 1);
+        let margin = cmp::min(self.scroll_off, height.saturating_sub(1) / 2);
+        if !self.soft_wrap && self.folds.is_empty() {
+            let below = cmp::min(y + margin, self.rows.len().saturating_sub(1));
+            self.offset.y = cmp::min(self.offset.y, y.saturating_sub(margin));
+            self.offset.y = cmp::max(self.offset.y, (below + 1).saturating_sub(height));
+            self.cursor.y = y - self.offset.y + OFFSET;
+            return;
+        }
+        // The margin is counted in lines that are shown, skipping folded ones
+        let above = (0..y)
+            .rev()
+            .filter(|&line| !self.hidden(line))
+            .take(margin)
+            .last()
+            .unwrap_or(y);
+        self.offset.y = cmp::min(self.offset.y, above);
+        self.cursor.y = y - self.offset.y + OFFSET;
+        // Wrapped lines can take up several rows, so scroll down a line at a time until they fit
+        let width = self.wrap_width(term);
+        let points = self.points(y, width);
+        let after = points.len() - 1 - wrap_chunk(&points, self.cursor.x)
+            + (y + 1..self.rows.len())
+                .filter(|&line| !self.hidden(line))
+                .take(margin)
+                .map(|line| self.screen_rows(line, width))
+                .sum::<usize>();
+        let after = cmp::min(after, margin);
+        while self.cursor.y > OFFSET
+            && self.cursor_from(self.offset.y, term).y + after > term.height.saturating_sub(3)
+        {
+            self.offset.y += 1;
+            self.cursor.y -= 1;
+        }
+    }
+    pub fn record_jump(&mut self, from: Position) {
+        // Remember where a large jump was made from, forgetting the jumps gone back past
+        self.jumps.truncate(self.jump_index);
+        self.jumps.retain(|jump| jump.y != from.y);
+        self.jumps.push(from);
+        let extra = self.jumps.len().saturating_sub(JUMPS);
+        self.jumps.drain(..extra);
+        self.jump_index = self.jumps.len();
+    }
+    pub fn retrace(&mut self, back: bool, current: Position, term: &Size) -> bool {
+        // Go back to where the last jump was made from or forward again, if there is one
+        if back {
+            if self.jump_index == self.jumps.len() {
+                // Keep where the cursor is so that going forward comes back to it
+                if self.jumps.last().is_some_and(|jump| jump.y == current.y) {
+                    self.jumps.pop();
+                }
+                self.jumps.push(current);
+                self.jump_index = self.jumps.len() - 1;
+            }
+            if self.jump_index == 0 {
+                return false;
+            }
+            self.jump_index -= 1;
+        } else if self.jump_index + 1 < self.jumps.len() {
+            self.jump_index += 1;
+        } else {
+            return false;
+        }
+        let jump = self.jumps[self.jump_index];
+        let y = jump.y.min(self.rows.len() - 1);
+        let x = jump.x.min(self.rows[y].length());
+        self.goto(Position { x, y }, term);
+        true
+    }
+    pub fn hidden(&self, y: usize) -> bool {
+        // Whether a line is folded away under the one before it
+        self.folds
+            .iter()
+            .any(|&(header, last)| header < y && y <= last)
+    }
+    fn shown_after(&self, y: usize) -> Option<usize> {
+        // The next line that isn't folded away
+        (y + 1..self.rows.len()).find(|&line| !self.hidden(line))
+    }
+    fn shown_before(&self, y: usize) -> Option<usize> {
+        // The previous line that isn't folded away
+        (0..y).rev().find(|&line| !self.hidden(line))
+    }
+    fn points(&self, y: usize, width: usize) -> Vec<usize> {
+        // Where each screen row of a line starts, which is only one when lines don't wrap
+        if self.soft_wrap {
+            self.rows[y].wrap_points(width)
+        } else {
+            vec![0]
+        }
+    }
+    fn screen_rows(&self, y: usize, width: usize) -> usize {
+        // How many rows of the screen a line takes up
+        if self.hidden(y) {
+            0
+        } else {
+            self.points(y, width).len()
+        }
+    }
+    fn fold_range(&self, y: usize) -> Option<(usize, usize)> {
+        // The lines after a line that are indented further, along with a bracket closing them
+        let depth = indentation_of(&self.rows[y])?;
+        let mut last = y;
+        for (line, row) in self.rows.iter().enumerate().skip(y + 1) {
+            match indentation_of(row) {
+                None => (),
+                Some(indent) if indent > depth => last = line,
+                Some(indent) => {
+                    let closer = row.string.trim_start().starts_with(&['}', ')', ']'][..]);
+                    if indent == depth && closer && last > y {
+                        last = line;
+                    }
+                    break;
+                }
+            }
+        }
+        (last > y).then_some((y, last))
+    }
+    fn outer_fold(&self, y: usize) -> Option<(usize, usize)> {
+        // The nearest fold starting above a line that it is part of
+        let mut depth = indentation_of(&self.rows[y]).unwrap_or(usize::MAX);
+        for line in (0..y).rev() {
+            match indentation_of(&self.rows[line]) {
+                Some(indent) if indent < depth => {
+                    if let Some(fold) = self.fold_range(line).filter(|fold| fold.1 >= y) {
+                        return Some(fold);
+                    }
+                    depth = indent;
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+    pub fn fold(&mut self, all: bool, term: &Size) -> bool {
+        // Fold away the block the cursor is in, or every outermost block, returning whether any were
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        if all {
+            self.folds.clear();
+            let mut line = 0;
+            while line < self.rows.len() {
+                if let Some(fold) = self.fold_range(line) {
+                    self.folds.push(fold);
+                    line = fold.1;
+                }
+                line += 1;
+            }
+        } else {
+            // Folding a folded line again folds the block around it
+            let found = if self.folds.iter().any(|fold| fold.0 == y) {
+                self.outer_fold(y)
+            } else {
+                self.fold_range(y).or_else(|| self.outer_fold(y))
+            };
+            match found {
+                Some(fold) => self.folds.push(fold),
+                None => return false,
+            }
+        }
+        // Bring the cursor out onto the line its fold is shown as
+        if let Some(&(header, _)) = self
+            .folds
+            .iter()
+            .find(|&&(header, last)| header < y && y <= last)
+        {
+            let x = cmp::min(self.cursor.x + self.offset.x, self.rows[header].length());
+            self.goto(Position { x, y: header }, term);
+        }
+        !self.folds.is_empty()
+    }
+    pub fn unfold(&mut self, all: bool) -> bool {
+        // Open the folds on the cursor's line, or all of them, returning whether there were any
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        let before = self.folds.len();
+        if all {
+            self.folds.clear();
+        } else {
+            self.folds.retain(|&(header, _)| header != y);
+        }
+        self.folds.len() != before
+    }
+    pub fn center(&mut self, term: &Size) {
+        // Scroll so that the line the cursor is on is in the middle of the screen
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        let half = term.height.saturating_sub(OFFSET + 2) / 2;
+        self.offset.y = y;
+        self.cursor.y = OFFSET;
+        while self.offset.y > 0 && self.cursor_from(self.offset.y - 1, term).y - OFFSET <= half {
+            self.offset.y -= 1;
+            self.cursor.y += 1;
+        }
+        self.peek = None;
+    }
+    pub fn wrap_scroll(&mut self, term: &Size) {
+        // Scroll down until the cursor fits on the screen with wrapped lines
+        while (self.soft_wrap || !self.folds.is_empty())
+            && self.cursor.y > OFFSET
+            && self.cursor_from(self.offset.y, term).y > term.height.saturating_sub(3)
+        {
+            self.offset.y = self.offset.y.saturating_add(1);
+            self.cursor.y = self.cursor.y.saturating_sub(1);
+        }
+    }
+    pub fn toggle_wrap(&mut self, term: &Size) {
+        // Switch soft wrapping on or off, keeping the cursor in place
+        let pos = Position {
+            x: self.cursor.x + self.offset.x,
+            y: self.cursor.y + self.offset.y - OFFSET,
+        };
+        self.soft_wrap = !self.soft_wrap;
+        self.offset.x = 0;
+        self.cursor.x = pos.x;
+        self.goto(pos, term);
+        self.mass_redraw();
+    }
     pub fn snap_cursor(&mut self, term: &Size) {
-        // Snap the cursor to the end of the row when outside
-        let current = self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-        if current.length() <= self.cursor.x + self.offset.x {
+        // Snap the cursor to the end of the row when outside, and to the last row when below it
+        let last = self.rows.len().saturating_sub(1);
+        if self.cursor.y + self.offset.y - OFFSET > last {
+            self.scroll_to(last, term);
+        }
+        if self.rows[self.cursor.y + self.offset.y - OFFSET].length()
+            <= self.cursor.x + self.offset.x
+        {
             // If the cursor is out of bounds
-            self.leap_cursor(Key::Home, term);
-            self.leap_cursor(Key::End, term);
+            self.leap_cursor(Leap::Home, term);
+            self.leap_cursor(Leap::End, term);
         }
     }
     pub fn prevent_unicode_hell(&mut self) {
@@ -402,7 +1480,6 @@ impl Document {
             // As long as the cursor is within range
             let boundaries = line.boundaries();
             let mut index = self.cursor.x + self.offset.x;
-            if !boundaries.contains(&index) && index != 0 {}
             while !boundaries.contains(&index) && index != 0 {
                 self.cursor.x = self.cursor.x.saturating_sub(1);
                 self.graphemes = self.graphemes.saturating_sub(1);
@@ -412,8 +1489,7 @@ impl Document {
     }
     pub fn recalculate_graphemes(&mut self) {
         // Recalculate the grapheme cursor after moving up and down
-        let current = self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-        let jumps = current.get_jumps();
+        let jumps = self.rows[self.cursor.y + self.offset.y - OFFSET].get_jumps();
         let mut counter = 0;
         for (mut counter2, i) in jumps.into_iter().enumerate() {
             if counter == self.cursor.x + self.offset.x {
@@ -424,17 +1500,48 @@ impl Document {
             counter += i;
         }
     }
-    pub fn recalculate_offset(&mut self, config: &Reader) {
+    pub fn recalculate_offset(&mut self, config: &Reader, term: &Size) {
         // Calculate the offset for the line numbers
-        self.line_offset = self.rows.len().to_string().len()
-            + config.general.line_number_padding_right
-            + config.general.line_number_padding_left;
+        let start = self.lazy.as_ref().map_or(0, |lazy| lazy.start);
+        self.line_offset = if config.general.line_numbers {
+            (self.rows.len() + start).to_string().len()
+                + config.general.line_number_padding_right
+                + config.general.line_number_padding_left
+        } else {
+            0
+        };
+        // Scroll across if the gutter grew underneath the cursor
+        let max_x = self.width(term).saturating_sub(self.line_offset + 1);
+        if self.cursor.x > max_x {
+            self.offset.x += self.cursor.x - max_x;
+            self.cursor.x = max_x;
+        }
+    }
+    pub fn fit(&mut self, config: &Reader, term: &Size) {
+        // Scroll to keep the cursor on the same character after a resize
+        let max_y = term.height.saturating_sub(3);
+        if self.cursor.y > max_y && max_y >= OFFSET {
+            self.offset.y += self.cursor.y - max_y;
+            self.cursor.y = max_y;
+        }
+        self.recalculate_offset(config, term);
+    }
+    fn tab_type(file: &str, config: &Reader) -> TabType {
+        // Keep to the indentation a file already uses, or the configured one
+        let indented = |c| file.starts_with(c) || file.contains(&format!("\n{c}"));
+        if indented('\t') {
+            TabType::Tabs
+        } else if indented(' ') || config.general.expand_tabs {
+            TabType::Spaces
+        } else {
+            TabType::Tabs
+        }
     }
     pub fn tab(&mut self, pos: &Position, config: &Reader, term: &Size) {
         // Insert a tab
-        for _ in 0..config.general.tab_width {
-            self.rows[pos.y].insert(' ', pos.x);
-            self.move_cursor(Key::Right, term, config.general.wrap_cursor);
+        self.rows[pos.y].insert(&" ".repeat(self.tab_width), pos.x);
+        for _ in 0..self.tab_width {
+            self.move_cursor(Direction::Right, term, config.general.wrap_cursor);
         }
     }
     fn overwrite(&mut self, after: &[Row]) {
@@ -457,6 +1564,19 @@ impl Document {
             self.rows.remove(ind);
         }
     }
+    fn swap_line(&mut self, pos: &Position, direction: Direction) -> Option<usize> {
+        // Swap a line with its neighbour, returning where it ended up
+        let other = match direction {
+            Direction::Up => pos.y.checked_sub(1)?,
+            Direction::Down if pos.y + 1 < self.rows.len() => pos.y + 1,
+            _ => return None,
+        };
+        self.dirty = true;
+        self.rows.swap(pos.y, other);
+        self.rows[pos.y].updated = true;
+        self.rows[other].updated = true;
+        Some(other)
+    }
     fn splice_up(&mut self, pos: &Position, reversed: bool, term: &Size, other: &Position) {
         // Splice the line up to the next
         self.dirty = true;
@@ -487,11 +1607,9 @@ impl Document {
         // Split the line in half
         self.dirty = true;
         let current = self.rows[pos.y].clone();
-        let left: String = current.string.chars().take(pos.x).collect();
-        let right: String = current.string.chars().skip(pos.x).collect();
-        self.rows[pos.y] = Row::from(&left[..]);
-        self.rows
-            .insert(pos.y.saturating_add(1), Row::from(&right[..]));
+        let (left, right) = current.string.split_at(current.byte_index(pos.x));
+        self.rows[pos.y] = Row::from(left);
+        self.rows.insert(pos.y.saturating_add(1), Row::from(right));
         if reversed {
             self.goto(*other, term);
         } else {
@@ -504,11 +1622,52 @@ impl Document {
             self.undo_stack.commit();
         }
     }
+    fn insert_text(&mut self, pos: &Position, text: &str) -> Position {
+        // Insert text that may span several lines, returning where it ends
+        let mut lines = text.split('\n');
+        let first = lines.next().unwrap_or_default();
+        let x = self.rows[pos.y].insert(first, pos.x);
+        let mut end = Position { x, y: pos.y };
+        let mut tail = String::new();
+        for line in lines {
+            if end.y == pos.y {
+                // Carry the rest of the first line down to the last one
+                let row = &mut self.rows[pos.y];
+                tail = row.string.split_off(row.byte_index(x));
+            }
+            end.y += 1;
+            self.rows.insert(end.y, Row::from(line));
+            end.x = self.rows[end.y].length();
+        }
+        self.rows[end.y].string.push_str(&tail);
+        end
+    }
+    fn delete_text(&mut self, pos: &Position, text: &str) {
+        // Remove text that may span several lines, starting at a position
+        let lines: Vec<&str> = text.split('\n').collect();
+        let last = pos.y + lines.len() - 1;
+        let width = UnicodeWidthStr::width(lines[lines.len() - 1]);
+        if last == pos.y {
+            self.rows[pos.y].delete(pos.x, pos.x + width);
+            return;
+        }
+        let tail = self.rows[last].delete(width, usize::MAX);
+        let row = &mut self.rows[pos.y];
+        row.delete(pos.x, usize::MAX);
+        row.string.push_str(&tail);
+        self.rows.drain(pos.y + 1..=last);
+    }
+    #[allow(clippy::too_many_lines)] // One arm per event, kept together so undo mirrors it
     pub fn execute(&mut self, event: Event, reversed: bool, term: &Size, config: &Reader) {
         // Document edit event executor
-        if self.read_only && Editor::will_edit(&event) {
+        if self.read_only && will_edit(&event) {
             return;
         }
+        let (lines, moved) = (self.rows.len(), self.moved_lines(&event));
+        // The extra cursors are moved with the text once the edit is made
+        let shifted = (!self.cursors.is_empty()).then(|| event.clone());
+        // Folds are put aside during the edit so the cursor isn't moved past lines that shift
+        let folds = std::mem::take(&mut self.folds);
         match event {
             Event::Set(variable, value) => match variable {
                 Variable::Saved => self.dirty = !value,
@@ -527,46 +1686,50 @@ impl Document {
                     self.undo_stack.push(event);
                 }
             }
+            Event::SwapLine(pos, direction) => {
+                if let Some(other) = self.swap_line(&pos, direction) {
+                    self.goto(Position { x: pos.x, y: other }, term);
+                    if !reversed {
+                        self.undo_stack.push(event);
+                    }
+                }
+            }
             Event::DeleteLine(pos, offset, _) => {
                 self.delete_line(&pos, offset);
-                self.goto(pos, term);
-                if self.cursor.y + self.offset.y - OFFSET >= self.rows.len() {
-                    self.move_cursor(Key::Up, term, config.general.wrap_cursor);
-                }
+                // Deleting the last line leaves the cursor on the one before it
+                let y = cmp::min(pos.y, self.rows.len().saturating_sub(1));
+                let x = cmp::min(pos.x, self.rows.get(y).map_or(0, Row::length));
+                self.goto(Position { x, y }, term);
                 if !reversed {
                     self.undo_stack.push(event);
                 }
             }
-            Event::Insertion(pos, ch) => {
+            Event::Insertion(pos, ref text) => {
                 self.dirty = true;
-                self.rows[pos.y].insert(ch, pos.x);
-                self.move_cursor(Key::Right, term, config.general.wrap_cursor);
-                self.goto(pos, term);
-                self.move_cursor(Key::Right, term, config.general.wrap_cursor);
+                let end = self.insert_text(&pos, text);
+                self.goto(end, term);
                 if !reversed {
+                    let space = text == " ";
                     self.undo_stack.push(event);
-                    if ch == ' ' {
+                    if space {
                         self.undo_stack.commit();
                     }
                 }
             }
-            Event::Deletion(pos, _) => {
+            Event::Deletion(pos, ref text) => {
                 self.dirty = true;
                 self.show_welcome = false;
-                self.recalculate_graphemes();
+                self.delete_text(&pos, text);
                 self.goto(pos, term);
-                if reversed {
-                    self.move_cursor(Key::Left, term, config.general.wrap_cursor);
-                } else {
+                if !reversed {
                     self.undo_stack.push(event);
                 }
-                self.rows[pos.y].delete(self.graphemes.saturating_sub(1));
             }
             Event::InsertLineAbove(pos) => {
                 self.dirty = true;
                 self.rows.insert(pos.y, Row::from(""));
                 self.goto(pos, term);
-                self.move_cursor(Key::Down, term, config.general.wrap_cursor);
+                self.move_cursor(Direction::Down, term, config.general.wrap_cursor);
                 if !reversed {
                     self.undo_stack.push(event);
                     self.undo_stack.commit();
@@ -586,7 +1749,7 @@ impl Document {
             Event::InsertTab(pos) => {
                 self.dirty = true;
                 self.goto(pos, term);
-                self.tab(&pos, &config, term);
+                self.tab(&pos, config, term);
                 if !reversed {
                     self.undo_stack.push(event);
                 }
@@ -594,33 +1757,167 @@ impl Document {
             Event::DeleteTab(pos) => {
                 self.dirty = true;
                 self.goto(pos, term);
-                for _ in 0..config.general.tab_width {
-                    self.rows[pos.y].delete(pos.x);
-                }
+                self.rows[pos.y].delete(pos.x, pos.x + self.tab_width);
                 if !reversed {
                     self.undo_stack.push(event);
                 }
             }
-            Event::DeleteWord(pos, _) => self.delete_word(&pos, term),
+            Event::DeleteWord(pos) => self.delete_word(&pos, term),
             _ => (),
         }
+        self.folds = folds;
+        if let Some(from) = moved {
+            self.shift_marks(from, lines);
+        }
+        if let Some(event) = shifted {
+            self.shift_cursors(&event);
+        }
+        // An edit that leaves the cursor inside a fold opens it
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        self.folds
+            .retain(|&(header, last)| !(header < y && y <= last));
         self.recalculate_graphemes();
     }
-    pub fn word_left(&mut self, term: &Size) {
-        self.move_cursor(Key::Left, term, false);
-        let row = self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-        while self.cursor.x + self.offset.x != 0
-            && row.chars()[self.graphemes.saturating_sub(1)] != " "
-        {
-            self.move_cursor(Key::Left, term, false);
+    fn moved_lines(&mut self, event: &Event) -> Option<usize> {
+        // The first line an edit might move up or down, swapping the marks of swapped lines
+        match event {
+            Event::Insertion(pos, _)
+            | Event::Deletion(pos, _)
+            | Event::SplitDown(pos, _)
+            | Event::InsertLineBelow(pos) => Some(pos.y + 1),
+            Event::InsertLineAbove(pos) | Event::SpliceUp(pos, _) => Some(pos.y),
+            Event::DeleteLine(pos, offset, _) => Some(line_offset(pos.y, *offset, self.rows.len())),
+            Event::SwapLine(pos, direction) => {
+                let other = match direction {
+                    Direction::Up => pos.y.checked_sub(1)?,
+                    Direction::Down if pos.y + 1 < self.rows.len() => pos.y + 1,
+                    _ => return None,
+                };
+                // Folds that a line moves into or out of are opened
+                self.folds.retain(|&(header, last)| {
+                    !(header..=last).contains(&pos.y) && !(header..=last).contains(&other)
+                });
+                for mark in self.marks.values_mut().chain(&mut self.jumps) {
+                    if mark.y == pos.y {
+                        mark.y = other;
+                    } else if mark.y == other {
+                        mark.y = pos.y;
+                    }
+                }
+                None
+            }
+            Event::Overwrite(..) => {
+                self.folds.clear();
+                None
+            }
+            _ => None,
+        }
+    }
+    fn shift_marks(&mut self, from: usize, lines: usize) {
+        // Keep the marks, jumps and folds with their lines after lines were added or taken away
+        let now = self.rows.len();
+        if now == lines {
+            return;
+        }
+        let shift = |y: usize| {
+            if now > lines {
+                if y >= from {
+                    y + now - lines
+                } else {
+                    y
+                }
+            } else if y >= from + (lines - now) {
+                y - (lines - now)
+            } else if y >= from {
+                // The line went, so what was on it moves to the one before
+                from.saturating_sub(1)
+            } else {
+                y
+            }
+        };
+        for mark in self.marks.values_mut().chain(&mut self.jumps) {
+            mark.y = shift(mark.y);
+        }
+        for cursor in &mut self.cursors {
+            cursor.y = shift(cursor.y);
+        }
+        // A fold opens when lines are added or taken away at the line it is shown as
+        self.folds
+            .retain(|&(header, last)| !(header < from && from <= last + 1));
+        for fold in &mut self.folds {
+            *fold = (shift(fold.0), shift(fold.1));
+        }
+        self.folds.retain(|&(header, last)| header < last);
+    }
+    fn shift_cursors(&mut self, event: &Event) {
+        // Keep the extra cursors by their text after an edit on their line, and in the document
+        match event {
+            Event::Insertion(pos, text) => {
+                let (lines, last) = match text.rsplit_once('\n') {
+                    Some((before, last)) => (before.matches('\n').count() + 1, last),
+                    None => (0, text.as_str()),
+                };
+                for cursor in &mut self.cursors {
+                    if cursor.y == pos.y && cursor.x >= pos.x {
+                        let start = if lines == 0 { pos.x } else { 0 };
+                        cursor.x = cursor.x - pos.x + start + last.width();
+                        cursor.y += lines;
+                    }
+                }
+            }
+            Event::Deletion(pos, text) if !text.contains('\n') => {
+                let width = text.width();
+                for cursor in &mut self.cursors {
+                    if cursor.y == pos.y && cursor.x >= pos.x {
+                        cursor.x = cmp::max(cursor.x.saturating_sub(width), pos.x);
+                    }
+                }
+            }
+            _ => (),
+        }
+        let last = self.rows.len().saturating_sub(1);
+        for cursor in &mut self.cursors {
+            cursor.y = cmp::min(cursor.y, last);
+            cursor.x = cmp::min(cursor.x, self.rows[cursor.y].length());
         }
+        let mut seen = vec![];
+        self.cursors.retain(|cursor| {
+            let new = !seen.contains(cursor);
+            seen.push(*cursor);
+            new
+        });
+    }
+    pub fn word_left(&mut self, term: &Size) {
+        // Move the cursor to the start of the word before it
+        let current = Position {
+            x: self.cursor.x + self.offset.x,
+            y: self.cursor.y + self.offset.y - OFFSET,
+        };
+        self.goto(cursor::word_before(&self.rows, current), term);
+        self.recalculate_graphemes();
     }
     pub fn word_right(&mut self, term: &Size) {
-        let row = self.rows[self.cursor.y + self.offset.y - OFFSET].clone();
-        while self.cursor.x + self.offset.x != row.length() && row.chars()[self.graphemes] != " " {
-            self.move_cursor(Key::Right, term, false);
+        // Move the cursor to the end of the word after it
+        let current = Position {
+            x: self.cursor.x + self.offset.x,
+            y: self.cursor.y + self.offset.y - OFFSET,
+        };
+        self.goto(cursor::word_after(&self.rows, current), term);
+        self.recalculate_graphemes();
+    }
+    pub fn paragraph(&mut self, down: bool, term: &Size) {
+        // Move the cursor to the blank line before or after the paragraph it is in
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        self.goto(cursor::paragraph(&self.rows, y, down), term);
+        self.recalculate_graphemes();
+    }
+    pub fn block_edge(&mut self, end: bool, term: &Size) {
+        // Move the cursor to the edge of the indented block it is in, and then out of it
+        let y = self.cursor.y + self.offset.y - OFFSET;
+        if let Some(target) = cursor::block_edge(&self.rows, y, end) {
+            self.goto(target, term);
+            self.recalculate_graphemes();
         }
-        self.move_cursor(Key::Right, term, false);
     }
     pub fn find_word_boundary_left(&self, pos: &Position) -> Option<Position> {
         self.find_prev(" ", pos)
@@ -649,20 +1946,20 @@ impl Document {
             left = Position { x: 0, y: pos.y };
         }
         self.goto(left, term);
-        for _ in left.x..right.x {
-            self.rows[pos.y].delete(left.x);
-        }
+        self.rows[pos.y].delete(left.x, right.x);
     }
     pub fn goto(&mut self, mut pos: Position, term: &Size) {
-        // Move the cursor to a specific location
+        // Move the cursor to a specific location, opening the folds it is hidden in
+        self.folds
+            .retain(|&(header, last)| !(header < pos.y && pos.y <= last));
         let on_y = pos.y >= self.offset.y
             && pos.y <= self.offset.y.saturating_add(term.height.saturating_sub(4));
         let on_x = pos.x >= self.offset.x
             && pos.x
-                <= self
+                < self
                     .offset
                     .x
-                    .saturating_add(term.width.saturating_sub(self.line_offset));
+                    .saturating_add(self.width(term).saturating_sub(self.line_offset + 1));
         // Verify that the goto is necessary
         if on_y && on_x {
             // No need to adjust offset
@@ -672,7 +1969,7 @@ impl Document {
         }
         // Move to that position
         let max_y = term.height.saturating_sub(3);
-        let max_x = (term.width).saturating_sub(self.line_offset);
+        let max_x = self.width(term).saturating_sub(self.line_offset + 1);
         let halfway_y = max_y / 2;
         let halfway_x = max_x / 2;
         pos.y = pos.y.saturating_add(1);
@@ -701,11 +1998,214 @@ impl Document {
             }
         }
     }
-    pub fn save(&self, path: &str, tab: usize) -> std::io::Result<()> {
-        // Save a file
-        let contents = self.render(self.tabs, tab);
+    pub fn trim_trailing_whitespace(&mut self, term: &Size) {
+        // Remove the whitespace from the end of every line, as one edit that can be undone
+        self.undo_stack.commit();
+        for (y, row) in self.rows.iter_mut().enumerate() {
+            let trimmed = row.string.trim_end_matches(&[' ', '\t'][..]);
+            if trimmed.len() != row.string.len() {
+                let after = Row::from(trimmed);
+                let pos = Position {
+                    x: after.length(),
+                    y,
+                };
+                let before = std::mem::replace(row, after.clone());
+                self.undo_stack
+                    .push(Event::UpdateLine(pos, 0, Box::new(before), Box::new(after)));
+                self.edits += 1;
+            }
+        }
+        self.undo_stack.commit();
+        // Ensure the cursor isn't left beyond the end of a shortened line
+        self.snap_cursor(term);
+        self.prevent_unicode_hell();
+        self.recalculate_graphemes();
+    }
+    pub fn compression_for(&self, path: &str) -> Option<Compression> {
+        // How a file is compressed when the document is saved to it
+        // The file it came from stays as it was, and others go by their extension
+        if path == self.path {
+            self.compression
+        } else {
+            Compression::from_name(path)
+        }
+    }
+    fn contents(&self, path: &str) -> io::Result<(Vec<u8>, bool)> {
+        // The bytes to write for the document to a file, and whether they end with a newline
+        // Only a window of a large file is held, which mustn't be written over all of it
+        if self.lazy.is_some() {
+            return Err(io::Error::other("only part of the file is read in"));
+        }
+        let mut contents = self.render(self.tabs, self.tab_width);
+        let empty = self.rows.len() == 1 && self.rows[0].string.is_empty();
+        let newline = self.trailing_newline || (self.final_newline && !empty);
+        if !newline {
+            contents.pop();
+        }
+        if self.crlf {
+            contents = contents.replace('\n', "\r\n");
+        }
         log!("Saved file", format!("File tab status is {:?}", self.tabs));
-        fs::write(path, contents)
+        let bytes = self.encoding.encode(&contents)?;
+        match self.compression_for(path) {
+            Some(compression) => Ok((compression.compress(&bytes)?, newline)),
+            None => Ok((bytes, newline)),
+        }
+    }
+    fn saved(&mut self, target: &Path, newline: bool) {
+        // Note that what is on disk is now the document
+        self.trailing_newline = newline;
+        self.modified = fs::metadata(target).and_then(|meta| meta.modified()).ok();
+        self.kept = None;
+        self.fingerprint = Some(self.hash());
+        self.remove_swap();
+        // What git has may have moved on as well, so it is read again
+        self.git_edits = None;
+    }
+    pub fn save(&mut self, path: &str, config: &Reader) -> std::io::Result<usize> {
+        // Save a file
+        let (bytes, newline) = self.contents(path)?;
+        // Write to a temporary file next to the target and move it into place
+        // so that a crash or a full disk can't leave the file half written
+        let target = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        let name = target
+            .file_name()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Invalid file name"))?
+            .to_string_lossy()
+            .to_string();
+        let temp = target.with_file_name(format!(".{name}.ox"));
+        let original = fs::metadata(&target).ok();
+        let write = || -> io::Result<()> {
+            let mut file = fs::File::create(&temp)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            if let Some(metadata) = &original {
+                fs::set_permissions(&temp, metadata.permissions())?;
+                if config.general.backup {
+                    fs::copy(&target, target.with_file_name(format!("{name}~")))?;
+                }
+            }
+            fs::rename(&temp, &target)
+        };
+        if let Err(error) = write() {
+            let _ = fs::remove_file(&temp);
+            return Err(error);
+        }
+        self.saved(&target, newline);
+        Ok(bytes.len())
+    }
+    pub fn save_remote(&mut self, host: &str, file: &str) -> io::Result<usize> {
+        // Save a file on another machine through ssh
+        let (bytes, newline) = self.contents(&self.path)?;
+        ssh::store(host, file, &bytes)?;
+        let path = PathBuf::from(&self.path);
+        self.saved(&path, newline);
+        Ok(bytes.len())
+    }
+    pub fn save_as_root(&mut self, path: &str) -> io::Result<usize> {
+        // Save a file that can't be written to normally, by having sudo run tee to write it
+        // The file is written over in place, as there may be nowhere to put a temporary one
+        let (bytes, newline) = self.contents(path)?;
+        let mut tee = Command::new("sudo")
+            .arg("tee")
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = tee.stdin.take() {
+            stdin.write_all(&bytes)?;
+        }
+        if !tee.wait()?.success() {
+            return Err(io::Error::new(
+                ErrorKind::PermissionDenied,
+                "sudo couldn't write the file",
+            ));
+        }
+        self.saved(Path::new(path), newline);
+        Ok(bytes.len())
+    }
+    pub fn hash(&self) -> u64 {
+        // A hash of the text, for telling whether a kept history still fits the file
+        fnv1a(
+            self.rows
+                .iter()
+                .flat_map(|row| row.string.bytes().chain(iter::once(b'\n'))),
+        )
+    }
+    pub fn swap_path(&self) -> Option<PathBuf> {
+        // Unsaved changes are kept in a hidden file next to the document
+        if self.path.is_empty() || ssh::parse(&self.path).is_some() {
+            return None;
+        }
+        let path = Path::new(&self.path);
+        let name = path.file_name()?.to_string_lossy().to_string();
+        Some(path.with_file_name(format!(".{name}.ox-swap")))
+    }
+    pub fn write_swap(&mut self) {
+        // Keep a copy of the unsaved changes in case Ox doesn't get to save them
+        if let Some(swap) = self.swap_path() {
+            self.swapped = fs::write(swap, self.render(self.tabs, self.tab_width)).is_ok();
+        }
+    }
+    pub fn remove_swap(&mut self) {
+        // Clean up the swap file once the changes are saved or thrown away
+        if let (true, Some(swap)) = (self.swapped, self.swap_path()) {
+            let _ = fs::remove_file(swap);
+        }
+        self.swapped = false;
+    }
+    pub fn recover(&mut self) -> io::Result<()> {
+        // Replace the contents with the changes left behind in the swap file
+        let swap = self
+            .swap_path()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No swap file"))?;
+        let file = tabs_to_spaces(&fs::read_to_string(swap)?, self.tab_width);
+        let mut lines = Document::split_file(&file);
+        if lines.len() > 1 && lines.last() == Some(&"") {
+            lines.pop();
+        }
+        self.rows = lines.iter().map(|row| Row::from(*row)).collect();
+        self.lazy = None;
+        self.undo_stack = EventStack::new();
+        // The history starts from the recovered text now, which isn't what is on disk
+        self.fingerprint = None;
+        self.cursor = Position { x: 0, y: OFFSET };
+        self.offset = Position { x: 0, y: 0 };
+        self.graphemes = 0;
+        self.dirty = true;
+        self.swapped = true;
+        self.edits += 1;
+        Ok(())
+    }
+    pub fn line_count(&self) -> usize {
+        // Count the lines the way `wc -l` would, by counting newlines
+        self.rows.len() - usize::from(!self.trailing_newline)
+    }
+    pub fn indentation(&self, pos: &Position, tab_width: usize) -> String {
+        // Work out the indentation of a new line broken off at a position
+        let before = self.rows[pos.y].slice(0, pos.x);
+        let content = before.trim_start_matches([' ', '\t']);
+        let mut indent = before[..before.len() - content.len()].to_string();
+        // Blocks are opened by brackets, and by colons in some languages
+        let openers: &[char] = match self.kind.as_str() {
+            "Unknown" => &[],
+            "Python" | "Nim" | "YAML" => &['{', '[', '(', ':'],
+            _ => &['{', '[', '('],
+        };
+        if content.trim_end().ends_with(openers) {
+            indent.push_str(&" ".repeat(tab_width));
+        }
+        indent
+    }
+    pub fn statistics(&self) -> (usize, usize, usize) {
+        // Count the words, characters and lines the way `wc` would
+        let words = self
+            .rows
+            .iter()
+            .map(|r| r.string.split_whitespace().count());
+        let chars = self.rows.iter().map(|r| r.string.chars().count());
+        let lines = self.line_count();
+        (words.sum(), chars.sum::<usize>() + lines, lines)
     }
     pub fn find_prev(&self, needle: &str, current: &Position) -> Option<Position> {
         // Find all the points where "needle" occurs before the current position
@@ -718,13 +2218,7 @@ impl Document {
             .enumerate()
             .rev()
         {
-            let mut xs = vec![];
-            for i in re.captures_iter(r) {
-                for j in 0..i.len() {
-                    let j = i.get(j).unwrap();
-                    xs.push(j.start());
-                }
-            }
+            let mut xs: Vec<usize> = re.find_iter(r).map(|m| column(r, m.start())).collect();
             while let Some(i) = xs.pop() {
                 if i < current.x || c != current.y {
                     return Some(Position { x: i, y: c });
@@ -743,33 +2237,55 @@ impl Document {
             .map(|x| x.string.as_str())
             .enumerate()
         {
-            for i in re.captures_iter(r) {
-                for cap in 0..i.len() {
-                    let cap = i.get(cap).unwrap();
-                    if c != 0 || cap.start() > current.x {
-                        return Some(Position {
-                            x: cap.start(),
-                            y: current.y + c,
-                        });
-                    }
+            for m in re.find_iter(r) {
+                let x = column(r, m.start());
+                if c != 0 || x > current.x {
+                    return Some(Position {
+                        x,
+                        y: current.y + c,
+                    });
                 }
             }
         }
         None
     }
+    pub fn replace_at(&mut self, re: &Regex, pos: &Position, with: &str) -> Option<usize> {
+        // Replace the match starting at a position, giving back the column where the new text ends
+        let before = self.rows[pos.y].clone();
+        let caps = re.captures_iter(&before.string).find(|caps| {
+            caps.get(0)
+                .is_some_and(|m| column(&before.string, m.start()) == pos.x)
+        })?;
+        let whole = caps.get(0)?;
+        let mut text = String::new();
+        caps.expand(with, &mut text);
+        let end = column(&before.string, whole.start()) + UnicodeWidthStr::width(&text[..]);
+        let after = Row::from(&*format!(
+            "{}{}{}",
+            &before.string[..whole.start()],
+            text,
+            &before.string[whole.end()..]
+        ));
+        self.rows[pos.y] = after.clone();
+        self.undo_stack.push(Event::UpdateLine(
+            *pos,
+            0,
+            Box::new(before),
+            Box::new(after),
+        ));
+        self.dirty = true;
+        Some(end)
+    }
     pub fn find_all(&self, needle: &str) -> Option<Vec<Position>> {
         // Find all the places where the needle is
         let mut result = vec![];
         let re = Regex::new(needle).ok()?;
-        for (c, r) in self.rows.iter().map(|x| x.string.to_string()).enumerate() {
-            for i in re.captures_iter(&r) {
-                for cap in 0..i.len() {
-                    let cap = i.get(cap).unwrap();
-                    result.push(Position {
-                        x: cap.start(),
-                        y: c,
-                    });
-                }
+        for (c, r) in self.rows.iter().map(|x| x.string.clone()).enumerate() {
+            for m in re.find_iter(&r) {
+                result.push(Position {
+                    x: column(&r, m.start()),
+                    y: c,
+                });
             }
         }
         Some(result)
@@ -789,9 +2305,27 @@ impl Document {
             render
         }
     }
+    pub fn comment_markers(path: &str) -> Option<(&'static str, &'static str)> {
+        // What starts and ends a comment in the type of file a buffer is
+        Some(match path.split('.').next_back()? {
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "cs" | "d" | "dart" | "go" | "java"
+            | "js" | "jsx" | "json5" | "kt" | "less" | "php" | "rs" | "scala" | "scss"
+            | "swift" | "ts" | "tsx" | "v" | "zig" => ("//", ""),
+            "bash" | "cmake" | "conf" | "cr" | "coffee" | "ex" | "exs" | "fish" | "jl" | "nim"
+            | "pl" | "ps1" | "py" | "r" | "rb" | "sh" | "tcl" | "toml" | "yaml" | "yml" | "zsh"
+            | "Dockerfile" | "Makefile" | "gitignore" => ("#", ""),
+            "ada" | "elm" | "hs" | "lua" | "sql" | "sqlite" => ("--", ""),
+            "asm" | "clj" | "el" | "ini" | "lisp" | "scm" => (";", ""),
+            "erl" | "tex" => ("%", ""),
+            "vim" => ("\"", ""),
+            "css" => ("/*", "*/"),
+            "htm" | "html" | "md" | "svg" | "vue" | "xml" => ("<!--", "-->"),
+            _ => return None,
+        })
+    }
     pub fn identify(path: &str) -> (&str, &str) {
         // Identify which type of file the current buffer is
-        match path.split('.').last() {
+        match path.split('.').next_back() {
             Some(ext) => match ext {
                 "asm" => ("Assembly ", "\u{f471} "),
                 "b" => ("B", "\u{e7a3} "),
@@ -805,9 +2339,11 @@ impl Document {
                 "cpp" => ("C++", "\u{e61d} "),
                 "css" => ("CSS", "\u{e749} "),
                 "csv" => ("CSV", "\u{f1c0} "),
+                "cmake" => ("CMake", "\u{e615} "),
                 "class" | "java" => ("Java", "\u{e738} "),
                 "d" => ("D", "\u{e7af} "),
                 "db" => ("Database", "\u{f1c0} "),
+                "Dockerfile" => ("Dockerfile", "\u{f308} "),
                 "erb" => ("ERB", "\u{e739} "),
                 "fish" => ("Fish shell", "\u{f739} "),
                 "go" => ("Go", "\u{e724} "),
@@ -819,6 +2355,7 @@ impl Document {
                 "json" => ("JSON", "\u{e60b} "),
                 "lua" => ("LUA", "\u{e620} "),
                 "log" => ("Log file", "\u{f15c} "),
+                "Makefile" => ("Makefile", "\u{e615} "),
                 "md" => ("Markdown", "\u{e73e} "),
                 "nim" => ("Nim", "\u{e26e} "),
                 "py" | "pyc" | "pyw" => ("Python", "\u{e73c} "),
@@ -843,3 +2380,13 @@ impl Document {
         }
     }
 }
+
+fn wrap_chunk(points: &[usize], x: usize) -> usize {
+    // Find which screen row of a wrapped line a column falls on
+    points.iter().rposition(|&p| p <= x).unwrap_or(0)
+}
+
+fn column(line: &str, index: usize) -> usize {
+    // Convert a byte index from a regex match into a screen column
+    UnicodeWidthStr::width(&line[..index])
+}
@@ -1,22 +1,66 @@
 // Editor.rs - Controls the editor and brings everything together
+use crate::clipboard;
+use crate::compress::Compression;
 use crate::config::{KeyBinding, RawKey, Reader, Status, Theme};
-use crate::document::{TabType, Type};
+use crate::console::Console;
+use crate::cursor::{Leap, OFFSET};
+use crate::document::{CommandLine, TabType, Type};
+use crate::editorconfig;
+use crate::encoding::Encoding;
+use crate::explorer::{self, Explorer};
+use crate::export;
+use crate::filetype;
+use crate::finder::Finder;
+use crate::git::{self, Change, Comparison};
+use crate::hex::Hex;
 use crate::highlight::Token;
-use crate::oxa::interpret_line;
-use crate::undo::{reverse, BankType};
-use crate::util::{title, trim_end, Exp};
-use crate::{log, Document, Event, Row, Size, Terminal, VERSION};
-use clap::App;
-use crossterm::event::{Event as InputEvent, KeyCode, KeyEvent, KeyModifiers};
+use crate::input::{keys, Input as TerminalInput};
+use crate::lazy;
+use crate::lsp::{self, Server};
+use crate::markdown::{self, Palette};
+use crate::mode::{self, Action, Mode, Operator};
+use crate::oxa::{self, has_range, interpret_line, range_error};
+use crate::plugin::{self, Plugin};
+use crate::prompt::{Input, Prompt};
+use crate::quickfix::{self, Problem};
+use crate::remote::Remote;
+use crate::scheme;
+use crate::spell::{self, Dictionary};
+use crate::ssh;
+use crate::tags;
+use crate::terminal::Shape;
+use crate::undo::{reverse, shifted, will_edit, BankType};
+use crate::util::{
+    bump, fnv1a, line_column, tabs_to_spaces, text_between, title, transform, trim_end,
+    word_around, Exp,
+};
+use crate::{
+    log, set_log_level, Direction, Document, Event, EventStack, History, Position, Row, Size,
+    Terminal, Transform, VERSION,
+};
+use clap::ArgMatches;
+use crossterm::event::{
+    Event as InputEvent, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent,
+};
 use crossterm::style::{Attribute, Color, SetBackgroundColor, SetForegroundColor};
-use crossterm::ErrorKind;
+use directories::BaseDirs;
 use regex::Regex;
-use std::collections::HashMap;
+use ron::de::from_str;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::env;
 use std::ffi::OsStr;
-use std::fs::OpenOptions;
-use std::io::{BufRead, BufReader, Error, ErrorKind as Iek, Write};
-use std::path::Path;
-use std::process::{Command, Stdio};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Error, ErrorKind as Iek, IsTerminal, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
 use std::time::{Duration, Instant};
 use unicode_width::UnicodeWidthStr;
 
@@ -24,14 +68,66 @@ use unicode_width::UnicodeWidthStr;
 pub const RESET_BG: SetBackgroundColor = SetBackgroundColor(Color::Reset);
 pub const RESET_FG: SetForegroundColor = SetForegroundColor(Color::Reset);
 
-// Set up offset rules
-pub const OFFSET: usize = 1;
+// How long typing has to pause for before the gutter is compared with git
+const GIT_DELAY: Duration = Duration::from_millis(300);
+
+// How often the file being edited is checked for changes made by other programs
+const DISK_DELAY: Duration = Duration::from_secs(1);
+
+// How often the repository of the file being edited is asked what branch it is on
+const BRANCH_DELAY: Duration = Duration::from_secs(2);
+
+// How many files opened lately are remembered, and how many of them the welcome screen lists
+const RECENT: usize = 50;
+const RECENT_SHOWN: usize = 8;
+
+// The keys that do the same thing whatever the config says, for the help screen
+const BUILT_IN_KEYS: &[(&str, &str)] = &[
+    ("Arrows", "Move the cursor"),
+    ("Shift+Arrows", "Select text"),
+    (
+        "Home",
+        "Go to the first character of the line, then the start of it",
+    ),
+    ("End", "Go to the end of the line"),
+    ("PgUp / PgDn", "Move up or down a screen"),
+    ("Enter", "Break the line"),
+    (
+        "Tab",
+        "Move through a snippet, expand one, complete a word or indent",
+    ),
+    ("Shift+Tab", "Move back through a snippet or unindent"),
+    ("Backspace", "Delete the character before the cursor"),
+    ("Esc", "Stop selecting and remove the extra cursors"),
+    (
+        "Alt+0 to Alt+9",
+        "Repeat the next key, when the digit isn't bound",
+    ),
+];
+
+// The path, line and number of edits of a line that git is asked about
+type BlameKey = (String, usize, usize);
+
+// A line that git was asked about and what it said
+type Blame = (BlameKey, Option<String>);
+
+// How a build exited and what it wrote
+type Built = io::Result<(Option<i32>, String)>;
+
+// The places a search of the files found
+type Searched = io::Result<Vec<Problem>>;
 
 // Macro for running shell commands within the editor
 macro_rules! shell {
-    ($command:expr, $confirm:expr, $root:expr) => {
-        // Execute a shell command
-        let command = if $root {
+    ($term:expr, $command:expr, $confirm:expr, $root:expr) => {
+        // Execute a shell command, with cmd on Windows where there is no sudo
+        let command = if cfg!(windows) {
+            Command::new("cmd")
+                .arg("/C")
+                .arg($command)
+                .stdout(Stdio::piped())
+                .spawn()
+        } else if $root {
             Command::new("sudo")
                 .arg("bash")
                 .arg("-c")
@@ -52,7 +148,7 @@ macro_rules! shell {
                 .ok_or_else(|| Error::new(Iek::Other, "Could not capture standard output."))
             {
                 // Go back into canonical mode to restore normal operation
-                Terminal::exit();
+                $term.lend();
                 log!("Shell", "Ready to go");
                 // Stream the input and output of the command to the current stdout
                 BufReader::new(s)
@@ -66,12 +162,13 @@ macro_rules! shell {
                     let mut output = String::new();
                     let _ = std::io::stdin().read_line(&mut output);
                 }
-                Terminal::enter();
+                $term.take_back();
             } else {
-                log!("Failure to open standard output", "");
+                log!(error, "Failure to open standard output", "");
             }
         } else {
             log!(
+                error,
                 "Failure to run command",
                 format!(
                     "{} {:?}",
@@ -87,137 +184,361 @@ macro_rules! shell {
 enum PromptEvent {
     Update,
     CharPress(bool),
-    KeyPress(KeyCode),
+    KeyPress(KeyCode, KeyModifiers),
+}
+
+// Holds the layout of the screen while it is split into two panes
+struct Split {
+    other: usize,   // The tab shown in the pane without focus
+    vertical: bool, // Whether the panes sit side by side or on top of each other
+    first: bool,    // Whether the focused pane is the left / top one
+}
+
+// Two documents shown side by side with the lines that differ picked out
+struct Diff {
+    left: usize,            // The tab of the file compared from
+    right: usize,           // The tab of the file compared with
+    edits: (usize, usize),  // How many edits each had when they were last compared
+    comparison: Comparison, // How their lines differ and line up
+}
+
+impl Diff {
+    fn change(&self, tab: usize, line: usize) -> Option<Change> {
+        // How a line of one of the documents differs from the other
+        if tab == self.left {
+            self.comparison.old.get(&line).copied()
+        } else if tab == self.right {
+            self.comparison.new.get(&line).copied()
+        } else {
+            None
+        }
+    }
+    fn across(&self, line: usize, from_left: bool) -> usize {
+        // The line of the other document that sits across from a line of this one
+        let pairs = &self.comparison.pairs;
+        let index = pairs.partition_point(|pair| if from_left { pair.0 } else { pair.1 } <= line);
+        match index.checked_sub(1).map(|index| pairs[index]) {
+            Some((left, right)) if from_left => right + line - left,
+            Some((left, right)) => left + line - right,
+            None => line,
+        }
+    }
+}
+
+// A Markdown document drawn formatted beside its source
+struct Preview {
+    tab: usize,                  // The document being previewed
+    edits: usize,                // How many edits it had when it was last drawn
+    width: usize,                // How wide it was drawn
+    lines: Vec<(usize, String)>, // The lines drawn, each with the line of the source it shows
 }
 
-// For representing positions
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct Position {
-    pub x: usize,
-    pub y: usize,
+// A line that replacing across the files would change
+struct Replacement {
+    path: String,   // The file it is in
+    line: usize,    // Which line it is, counting from 0
+    before: String, // The line as it is
+    after: String,  // The line as it would be
+    include: bool,  // Whether it is going to be changed
 }
 
-// Enum for direction
-#[derive(Clone, Copy, Debug)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+// What was open when Ox was last closed, so that it can be opened again
+#[derive(Default, Serialize, Deserialize)]
+struct Session {
+    files: Vec<(String, (usize, usize), usize)>, // Each file with its cursor and top line in view
+    tab: usize,                                  // The file that had focus
+    split: Option<(usize, bool, bool)>,          // The other tab, direction and focus of a split
 }
 
 // The main editor struct
+#[allow(clippy::struct_excessive_bools)] // Independent editor-wide flags
 pub struct Editor {
-    pub config: Reader,                      // Storage for configuration
-    pub status: Status,                      // Holding the status of the config
-    config_path: String,                     // Holds the file path of the config file
-    quit: bool,                              // Toggle for cleanly quitting the editor
-    term: Terminal,                          // For the handling of the terminal
-    doc: Vec<Document>,                      // For holding our document
-    tab: usize,                              // Holds the number of the current tab
-    last_keypress: Option<Instant>,          // For holding the time of the last input event
-    keypress: KeyBinding,                    // For holding the last keypress event
-    exp: Exp,                                // For holding expressions
+    pub config: Reader,                         // Storage for configuration
+    pub status: Status,                         // Holding the status of the config
+    config_path: String,                        // Holds the file path of the config file
+    quit: bool,                                 // Toggle for cleanly quitting the editor
+    term: Terminal,                             // For the handling of the terminal
+    size: Size,                                 // Size of the pane with focus
+    split: Option<Split>,                       // Layout of split panes, if any
+    diff: Option<Diff>,                         // Comparison of the two panes, if any
+    preview: Option<Preview>,                   // Formatted Markdown shown on the right, if any
+    explorer: Option<Explorer>,                 // File tree shown down the left, if any
+    console: Option<Console>,                   // Shell shown along the bottom, if any
+    doc: Vec<Document>,                         // For holding our document
+    tab: usize,                                 // Holds the number of the current tab
+    last_keypress: Option<Instant>,             // For holding the time of the last input event
+    idle_since: Instant,                        // When the last input event or autosave was
+    disk_checked: Instant, // When the file was last checked for outside changes
+    keypress: KeyBinding,  // For holding the last keypress event
+    chord: Vec<KeyBinding>, // Keys typed so far of an unfinished chord
+    chord_since: Option<Instant>, // When the last key of that chord was typed
+    exp: Exp,              // For holding expressions
     position_bank: HashMap<usize, Position>, // Bank for cursor positions
-    row_bank: HashMap<usize, Row>,           // Bank for lines
-    theme: String,                           // Currently used theme
+    row_bank: HashMap<usize, Row>, // Bank for lines
+    theme: String,         // Currently used theme
+    positions: HashMap<String, (usize, usize)>, // Last cursor positions of files
+    recent: Vec<String>,   // Files opened lately, newest first
+    recent_chosen: Option<usize>, // The recent file picked on the welcome screen, if any
+    count: usize,          // Pending repeat count for movement keys
+    clipboard: String,     // Copied text for when there's no system clipboard
+    registers: HashMap<char, String>, // Text copied into registers named by letters
+    deleted: VecDeque<String>, // Text cut most recently, newest first
+    register: Option<char>, // The register named for the next copy, cut or paste
+    mode: Mode,            // Current mode when modal editing is on
+    pending: String,       // Keys of an unfinished normal mode command
+    recording: Option<Vec<InputEvent>>, // Keys typed since recording started
+    recorded: Vec<InputEvent>, // The last recording of keys
+    playback: VecDeque<InputEvent>, // Recorded keys that are still to be replayed
+    held: VecDeque<InputEvent>, // Keys of pasted text that are to be handled one at a time
+    replaying: bool,       // Whether the current key is being replayed
+    key_start: usize,      // Length of the recording before the current key
+    keys: Vec<InputEvent>, // Keys read for the command in progress
+    keys_mode: Mode,       // The mode the command in progress started in
+    edited: bool,          // Whether the command in progress changed the text
+    editing: bool,         // Whether the last command changed the text, so a run goes on
+    last_edit: (Mode, Vec<InputEvent>, Mode), // The last run of edits, with the modes around it
+    closers: Vec<Position>, // Closing characters that were typed automatically
+    stops: Vec<Position>,  // Places to fill in of the snippet last expanded
+    stop: usize,           // The place of the snippet the cursor was moved to
+    dictionary: Option<Dictionary>, // Words for spell checking, read when first needed
+    hex_files: Vec<String>, // Files to show as bytes once Ox starts
+    servers: HashMap<String, Option<Server>>, // Language servers by language, None if they failed
+    synced: HashMap<String, usize>, // How many edits of each document servers have seen
+    blame: Option<Blame>,  // The last line blamed and what git said about it
+    blaming: Option<Receiver<Blame>>, // What git says about the line it is looking into
+    git_checked: Option<Instant>, // When the branch was last asked for, None to ask straight away
+    git_checking: Option<Receiver<(String, Option<git::Branch>)>>, // The branch being asked for
+    building: Option<(String, Receiver<Built>)>, // The build running in the background, if any
+    searching: Option<(String, Receiver<Searched>)>, // The search of the files running, if any
+    problems: Vec<Problem>, // Where the last build found errors
+    problem: Option<usize>, // Which of them was gone to last
+    tag_origins: Vec<(String, Position)>, // Where definitions were gone to from, latest last
+    listing: bool,         // Whether the problems are listed below
+    plugins: Vec<Plugin>,  // The programs extending the editor
+    remote: Option<Remote>, // The socket other programs control Ox through
+    session: Option<Session>, // What was open before closing every document
+    histories: HashMap<String, Vec<String>>, // Earlier input to each prompt, by its name
+    prompting: Option<usize>, // Where the cursor is on the command line, if asking
+    completing: bool,      // Whether the prompt waits on a directory for paths to complete
+    matches: Option<(usize, usize)>, // The search match the cursor is on, out of how many
+    batch: Option<String>, // The script run over the files without the terminal, if any
+    dragging: Option<usize>, // The document whose scrollbar is held by the mouse
 }
 
 // Implementing methods for our editor struct / class
 impl Editor {
-    pub fn new(args: App) -> Result<Self, ErrorKind> {
-        // Create a new editor instance
-        let args = args.get_matches();
-        // Set up terminal
-        let term = Terminal::new()?;
+    #[allow(clippy::too_many_lines)] // Opens every file the command line asked for
+    pub fn new(args: &ArgMatches, term: Terminal) -> Self {
+        // Create a new editor instance, drawing on the terminal given
+        if let Some(level) = args.value_of("log") {
+            set_log_level(level);
+        }
+        log!("Ox started", "Ox has just been started");
+        let batch = args.value_of("batch").map(str::to_string);
         // Set up the arguments
-        let files: Vec<&str> = args.values_of("files").unwrap_or_default().collect();
+        let mut files = Editor::attach_lines(args.values_of("files").unwrap_or_default());
+        let compare = args.is_present("diff");
+        if let Some(compared) = args.values_of("diff") {
+            files = compared.map(str::to_string).collect();
+        }
         let config_path = args.value_of("config").unwrap_or_default();
         let mut config = Reader::read(config_path);
-        // Check for fallback colours
-        if config.0.theme.fallback {
-            let max = Terminal::availablility();
-            log!("Available Colours", max);
-            if max != 24 {
-                // Fallback to 16 bit colours
-                config.0.highlights.insert(
-                    "16fallback".to_string(),
-                    [
-                        ("comments".to_string(), (128, 128, 128)),
-                        ("keywords".to_string(), (0, 0, 255)),
-                        ("namespaces".to_string(), (0, 0, 255)),
-                        ("references".to_string(), (0, 0, 128)),
-                        ("strings".to_string(), (0, 128, 0)),
-                        ("characters".to_string(), (0, 128, 128)),
-                        ("digits".to_string(), (0, 128, 128)),
-                        ("booleans".to_string(), (0, 255, 0)),
-                        ("functions".to_string(), (0, 128, 128)),
-                        ("structs".to_string(), (0, 128, 128)),
-                        ("macros".to_string(), (128, 0, 128)),
-                        ("attributes".to_string(), (0, 128, 128)),
-                        ("headers".to_string(), (0, 128, 128)),
-                        ("symbols".to_string(), (128, 128, 0)),
-                        ("global".to_string(), (0, 255, 0)),
-                        ("operators".to_string(), (0, 128, 128)),
-                        ("regex".to_string(), (0, 255, 0)),
-                        ("search_inactive".to_string(), (128, 128, 128)),
-                        ("search_active".to_string(), (0, 128, 128)),
-                    ]
-                    .iter()
-                    .cloned()
-                    .collect(),
-                );
-                config.0.theme = Theme {
-                    transparent_editor: false,
-                    editor_bg: (0, 0, 0),
-                    editor_fg: (255, 255, 255),
-                    status_bg: (128, 128, 128),
-                    status_fg: (255, 255, 255),
-                    line_number_fg: (255, 255, 255),
-                    line_number_bg: (0, 0, 0),
-                    active_tab_fg: (255, 255, 255),
-                    inactive_tab_fg: (255, 255, 255),
-                    active_tab_bg: (128, 128, 128),
-                    inactive_tab_bg: (0, 0, 0),
-                    warning_fg: (255, 255, 0),
-                    error_fg: (255, 0, 0),
-                    info_fg: (255, 255, 255),
-                    default_theme: "16fallback".to_string(),
-                    fallback: true,
-                };
-            }
+        // Colours are drawn as close as the terminal can show them
+        let max = term.colours();
+        log!("Available Colours", max);
+        Reader::set_colours(max);
+        // Terminals with 256 colours get close enough to the theme, but 16 need a theme of their own
+        if config.0.theme.fallback && max == 16 {
+            // Fallback to 16 bit colours
+            config.0.highlights.insert(
+                "16fallback".to_string(),
+                [
+                    ("comments".to_string(), (128, 128, 128)),
+                    ("keywords".to_string(), (0, 0, 255)),
+                    ("namespaces".to_string(), (0, 0, 255)),
+                    ("references".to_string(), (0, 0, 128)),
+                    ("strings".to_string(), (0, 128, 0)),
+                    ("characters".to_string(), (0, 128, 128)),
+                    ("digits".to_string(), (0, 128, 128)),
+                    ("booleans".to_string(), (0, 255, 0)),
+                    ("functions".to_string(), (0, 128, 128)),
+                    ("structs".to_string(), (0, 128, 128)),
+                    ("macros".to_string(), (128, 0, 128)),
+                    ("attributes".to_string(), (0, 128, 128)),
+                    ("headers".to_string(), (0, 128, 128)),
+                    ("symbols".to_string(), (128, 128, 0)),
+                    ("global".to_string(), (0, 255, 0)),
+                    ("operators".to_string(), (0, 128, 128)),
+                    ("regex".to_string(), (0, 255, 0)),
+                    ("search_inactive".to_string(), (128, 128, 128)),
+                    ("search_active".to_string(), (0, 128, 128)),
+                ]
+                .iter()
+                .cloned()
+                .collect(),
+            );
+            config.0.theme = Theme {
+                transparent_editor: false,
+                editor_bg: (0, 0, 0),
+                editor_fg: (255, 255, 255),
+                status_bg: (128, 128, 128),
+                status_fg: (255, 255, 255),
+                line_number_fg: (255, 255, 255),
+                line_number_bg: (0, 0, 0),
+                line_highlight_bg: (0, 0, 128),
+                selection_bg: (0, 128, 128),
+                bracket_bg: (128, 0, 128),
+                git_added_fg: (0, 255, 0),
+                git_modified_fg: (255, 255, 0),
+                git_removed_fg: (255, 0, 0),
+                bookmark_fg: (0, 255, 255),
+                diff_added_bg: (0, 128, 0),
+                diff_removed_bg: (128, 0, 0),
+                diff_modified_bg: (0, 0, 128),
+                guide_fg: (128, 128, 128),
+                whitespace_fg: (128, 128, 128),
+                ruler_bg: (0, 0, 128),
+                active_tab_fg: (255, 255, 255),
+                inactive_tab_fg: (255, 255, 255),
+                active_tab_bg: (128, 128, 128),
+                inactive_tab_bg: (0, 0, 0),
+                warning_fg: (255, 255, 0),
+                error_fg: (255, 0, 0),
+                info_fg: (255, 255, 255),
+                default_theme: "16fallback".to_string(),
+                fallback: true,
+            };
         }
-        // Read in documents
+        // Read in documents, leaving files that aren't text to be shown as bytes
+        let read_only = args.is_present("readonly") || args.is_present("view");
         let mut documents = vec![];
-        if files.is_empty() {
-            documents.push(Document::new(
-                &config.0,
-                &config.1,
-                args.is_present("readonly"),
-            ));
+        let mut hex_files = vec![];
+        // Text piped in is read from standard input while keys come from the terminal
+        let piped = files.is_empty() && !io::stdin().is_terminal();
+        // The files of the last session go before any others when it is picked up again
+        let restore = !compare
+            && (args.is_present("restore")
+                || (config.0.general.restore_session && files.is_empty() && !piped));
+        let session = if restore {
+            Editor::load_session()
         } else {
-            for file in &files {
-                documents.push(Document::from(
-                    &config.0,
-                    &config.1,
-                    file,
-                    args.is_present("readonly"),
-                ));
+            None
+        };
+        if let Some(session) = &session {
+            let restored = session
+                .files
+                .iter()
+                .map(|file| file.0.clone())
+                .filter(|path| Path::new(path).exists());
+            files = restored.chain(files).collect();
+        }
+        for file in files.iter().map(String::as_str).chain(piped.then_some("-")) {
+            let path = file.split(':').next().unwrap_or(file);
+            if file == "-" {
+                let mut bytes = vec![];
+                if let Err(error) = io::stdin().read_to_end(&mut bytes) {
+                    log!(error, "Reading standard input", error.to_string());
+                }
+                documents.push(Document::from_text(&config.0, &config.1, &bytes, read_only));
+            } else if args.is_present("hex") || Editor::is_binary(path, &config.0) {
+                hex_files.push(path.to_string());
+            } else {
+                documents.push(Document::from(&config.0, &config.1, file, read_only));
+            }
+        }
+        if documents.is_empty() {
+            documents.push(Document::new(&config.0, &config.1, read_only));
+        }
+        // How reading the config went is the first message kept
+        let loaded = Document::config_to_commandline(&config.1);
+        documents[0].set_command_line(loaded.text, loaded.msg);
+        // Listen for other programs that want to control the editor
+        let remote = args.value_of("listen").and_then(|path| {
+            Remote::listen(path)
+                .map_err(|error| {
+                    documents[0].set_command_line(
+                        format!("Couldn't listen at {path}: {error}"),
+                        Type::Error,
+                    );
+                })
+                .ok()
+        });
+        // Restore the cursor to where it was left last time
+        let positions = Editor::load_positions();
+        for d in &mut documents {
+            if args.is_present("no-restore") || batch.is_some() || d.true_path.contains(':') {
+                continue;
+            }
+            if let Some(pos) = fs::canonicalize(&d.path)
+                .ok()
+                .and_then(|path| positions.get(&*path.to_string_lossy()))
+            {
+                d.restore(*pos, &term.size);
+            }
+        }
+        // Put the files of the last session back the way they were
+        let (mut tab, mut split) = (0, None);
+        if let Some(session) = session {
+            for (path, cursor, top) in &session.files {
+                if let Some(d) = documents.iter_mut().find(|d| d.true_path == *path) {
+                    d.offset.y = cmp::min(*top, d.rows.len().saturating_sub(1));
+                    d.restore(*cursor, &term.size);
+                }
             }
+            // Files may have gone missing since, so tabs are found again by their paths
+            let find = |index: usize| {
+                let path = &session.files.get(index)?.0;
+                documents.iter().position(|d| d.true_path == *path)
+            };
+            tab = find(session.tab).unwrap_or_default();
+            split = session.split.and_then(|(other, vertical, first)| {
+                let other = find(other).filter(|other| *other != tab)?;
+                Some(Split {
+                    other,
+                    vertical,
+                    first,
+                })
+            });
+        }
+        // Two files being compared start off side by side
+        let mut diff = None;
+        if compare && documents.len() == 2 {
+            tab = 0;
+            split = Some(Split {
+                other: 1,
+                vertical: true,
+                first: true,
+            });
+            diff = Some(Diff {
+                left: 0,
+                right: 1,
+                edits: (usize::MAX, usize::MAX),
+                comparison: Comparison::default(),
+            });
         }
         // Calculate neater paths
         for d in &mut documents {
             d.correct_path(&term.size);
         }
         // Create the new editor instance
-        Ok(Self {
+        Self {
             quit: false,
             // Display information about the config file into text for the status line
+            size: term.size,
             term,
-            tab: 0,
+            split,
+            diff,
+            preview: None,
+            explorer: None,
+            console: None,
+            tab,
             doc: documents,
             last_keypress: None,
+            idle_since: Instant::now(),
+            disk_checked: Instant::now(),
             keypress: KeyBinding::Unsupported,
+            chord: vec![],
+            chord_since: None,
             config: config.0.clone(),
             config_path: config_path.to_string(),
             status: config.1,
@@ -225,26 +546,444 @@ impl Editor {
             position_bank: HashMap::new(),
             row_bank: HashMap::new(),
             theme: config.0.theme.default_theme,
-        })
+            positions,
+            recent: Editor::load_recent(),
+            recent_chosen: None,
+            count: 0,
+            clipboard: String::new(),
+            registers: HashMap::new(),
+            deleted: VecDeque::new(),
+            register: None,
+            mode: Mode::Normal,
+            pending: String::new(),
+            recording: None,
+            recorded: vec![],
+            playback: VecDeque::new(),
+            replaying: false,
+            held: VecDeque::new(),
+            key_start: 0,
+            keys: vec![],
+            keys_mode: Mode::Normal,
+            edited: false,
+            editing: false,
+            last_edit: (Mode::Normal, vec![], Mode::Normal),
+            closers: vec![],
+            stops: vec![],
+            stop: 0,
+            dictionary: None,
+            hex_files,
+            servers: HashMap::new(),
+            synced: HashMap::new(),
+            blame: None,
+            blaming: None,
+            git_checked: None,
+            git_checking: None,
+            building: None,
+            searching: None,
+            problems: vec![],
+            problem: None,
+            tag_origins: vec![],
+            listing: false,
+            plugins: vec![],
+            remote,
+            session: None,
+            histories: HashMap::new(),
+            prompting: None,
+            completing: false,
+            matches: None,
+            batch,
+            dragging: None,
+        }
+    }
+    fn attach_lines<'a>(args: impl Iterator<Item = &'a str>) -> Vec<String> {
+        // Turn `+N` arguments into `file:N` for the file before them (or after when first)
+        let mut files: Vec<String> = vec![];
+        let mut pending = None;
+        for arg in args {
+            match arg.strip_prefix('+').filter(|n| line_column(n).is_some()) {
+                Some(line) => match files.last_mut() {
+                    Some(file) if !file.contains(':') => *file = format!("{file}:{line}"),
+                    _ => pending = Some(line),
+                },
+                None => files.push(match pending.take() {
+                    Some(line) => format!("{arg}:{line}"),
+                    None => arg.to_string(),
+                }),
+            }
+        }
+        files
     }
     pub fn run(&mut self) {
         // Run the editor instance
+        if let Some(script) = self.batch.clone() {
+            let ok = self.run_script(&script);
+            self.servers.clear();
+            std::process::exit(i32::from(!ok));
+        }
         log!("Ox opened", "Ox was opened successfully");
+        let focus = self.tab;
+        for tab in 0..self.doc.len() {
+            self.tab = tab;
+            self.remember_recent();
+            self.restore_history();
+            self.recover_swap();
+        }
+        self.tab = focus;
+        for path in std::mem::take(&mut self.hex_files) {
+            self.hex_edit(&path);
+        }
+        self.load_plugins();
         while !self.quit {
+            self.check_disk();
             self.update();
             self.process_input();
         }
+        // Changes that weren't saved on the way out were thrown away on purpose
+        for doc in &mut self.doc {
+            doc.remove_swap();
+        }
+        // Shut the language servers and plugins down
+        self.servers.clear();
+        self.plugins.clear();
+        self.save_positions();
+        self.save_recent();
+        self.save_session();
         // Leave alternative screen and disable raw mode
-        Terminal::exit();
+        self.term.lend();
+    }
+    fn run_script(&mut self, script: &str) -> bool {
+        // Run each line of a script as a command over every file in turn, without drawing
+        // anything, and move on to the next file at the first error, which goes to stderr
+        let text = match fs::read_to_string(script) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("Couldn't read {script}: {error}");
+                return false;
+            }
+        };
+        let mut ok = true;
+        for tab in 0..self.doc.len() {
+            self.tab = tab;
+            for (number, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                // Quitting leaves the rest of the script and whatever wasn't written
+                let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+                let quit = matches!(command, "quit" | "q" | "q!" | "wq");
+                if command == "wq" {
+                    self.text_to_event(format!("save {rest}").trim_end());
+                } else if !quit {
+                    self.text_to_event(line);
+                }
+                let doc = &mut self.doc[self.tab];
+                let errors: Vec<String> = doc
+                    .messages
+                    .drain(..)
+                    .filter(|message| message.msg == Type::Error)
+                    .map(|message| message.text)
+                    .collect();
+                for error in &errors {
+                    eprintln!("{}:{}: {}: {}", script, number + 1, doc.path, error);
+                }
+                if !errors.is_empty() {
+                    ok = false;
+                    break;
+                }
+                if quit {
+                    break;
+                }
+            }
+        }
+        ok
+    }
+    fn data_path(name: &str) -> Option<PathBuf> {
+        // Work out where something kept between sessions goes
+        let base_dirs = BaseDirs::new()?;
+        Some(base_dirs.data_dir().join("ox").join(name))
+    }
+    fn load_positions() -> HashMap<String, (usize, usize)> {
+        // Read in the cursor positions from the last session
+        Editor::data_path("positions.ron")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|file| from_str(&file).ok())
+            .unwrap_or_default()
+    }
+    fn load_session() -> Option<Session> {
+        // Read in what was open when Ox was last closed
+        let path = Editor::data_path("session.ron")?;
+        from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+    fn session(&self) -> Session {
+        // Describe the files that are open and how they are laid out, leaving out unnamed ones
+        let mut session = Session::default();
+        let mut tabs = HashMap::new();
+        for (tab, doc) in self.doc.iter().enumerate() {
+            if let Ok(path) = fs::canonicalize(&doc.path) {
+                let cursor = (
+                    doc.cursor.x + doc.offset.x,
+                    doc.cursor.y + doc.offset.y - OFFSET,
+                );
+                tabs.insert(tab, session.files.len());
+                session
+                    .files
+                    .push((path.to_string_lossy().to_string(), cursor, doc.top()));
+            }
+        }
+        session.tab = tabs.get(&self.tab).copied().unwrap_or_default();
+        session.split = self.split.as_ref().and_then(|split| {
+            let other = *tabs.get(&split.other)?;
+            tabs.contains_key(&self.tab)
+                .then_some((other, split.vertical, split.first))
+        });
+        session
+    }
+    fn save_session(&mut self) {
+        // Write out what is open for the next time Ox is asked to restore it
+        let session = self.session.take().unwrap_or_else(|| self.session());
+        if let Some(path) = Editor::data_path("session.ron") {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(state) = ron::ser::to_string(&session) {
+                if fs::write(path, state).is_err() {
+                    log!(error, "Session", "Couldn't save the session");
+                }
+            }
+        }
+    }
+    fn save_positions(&self) {
+        // Write out the cursor positions for the next session
+        if let Some(path) = Editor::data_path("positions.ron") {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(state) = ron::ser::to_string(&self.positions) {
+                if fs::write(path, state).is_err() {
+                    log!(error, "Positions", "Couldn't save the cursor positions");
+                }
+            }
+        }
+    }
+    fn load_recent() -> Vec<String> {
+        // Read in the files opened lately, leaving out the ones that have gone since
+        let recent: Vec<String> = Editor::data_path("recent.ron")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|file| from_str(&file).ok())
+            .unwrap_or_default();
+        recent
+            .into_iter()
+            .filter(|path| Path::new(path).is_file())
+            .collect()
+    }
+    fn save_recent(&self) {
+        // Write out the files opened lately for the next session
+        if let Some(path) = Editor::data_path("recent.ron") {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(state) = ron::ser::to_string(&self.recent) {
+                if fs::write(path, state).is_err() {
+                    log!(error, "Recent", "Couldn't save the recent files");
+                }
+            }
+        }
+    }
+    fn remember_recent(&mut self) {
+        // Put the current document at the top of the files opened lately
+        if let Ok(path) = fs::canonicalize(&self.doc[self.tab].path) {
+            let path = path.to_string_lossy().to_string();
+            self.recent.retain(|recent| *recent != path);
+            self.recent.insert(0, path);
+            self.recent.truncate(RECENT);
+        }
+    }
+    fn recent_name(path: &str) -> String {
+        // How a recent file is shown, from the working directory or home where it can be
+        let path = Path::new(path);
+        if let Some(relative) = env::current_dir()
+            .ok()
+            .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+        {
+            return relative.to_string_lossy().to_string();
+        }
+        if let Some(relative) = BaseDirs::new().and_then(|dirs| {
+            path.strip_prefix(dirs.home_dir())
+                .ok()
+                .map(Path::to_path_buf)
+        }) {
+            return format!("~/{}", relative.to_string_lossy());
+        }
+        path.to_string_lossy().to_string()
+    }
+    fn open_recent(&mut self) {
+        // Pick one of the files opened lately that isn't open now and open it
+        let open: Vec<PathBuf> = self
+            .doc
+            .iter()
+            .filter_map(|doc| fs::canonicalize(&doc.path).ok())
+            .collect();
+        let recent: Vec<String> = self
+            .recent
+            .iter()
+            .filter(|path| {
+                Path::new(path).is_file() && !open.iter().any(|open| open == Path::new(path))
+            })
+            .cloned()
+            .collect();
+        if recent.is_empty() {
+            self.doc[self.tab].set_command_line("No recent files".to_string(), Type::Info);
+            return;
+        }
+        let names: Vec<String> = recent
+            .iter()
+            .map(|path| Editor::recent_name(path))
+            .collect();
+        if let Some(index) = self.popup(&names) {
+            self.open_document(Some(recent[index].clone()));
+        }
+    }
+    fn pick_recent(&mut self, key: KeyEvent) -> bool {
+        // Go through the recent files on the welcome screen, opening one with enter in its place
+        let shown = self.recent.len().min(RECENT_SHOWN);
+        if shown == 0 || !key.modifiers.is_empty() {
+            return false;
+        }
+        let chosen = match (key.code, self.recent_chosen) {
+            (KeyCode::Down, None) => 0,
+            (KeyCode::Up, None) => shown - 1,
+            (KeyCode::Down, Some(chosen)) => (chosen + 1) % shown,
+            (KeyCode::Up, Some(chosen)) => (chosen + shown - 1) % shown,
+            (KeyCode::Enter, Some(chosen)) => {
+                self.recent_chosen = None;
+                let welcome = self.tab;
+                self.open_document(Some(self.recent[chosen].clone()));
+                if self.tab != welcome {
+                    // The new document is last, so it can take the empty one's place
+                    self.doc.swap_remove(welcome);
+                    self.tab = welcome;
+                }
+                return true;
+            }
+            _ => {
+                self.recent_chosen = None;
+                return false;
+            }
+        };
+        self.recent_chosen = Some(chosen);
+        true
+    }
+    fn remember_position(&mut self) {
+        // Keep track of where the cursor was left in the current document
+        if let Ok(path) = fs::canonicalize(&self.doc[self.tab].path) {
+            let pos = self.current();
+            self.positions
+                .insert(path.to_string_lossy().to_string(), (pos.x, pos.y));
+        }
+    }
+    fn history_path(path: &str) -> Option<PathBuf> {
+        // The history of each file is kept under a hash of where it is
+        let path = fs::canonicalize(path).ok()?;
+        let hash = fnv1a(path.to_string_lossy().bytes());
+        Editor::data_path(&format!("history/{hash:016x}.ron"))
+    }
+    fn remember_history(&self) {
+        // Keep the history of the current document for the next time it is opened
+        // Only the changes up to what is on disk can be picked up again
+        let doc = &self.doc[self.tab];
+        let Some(path) = Editor::history_path(&doc.path) else {
+            return;
+        };
+        let history = doc.fingerprint.and_then(|fingerprint| {
+            doc.undo_stack.keep(
+                doc.last_save_index,
+                fingerprint,
+                self.config.general.undo_history,
+            )
+        });
+        match history {
+            Some(history) if doc.changed_on_disk().is_none() => {
+                if let Some(dir) = path.parent() {
+                    let _ = fs::create_dir_all(dir);
+                }
+                if let Ok(state) = ron::ser::to_string(&history) {
+                    if fs::write(path, state).is_err() {
+                        log!(error, "History", "Couldn't save the undo history");
+                    }
+                }
+            }
+            // A history that no longer fits the file is no use next time
+            _ => {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+    fn restore_history(&mut self) {
+        // Pick up the history the current document had when it was last closed
+        let doc = &mut self.doc[self.tab];
+        if self.config.general.undo_history == 0 || doc.undo_stack.state() != 0 {
+            return;
+        }
+        let history = Editor::history_path(&doc.path)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|file| from_str::<History>(&file).ok());
+        if let (Some(history), Some(fingerprint)) = (history, doc.fingerprint) {
+            if let Some(stack) = EventStack::resume(history, fingerprint) {
+                doc.undo_stack = stack;
+                doc.last_save_index = doc.undo_stack.state();
+            }
+        }
     }
     fn read_event(&mut self) -> InputEvent {
-        // Wait until a key, mouse or terminal resize event
+        // Wait until a key, mouse or terminal resize event, typing out pasted text key by key
+        loop {
+            match self.read_input() {
+                TerminalInput::Event(event) => return event,
+                TerminalInput::Paste(text) => self.held.extend(keys(&text)),
+            }
+        }
+    }
+    fn read_input(&mut self) -> TerminalInput {
+        // Wait until a key, mouse or terminal resize event, or text is pasted
+        if let Some(key) = self.playback.pop_front() {
+            // Feed back recorded keys before reading any more
+            self.replaying = true;
+            self.keys.push(key);
+            return TerminalInput::Event(key);
+        }
+        self.replaying = false;
+        if self.batch.is_some() {
+            // Scripts have no keys to give, so whatever asks for one is cancelled
+            return TerminalInput::Event(InputEvent::Key(KeyEvent {
+                code: KeyCode::Esc,
+                modifiers: KeyModifiers::NONE,
+            }));
+        }
+        if let Some(key) = self.held.pop_front() {
+            // Pasted text that couldn't go in as it is goes in as the keys that type it
+            if let Some(recording) = &mut self.recording {
+                recording.push(key);
+            }
+            self.keys.push(key);
+            return TerminalInput::Event(key);
+        }
         loop {
-            if let Ok(true) = crossterm::event::poll(Duration::from_millis(16)) {
-                if let Ok(key) = crossterm::event::read() {
+            // Sleep until there is input or the next timer is due, rather than polling
+            let ready = self.next_timer().is_none_or(|wait| self.term.poll(wait));
+            if ready {
+                if let Some(input) = self.term.read() {
                     // When a keypress was detected
+                    log!(debug, "Input", format!("{:?}", input));
                     self.last_keypress = Some(Instant::now());
-                    return key;
+                    self.idle_since = Instant::now();
+                    if let TerminalInput::Event(key @ InputEvent::Key(_)) = input {
+                        if let Some(recording) = &mut self.recording {
+                            recording.push(key);
+                        }
+                        self.keys.push(key);
+                    }
+                    return input;
                 }
             } else {
                 // Check for a period of inactivity
@@ -254,416 +993,4656 @@ impl Editor {
                         // Commit the undo changes to the stack
                         self.doc[self.tab].undo_stack.commit();
                         self.last_keypress = None;
+                        if self.config.general.swap_files {
+                            self.write_swaps();
+                        }
                     }
                 }
+                // Show what the language servers have found
+                let mut redraw = self.lsp_receive();
+                redraw |= self.git_update();
+                redraw |= self.blame_update();
+                redraw |= self.branch_update();
+                redraw |= self.plugin_receive();
+                redraw |= self.remote_receive();
+                redraw |= self.console_receive();
+                redraw |= self.build_update();
+                redraw |= self.grep_update();
+                redraw |= self.chord_update();
+                redraw |= self.disk_update();
+                redraw |= self.message_update();
+                // Paths to complete show up in the prompt as soon as they are read
+                redraw |= self.completing;
+                let autosave = self.config.general.autosave;
+                if autosave != 0 && self.idle_since.elapsed().as_secs() >= autosave {
+                    self.idle_since = Instant::now();
+                    redraw |= self.autosave();
+                }
+                if redraw {
+                    // Redraw so that the changes show
+                    let (width, height) = (self.term.size.width, self.term.size.height);
+                    return TerminalInput::Event(InputEvent::Resize(width as u16, height as u16));
+                }
             }
         }
     }
-    fn key_event_to_ox_key(key: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
-        // Convert crossterm's complicated key structure into Ox's simpler one
-        let inner = match key {
-            KeyCode::Char(c) => RawKey::Char(c),
-            KeyCode::BackTab => RawKey::BackTab,
-            KeyCode::Insert => RawKey::Insert,
-            KeyCode::Esc => RawKey::Esc,
-            KeyCode::Backspace => RawKey::Backspace,
-            KeyCode::Tab => RawKey::Tab,
-            KeyCode::Enter => RawKey::Enter,
-            KeyCode::Delete => RawKey::Delete,
-            KeyCode::Null => RawKey::Null,
-            KeyCode::PageUp => RawKey::PageUp,
-            KeyCode::PageDown => RawKey::PageDown,
-            KeyCode::Home => RawKey::Home,
-            KeyCode::End => RawKey::End,
-            KeyCode::Up => RawKey::Up,
-            KeyCode::Down => RawKey::Down,
-            KeyCode::Left => RawKey::Left,
-            KeyCode::Right => RawKey::Right,
-            KeyCode::F(i) => return KeyBinding::F(i),
+    fn next_timer(&self) -> Option<Duration> {
+        // How long until something has to happen without any input, if anything does
+        let undo = self.last_keypress.map(|time| {
+            Duration::from_secs(self.config.general.undo_period).saturating_sub(time.elapsed())
+        });
+        let autosave = self.config.general.autosave;
+        let autosave = (autosave != 0 && self.doc.iter().any(|doc| doc.dirty))
+            .then(|| Duration::from_secs(autosave).saturating_sub(self.idle_since.elapsed()));
+        // Language servers send messages whenever they like, so check for them often
+        let lsp = self
+            .servers
+            .values()
+            .flatten()
+            .any(|server| server.alive)
+            .then(|| Duration::from_millis(100));
+        // Plugins can ask for things at any time as well
+        let plugins = self
+            .plugins
+            .iter()
+            .any(|plugin| plugin.alive)
+            .then(|| Duration::from_millis(100));
+        let remote = self.remote.as_ref().map(|_| Duration::from_millis(50));
+        // A shell in the console can write at any time and should show up straight away
+        let console = self.console.as_ref().map(|_| Duration::from_millis(20));
+        let build = self.building.as_ref().map(|_| Duration::from_millis(50));
+        let grep = self.searching.as_ref().map(|_| Duration::from_millis(50));
+        let completing = self.completing.then(|| Duration::from_millis(20));
+        let disk = (!self.doc[self.tab].path.is_empty())
+            .then(|| DISK_DELAY.saturating_sub(self.disk_checked.elapsed()));
+        let message = self.doc[self.tab].message_left(self.config.general.message_timeout);
+        let chord = self.chord_since.map(|since| {
+            Duration::from_millis(self.config.general.chord_timeout).saturating_sub(since.elapsed())
+        });
+        // Changes are compared with what git has once typing pauses
+        let git = (self.config.general.git_gutter
+            && self
+                .doc
+                .iter()
+                .any(|doc| !doc.path.is_empty() && doc.git_edits != Some(doc.edits)))
+        .then(|| GIT_DELAY.saturating_sub(self.idle_since.elapsed()));
+        // Git is asked about the cursor's line once it stays put, and then checked on often
+        let blame = if self.blaming.is_some() {
+            Some(Duration::from_millis(50))
+        } else {
+            self.blame_key()
+                .is_some_and(|key| self.blame.as_ref().map(|blame| &blame.0) != Some(&key))
+                .then(|| GIT_DELAY.saturating_sub(self.idle_since.elapsed()))
         };
-        match modifiers {
-            KeyModifiers::CONTROL => KeyBinding::Ctrl(inner),
-            KeyModifiers::ALT => KeyBinding::Alt(inner),
-            KeyModifiers::SHIFT => KeyBinding::Shift(inner),
-            KeyModifiers::NONE => KeyBinding::Raw(inner),
-            _ => KeyBinding::Unsupported,
+        // The branch is asked for every so often when the status line shows it
+        let branch = if self.git_checking.is_some() {
+            Some(Duration::from_millis(50))
+        } else {
+            self.shows_branch().then(|| {
+                self.git_checked.map_or(Duration::ZERO, |time| {
+                    BRANCH_DELAY.saturating_sub(time.elapsed())
+                })
+            })
+        };
+        undo.into_iter()
+            .chain(autosave)
+            .chain(lsp)
+            .chain(git)
+            .chain(blame)
+            .chain(branch)
+            .chain(plugins)
+            .chain(remote)
+            .chain(console)
+            .chain(build)
+            .chain(grep)
+            .chain(completing)
+            .chain(chord)
+            .chain(disk)
+            .chain(message)
+            .min()
+    }
+    fn disk_update(&mut self) -> bool {
+        // Look at the file now and then, giving whether something changed it that hasn't been dealt with
+        if self.disk_checked.elapsed() < DISK_DELAY {
+            return false;
         }
+        self.disk_checked = Instant::now();
+        let doc = &self.doc[self.tab];
+        doc.changed_on_disk()
+            .is_some_and(|time| doc.kept != Some(time))
     }
-    fn process_key(&mut self, key: KeyEvent) {
-        self.doc[self.tab].show_welcome = false;
-        let cursor = self.doc[self.tab].cursor;
-        let offset = self.doc[self.tab].offset;
-        let current = Position {
-            x: cursor.x + offset.x,
-            y: cursor.y + offset.y - OFFSET,
+    fn check_disk(&mut self) {
+        // Bring in changes other programs made to the file, asking first if there are changes here too
+        let time = match self.doc[self.tab].changed_on_disk() {
+            Some(time) if self.doc[self.tab].kept != Some(time) => time,
+            _ => return,
         };
-        let ox_key = Editor::key_event_to_ox_key(key.code, key.modifiers);
-        self.keypress = ox_key;
-        match ox_key {
-            KeyBinding::Raw(RawKey::Enter) => {
-                self.doc[self.tab].redo_stack.empty();
-                if current.x == 0 {
-                    // Return key pressed at the start of the line
-                    self.execute(Event::InsertLineAbove(current), false);
-                } else if current.x == self.doc[self.tab].rows[current.y].length() {
-                    // Return key pressed at the end of the line
-                    self.execute(Event::InsertLineBelow(current), false);
-                    self.execute(Event::MoveCursor(1, Direction::Down), false);
-                } else {
-                    // Return key pressed in the middle of the line
-                    self.execute(Event::SplitDown(current, current), false);
+        let name = self.doc[self.tab].name.clone();
+        if self.doc[self.tab].dirty {
+            loop {
+                self.doc[self.tab].set_prompt_line(
+                    format!(
+                        "{name} was changed by another program, (r)eload it or (k)eep your changes?"
+                    ),
+                    Type::Warning,
+                );
+                self.update();
+                let InputEvent::Key(key) = self.read_event() else {
+                    continue;
+                };
+                match key.code {
+                    KeyCode::Char('r') => break,
+                    KeyCode::Char('k') | KeyCode::Esc => {
+                        // Saving will ask before writing over the newer file
+                        self.doc[self.tab].kept = Some(time);
+                        self.doc[self.tab]
+                            .set_command_line(format!("Kept your changes to {name}"), Type::Info);
+                        return;
+                    }
+                    _ => (),
                 }
             }
-            KeyBinding::Raw(RawKey::Tab) => {
-                self.doc[self.tab].redo_stack.empty();
-                self.execute(Event::InsertTab(current), false);
-            }
-            KeyBinding::Raw(RawKey::Backspace) => {
-                self.doc[self.tab].redo_stack.empty();
-                self.execute(
-                    if current.x == 0 && current.y != 0 {
-                        // Backspace at the start of a line
-                        Event::SpliceUp(current, current)
-                    } else if current.x == 0 {
-                        return;
-                    } else {
-                        // Backspace in the middle of a line
-                        let row = self.doc[self.tab].rows[current.y].clone();
-                        let chr = row
-                            .ext_chars()
-                            .get(current.x.saturating_add(1))
-                            .map_or(" ", |chr| *chr);
-                        let current = Position {
-                            x: current.x.saturating_sub(UnicodeWidthStr::width(chr)),
-                            y: current.y,
-                        };
-                        Event::Deletion(current, chr.parse().unwrap_or(' '))
-                    },
-                    false,
-                );
+        }
+        let current = self.current();
+        let doc = &mut self.doc[self.tab];
+        if let Err(error) = doc.reload(&self.config) {
+            // The file may have been moved away or be half written, so it is left alone
+            doc.kept = Some(time);
+            doc.set_command_line(format!("Couldn't reload {name}: {error}"), Type::Error);
+            return;
+        }
+        // The cursor stays where it was, as far as the new text allows
+        let y = current.y.min(doc.rows.len() - 1);
+        let x = current.x.min(doc.rows[y].length());
+        doc.goto(Position { x, y }, &self.size);
+        doc.recalculate_graphemes();
+        doc.set_command_line(
+            format!("{name} was changed by another program and reloaded"),
+            Type::Info,
+        );
+        self.lsp_notify(self.tab, false);
+    }
+    fn write_swaps(&mut self) {
+        // Bring the swap files up to date with the changes that haven't been saved yet
+        for doc in &mut self.doc {
+            if doc.dirty {
+                doc.write_swap();
+            } else {
+                doc.remove_swap();
             }
-            // Detect control and alt and function key bindings
-            KeyBinding::Ctrl(_) | KeyBinding::Alt(_) | KeyBinding::F(_) => {
-                if let Some(commands) = self.config.keys.get(&ox_key) {
-                    for i in commands.clone() {
-                        self.text_to_event(&i);
+        }
+    }
+    fn recover_swap(&mut self) {
+        // Offer to bring back the changes from a session that ended without saving
+        let swap = match self.doc[self.tab].swap_path() {
+            Some(swap) if swap.exists() => swap,
+            _ => return,
+        };
+        if !self.config.general.swap_files || self.doc[self.tab].read_only {
+            return;
+        }
+        let name = self.doc[self.tab].name.clone();
+        loop {
+            self.doc[self.tab].set_prompt_line(
+                format!("{name} has unsaved changes from before, recover them? (y/n)"),
+                Type::Warning,
+            );
+            self.update();
+            let InputEvent::Key(key) = self.read_event() else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('y') => {
+                    if let Err(error) = self.doc[self.tab].recover() {
+                        self.doc[self.tab].set_command_line(
+                            format!("Couldn't recover the changes: {error}"),
+                            Type::Error,
+                        );
+                    } else {
+                        self.doc[self.tab]
+                            .set_command_line("Recovered unsaved changes".to_string(), Type::Info);
                     }
+                    return;
+                }
+                KeyCode::Char('n') => {
+                    let _ = fs::remove_file(swap);
+                    self.doc[self.tab]
+                        .set_command_line("Discarded unsaved changes".to_string(), Type::Info);
+                    return;
                 }
+                _ => (),
             }
-            KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
-                self.doc[self.tab].redo_stack.empty();
-                self.execute(Event::Insertion(current, c), false);
+        }
+    }
+    fn autosave(&mut self) -> bool {
+        // Save the documents with changes that already have a file, returning whether any did
+        let mut saved = vec![];
+        for (tab, doc) in self.doc.iter_mut().enumerate().filter(|(_, doc)| {
+            doc.dirty && !doc.read_only && !doc.path.is_empty() && doc.changed_on_disk().is_none()
+        }) {
+            // Trailing whitespace is left alone so the line being typed on doesn't change
+            let path = doc.path.clone();
+            doc.undo_stack.commit();
+            match doc.save(&path, &self.config) {
+                Ok(_) => {
+                    doc.dirty = false;
+                    doc.last_save_index = doc.undo_stack.state();
+                }
+                Err(error) => doc.set_command_line(
+                    format!("Failed to autosave \"{path}\": {error}"),
+                    Type::Error,
+                ),
             }
-            KeyBinding::Raw(RawKey::Up) => self.execute(Event::MoveCursor(1, Direction::Up), false),
-            KeyBinding::Raw(RawKey::Down) => {
-                self.execute(Event::MoveCursor(1, Direction::Down), false)
+            saved.push(tab);
+        }
+        for tab in &saved {
+            self.lsp_notify(*tab, false);
+        }
+        !saved.is_empty()
+    }
+    fn server_for(&self, tab: usize) -> Option<(String, String)> {
+        // The language of a document and the command of its server, if it should have one
+        let doc = &self.doc[tab];
+        // Large files that are only partly read in would give it the wrong text
+        if !self.config.general.lsp || doc.path.is_empty() || doc.lazy.is_some() {
+            return None;
+        }
+        Reader::get_language(&self.config, &doc.filetype)
+            .filter(|lang| !lang.server.is_empty())
+            .map(|lang| (lang.name.clone(), lang.server.clone()))
+    }
+    fn lsp_sync(&mut self) {
+        // Start the servers documents need and send them the changes made since last time
+        for tab in 0..self.doc.len() {
+            let Some((language, command)) = self.server_for(tab) else {
+                continue;
+            };
+            let uri = lsp::uri(&self.doc[tab].path);
+            let edits = self.doc[tab].edits;
+            let sent = self.synced.get(&uri).copied();
+            if sent == Some(edits) {
+                continue;
             }
-            KeyBinding::Raw(RawKey::Left) => {
-                self.execute(Event::MoveCursor(1, Direction::Left), false)
+            if !self.servers.contains_key(&language) {
+                let server = match Server::start(&command) {
+                    Ok(server) => Some(server),
+                    Err(error) => {
+                        self.doc[self.tab].set_command_line(
+                            format!("Couldn't start {command}: {error}"),
+                            Type::Error,
+                        );
+                        None
+                    }
+                };
+                self.servers.insert(language.clone(), server);
             }
-            KeyBinding::Raw(RawKey::Right) => {
-                self.execute(Event::MoveCursor(1, Direction::Right), false)
+            if let Some(Some(server)) = self.servers.get_mut(&language) {
+                // The rows are sent as they are, so positions match up with them
+                let text = self.doc[tab].render(TabType::Spaces, 0);
+                if sent.is_some() {
+                    server.did_change(&uri, edits, &text);
+                } else {
+                    server.did_open(&uri, &language.to_lowercase(), edits, &text);
+                }
+                self.synced.insert(uri, edits);
             }
-            KeyBinding::Raw(RawKey::PageDown) => self.execute(Event::PageDown, false),
-            KeyBinding::Raw(RawKey::PageUp) => self.execute(Event::PageUp, false),
-            KeyBinding::Raw(RawKey::Home) => self.execute(Event::Home, false),
-            KeyBinding::Raw(RawKey::End) => self.execute(Event::End, false),
-            _ => (),
         }
     }
-    fn process_input(&mut self) {
-        // Read a key and act on it
-        match self.read_event() {
-            InputEvent::Key(key) => self.process_key(key),
-            InputEvent::Resize(width, height) => {
-                // Terminal resize event
-                self.term.size = Size {
-                    width: width as usize,
-                    height: height as usize,
-                };
-                // Move cursor if needed
-                let size = self.term.size.height.saturating_sub(3);
-                if self.doc[self.tab].cursor.y > size && size != 0 {
-                    // Prevent cursor going off the screen and breaking everything
-                    self.doc[self.tab].cursor.y = size;
-                }
-                // Re-render everything to the new size
-                self.update();
+    fn lsp_notify(&mut self, tab: usize, closed: bool) {
+        // Tell the server of a document that it was saved or closed
+        if !closed {
+            self.lsp_sync();
+        }
+        let Some((language, _)) = self.server_for(tab) else {
+            return;
+        };
+        let uri = lsp::uri(&self.doc[tab].path);
+        if let (Some(Some(server)), true) = (
+            self.servers.get_mut(&language),
+            self.synced.contains_key(&uri),
+        ) {
+            if closed {
+                server.did_close(&uri);
+                self.synced.remove(&uri);
+            } else {
+                server.did_save(&uri);
             }
-            InputEvent::Mouse(_) => (),
         }
     }
-    fn new_document(&mut self) {
-        // Create a new document
-        self.doc
-            .push(Document::new(&self.config, &self.status, false));
-        self.tab = self.doc.len().saturating_sub(1);
+    fn lsp_receive(&mut self) -> bool {
+        // Take in what the servers sent, returning whether any diagnostics changed
+        let mut changed = false;
+        for server in self.servers.values_mut().flatten() {
+            changed |= server.receive();
+        }
+        if changed {
+            for tab in 0..self.doc.len() {
+                let Some((language, _)) = self.server_for(tab) else {
+                    continue;
+                };
+                if let Some(Some(server)) = self.servers.get(&language) {
+                    let uri = lsp::uri(&self.doc[tab].path);
+                    self.doc[tab].diagnostics =
+                        server.diagnostics.get(&uri).cloned().unwrap_or_default();
+                }
+            }
+        }
+        changed
     }
-    fn open_document(&mut self, file: Option<String>) {
-        // Open a document
-        let to_open = if let Some(path) = file {
-            // File was specified
-            path
-        } else if let Some(path) = self.prompt("Open", ": ", &|_, _, _| {}) {
-            // Ask for a file and open it
-            path
+    fn git_refresh(&mut self, tab: usize) -> bool {
+        // Compare a document with what git has if it changed since last time, returning
+        // whether the marks in the gutter did; what git has is read again after a save
+        let doc = &mut self.doc[tab];
+        let tab_width = doc.tab_width;
+        // Large files aren't all held, and what git has of compressed files isn't their text
+        let unlike = doc.lazy.is_some() || doc.compression.is_some();
+        if doc.path.is_empty() || unlike || doc.git_edits == Some(doc.edits) {
+            return false;
+        }
+        if doc.git_edits.is_none() {
+            doc.git_base = git::base(&doc.path, doc.encoding, tab_width);
+        }
+        let rows: Vec<&str> = doc.rows.iter().map(|row| row.string.as_str()).collect();
+        let changes = doc
+            .git_base
+            .as_ref()
+            .map(|base| git::changes(base, &rows))
+            .unwrap_or_default();
+        doc.git_edits = Some(doc.edits);
+        let before = std::mem::replace(&mut doc.git_changes, changes);
+        before != doc.git_changes
+    }
+    fn git_update(&mut self) -> bool {
+        // Bring the gutter up to date once typing has paused for a moment
+        if !self.config.general.git_gutter || self.idle_since.elapsed() < GIT_DELAY {
+            return false;
+        }
+        let mut changed = false;
+        for tab in 0..self.doc.len() {
+            changed |= self.git_refresh(tab);
+        }
+        changed
+    }
+    fn blame_key(&self) -> Option<BlameKey> {
+        // The line to show the blame of, if it is switched on and the file could be tracked
+        let doc = &self.doc[self.tab];
+        (self.config.general.blame && !doc.path.is_empty())
+            .then(|| (doc.path.clone(), self.current().y, doc.edits))
+    }
+    fn blame_update(&mut self) -> bool {
+        // Ask git about the cursor's line in the background, returning whether it answered
+        if let Some(receiver) = &self.blaming {
+            return match receiver.try_recv() {
+                Ok(blame) => {
+                    self.blame = Some(blame);
+                    self.blaming = None;
+                    true
+                }
+                Err(TryRecvError::Empty) => false,
+                Err(TryRecvError::Disconnected) => {
+                    self.blaming = None;
+                    false
+                }
+            };
+        }
+        let key = match self.blame_key() {
+            Some(key) if self.blame.as_ref().map(|blame| &blame.0) != Some(&key) => key,
+            _ => return false,
+        };
+        if self.idle_since.elapsed() < GIT_DELAY {
+            return false;
+        }
+        let doc = &self.doc[self.tab];
+        let contents = doc
+            .encoding
+            .encode(&doc.render(doc.tabs, doc.tab_width))
+            .unwrap_or_default();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let text = git::blame(&key.0, key.1, &contents);
+            sender.send((key, text))
+        });
+        self.blaming = Some(receiver);
+        false
+    }
+    fn shows_branch(&self) -> bool {
+        // Whether the status line has the git branch in it
+        let general = &self.config.general;
+        [
+            &general.status_left,
+            &general.status_center,
+            &general.status_right,
+        ]
+        .iter()
+        .any(|part| part.contains("%g"))
+    }
+    fn branch_update(&mut self) -> bool {
+        // Ask git about the repository of the file in the background, returning whether it changed
+        if let Some(receiver) = &self.git_checking {
+            return match receiver.try_recv() {
+                Ok((path, branch)) => {
+                    self.git_checking = None;
+                    let mut changed = false;
+                    for doc in self.doc.iter_mut().filter(|doc| doc.path == path) {
+                        changed |= doc.git_branch != branch;
+                        doc.git_branch.clone_from(&branch);
+                    }
+                    changed
+                }
+                Err(TryRecvError::Empty) => false,
+                Err(TryRecvError::Disconnected) => {
+                    self.git_checking = None;
+                    false
+                }
+            };
+        }
+        let due = self
+            .git_checked
+            .is_none_or(|time| time.elapsed() >= BRANCH_DELAY);
+        if !due || !self.shows_branch() {
+            return false;
+        }
+        self.git_checked = Some(Instant::now());
+        let path = self.doc[self.tab].path.clone();
+        if path.is_empty() || ssh::parse(&path).is_some() {
+            return false;
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let branch = git::branch(&path);
+            sender.send((path, branch))
+        });
+        self.git_checking = Some(receiver);
+        false
+    }
+    fn next_hunk(&mut self, forward: bool) {
+        // Move to the start of the next or previous run of changed lines
+        self.git_refresh(self.tab);
+        let doc = &self.doc[self.tab];
+        let y = self.current().y;
+        let mut starts: Vec<usize> = doc
+            .git_changes
+            .keys()
+            .copied()
+            .filter(|&row| row == 0 || !doc.git_changes.contains_key(&(row - 1)))
+            .collect();
+        starts.sort_unstable();
+        let target = if forward {
+            starts.into_iter().find(|&row| row > y)
         } else {
-            // User cancelled
-            return;
+            starts.into_iter().rev().find(|&row| row < y)
         };
-        if let Some(doc) = Document::open(&self.config, &self.status, &to_open, false) {
-            // Overwrite the current document
-            self.doc.push(doc);
-            self.tab = self.doc.len().saturating_sub(1);
+        if let Some(y) = target {
+            let current = self.current();
+            self.doc[self.tab].record_jump(current);
+            self.doc[self.tab].goto(Position { x: 0, y }, &self.size);
+            self.doc[self.tab].recalculate_graphemes();
         } else {
-            self.doc[self.tab].set_command_line("File couldn't be opened".to_string(), Type::Error);
+            self.doc[self.tab].set_command_line("No more changes".to_string(), Type::Info);
         }
     }
-    fn save_document(&mut self, file: Option<String>, prompt: bool) {
-        // Save the document
-        let save = if let Some(file) = file {
-            // File was specified
-            file
-        } else {
-            // File not specified
-            if prompt {
-                // Save as
-                if let Some(path) = self.prompt("Save as", ": ", &|_, _, _| {}) {
-                    path
+    fn load_plugins(&mut self) {
+        // Start the programs in the plugins folder next to the config file
+        let dir = match BaseDirs::new() {
+            Some(dirs) => dirs.config_dir().join("ox").join("plugins"),
+            None => return,
+        };
+        for path in plugin::find(&dir) {
+            match Plugin::start(&path) {
+                Ok(plugin) => self.plugins.push(plugin),
+                Err(error) => self.doc[self.tab].set_command_line(
+                    format!("Couldn't start the plugin {}: {}", path.display(), error),
+                    Type::Error,
+                ),
+            }
+        }
+    }
+    fn plugin_receive(&mut self) -> bool {
+        // Carry out what plugins asked for by themselves, returning whether they asked anything
+        let mut handled = false;
+        for index in 0..self.plugins.len() {
+            while let Some(message) = self.plugins[index].receive(None) {
+                self.plugin_request(index, &message);
+                handled = true;
+            }
+        }
+        handled
+    }
+    fn run_plugin(&mut self, name: &str, args: &str) {
+        // Hand a command to a plugin, carrying out its requests until it is done with it
+        let index = self
+            .plugins
+            .iter()
+            .position(|plugin| plugin.name == name && plugin.alive);
+        let Some(index) = index else {
+            self.doc[self.tab]
+                .set_command_line(format!("There is no plugin called {name}"), Type::Error);
+            return;
+        };
+        let args: Vec<&str> = args.split_whitespace().collect();
+        self.plugins[index].send(&json!({"event": "command", "args": args}));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while let Some(message) =
+            self.plugins[index].receive(Some(deadline.saturating_duration_since(Instant::now())))
+        {
+            if !self.plugin_request(index, &message) {
+                break;
+            }
+        }
+    }
+    fn plugin_request(&mut self, index: usize, message: &Value) -> bool {
+        // Carry out a request from a plugin and answer it, returning false once it is done
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let result = match method {
+            "done" => return false,
+            "bind" => self.bind_plugin_key(index, params),
+            _ => self.api(method, params),
+        };
+        if let Some(id) = message.get("id") {
+            self.plugins[index].reply(id, result);
+        }
+        true
+    }
+    fn remote_receive(&mut self) -> bool {
+        // Carry out what programs sent through the socket, returning whether they sent anything
+        let messages = match &mut self.remote {
+            Some(remote) => remote.receive(),
+            None => return false,
+        };
+        for (client, message) in &messages {
+            let method = message["method"].as_str().unwrap_or_default();
+            let result = self.api(method, &message["params"]);
+            if let (Some(id), Some(remote)) = (message.get("id"), &mut self.remote) {
+                remote.reply(*client, id, result);
+            }
+        }
+        if let Some(remote) = &mut self.remote {
+            remote.forget();
+        }
+        !messages.is_empty()
+    }
+    fn bind_plugin_key(&mut self, index: usize, params: &Value) -> Result<Value, String> {
+        // Have a key run a command of a plugin, with the key written as it is in the config
+        let key = params["key"].as_str().ok_or("bind needs a key")?;
+        let command = params["command"].as_str().unwrap_or_default();
+        let key: KeyBinding = from_str(key).map_err(|_| format!("{key} isn't a key"))?;
+        if !matches!(
+            key,
+            KeyBinding::Ctrl(_) | KeyBinding::Alt(_) | KeyBinding::F(_)
+        ) {
+            return Err("Only Ctrl, Alt and F keys can be bound".to_string());
+        }
+        let name = self.plugins[index].name.clone();
+        self.config
+            .keys
+            .insert(key, vec![format!("plugin {} {}", name, command)]);
+        Ok(Value::Null)
+    }
+    fn api(&mut self, method: &str, params: &Value) -> Result<Value, String> {
+        // The requests other programs can make of the editor, acting on the current document
+        let number = |name: &str| {
+            params[name]
+                .as_u64()
+                .and_then(|value| usize::try_from(value).ok())
+                .ok_or(format!("{method} needs a number for {name}"))
+        };
+        let text = |name: &str| {
+            params[name]
+                .as_str()
+                .ok_or(format!("{method} needs some text for {name}"))
+        };
+        let doc = &self.doc[self.tab];
+        match method {
+            "info" => Ok(json!({
+                "path": doc.path,
+                "lines": doc.rows.len(),
+                "dirty": doc.dirty,
+                "read_only": doc.read_only,
+            })),
+            "text" => Ok(json!(doc.render(TabType::Spaces, 0))),
+            "line" => {
+                let y = number("y")?;
+                doc.rows
+                    .get(y)
+                    .map(|row| json!(row.string))
+                    .ok_or(format!("There is no line {y}"))
+            }
+            "cursor" => {
+                let current = self.current();
+                Ok(json!({"x": current.x, "y": current.y}))
+            }
+            "move" => {
+                let y = cmp::min(number("y")?, doc.rows.len() - 1);
+                let x = cmp::min(number("x").unwrap_or(0), doc.rows[y].length());
+                self.doc[self.tab].goto(Position { x, y }, &self.size);
+                self.doc[self.tab].recalculate_graphemes();
+                Ok(Value::Null)
+            }
+            "insert" => {
+                let with = tabs_to_spaces(text("text")?, self.doc[self.tab].tab_width);
+                self.replace_text(self.current(), String::new(), with);
+                Ok(Value::Null)
+            }
+            "message" => {
+                let kind = match params["kind"].as_str() {
+                    Some("error") => Type::Error,
+                    Some("warning") => Type::Warning,
+                    _ => Type::Info,
+                };
+                let message = text("text")?.to_string();
+                self.doc[self.tab].set_command_line(message, kind);
+                Ok(Value::Null)
+            }
+            "command" => {
+                let command = text("command")?.to_string();
+                self.text_to_event(&command);
+                Ok(Value::Null)
+            }
+            "open" => {
+                let path = text("path")?.to_string();
+                let before = self.doc.len();
+                self.open_document(Some(path.clone()));
+                if self.doc.len() > before {
+                    Ok(Value::Null)
                 } else {
-                    // User cancelled
-                    return;
+                    Err(format!("Couldn't open {path}"))
                 }
-            } else {
-                // Use current document
-                self.doc[self.tab].path.clone()
             }
+            "save" => {
+                self.execute(Event::Save(None, false), false);
+                Ok(json!(!self.doc[self.tab].dirty))
+            }
+            _ => Err(format!("Unknown method: {method}")),
+        }
+    }
+    fn key_event_to_ox_key(key: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        // Convert crossterm's complicated key structure into Ox's simpler one
+        let inner = match key {
+            KeyCode::Char(c) => RawKey::Char(c),
+            KeyCode::BackTab => RawKey::BackTab,
+            KeyCode::Insert => RawKey::Insert,
+            KeyCode::Esc => RawKey::Esc,
+            KeyCode::Backspace => RawKey::Backspace,
+            KeyCode::Tab => RawKey::Tab,
+            KeyCode::Enter => RawKey::Enter,
+            KeyCode::Delete => RawKey::Delete,
+            KeyCode::Null => RawKey::Null,
+            KeyCode::PageUp => RawKey::PageUp,
+            KeyCode::PageDown => RawKey::PageDown,
+            KeyCode::Home => RawKey::Home,
+            KeyCode::End => RawKey::End,
+            KeyCode::Up => RawKey::Up,
+            KeyCode::Down => RawKey::Down,
+            KeyCode::Left => RawKey::Left,
+            KeyCode::Right => RawKey::Right,
+            KeyCode::F(i) => return KeyBinding::F(i),
         };
-        if self.doc[self.tab].path != save && Path::new(&save).exists() {
-            // File already exists, possible loss of data
-            self.doc[self.tab]
-                .set_command_line(format!("File {} already exists", save), Type::Error);
+        match modifiers {
+            KeyModifiers::CONTROL => KeyBinding::Ctrl(inner),
+            KeyModifiers::ALT => KeyBinding::Alt(inner),
+            KeyModifiers::SHIFT => KeyBinding::Shift(inner),
+            KeyModifiers::NONE => KeyBinding::Raw(inner),
+            _ => KeyBinding::Unsupported,
+        }
+    }
+    fn process_key(&mut self, key: KeyEvent) {
+        if self.doc[self.tab].show_welcome && self.pick_recent(key) {
             return;
         }
-        // Attempt document save
-        let tab_width = self.config.general.tab_width;
-        if self.doc[self.tab].save(&save, tab_width).is_ok() {
-            // The document saved successfully
-            let ext = save.split('.').last().unwrap_or(&"");
-            self.doc[self.tab].dirty = false;
-            self.doc[self.tab].set_command_line(
-                format!("File saved to \"{}\" successfully", save),
-                Type::Info,
-            );
-            // Update the current documents details in case of filetype change
-            self.doc[self.tab].last_save_index = self.doc[self.tab].undo_stack.len();
-            self.doc[self.tab].kind = Document::identify(&save).0.to_string();
-            self.doc[self.tab].icon = Document::identify(&save).1.to_string();
-            self.doc[self.tab].name = Path::new(&save)
-                .file_name()
-                .unwrap_or_else(|| OsStr::new(&save))
-                .to_str()
-                .unwrap_or(&save)
-                .to_string();
-            self.doc[self.tab].path = save.clone();
-            self.doc[self.tab].regex = Reader::get_syntax_regex(&self.config, ext);
-        } else if save.is_empty() {
-            // The document couldn't save due to an empty name
+        self.doc[self.tab].show_welcome = false;
+        let ox_key = Editor::key_event_to_ox_key(key.code, key.modifiers);
+        self.keypress = ox_key;
+        // Any key other than a digit uses up the pending count
+        let count = std::mem::take(&mut self.count);
+        // Only typing and backspace keep track of the closers put in automatically
+        if !matches!(
+            ox_key,
+            KeyBinding::Raw(RawKey::Char(_) | RawKey::Backspace)
+                | KeyBinding::Shift(RawKey::Char(_))
+        ) {
+            self.closers.clear();
+        }
+        // Snippets are filled in by typing and moved through with tab
+        if !matches!(
+            ox_key,
+            KeyBinding::Raw(RawKey::Char(_) | RawKey::Backspace | RawKey::Tab | RawKey::BackTab)
+                | KeyBinding::Shift(RawKey::Char(_) | RawKey::BackTab)
+        ) {
+            self.stops.clear();
+        }
+        if self.process_console(ox_key)
+            || self.process_explorer(ox_key)
+            || self.process_chord(ox_key)
+            || self.process_modal(ox_key, count)
+        {
+            return;
+        }
+        // Typing and moving happen at every cursor, other keys only at the main one
+        let everywhere = matches!(
+            ox_key,
+            KeyBinding::Raw(
+                RawKey::Char(_)
+                    | RawKey::Enter
+                    | RawKey::Tab
+                    | RawKey::Backspace
+                    | RawKey::Up
+                    | RawKey::Down
+                    | RawKey::Left
+                    | RawKey::Right
+                    | RawKey::Home
+                    | RawKey::End
+            ) | KeyBinding::Shift(RawKey::Char(_))
+        );
+        let typing = matches!(
+            ox_key,
+            KeyBinding::Raw(RawKey::Char(_) | RawKey::Enter | RawKey::Tab | RawKey::Backspace)
+                | KeyBinding::Shift(RawKey::Char(_))
+        );
+        if typing && self.doc[self.tab].block.is_some() {
+            // Typing over a block replaces it on every line, backspace only takes it away
+            let removed = self.split_block();
+            if !removed || ox_key != KeyBinding::Raw(RawKey::Backspace) {
+                self.at_cursors(|e| e.process_binding(ox_key, count));
+            }
+        } else if everywhere {
+            self.at_cursors(|e| e.process_binding(ox_key, count));
+        } else {
+            self.process_binding(ox_key, count);
+        }
+    }
+    fn process_chord(&mut self, key: KeyBinding) -> bool {
+        // Follow a key through the chords, returning false when it doesn't start or finish one
+        let mut sequence = std::mem::take(&mut self.chord);
+        sequence.push(key);
+        self.chord_since = None;
+        let chords = &self.config.chords;
+        let longer = chords
+            .keys()
+            .any(|chord| chord.len() > sequence.len() && chord.starts_with(&sequence));
+        if longer {
+            // Wait for the rest of the chord, showing what has been typed of it
+            let typed: Vec<String> = sequence.iter().map(|key| key.describe()).collect();
+            self.doc[self.tab].set_prompt_line(format!("{} ...", typed.join(" ")), Type::Info);
+            self.chord = sequence;
+            self.chord_since = Some(Instant::now());
+            true
+        } else if let Some(commands) = chords.get(&sequence).cloned() {
+            self.doc[self.tab].set_command_line(String::new(), Type::Info);
+            for command in commands {
+                self.text_to_event(&command);
+            }
+            true
+        } else if sequence.len() > 1 {
+            let typed: Vec<String> = sequence.iter().map(|key| key.describe()).collect();
             self.doc[self.tab].set_command_line(
-                "Filename is blank, please specify file name".to_string(),
-                Type::Error,
+                format!("{} isn't bound to anything", typed.join(" ")),
+                Type::Warning,
             );
+            true
         } else {
-            // The document couldn't save due to permission errors / invalid name
-            self.doc[self.tab]
-                .set_command_line(format!("Failed to save file to \"{}\"", save), Type::Error);
+            false
         }
-        // Commit to undo stack on document save
-        self.execute(Event::Commit, false);
     }
-    fn save_every_document(&mut self) {
-        // Save every document in the editor
-        let tab_width = self.config.general.tab_width;
-        let mut successes = 0;
-        let mut failiures = 0;
-        for i in 0..self.doc.len() {
-            let path = self.doc[i].path.clone();
-            if self.doc[i].save(&path, tab_width).is_ok() {
-                // The document saved successfully
-                self.doc[i].dirty = false;
-                successes += 1;
-            } else {
-                // The document couldn't save due to permission errors
-                failiures += 1;
+    fn chord_update(&mut self) -> bool {
+        // Give up waiting for the rest of a chord, doing what the keys typed so far do on their own
+        let timeout = Duration::from_millis(self.config.general.chord_timeout);
+        if self
+            .chord_since
+            .is_none_or(|since| since.elapsed() < timeout)
+        {
+            return false;
+        }
+        self.chord_since = None;
+        let sequence = std::mem::take(&mut self.chord);
+        self.doc[self.tab].set_command_line(String::new(), Type::Info);
+        if let Some(commands) = self.config.chords.get(&sequence).cloned() {
+            for command in commands {
+                self.text_to_event(&command);
             }
-            self.doc[i].set_command_line(
-                format!("Saved {} documents, {} failed", successes, failiures),
-                Type::Info,
-            );
-            // Commit to undo stack on document save
-            self.execute(Event::Commit, false);
+        } else if let [key] = sequence[..] {
+            self.process_binding(key, 0);
         }
+        true
     }
-    fn quit_document(&mut self, force: bool) {
-        // For handling a quit event
-        if let KeyBinding::Ctrl(_) | KeyBinding::Alt(_) = self.keypress {
-            if force || self.dirty_prompt(self.keypress, "quit") {
-                if self.doc.len() <= 1 {
-                    // Quit Ox
-                    self.quit = true;
-                    return;
-                } else if self.tab == self.doc.len().saturating_sub(1) {
-                    // Close current tab and move right
-                    self.doc.remove(self.tab);
-                    self.tab -= 1;
-                } else {
-                    // Close current tab and move left
-                    self.doc.remove(self.tab);
+    fn process_binding(&mut self, ox_key: KeyBinding, count: usize) {
+        // Act on a key at the cursor, as many times as the count says
+        if let KeyBinding::Alt(RawKey::Char(c)) = ox_key {
+            // Alt and a digit build up a count when it isn't bound to anything
+            if c.is_ascii_digit() && !self.config.keys.contains_key(&ox_key) {
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                self.count = count.saturating_mul(10).saturating_add(digit);
+                self.doc[self.tab].set_command_line(format!("Repeat: {}", self.count), Type::Info);
+                return;
+            }
+        }
+        self.repeat(count, |e| e.process_binding_once(ox_key));
+    }
+    fn process_binding_once(&mut self, ox_key: KeyBinding) {
+        // Act on a key at the cursor once
+        match ox_key {
+            KeyBinding::Raw(RawKey::Enter) => self.insert_newline(),
+            KeyBinding::Raw(RawKey::Tab) => {
+                // Tab moves through snippets, expands them, then completes the word before it
+                let done = self.next_stop(true)
+                    || self.expand_snippet()
+                    || (self.config.general.word_completion && self.complete_word());
+                if !done {
+                    self.insert_tab();
                 }
-                self.doc[self.tab].set_command_line("Closed tab".to_string(), Type::Info);
             }
+            KeyBinding::Raw(RawKey::BackTab) | KeyBinding::Shift(RawKey::BackTab) => {
+                self.back_tab();
+            }
+            KeyBinding::Raw(RawKey::Backspace) => self.delete_backward(),
+            KeyBinding::Raw(RawKey::Esc) => {
+                self.doc[self.tab].anchor = None;
+                self.doc[self.tab].cursors.clear();
+                self.doc[self.tab].block = None;
+            }
+            // Detect control and alt and function key bindings
+            KeyBinding::Ctrl(_) | KeyBinding::Alt(_) | KeyBinding::F(_) => {
+                if let Some(commands) = self.config.keys.get(&ox_key) {
+                    for i in commands.clone() {
+                        self.text_to_event(&i);
+                    }
+                }
+            }
+            KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
+                let abbreviation = self.abbreviation(c);
+                let edits = self.doc[self.tab].edits;
+                self.insert_char(c);
+                // Nothing is expanded when the character wasn't typed, as in read only documents
+                if let Some(abbreviation) = abbreviation {
+                    if self.doc[self.tab].edits != edits {
+                        self.expand_abbreviation(abbreviation);
+                    }
+                }
+            }
+            KeyBinding::Raw(RawKey::Up) => self.move_cursor(Direction::Up),
+            KeyBinding::Raw(RawKey::Down) => self.move_cursor(Direction::Down),
+            KeyBinding::Raw(RawKey::Left) => self.move_cursor(Direction::Left),
+            KeyBinding::Raw(RawKey::Right) => self.move_cursor(Direction::Right),
+            KeyBinding::Raw(RawKey::PageDown) => self.execute(Event::PageDown, false),
+            KeyBinding::Raw(RawKey::PageUp) => self.execute(Event::PageUp, false),
+            KeyBinding::Raw(RawKey::Home) => self.execute(Event::Home, false),
+            KeyBinding::Raw(RawKey::End) => self.execute(Event::End, false),
+            // Shift and a movement key extends the selection
+            KeyBinding::Shift(RawKey::Up) => self.extend_selection(Direction::Up),
+            KeyBinding::Shift(RawKey::Down) => self.extend_selection(Direction::Down),
+            KeyBinding::Shift(RawKey::Left) => self.extend_selection(Direction::Left),
+            KeyBinding::Shift(RawKey::Right) => self.extend_selection(Direction::Right),
+            _ => (),
+        }
+    }
+    fn record(&mut self) {
+        // Start recording keys, or stop and keep what was recorded
+        if let Some(mut recording) = self.recording.take() {
+            // Leave out the keys that stopped the recording
+            recording.truncate(self.key_start);
+            let message = format!("Recorded {} keys", recording.len());
+            self.doc[self.tab].set_command_line(message, Type::Info);
+            self.recorded = recording;
+        } else {
+            self.recording = Some(vec![]);
+            self.doc[self.tab].set_command_line("Recording keys".to_string(), Type::Info);
+        }
+    }
+    fn process_modal(&mut self, key: KeyBinding, count: usize) -> bool {
+        // Handle keys outside of insert mode, returning false to let them through
+        if !self.config.general.modal {
+            return false;
+        }
+        match (self.mode, key) {
+            (
+                Mode::Replace,
+                KeyBinding::Raw(RawKey::Char(_)) | KeyBinding::Shift(RawKey::Char(_)),
+            ) => {
+                // Typing takes the place of the character under the cursor
+                let current = self.current();
+                if current.x < self.doc[self.tab].rows[current.y].length() {
+                    self.text_to_event("delete");
+                }
+                return false;
+            }
+            (Mode::Insert | Mode::Replace, KeyBinding::Raw(RawKey::Esc)) => {
+                self.set_mode(Mode::Normal);
+            }
+            (Mode::Insert | Mode::Replace, _) => return false,
+            (_, KeyBinding::Raw(RawKey::Esc)) => {
+                self.pending.clear();
+                self.set_mode(Mode::Normal);
+            }
+            (_, KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c))) => {
+                self.modal_key(c, count);
+            }
+            // Keys that would type text do nothing
+            (_, KeyBinding::Raw(RawKey::Enter | RawKey::Tab | RawKey::Backspace)) => (),
+            _ => return false,
+        }
+        true
+    }
+    fn modal_key(&mut self, c: char, count: usize) {
+        // Build up and run a normal or visual mode command
+        if c.is_ascii_digit() && (c != '0' || count > 0) && self.pending.is_empty() {
+            let digit = c.to_digit(10).unwrap_or(0) as usize;
+            self.count = count.saturating_mul(10).saturating_add(digit);
+            self.doc[self.tab].set_command_line(format!("Repeat: {}", self.count), Type::Info);
+            return;
+        }
+        self.pending.push(c);
+        let action = mode::interpret(&self.pending, self.mode);
+        if let Action::Pending = action {
+            // Hold on to the count for the rest of the command
+            self.count = count;
+            return;
+        }
+        self.pending.clear();
+        match action {
+            Action::Pending | Action::Unknown => (),
+            Action::Run(commands) => self.repeat(count, |e| {
+                for command in &commands {
+                    e.text_to_event(command);
+                }
+            }),
+            Action::Enter(commands, mode) => {
+                for command in commands {
+                    self.text_to_event(command);
+                }
+                self.set_mode(mode);
+            }
+            Action::Append => {
+                let current = self.current();
+                if current.x < self.doc[self.tab].rows[current.y].length() {
+                    self.move_cursor(Direction::Right);
+                }
+                self.set_mode(Mode::Insert);
+            }
+            Action::Goto(top) => {
+                let line = match count {
+                    0 if top => 1,
+                    0 => self.doc[self.tab].rows.len(),
+                    line => line,
+                };
+                self.text_to_event(&format!("goto {line}"));
+            }
+            Action::Operate(op, motion) => {
+                self.doc[self.tab].anchor = Some(self.current());
+                self.repeat(count, |e| e.text_to_event(motion));
+                self.operate(op);
+            }
+            Action::Register(name) => {
+                // The command that follows uses the register
+                self.register = Some(name);
+                self.count = count;
+                self.doc[self.tab].set_command_line(format!("\"{name}"), Type::Info);
+                return;
+            }
+            Action::Line(Operator::Yank) => self.copy(None),
+            Action::Line(Operator::Delete) => {
+                self.cut_line();
+                self.repeat(count, |e| e.text_to_event("delete 0"));
+            }
+            Action::Line(Operator::Change) => {
+                self.text_to_event("move home");
+                self.doc[self.tab].anchor = Some(self.current());
+                self.text_to_event("move end");
+                self.operate(Operator::Change);
+            }
+            Action::Selection(op) => self.operate(op),
+        }
+        self.register = None;
+    }
+    fn operate(&mut self, op: Operator) {
+        // Carry out an operator on the selection
+        if let Some((start, _)) = self.doc[self.tab].selection() {
+            match op {
+                Operator::Delete | Operator::Change => self.cut(None),
+                Operator::Yank => {
+                    self.copy(None);
+                    self.doc[self.tab].goto(start, &self.size);
+                }
+            }
+        }
+        self.set_mode(if op == Operator::Change {
+            Mode::Insert
+        } else {
+            Mode::Normal
+        });
+    }
+    fn set_mode(&mut self, mode: Mode) {
+        // Switch editing mode, visual mode selects from the cursor onwards
+        self.closers.clear();
+        self.stops.clear();
+        self.doc[self.tab].anchor = if mode == Mode::Visual {
+            Some(self.current())
+        } else {
+            None
+        };
+        self.mode = mode;
+    }
+    fn extend_selection(&mut self, direction: Direction) {
+        // Move the cursor while keeping the other end of the selection in place
+        if self.doc[self.tab].anchor.is_none() {
+            self.doc[self.tab].anchor = Some(self.current());
+        }
+        self.move_cursor(direction);
+    }
+    fn repeat(&mut self, count: usize, action: impl Fn(&mut Self)) {
+        // Carry out an action count times, or once when there is no count
+        for _ in 0..count.max(1) {
+            action(self);
+        }
+    }
+    fn at_cursors(&mut self, action: impl Fn(&mut Self)) {
+        // Carry out an action at the cursor and at each extra cursor, starting from the last in
+        // the document so that the edits don't move the places still to be visited
+        if self.doc[self.tab].cursors.is_empty() {
+            action(self);
+            return;
+        }
+        let main = self.current();
+        self.doc[self.tab].undo_stack.hold(true);
+        let mut places = std::mem::take(&mut self.doc[self.tab].cursors);
+        places.push(main);
+        places.sort_by_key(|pos| cmp::Reverse((pos.y, pos.x)));
+        places.dedup();
+        // Where each cursor ends up is kept as a distance from the end of the document and of
+        // its line, which edits before it leave the same
+        let mut done = vec![];
+        for pos in places {
+            let doc = &mut self.doc[self.tab];
+            let y = cmp::min(pos.y, doc.rows.len() - 1);
+            let x = cmp::min(pos.x, doc.rows[y].length());
+            doc.goto(Position { x, y }, &self.size);
+            doc.recalculate_graphemes();
+            action(self);
+            self.closers.clear();
+            self.stops.clear();
+            let at = self.current();
+            let rows = &self.doc[self.tab].rows;
+            let y = cmp::min(at.y, rows.len() - 1);
+            let columns = rows[y].length().saturating_sub(at.x);
+            done.push((pos == main, rows.len() - y, columns));
+        }
+        let rows = &self.doc[self.tab].rows;
+        let mut main = None;
+        let mut cursors = vec![];
+        for (is_main, lines, columns) in done {
+            let y = rows.len().saturating_sub(lines);
+            let pos = Position {
+                x: rows[y].length().saturating_sub(columns),
+                y,
+            };
+            if is_main {
+                main = Some(pos);
+            } else if !cursors.contains(&pos) {
+                cursors.push(pos);
+            }
+        }
+        if let Some(main) = main {
+            cursors.retain(|pos| *pos != main);
+            self.doc[self.tab].goto(main, &self.size);
+            self.doc[self.tab].recalculate_graphemes();
+        }
+        self.doc[self.tab].cursors = cursors;
+        self.doc[self.tab].undo_stack.hold(false);
+    }
+    fn block(&self) -> Option<(Position, Position)> {
+        // The top left and bottom right corners of the block selection
+        let corner = self.doc[self.tab].block?;
+        let current = self.current();
+        Some((
+            Position {
+                x: cmp::min(corner.x, current.x),
+                y: cmp::min(corner.y, current.y),
+            },
+            Position {
+                x: cmp::max(corner.x, current.x),
+                y: cmp::max(corner.y, current.y),
+            },
+        ))
+    }
+    fn split_block(&mut self) -> bool {
+        // Take the text out of a block selection and leave a cursor on each line that reaches
+        // it, giving back whether there was any text
+        let Some((start, end)) = self.block() else {
+            return false;
+        };
+        let current = self.current();
+        self.doc[self.tab].block = None;
+        self.doc[self.tab].undo_stack.commit();
+        let mut removed = false;
+        let mut cursors = vec![];
+        for y in start.y..=end.y {
+            let row = &self.doc[self.tab].rows[y];
+            if row.length() < start.x {
+                continue;
+            }
+            let pos = Position {
+                x: row.string[..row.byte_index(start.x)].width(),
+                y,
+            };
+            let text = row.slice(start.x, end.x).to_string();
+            if !text.is_empty() {
+                self.execute(Event::Deletion(pos, text), false);
+                removed = true;
+            }
+            cursors.push(pos);
+        }
+        // The cursor stays on its line, or goes to the first one long enough to reach the block
+        let main = cursors
+            .iter()
+            .position(|pos| pos.y == current.y)
+            .unwrap_or(0);
+        if main < cursors.len() {
+            let pos = cursors.remove(main);
+            self.doc[self.tab].goto(pos, &self.size);
+        }
+        self.doc[self.tab].recalculate_graphemes();
+        self.doc[self.tab].cursors = cursors;
+        removed
+    }
+    fn add_cursor(&mut self, direction: Direction) {
+        // Leave a cursor where the cursor is and move it to the line above or below
+        let current = self.current();
+        let last = self.doc[self.tab].rows.len() - 1;
+        if matches!(direction, Direction::Up if current.y == 0)
+            || matches!(direction, Direction::Down if current.y == last)
+        {
+            return;
+        }
+        self.doc[self.tab].cursors.push(current);
+        self.move_cursor(direction);
+    }
+    fn add_cursor_at_next(&mut self) {
+        // Leave a cursor where the cursor is and move it to where the word it is in appears next
+        let current = self.current();
+        let doc = &self.doc[self.tab];
+        let Some((start, end)) = word_around(&doc.rows[current.y], current.x) else {
+            self.doc[self.tab]
+                .set_command_line("There is no word at the cursor".to_string(), Type::Info);
+            return;
+        };
+        let word = doc.rows[current.y].slice(start, end).to_string();
+        let re = Regex::new(&format!(r"\b{}\b", regex::escape(&word))).unwrap();
+        // Look through the rest of the document and then from the top, skipping the places
+        // that already have a cursor
+        let taken = |y: usize, from: usize, to: usize| {
+            doc.cursors
+                .iter()
+                .chain(std::iter::once(&current))
+                .any(|pos| pos.y == y && (from..=to).contains(&pos.x))
+        };
+        let lines = (current.y..doc.rows.len()).chain(0..=current.y);
+        let found = lines.enumerate().find_map(|(pass, y)| {
+            let line = &doc.rows[y].string;
+            re.find_iter(line).find_map(|m| {
+                let from = line[..m.start()].width();
+                let to = from + m.as_str().width();
+                let wrapped = pass >= doc.rows.len() - current.y;
+                let allowed = y != current.y || if wrapped { to <= start } else { from >= end };
+                (allowed && !taken(y, from, to)).then_some(Position {
+                    x: from + current.x - start,
+                    y,
+                })
+            })
+        });
+        if let Some(pos) = found {
+            self.doc[self.tab].cursors.push(current);
+            self.doc[self.tab].goto(pos, &self.size);
+            self.doc[self.tab].recalculate_graphemes();
+        } else {
+            self.doc[self.tab]
+                .set_command_line(format!("{word} doesn't appear anywhere else"), Type::Info);
+        }
+    }
+    fn mark(&mut self, set: bool, letter: Option<char>) {
+        // Name the cursor's place with a letter, or go back to a place named before
+        let letter = if let Some(letter) = letter {
+            letter
+        } else {
+            let verb = if set { "set" } else { "go to" };
+            self.doc[self.tab].set_command_line(
+                format!("Press the letter of the mark to {verb}"),
+                Type::Info,
+            );
+            self.update();
+            if let InputEvent::Key(KeyEvent {
+                code: KeyCode::Char(c),
+                ..
+            }) = self.read_event()
+            {
+                c
+            } else {
+                self.doc[self.tab].set_command_line("Mark cancelled".to_string(), Type::Info);
+                return;
+            }
+        };
+        if !letter.is_alphanumeric() {
+            self.doc[self.tab].set_command_line(format!("{letter} can't name a mark"), Type::Error);
+        } else if set {
+            let current = self.current();
+            self.doc[self.tab].marks.insert(letter, current);
+            self.doc[self.tab].set_command_line(format!("Set mark {letter}"), Type::Info);
+        } else if let Some(&mark) = self.doc[self.tab].marks.get(&letter) {
+            let current = self.current();
+            let doc = &mut self.doc[self.tab];
+            doc.record_jump(current);
+            let y = mark.y.min(doc.rows.len() - 1);
+            let x = mark.x.min(doc.rows[y].length());
+            doc.goto(Position { x, y }, &self.size);
+        } else {
+            self.doc[self.tab].set_command_line(format!("Mark {letter} isn't set"), Type::Error);
+        }
+    }
+    fn current(&self) -> Position {
+        // Work out the position of the cursor within the document
+        let cursor = self.doc[self.tab].cursor;
+        let offset = self.doc[self.tab].offset;
+        Position {
+            x: cursor.x + offset.x,
+            y: cursor.y + offset.y - OFFSET,
+        }
+    }
+    pub fn insert_char(&mut self, c: char) {
+        // Insert a character at the cursor, closing brackets and quotes as they're opened
+        let current = self.current();
+        let (before, after) = self.around(&current);
+        if self.closers.last() == Some(&current) && after == Some(c) {
+            // Type over a closer that was put in automatically
+            self.closers.pop();
+            self.move_cursor(Direction::Right);
+            return;
+        }
+        let pairs = self.pairs();
+        // Pairs are only closed in front of a space or a closing bracket, and
+        // quotes aren't closed straight after a word, where they're likely apostrophes
+        let free = after.is_none_or(|after| {
+            after.is_whitespace() || pairs.iter().any(|&(o, cl)| cl == after && o != cl)
+        });
+        let close = pairs
+            .iter()
+            .find(|&&(open, _)| open == c)
+            .map(|&(_, close)| close)
+            .filter(|&close| free && !(close == c && before.is_some_and(char::is_alphanumeric)));
+        let text = close.map_or(c.to_string(), |close| format!("{c}{close}"));
+        self.shift_marks(&current, text.width(), 0);
+        self.execute(Event::Insertion(current, text), false);
+        if close.is_some() {
+            self.move_cursor(Direction::Left);
+            self.closers.push(Position {
+                x: current.x + c.to_string().width(),
+                y: current.y,
+            });
+        }
+    }
+    fn abbreviation(&self, typed: char) -> Option<(Position, String, String)> {
+        // The abbreviation a space or punctuation is about to end, with where it starts and what
+        // it stands for, as long as it starts a word rather than ending one
+        if !(typed.is_whitespace() || typed.is_ascii_punctuation()) || typed == '_' {
+            return None;
+        }
+        let current = self.current();
+        let row = &self.doc[self.tab].rows[current.y];
+        let before = &row.string[..row.byte_index(current.x)];
+        let word = |c: char| c.is_alphanumeric() || c == '_';
+        let (short, long) = self
+            .config
+            .abbreviations
+            .iter()
+            .filter(|(short, _)| !short.is_empty() && before.ends_with(short.as_str()))
+            .filter(|(short, _)| {
+                let previous = before[..before.len() - short.len()].chars().next_back();
+                previous.is_none_or(|p| p.is_whitespace() || (!word(p) && short.starts_with(word)))
+            })
+            .max_by_key(|(short, _)| short.len())?;
+        let start = Position {
+            x: current.x - short.width(),
+            y: current.y,
+        };
+        Some((start, short.clone(), long.clone()))
+    }
+    fn expand_abbreviation(&mut self, (start, short, long): (Position, String, String)) {
+        // Put what an abbreviation stands for in its place, as a change of its own to undo
+        let current = self.current();
+        let doc = &mut self.doc[self.tab];
+        if doc.read_only {
+            return;
+        }
+        // What was typed after the abbreviation stays after the cursor as it was
+        let after = current.x.saturating_sub(start.x + short.width());
+        // Lines after the first line up with the line the abbreviation was on, as in snippets
+        let row = &doc.rows[start.y];
+        let indent = " ".repeat(row.string.chars().take_while(|c| *c == ' ').count());
+        let tab = " ".repeat(doc.tab_width);
+        let text = long
+            .replace('\t', &tab)
+            .replace('\n', &format!("\n{indent}"));
+        doc.undo_stack.commit();
+        let edits = doc.edits;
+        self.execute(Event::Deletion(start, short.clone()), false);
+        if self.doc[self.tab].edits == edits {
+            return;
+        }
+        self.execute(Event::Insertion(start, text.clone()), false);
+        self.doc[self.tab].undo_stack.commit();
+        // The cursor is only put after the text when both halves of the change were made
+        if self.doc[self.tab].edits != edits + 2 {
+            return;
+        }
+        let cursor = if let Some((lines, last)) = text.rsplit_once('\n') {
+            self.closers.clear();
+            self.stops.clear();
+            Position {
+                x: last.width() + after,
+                y: start.y + lines.matches('\n').count() + 1,
+            }
+        } else {
+            self.shift_marks(&start, text.width(), short.width());
+            Position {
+                x: start.x + text.width() + after,
+                y: start.y,
+            }
+        };
+        self.doc[self.tab].goto(cursor, &self.size);
+        self.doc[self.tab].recalculate_graphemes();
+    }
+    fn pairs(&self) -> Vec<(char, char)> {
+        // The pairs of characters closed automatically in the current document
+        Reader::get_pairs(&self.config, &self.doc[self.tab].filetype)
+    }
+    fn around(&self, pos: &Position) -> (Option<char>, Option<char>) {
+        // The characters either side of a position
+        let row = &self.doc[self.tab].rows[pos.y];
+        let index = row.byte_index(pos.x);
+        (
+            row.string[..index].chars().next_back(),
+            row.string[index..].chars().next(),
+        )
+    }
+    fn shift_marks(&mut self, pos: &Position, added: usize, removed: usize) {
+        // Keep the closers and snippet places after an edit on the same line in step with the text
+        for mark in self.closers.iter_mut().chain(&mut self.stops) {
+            if mark.y == pos.y && mark.x >= pos.x {
+                mark.x = (mark.x + added).saturating_sub(removed).max(pos.x);
+            }
+        }
+    }
+    pub fn insert_newline(&mut self) {
+        // Break the line at the cursor
+        let current = self.current();
+        let indent = if self.config.general.auto_indent {
+            self.doc[self.tab].indentation(&current, self.doc[self.tab].tab_width)
+        } else {
+            String::new()
+        };
+        if let (Some(open), Some(close)) = self.around(&current) {
+            if open != close && self.pairs().contains(&(open, close)) {
+                // Open up the brackets, with the closer on a line of its own below the cursor
+                let row = &self.doc[self.tab].rows[current.y].string;
+                let base = if self.config.general.auto_indent {
+                    &row[..row.len() - row.trim_start_matches([' ', '\t']).len()]
+                } else {
+                    ""
+                };
+                let text = format!("\n{indent}\n{base}");
+                let middle = Position {
+                    x: indent.width(),
+                    y: current.y + 1,
+                };
+                self.doc[self.tab].undo_stack.commit();
+                self.execute(Event::Insertion(current, text), false);
+                self.doc[self.tab].undo_stack.commit();
+                self.doc[self.tab].goto(middle, &self.size);
+                self.doc[self.tab].recalculate_graphemes();
+                return;
+            }
+        }
+        if current.x != 0 && !indent.is_empty() {
+            // Carry the indentation onto the new line as a single edit
+            self.doc[self.tab].undo_stack.commit();
+            self.execute(Event::Insertion(current, format!("\n{indent}")), false);
+            self.doc[self.tab].undo_stack.commit();
+        } else if current.x == 0 {
+            // Return key pressed at the start of the line
+            self.execute(Event::InsertLineAbove(current), false);
+        } else if current.x == self.doc[self.tab].rows[current.y].length() {
+            // Return key pressed at the end of the line
+            self.execute(Event::InsertLineBelow(current), false);
+            self.execute(Event::MoveCursor(1, Direction::Down), false);
+        } else {
+            // Return key pressed in the middle of the line
+            self.execute(Event::SplitDown(current, current), false);
+        }
+    }
+    pub fn insert_tab(&mut self) {
+        // Indent at the cursor by one tab width
+        let current = self.current();
+        self.execute(Event::InsertTab(current), false);
+    }
+    #[allow(clippy::too_many_lines)] // Covers line and block comments in one pass
+    fn toggle_comment(&mut self) {
+        // Comment out the selected lines or the cursor's line, or uncomment them if all of them are
+        let doc = &self.doc[self.tab];
+        let Some((open, close)) = Document::comment_markers(&doc.filetype) else {
+            self.doc[self.tab].set_command_line(
+                "Comments aren't known for this type of file".to_string(),
+                Type::Error,
+            );
+            return;
+        };
+        let current = self.current();
+        let anchor = doc.anchor.filter(|_| doc.selection().is_some());
+        // A selection ending at the start of a line leaves that line alone
+        let (first, last) = doc
+            .selection()
+            .map_or((current.y, current.y), |(start, end)| {
+                let last = if end.x == 0 && end.y > start.y {
+                    end.y - 1
+                } else {
+                    end.y
+                };
+                (start.y, last)
+            });
+        let lines: Vec<usize> = (first..=last)
+            .filter(|&y| !doc.rows[y].string.trim().is_empty())
+            .collect();
+        let commented = lines.iter().all(|&y| {
+            let text = doc.rows[y].string.trim();
+            text.starts_with(open)
+                && text.ends_with(close)
+                && text.len() >= open.len() + close.len()
+        });
+        // Comments are lined up with the least indented line, keeping the indentation
+        let column = lines
+            .iter()
+            .map(|&y| {
+                let string = &doc.rows[y].string;
+                string.len() - string.trim_start().len()
+            })
+            .min()
+            .unwrap_or(0);
+        let doc = &mut self.doc[self.tab];
+        doc.undo_stack.commit();
+        let (mut cursor_shift, mut anchor_shift) = (0, 0);
+        for &y in &lines {
+            let before = doc.rows[y].clone();
+            let string = &before.string;
+            let (text, shift) = if commented {
+                let indent = string.len() - string.trim_start().len();
+                let body = &string.trim_end()[indent + open.len()..];
+                let body = body.strip_prefix(' ').unwrap_or(body);
+                let gone = string.len() - indent - body.len();
+                let body = &body[..body.len() - close.len()];
+                let body = if close.is_empty() {
+                    body
+                } else {
+                    body.strip_suffix(' ').unwrap_or(body)
+                };
+                (
+                    format!("{}{}", &string[..indent], body),
+                    -gone.cast_signed(),
+                )
+            } else {
+                let (indent, body) = string.split_at(column);
+                let text = if close.is_empty() {
+                    format!("{indent}{open} {body}")
+                } else {
+                    format!("{indent}{open} {body} {close}")
+                };
+                (text, open.len().cast_signed() + 1)
+            };
+            if y == current.y {
+                cursor_shift = shift;
+            }
+            if anchor.is_some_and(|anchor| anchor.y == y) {
+                anchor_shift = shift;
+            }
+            let after = Row::from(text.as_str());
+            doc.rows[y] = after.clone();
+            doc.undo_stack.push(Event::UpdateLine(
+                Position { x: current.x, y },
+                0,
+                Box::new(before),
+                Box::new(after),
+            ));
+        }
+        doc.undo_stack.commit();
+        if lines.is_empty() {
+            return;
+        }
+        doc.dirty = true;
+        doc.edits += 1;
+        // The cursor and the other end of the selection stay on the same characters
+        let moved = |pos: Position, shift: isize| Position {
+            x: if pos.x >= column {
+                cmp::min(
+                    pos.x.saturating_add_signed(shift).max(column),
+                    doc.rows[pos.y].length(),
+                )
+            } else {
+                pos.x
+            },
+            y: pos.y,
+        };
+        let cursor = moved(current, cursor_shift);
+        doc.anchor = anchor.map(|anchor| moved(anchor, anchor_shift));
+        doc.goto(cursor, &self.size);
+        doc.recalculate_graphemes();
+    }
+    pub fn dedent(&mut self) {
+        // Remove one level of indentation from the current line
+        let current = self.current();
+        let before = self.doc[self.tab].rows[current.y].clone();
+        let indent = before
+            .string
+            .chars()
+            .take(self.doc[self.tab].tab_width)
+            .take_while(|c| *c == ' ')
+            .count();
+        if indent != 0 {
+            let after = Row::from(&before.string[indent..]);
+            let pos = Position {
+                x: current.x.saturating_sub(indent),
+                y: current.y,
+            };
+            self.execute(
+                Event::UpdateLine(pos, 0, Box::new(before), Box::new(after)),
+                false,
+            );
+        }
+    }
+    pub fn delete_backward(&mut self) {
+        // Remove the selection, or the character before the cursor
+        if let Some((start, text)) = self.doc[self.tab].selected_text() {
+            self.execute(Event::Deletion(start, text), false);
+            return;
+        }
+        let current = self.current();
+        if current.x == 0 && current.y == 0 {
+            return;
+        }
+        let (before, after) = self.around(&current);
+        if let (Some(before), Some(after)) = (before, after) {
+            if self.pairs().contains(&(before, after)) {
+                // Backspace inside an empty pair removes both halves
+                let start = Position {
+                    x: current.x - before.to_string().width(),
+                    y: current.y,
+                };
+                if self.closers.last() == Some(&current) {
+                    self.closers.pop();
+                }
+                let width = format!("{before}{after}").width();
+                self.shift_marks(&start, 0, width);
+                self.execute(Event::Deletion(start, format!("{before}{after}")), false);
+                return;
+            }
+        }
+        if let Some(before) = before {
+            let width = before.to_string().width();
+            let start = Position {
+                x: current.x - width,
+                y: current.y,
+            };
+            self.shift_marks(&start, 0, width);
+        }
+        self.execute(
+            if current.x == 0 {
+                // Backspace at the start of a line
+                Event::SpliceUp(current, current)
+            } else {
+                // Backspace in the middle of a line removes the whole character before it
+                let row = &self.doc[self.tab].rows[current.y];
+                let start = row
+                    .boundaries()
+                    .into_iter()
+                    .take_while(|&boundary| boundary < current.x)
+                    .last()
+                    .unwrap_or(0);
+                let text = row.slice(start, current.x).to_string();
+                Event::Deletion(
+                    Position {
+                        x: start,
+                        y: current.y,
+                    },
+                    text,
+                )
+            },
+            false,
+        );
+    }
+    fn copy(&mut self, register: Option<char>) {
+        // Copy the selection, or the current line when nothing is selected
+        let text = if let Some((_, text)) = self.doc[self.tab].selected_text() {
+            self.doc[self.tab].anchor = None;
+            text
+        } else {
+            let current = self.current();
+            self.doc[self.tab].rows[current.y].string.clone()
+        };
+        self.store(text, register, false);
+    }
+    fn cut(&mut self, register: Option<char>) {
+        // Copy the selection and then remove it
+        if let Some((start, text)) = self.doc[self.tab].selected_text() {
+            self.store(text.clone(), register, true);
+            self.doc[self.tab].undo_stack.commit();
+            self.execute(Event::Deletion(start, text), false);
+            self.doc[self.tab].undo_stack.commit();
+        } else {
+            self.doc[self.tab].set_command_line("Nothing is selected".to_string(), Type::Error);
+        }
+    }
+    fn cut_line(&mut self) {
+        // Keep the current line as cut text before it is deleted
+        let current = self.current();
+        let text = self.doc[self.tab].rows[current.y].string.clone();
+        self.store(text, None, true);
+    }
+    fn store(&mut self, text: String, register: Option<char>, cut: bool) {
+        // Put text in the register named, or on the clipboard when there isn't one
+        // Capital letters add to the end of their register instead of replacing it
+        if cut {
+            self.deleted.push_front(text.clone());
+            self.deleted.truncate(9);
+        }
+        match register.or_else(|| self.register.take()) {
+            Some(name) if name.is_ascii_uppercase() => {
+                let name = name.to_ascii_lowercase();
+                self.registers.entry(name).or_default().push_str(&text);
+                self.doc[self.tab]
+                    .set_command_line(format!("Added to register {name}"), Type::Info);
+            }
+            Some(name) => {
+                self.registers.insert(name, text);
+                self.doc[self.tab]
+                    .set_command_line(format!("Copied to register {name}"), Type::Info);
+            }
+            None => self.share(text),
+        }
+    }
+    fn share(&mut self, text: String) {
+        // Put text on the system clipboard, keeping a copy in case there isn't one
+        if clipboard::copy(&text) {
+            self.doc[self.tab].set_command_line("Copied to clipboard".to_string(), Type::Info);
+        } else {
+            self.doc[self.tab].set_command_line(
+                "Copied within Ox only, install xclip, xsel or wl-clipboard to share it"
+                    .to_string(),
+                Type::Warning,
+            );
+        }
+        self.clipboard = text;
+    }
+    fn paste(&mut self, register: Option<char>) {
+        // Insert the contents of a register, or of the clipboard, at the cursor
+        // Registers 1 to 9 hold the text cut most recently, 1 being the newest
+        let text = match register.or_else(|| self.register.take()) {
+            None => Some(clipboard::paste().unwrap_or_else(|| self.clipboard.clone())),
+            Some(digit @ '1'..='9') => self.deleted.get(digit as usize - '1' as usize).cloned(),
+            Some(name) => self.registers.get(&name.to_ascii_lowercase()).cloned(),
+        };
+        if let Some(text) = text {
+            self.insert_text(&text);
+        } else {
+            self.doc[self.tab].set_command_line("That register is empty".to_string(), Type::Error);
+        }
+    }
+    fn show_registers(&mut self) {
+        // List what the registers hold by the cursor, pasting the one picked
+        let mut names: Vec<char> = self.registers.keys().copied().collect();
+        names.sort_unstable();
+        names.extend(('1'..='9').take(self.deleted.len()));
+        if names.is_empty() {
+            self.doc[self.tab].set_command_line(
+                "Nothing has been copied into a register or cut".to_string(),
+                Type::Info,
+            );
+            return;
+        }
+        let items: Vec<String> = names
+            .iter()
+            .map(|name| {
+                let text = match name {
+                    '1'..='9' => &self.deleted[*name as usize - '1' as usize],
+                    _ => &self.registers[name],
+                };
+                let shown: String = text.replace('\n', "\u{21b5}").chars().take(50).collect();
+                format!("{name}  {shown}")
+            })
+            .collect();
+        if let Some(index) = self.popup(&items) {
+            self.paste(Some(names[index]));
+        }
+    }
+    fn insert_text(&mut self, text: &str) {
+        // Insert text at every cursor as a single edit
+        let text = tabs_to_spaces(&text.replace("\r\n", "\n"), self.doc[self.tab].tab_width);
+        if text.is_empty() {
+            return;
+        }
+        self.doc[self.tab].undo_stack.commit();
+        self.at_cursors(|e| {
+            let current = e.current();
+            e.execute(Event::Insertion(current, text.clone()), false);
+        });
+        self.doc[self.tab].undo_stack.commit();
+    }
+    pub fn move_cursor(&mut self, direction: Direction) {
+        // Move the cursor by one character or line
+        self.execute(Event::MoveCursor(1, direction), false);
+    }
+    fn process_input(&mut self) {
+        // Read a key and act on it
+        self.key_start = self.recording.as_ref().map_or(0, Vec::len);
+        let tab = self.tab;
+        if self.pending.is_empty() && self.chord.is_empty() && self.count == 0 {
+            // A new command starts, rather than more keys of an unfinished one
+            self.keys.clear();
+            self.keys_mode = self.mode;
+            self.edited = false;
+        }
+        match self.read_input() {
+            TerminalInput::Paste(text) if self.can_paste() => {
+                // Pasted text goes in as one edit, without running any key bindings
+                for key in keys(&text) {
+                    if let Some(recording) = &mut self.recording {
+                        recording.push(key);
+                    }
+                    self.keys.push(key);
+                }
+                self.doc[self.tab].show_welcome = false;
+                self.count = 0;
+                self.insert_text(&text);
+            }
+            TerminalInput::Paste(text) => self.held.extend(keys(&text)),
+            TerminalInput::Event(InputEvent::Key(key)) => self.process_key(key),
+            TerminalInput::Event(InputEvent::Resize(width, height)) => self.resize(width, height),
+            TerminalInput::Event(InputEvent::Mouse(event)) => self.process_mouse(event),
+        }
+        self.remember_edit(tab);
+    }
+    fn remember_edit(&mut self, tab: usize) {
+        // Keep the keys of edits made one after the other as a run that can be repeated
+        if !self.pending.is_empty() || !self.chord.is_empty() || self.count > 0 {
+            return;
+        }
+        if self.edited && self.tab == tab {
+            let keys = std::mem::take(&mut self.keys);
+            if self.editing {
+                self.last_edit.1.extend(keys);
+            } else {
+                self.last_edit = (self.keys_mode, keys, self.mode);
+            }
+            self.last_edit.2 = self.mode;
+            self.editing = true;
+        } else {
+            self.editing = false;
+        }
+    }
+    fn repeat_edit(&mut self) {
+        // Queue the last run of edits up to be typed again at the cursor
+        let (start, keys, end) = self.last_edit.clone();
+        if keys.is_empty() {
+            self.doc[self.tab].set_command_line("Nothing to repeat".to_string(), Type::Info);
+            return;
+        }
+        // Text typed in insert mode is typed in insert mode again, going back to normal after
+        let key = |code| {
+            InputEvent::Key(KeyEvent {
+                code,
+                modifiers: KeyModifiers::NONE,
+            })
+        };
+        let modal = self.config.general.modal && self.mode != Mode::Insert;
+        if modal && start == Mode::Insert {
+            self.playback.push_back(key(KeyCode::Char('i')));
+        }
+        self.playback.extend(keys);
+        if modal && (start == Mode::Insert || end == Mode::Insert) {
+            self.playback.push_back(key(KeyCode::Esc));
+        }
+    }
+    fn can_paste(&self) -> bool {
+        // Whether typed keys would go into the document as they are
+        let console = self.console.as_ref().is_some_and(|console| console.focus);
+        let explorer = self
+            .explorer
+            .as_ref()
+            .is_some_and(|explorer| explorer.focus);
+        let modal = self.config.general.modal && self.mode != Mode::Insert;
+        !self.replaying && self.chord.is_empty() && !console && !explorer && !modal
+    }
+    fn resize(&mut self, width: u16, height: u16) {
+        // Terminal resize event
+        self.term.forget();
+        self.term.size = Size {
+            width: width as usize,
+            height: height as usize,
+        };
+        // Move cursors if needed
+        self.resize_panes();
+        let panes = self.panes();
+        for (index, doc) in self.doc.iter_mut().enumerate() {
+            let size = panes
+                .iter()
+                .find(|pane| pane.0 == index)
+                .map_or(self.size, |pane| pane.1);
+            doc.fit(&self.config, &size);
+            doc.mass_redraw();
+        }
+        // Re-render everything to the new size
+        self.update();
+    }
+    fn suspend(&mut self) {
+        // Stop the editor for the shell, drawing it all again at its new size when it comes back
+        if let Some((width, height)) = self.term.suspend() {
+            self.resize(width, height);
+        } else {
+            self.doc[self.tab].set_command_line(
+                "Suspending isn't supported on this system".to_string(),
+                Type::Error,
+            );
+        }
+    }
+    fn process_mouse(&mut self, event: MouseEvent) {
+        // Place the cursor on a left click and scroll with the wheel
+        self.closers.clear();
+        self.stops.clear();
+        // Clicking with Ctrl held leaves a cursor behind where the cursor was
+        let mut add = false;
+        let (scroll, x, y) = match event {
+            MouseEvent::Down(MouseButton::Left, x, y, modifiers) => {
+                add = modifiers.contains(KeyModifiers::CONTROL);
+                (None, x as usize, y as usize)
+            }
+            MouseEvent::ScrollUp(x, y, _) => (Some(Direction::Up), x as usize, y as usize),
+            MouseEvent::ScrollDown(x, y, _) => (Some(Direction::Down), x as usize, y as usize),
+            MouseEvent::Drag(MouseButton::Left, _, y, _) => {
+                // A held scrollbar follows the pointer, even once it leaves the column
+                if let Some(index) = self.dragging {
+                    self.drag_scrollbar(index, y as usize);
+                    self.update();
+                }
+                return;
+            }
+            MouseEvent::Up(..) => {
+                self.dragging = None;
+                return;
+            }
+            _ => return,
+        };
+        // Browse the file tree when the pointer is over it
+        let sidebar = self.sidebar_width();
+        let rows = OFFSET..self.term.size.height.saturating_sub(2);
+        if x < sidebar && rows.contains(&y) {
+            let height = rows.len();
+            if let Some(explorer) = &mut self.explorer {
+                if let Some(direction) = scroll {
+                    for _ in 0..3 {
+                        explorer.scroll(matches!(direction, Direction::Up), height);
+                    }
+                } else {
+                    explorer.focus = true;
+                    if let Some(path) = explorer.click(y - OFFSET) {
+                        self.show_file(&path);
+                    }
+                }
+            }
+            self.update();
+            return;
+        }
+        // Clicking the shell gives it the keys
+        let (rows, listed) = (self.console_size().1, self.panel_height().saturating_sub(1));
+        let shell = self.term.size.height.saturating_sub(2 + rows + listed);
+        if let Some(console) = &mut self.console {
+            if x >= sidebar && (shell..shell + rows).contains(&y) {
+                console.focus |= scroll.is_none();
+                self.update();
+                return;
+            }
+        }
+        // Clicking a problem the build found goes to it
+        let list = self.term.size.height.saturating_sub(2 + listed);
+        if x >= sidebar && (list..list + listed).contains(&y) {
+            if scroll.is_none() {
+                let index = self.problems_shown().start + y - list;
+                self.go_to_problem(index);
+            }
+            self.update();
+            return;
+        }
+        // Find the text area of the pane under the pointer
+        let bar = usize::from(self.config.general.scrollbar);
+        let pane = self.panes().into_iter().find(|(_, size, origin)| {
+            (origin.x..origin.x + size.width + bar).contains(&x)
+                && (origin.y + OFFSET..origin.y + size.height.saturating_sub(2)).contains(&y)
+        });
+        if let Some((index, size, origin)) = pane {
+            if let Some(direction) = scroll {
+                // Move a few lines per notch of the wheel
+                for _ in 0..3 {
+                    self.doc[index].scroll(direction);
+                }
+            } else if x == origin.x + size.width {
+                // Clicking the scrollbar jumps there and holds it for dragging
+                self.dragging = Some(index);
+                self.drag_scrollbar(index, y);
+            } else {
+                if let Some(explorer) = &mut self.explorer {
+                    explorer.focus = false;
+                }
+                if let Some(console) = &mut self.console {
+                    console.focus = false;
+                }
+                if index != self.tab {
+                    self.switch_pane();
+                }
+                let current = self.current();
+                let doc = &mut self.doc[index];
+                if add {
+                    doc.cursors.push(current);
+                } else {
+                    doc.cursors.clear();
+                }
+                doc.click(y - origin.y - OFFSET, x - origin.x, &size);
+            }
+            self.update();
+        }
+    }
+    fn drag_scrollbar(&mut self, index: usize, y: usize) {
+        // Show the part of a document the scrollbar row under the pointer stands for
+        let pane = self.panes().into_iter().find(|pane| pane.0 == index);
+        let Some((_, size, origin)) = pane else {
+            return;
+        };
+        let height = cmp::max(size.height.saturating_sub(OFFSET + 2), 1);
+        let row = cmp::min(y.saturating_sub(origin.y + OFFSET), height - 1);
+        // The top row is the start of the document and the bottom row is where its last lines fill the pane
+        let doc = &mut self.doc[index];
+        let scrollable = doc.rows.len().saturating_sub(height);
+        doc.peek_at(row * scrollable / cmp::max(height - 1, 1));
+    }
+    fn new_document(&mut self) {
+        // Create a new document
+        self.doc
+            .push(Document::new(&self.config, &self.status, false));
+        self.tab = self.doc.len().saturating_sub(1);
+    }
+    fn open_document(&mut self, file: Option<String>) {
+        // Open a document
+        let to_open = if let Some(path) = file {
+            // File was specified
+            path
+        } else if let Some(path) = self.path_prompt("Open") {
+            // Ask for a file and open it
+            path
+        } else {
+            // User cancelled
+            return;
+        };
+        match Document::open(&self.config, &self.status, &to_open, false) {
+            Ok(mut doc) => {
+                // Overwrite the current document
+                doc.correct_path(&self.size);
+                self.doc.push(doc);
+                self.tab = self.doc.len().saturating_sub(1);
+                self.remember_recent();
+                self.restore_history();
+                self.recover_swap();
+            }
+            Err(error) if error.kind() == Iek::InvalidData => {
+                // Files that aren't text are shown as bytes instead
+                self.hex_edit(to_open.split(':').next().unwrap_or(&to_open));
+            }
+            Err(error) => {
+                let reason = match error.kind() {
+                    Iek::NotFound => "it doesn't exist".to_string(),
+                    Iek::InvalidData => "it isn't a text file".to_string(),
+                    _ => error.to_string(),
+                };
+                self.doc[self.tab]
+                    .set_command_line(format!("File couldn't be opened, {reason}"), Type::Error);
+            }
+        }
+    }
+    fn export(&mut self, file: Option<String>) {
+        // Write the document with its highlighting to a web page, next to it unless told where
+        let doc = &self.doc[self.tab];
+        let file = if let Some(file) = file {
+            file
+        } else if doc.path.is_empty() {
+            if let Some(file) = self.path_prompt("Export to") {
+                file
+            } else {
+                return;
+            }
+        } else {
+            format!("{}.html", doc.path)
+        };
+        let page = export::html(&self.doc[self.tab], &self.config, &self.theme);
+        let message = match fs::write(&file, &page) {
+            Ok(()) => (
+                format!("Exported to \"{}\" ({} bytes)", file, page.len()),
+                Type::Info,
+            ),
+            Err(error) => (
+                format!("Failed to export to \"{file}\": {error}"),
+                Type::Error,
+            ),
+        };
+        self.doc[self.tab].set_command_line(message.0, message.1);
+    }
+    #[allow(clippy::too_many_lines)] // Walks the prompt, conflict, format and write steps in order
+    fn save_document(&mut self, file: Option<String>, prompt: bool) {
+        // Save the document
+        let save = if let Some(file) = file {
+            // File was specified
+            file
+        } else {
+            // File not specified, ask for one if the document doesn't have a name yet
+            if prompt || self.doc[self.tab].path.is_empty() {
+                // Save as
+                if let Some(path) = self.path_prompt("Save as") {
+                    path
+                } else {
+                    // User cancelled
+                    return;
+                }
+            } else {
+                // Use current document
+                self.doc[self.tab].path.clone()
+            }
+        };
+        if self.doc[self.tab].path == save && self.doc[self.tab].changed_on_disk().is_some() {
+            // Another program changed the file since it was opened, which saving would throw away
+            let name = self.doc[self.tab].name.clone();
+            loop {
+                self.doc[self.tab].set_prompt_line(
+                    format!("{name} was changed by another program, (o)verwrite it or (c)ancel?"),
+                    Type::Warning,
+                );
+                self.update();
+                let InputEvent::Key(key) = self.read_event() else {
+                    continue;
+                };
+                match key.code {
+                    KeyCode::Char('o') => break,
+                    KeyCode::Char('c') | KeyCode::Esc => {
+                        self.doc[self.tab]
+                            .set_command_line("Save cancelled".to_string(), Type::Info);
+                        return;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        if self.doc[self.tab].path != save && Path::new(&save).exists() {
+            // File already exists, possible loss of data
+            self.doc[self.tab].set_command_line(format!("File {save} already exists"), Type::Error);
+            return;
+        }
+        // Attempt document save
+        if self.read_whole(self.tab).is_none() {
+            return;
+        }
+        if self.format_on_save(self.tab) && !self.doc[self.tab].read_only {
+            self.format(true);
+        }
+        if self.doc[self.tab].trim_whitespace && !self.doc[self.tab].read_only {
+            self.doc[self.tab].trim_trailing_whitespace(&self.size);
+        }
+        let mut result = if let Some((host, file)) = ssh::parse(&save) {
+            self.doc[self.tab].save_remote(host, file)
+        } else {
+            self.doc[self.tab].save(&save, &self.config)
+        };
+        if matches!(&result, Err(error) if error.kind() == Iek::PermissionDenied)
+            && cfg!(unix)
+            && self.confirm(&format!(
+                "Permission denied saving \"{save}\", save it as root with sudo? (y/n)"
+            ))
+        {
+            // Sudo asks for a password on the terminal, so the editor steps aside while it runs
+            self.term.lend();
+            println!("Saving \"{save}\" as root");
+            result = self.doc[self.tab].save_as_root(&save);
+            let (width, height) = self.term.take_back();
+            self.resize(width, height);
+        }
+        if let Ok(bytes) = result {
+            // The document saved successfully
+            self.doc[self.tab].dirty = false;
+            self.doc[self.tab].set_command_line(
+                format!("File saved to \"{save}\" successfully ({bytes} bytes)"),
+                Type::Info,
+            );
+            // Update the current documents details in case of filetype change
+            self.doc[self.tab].last_save_index = self.doc[self.tab].undo_stack.state();
+            self.doc[self.tab].name = Path::new(&save)
+                .file_name()
+                .unwrap_or_else(|| OsStr::new(&save))
+                .to_str()
+                .unwrap_or(&save)
+                .to_string();
+            self.doc[self.tab].compression = self.doc[self.tab].compression_for(&save);
+            self.doc[self.tab].path.clone_from(&save);
+            let first = self.doc[self.tab].rows[0].string.clone();
+            let inside = Compression::strip(&save);
+            self.doc[self.tab].retype(&self.config, filetype::detect(inside, &first));
+            self.lsp_notify(self.tab, false);
+            // Saving may have made the repository dirty, or put the file in one
+            self.git_checked = None;
+        } else if save.is_empty() {
+            // The document couldn't save due to an empty name
+            self.doc[self.tab].set_command_line(
+                "Filename is blank, please specify file name".to_string(),
+                Type::Error,
+            );
+        } else if let Err(error) = result {
+            // The document couldn't save due to permission errors / invalid name
+            self.doc[self.tab].set_command_line(
+                format!("Failed to save file to \"{save}\": {error}"),
+                Type::Error,
+            );
+        }
+        // Commit to undo stack on document save
+        self.execute(Event::Commit, false);
+    }
+    fn read_whole(&mut self, tab: usize) -> Option<usize> {
+        // Read in all of a large file before it changes or is written out, giving how far down
+        // the lines held have moved, or saying why it couldn't be read
+        match self.doc[tab].load_whole(&self.config, &self.status) {
+            Ok(lines) => Some(lines),
+            Err(error) => {
+                let name = self.doc[tab].name.clone();
+                self.doc[tab]
+                    .set_command_line(format!("Couldn't read all of {name}: {error}"), Type::Error);
+                None
+            }
+        }
+    }
+    fn save_every_document(&mut self) {
+        // Save every document in the editor
+        let mut successes = 0;
+        let mut failiures = 0;
+        for i in 0..self.doc.len() {
+            if self.doc[i].read_only {
+                // Read only documents are left as they are on disk
+                continue;
+            }
+            if self.read_whole(i).is_none() {
+                failiures += 1;
+                continue;
+            }
+            let path = self.doc[i].path.clone();
+            if self.format_on_save(i) {
+                // The formatter works on the current document, so each one has its turn
+                let tab = std::mem::replace(&mut self.tab, i);
+                self.format(true);
+                self.tab = tab;
+            }
+            if self.doc[i].trim_whitespace {
+                self.doc[i].trim_trailing_whitespace(&self.size);
+            }
+            if self.doc[i].save(&path, &self.config).is_ok() {
+                // The document saved successfully
+                self.doc[i].dirty = false;
+                self.lsp_notify(i, false);
+                successes += 1;
+            } else {
+                // The document couldn't save due to permission errors
+                failiures += 1;
+            }
+            self.doc[i].set_command_line(
+                format!("Saved {successes} documents, {failiures} failed"),
+                Type::Info,
+            );
+            // Commit to undo stack on document save
+            self.execute(Event::Commit, false);
+        }
+    }
+    fn quit_document(&mut self, force: bool) {
+        // For handling a quit event
+        if let KeyBinding::Ctrl(_) | KeyBinding::Alt(_) = self.keypress {
+            if force || self.dirty_prompt(self.keypress, "quit") {
+                self.remember_position();
+                self.remember_history();
+                self.doc[self.tab].remove_swap();
+                self.lsp_notify(self.tab, true);
+                if self.doc.len() <= 1 {
+                    // Quit Ox
+                    self.quit = true;
+                    return;
+                }
+                if let Some(split) = &mut self.split {
+                    // Keep the other pane pointing at the same document
+                    if split.other > self.tab {
+                        split.other -= 1;
+                    }
+                }
+                if self.tab == self.doc.len().saturating_sub(1) {
+                    // Close current tab and move right
+                    self.doc.remove(self.tab);
+                    self.tab -= 1;
+                } else {
+                    // Close current tab and move left
+                    self.doc.remove(self.tab);
+                }
+                self.doc[self.tab].set_command_line("Closed tab".to_string(), Type::Info);
+            }
+        }
+    }
+    fn quit_all(&mut self, force: bool) {
+        // Quit all the documents in the editor, remembering them for next time
+        self.session = Some(self.session());
+        self.tab = 0;
+        while !self.quit {
+            let open = self.doc.len();
+            self.execute(Event::Quit(force), false);
+            if !self.quit && self.doc.len() == open {
+                // A document was kept, so the rest stay open too
+                self.session = None;
+                break;
+            }
+        }
+    }
+    fn split_view(&mut self, vertical: bool) {
+        // Split the screen to show the next tab alongside the current one
+        if self.doc.len() < 2 {
+            self.doc[self.tab]
+                .set_command_line("Open another tab to split with".to_string(), Type::Error);
+            return;
+        }
+        let other = match &self.split {
+            Some(split) => split.other,
+            None => (self.tab + 1) % self.doc.len(),
+        };
+        self.split = Some(Split {
+            other,
+            vertical,
+            first: true,
+        });
+        if !self.resize_panes() {
+            self.doc[self.tab]
+                .set_command_line("Not enough room to split".to_string(), Type::Error);
+        }
+        self.fit_panes();
+    }
+    fn toggle_diff(&mut self) {
+        // Compare the documents of the two panes, or stop comparing them
+        if self.diff.take().is_some() {
+            return;
+        }
+        if let Some(split) = &self.split {
+            let (left, right) = if split.first {
+                (self.tab, split.other)
+            } else {
+                (split.other, self.tab)
+            };
+            self.diff = Some(Diff {
+                left,
+                right,
+                edits: (usize::MAX, usize::MAX),
+                comparison: Comparison::default(),
+            });
+        } else {
+            self.doc[self.tab].set_command_line(
+                "Split the screen to compare two documents".to_string(),
+                Type::Error,
+            );
+        }
+    }
+    fn sync_diff(&mut self) {
+        // Keep the compared documents up to date and scrolled together while both are shown
+        let Some(diff) = &mut self.diff else {
+            return;
+        };
+        let tabs = (diff.left, diff.right);
+        let tab = self.tab;
+        let shown = self
+            .split
+            .as_ref()
+            .is_some_and(|split| tabs == (tab, split.other) || tabs == (split.other, tab));
+        if !shown {
+            self.diff = None;
+            return;
+        }
+        let edits = (self.doc[diff.left].edits, self.doc[diff.right].edits);
+        if edits != diff.edits {
+            let old: Vec<String> = self.doc[diff.left]
+                .rows
+                .iter()
+                .map(|row| row.string.clone())
+                .collect();
+            let new: Vec<&str> = self.doc[diff.right]
+                .rows
+                .iter()
+                .map(|row| row.string.as_str())
+                .collect();
+            diff.comparison = git::compare(&old, &new);
+            diff.edits = edits;
+        }
+        // The other pane shows the lines across from those in the focused one
+        let from_left = self.tab == diff.left;
+        let other = if from_left { diff.right } else { diff.left };
+        let top = diff.across(self.doc[self.tab].top(), from_left);
+        let doc = &mut self.doc[other];
+        let top = cmp::min(top, doc.rows.len().saturating_sub(1));
+        doc.peek = if top == doc.offset.y { None } else { Some(top) };
+    }
+    fn unsplit(&mut self) {
+        // Go back to showing just the focused tab
+        self.split = None;
+        self.resize_panes();
+        self.fit_panes();
+    }
+    fn switch_pane(&mut self) {
+        // Move focus to the other pane
+        if let Some(split) = &mut self.split {
+            std::mem::swap(&mut self.tab, &mut split.other);
+            split.first = !split.first;
+            self.resize_panes();
+        }
+    }
+    fn toggle_explorer(&mut self) {
+        // Open and focus the file tree, or close it when it already has focus
+        match &mut self.explorer {
+            Some(explorer) if !explorer.focus => explorer.focus = true,
+            Some(_) => self.explorer = None,
+            None => {
+                let root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                self.explorer = Some(Explorer::new(root));
+            }
+        }
+        if let Some(console) = &mut self.console {
+            console.focus = false;
+        }
+        self.resize_panes();
+        self.fit_panes();
+    }
+    fn toggle_console(&mut self) {
+        // Open a shell below the documents, or move focus between it and the documents
+        if let Some(console) = &mut self.console {
+            console.focus = !console.focus;
+        } else {
+            let (width, height) = self.console_size();
+            match Console::open(width, height.max(1)) {
+                Ok(console) => self.console = Some(console),
+                Err(error) => {
+                    self.doc[self.tab]
+                        .set_command_line(format!("Couldn't start a shell: {error}"), Type::Error);
+                    return;
+                }
+            }
+        }
+        if let Some(explorer) = &mut self.explorer {
+            explorer.focus = false;
+        }
+        self.resize_panes();
+        self.fit_panes();
+    }
+    fn process_console(&mut self, key: KeyBinding) -> bool {
+        // Send keys to the shell while it has focus, apart from those that move focus away
+        let console = match &mut self.console {
+            Some(console) if console.focus => console,
+            _ => return false,
+        };
+        let leaves = self.config.keys.get(&key).is_some_and(|commands| {
+            commands
+                .iter()
+                .any(|command| command.split(' ').next() == Some("console"))
+        });
+        if leaves {
+            return false;
+        }
+        console.key(key);
+        true
+    }
+    fn console_receive(&mut self) -> bool {
+        // Show what the shell wrote, closing the pane once the shell has exited
+        let Some(console) = &mut self.console else {
+            return false;
+        };
+        let changed = console.receive();
+        if !console.alive {
+            self.console = None;
+            self.resize_panes();
+            self.fit_panes();
+            return true;
+        }
+        changed
+    }
+    fn console_height(&self) -> usize {
+        // How many rows the shell takes up, including its divider
+        let rows = self.term.size.height.saturating_sub(OFFSET + 2);
+        if self.console.is_some() && rows >= 9 {
+            rows / 3 + 1
+        } else {
+            0
+        }
+    }
+    fn build(&mut self, command: Option<String>) {
+        // Run the build command of the document's language, or the one given, in the background
+        if self.building.is_some() {
+            self.doc[self.tab]
+                .set_command_line("A build is already running".to_string(), Type::Warning);
+            return;
+        }
+        let doc = &self.doc[self.tab];
+        let language = Reader::get_language(&self.config, &doc.filetype);
+        let command = command
+            .or_else(|| language.map(|lang| lang.build.clone()))
+            .filter(|command| !command.trim().is_empty());
+        let command = if let Some(command) = command {
+            command.replace("%F", &doc.path)
+        } else {
+            self.doc[self.tab].set_command_line(
+                "There is no build command for this type of file".to_string(),
+                Type::Error,
+            );
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        let running = command.clone();
+        thread::spawn(move || {
+            let _ = sender.send(quickfix::run(&running));
+        });
+        self.doc[self.tab].set_command_line(format!("Running {command}"), Type::Info);
+        self.building = Some((command, receiver));
+    }
+    fn build_update(&mut self) -> bool {
+        // Collect the problems once the build has finished, returning whether it has
+        let (command, outcome) = match &self.building {
+            Some((command, receiver)) => match receiver.try_recv() {
+                Ok(outcome) => (command.clone(), outcome),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => {
+                    (command.clone(), Err(io::Error::from(Iek::Interrupted)))
+                }
+            },
+            None => return false,
+        };
+        self.building = None;
+        let (status, output) = match outcome {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't run {command}: {error}"), Type::Error);
+                return true;
+            }
+        };
+        self.problems = quickfix::parse(&output);
+        self.problem = None;
+        self.listing = !self.problems.is_empty();
+        let status = status.map_or("a signal".to_string(), |code| format!("status {code}"));
+        let found = match self.problems.len() {
+            0 => "no problems found".to_string(),
+            1 => "1 problem found".to_string(),
+            count => format!("{count} problems found"),
+        };
+        let kind = if self.problems.is_empty() {
+            Type::Info
+        } else {
+            Type::Warning
+        };
+        self.doc[self.tab]
+            .set_command_line(format!("{command} finished with {status}, {found}"), kind);
+        self.resize_panes();
+        self.fit_panes();
+        true
+    }
+    fn grep(&mut self, term: Option<String>) {
+        // Search every file under the working directory in the background, following the search options
+        if self.searching.is_some() {
+            self.doc[self.tab]
+                .set_command_line("A search is already running".to_string(), Type::Warning);
+            return;
+        }
+        let term = if let Some(term) = term {
+            term
+        } else if let Some(term) = self.prompt("Search files", ": ", &|_, _, _| {}) {
+            term
+        } else {
+            return;
+        };
+        if term.is_empty() {
+            return;
+        }
+        let pattern = self.search_pattern(&term);
+        let root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(quickfix::search(&pattern, &root));
+        });
+        self.doc[self.tab].set_command_line(format!("Searching the files for {term}"), Type::Info);
+        self.searching = Some((term, receiver));
+    }
+    fn grep_update(&mut self) -> bool {
+        // List what the search of the files found once it has finished, returning whether it has
+        let (term, outcome) = match &self.searching {
+            Some((term, receiver)) => match receiver.try_recv() {
+                Ok(outcome) => (term.clone(), outcome),
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => {
+                    (term.clone(), Err(io::Error::from(Iek::Interrupted)))
+                }
+            },
+            None => return false,
+        };
+        self.searching = None;
+        let found = match outcome {
+            Ok(found) => found,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't search for {term}: {error}"), Type::Error);
+                return true;
+            }
+        };
+        // The places found take the place of the problems, so they are gone through the same way
+        let mut files: Vec<&str> = found.iter().map(|place| place.path.as_str()).collect();
+        files.dedup();
+        let message = match (found.len(), files.len()) {
+            (0, _) => format!("No matches for {term} in the files"),
+            (1, _) => format!("1 match for {term}"),
+            (count, 1) => format!("{count} matches for {term} in 1 file"),
+            (count, in_files) => format!("{count} matches for {term} in {in_files} files"),
+        };
+        self.doc[self.tab].set_command_line(message, Type::Info);
+        self.problems = found;
+        self.problem = None;
+        self.listing = !self.problems.is_empty();
+        self.resize_panes();
+        self.fit_panes();
+        true
+    }
+    fn grep_replace(&mut self) {
+        // Replace a pattern in every file under the working directory, after going through the changes
+        let term = match self.prompt("Replace in files", ": ", &|_, _, _| {}) {
+            Some(term) if !term.is_empty() => term,
+            _ => return,
+        };
+        let pattern = self.search_pattern(&term);
+        let Ok(re) = Regex::new(&pattern) else {
+            self.doc[self.tab].set_command_line("Invalid Regex".to_string(), Type::Error);
+            return;
+        };
+        let Some(arrow) = self.prompt("With", ": ", &|_, _, _| {}) else {
+            return;
+        };
+        self.doc[self.tab].set_command_line(format!("Searching the files for {term}"), Type::Info);
+        self.update();
+        let root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let mut found = match quickfix::search(&pattern, &root) {
+            Ok(found) => found,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't search for {term}: {error}"), Type::Error);
+                return;
+            }
+        };
+        found.dedup_by(|a, b| a.path == b.path && a.line == b.line);
+        // Lines are taken from the open documents where there are any, so unsaved changes count
+        let mut replacements = vec![];
+        let mut files: HashMap<String, Vec<String>> = HashMap::new();
+        for place in found {
+            let lines = files.entry(place.path.clone()).or_insert_with(|| {
+                let open = self.doc.iter().find(|doc| doc.path == place.path);
+                if let Some(doc) = open {
+                    doc.rows.iter().map(|row| row.string.clone()).collect()
+                } else {
+                    fs::read_to_string(root.join(&place.path))
+                        .map(|text| {
+                            let path = root.join(&place.path);
+                            let width = editorconfig::settings(&path).width();
+                            let width = width.unwrap_or(self.config.general.tab_width);
+                            let text = tabs_to_spaces(&text, width);
+                            text.lines().map(ToString::to_string).collect()
+                        })
+                        .unwrap_or_default()
+                }
+            });
+            if let Some(before) = lines.get(place.line.saturating_sub(1)) {
+                let after = re.replace_all(before, arrow.as_str()).to_string();
+                if after != *before {
+                    replacements.push(Replacement {
+                        path: place.path,
+                        line: place.line - 1,
+                        before: before.clone(),
+                        after,
+                        include: true,
+                    });
+                }
+            }
+        }
+        if replacements.is_empty() {
+            self.doc[self.tab]
+                .set_command_line(format!("No matches for {term} in the files"), Type::Info);
+            return;
+        }
+        if self.review_replacements(&mut replacements) {
+            self.apply_replacements(&replacements);
+        } else {
+            self.doc[self.tab].set_command_line("Replace cancelled".to_string(), Type::Info);
+        }
+    }
+    fn review_replacements(&mut self, replacements: &mut [Replacement]) -> bool {
+        // List the changes to leave some out of, giving whether to go ahead with the rest
+        let mut selected: usize = 0;
+        loop {
+            let included = replacements.iter().filter(|change| change.include).count();
+            let height = self.term.size.height.saturating_sub(OFFSET + 2).max(1);
+            let first = (selected + 1).saturating_sub(height);
+            let lines: Vec<String> = replacements
+                .iter()
+                .enumerate()
+                .skip(first)
+                .take(height)
+                .map(|(index, change)| {
+                    // Changes in the same file as the one above leave its name out, grouping them
+                    let path = if index > 0 && replacements[index - 1].path == change.path {
+                        " ".repeat(change.path.width())
+                    } else {
+                        change.path.clone()
+                    };
+                    format!(
+                        "[{}] {}:{} {} \u{2192} {}",
+                        if change.include { 'x' } else { ' ' },
+                        path,
+                        change.line + 1,
+                        change.before.trim(),
+                        change.after.trim()
+                    )
+                })
+                .collect();
+            let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+            self.doc[self.tab].set_prompt_line(
+                format!(
+                    "Replace {} of {}: Space picks, a picks all, Enter replaces, Esc cancels",
+                    included,
+                    replacements.len()
+                ),
+                Type::Info,
+            );
+            self.update();
+            self.draw_results(&lines, selected - first);
+            let InputEvent::Key(key) = self.read_event() else {
+                continue;
+            };
+            match Editor::key_event_to_ox_key(key.code, key.modifiers) {
+                KeyBinding::Raw(RawKey::Enter) => return included > 0,
+                KeyBinding::Raw(RawKey::Esc) => return false,
+                KeyBinding::Raw(RawKey::Up) => selected = selected.saturating_sub(1),
+                KeyBinding::Raw(RawKey::Down) => {
+                    selected = cmp::min(selected + 1, replacements.len() - 1);
+                }
+                KeyBinding::Raw(RawKey::Char(' ')) => {
+                    replacements[selected].include = !replacements[selected].include;
+                    selected = cmp::min(selected + 1, replacements.len() - 1);
+                }
+                KeyBinding::Raw(RawKey::Char('a')) => {
+                    // Everything is picked unless it all was already
+                    let all = included == replacements.len();
+                    for change in replacements.iter_mut() {
+                        change.include = !all;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    fn apply_replacements(&mut self, replacements: &[Replacement]) {
+        // Make the picked changes in the documents, opening the files that aren't open yet
+        let save = self.confirm("Save the changed files? (y/n)");
+        let focus = self.tab;
+        let mut changed: Vec<usize> = vec![];
+        for change in replacements.iter().filter(|change| change.include) {
+            let tab = if let Some(tab) = self.doc.iter().position(|doc| doc.path == change.path) {
+                tab
+            } else {
+                match Document::open(&self.config, &self.status, &change.path, false) {
+                    Ok(doc) => {
+                        self.doc.push(doc);
+                        self.doc.len() - 1
+                    }
+                    Err(_) => continue,
+                }
+            };
+            // The lines listed are lines of the whole file
+            if self.read_whole(tab).is_none() {
+                continue;
+            }
+            let doc = &mut self.doc[tab];
+            // Lines that have changed since they were listed are left alone
+            if doc.read_only
+                || doc.rows.get(change.line).map(|row| &row.string) != Some(&change.before)
+            {
+                continue;
+            }
+            let before = doc.rows[change.line].clone();
+            let after = Row::from(change.after.as_str());
+            doc.rows[change.line] = after.clone();
+            doc.undo_stack.push(Event::UpdateLine(
+                Position {
+                    x: 0,
+                    y: change.line,
+                },
+                0,
+                Box::new(before),
+                Box::new(after),
+            ));
+            doc.dirty = true;
+            doc.edits += 1;
+            if !changed.contains(&tab) {
+                changed.push(tab);
+            }
+        }
+        let mut failed = vec![];
+        for &tab in &changed {
+            let doc = &mut self.doc[tab];
+            doc.undo_stack.commit();
+            doc.mass_redraw();
+            if save {
+                let path = doc.path.clone();
+                if doc.save(&path, &self.config).is_ok() {
+                    doc.dirty = false;
+                    doc.last_save_index = doc.undo_stack.state();
+                } else {
+                    failed.push(doc.name.clone());
+                }
+            }
+            self.lsp_notify(tab, false);
+        }
+        self.tab = focus;
+        let message = if failed.is_empty() {
+            let saved = if save { "and saved" } else { "left unsaved" };
+            (
+                format!("Replaced in {} files {}", changed.len(), saved),
+                Type::Info,
+            )
+        } else {
+            (format!("Couldn't save {}", failed.join(", ")), Type::Error)
+        };
+        self.doc[self.tab].set_command_line(message.0, message.1);
+    }
+    fn next_problem(&mut self, forward: bool) {
+        // Go to the next or previous place the last build complained about
+        if self.problems.is_empty() {
+            self.doc[self.tab].set_command_line(
+                "The last build or search found nothing".to_string(),
+                Type::Info,
+            );
+            return;
+        }
+        let last = self.problems.len() - 1;
+        let index = match (self.problem, forward) {
+            (None, true) => 0,
+            (None, false) => last,
+            (Some(index), true) => cmp::min(index + 1, last),
+            (Some(index), false) => index.saturating_sub(1),
+        };
+        self.go_to_problem(index);
+    }
+    fn go_to_problem(&mut self, index: usize) {
+        // Open the file of a problem at its line and column
+        let problem = if let Some(problem) = self.problems.get(index) {
+            problem.clone()
+        } else {
+            return;
+        };
+        self.problem = Some(index);
+        if !Path::new(&problem.path).is_file() {
+            self.doc[self.tab]
+                .set_command_line(format!("{} couldn't be found", problem.path), Type::Error);
+            return;
+        }
+        self.show_file(Path::new(&problem.path));
+        if let Some(console) = &mut self.console {
+            console.focus = false;
+        }
+        let current = self.current();
+        self.doc[self.tab].record_jump(current);
+        self.doc[self.tab].jump(problem.line, Some(problem.column), &self.size);
+        self.doc[self.tab].set_command_line(
+            format!(
+                "({}/{}) {}",
+                index + 1,
+                self.problems.len(),
+                problem.message
+            ),
+            Type::Info,
+        );
+    }
+    fn go_to_definition(&mut self, symbol: Option<String>) {
+        // Go to where the symbol at the cursor is defined, going by the project's tags file
+        let current = self.current();
+        let symbol = if let Some(symbol) = symbol {
+            symbol
+        } else {
+            let row = &self.doc[self.tab].rows[current.y];
+            if let Some((start, end)) = word_around(row, current.x) {
+                row.slice(start, end).to_string()
+            } else {
+                self.doc[self.tab]
+                    .set_command_line("There is no word at the cursor".to_string(), Type::Info);
+                return;
+            }
+        };
+        let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let Some(file) = tags::locate(&cwd) else {
+            self.doc[self.tab].set_command_line(
+                "No tags file was found, ctags -R makes one".to_string(),
+                Type::Error,
+            );
+            return;
+        };
+        let found = match tags::find(&file, &symbol) {
+            Ok(found) => found,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't read the tags file: {error}"), Type::Error);
+                return;
+            }
+        };
+        let tag = match found.len() {
+            0 => {
+                self.doc[self.tab]
+                    .set_command_line(format!("No definition of {symbol} was found"), Type::Info);
+                return;
+            }
+            1 => found[0].clone(),
+            _ => {
+                let choices: Vec<String> = found.iter().map(tags::Tag::describe).collect();
+                match self.popup(&choices) {
+                    Some(index) => found[index].clone(),
+                    None => return,
+                }
+            }
+        };
+        if !tag.path.is_file() {
+            self.doc[self.tab].set_command_line(
+                format!("{} couldn't be found", tag.path.display()),
+                Type::Error,
+            );
+            return;
+        }
+        // Remember where the jump came from, in the document and across files
+        self.doc[self.tab].record_jump(current);
+        let origin = self.doc[self.tab].path.clone();
+        self.tag_origins.push((origin, current));
+        self.show_file(&tag.path);
+        let rows: Vec<&str> = self.doc[self.tab]
+            .rows
+            .iter()
+            .map(|row| row.string.as_str())
+            .collect();
+        if let Some(line) = tag.line(&rows) {
+            self.doc[self.tab].jump(line + 1, None, &self.size);
+        }
+    }
+    fn tag_back(&mut self) {
+        // Go back to where the latest definition was gone to from
+        let Some((path, place)) = self.tag_origins.pop() else {
+            self.doc[self.tab]
+                .set_command_line("No definitions were gone to".to_string(), Type::Info);
+            return;
+        };
+        if path != self.doc[self.tab].path {
+            match self.doc.iter().position(|doc| doc.path == path) {
+                Some(index) => self.tab = index,
+                None if path.is_empty() => {
+                    self.doc[self.tab]
+                        .set_command_line("That document was closed".to_string(), Type::Info);
+                    return;
+                }
+                None => self.open_document(Some(path)),
+            }
+        }
+        let place = Position {
+            y: place.y.min(self.doc[self.tab].rows.len() - 1),
+            ..place
+        };
+        self.doc[self.tab].goto(place, &self.size);
+        self.doc[self.tab].recalculate_graphemes();
+    }
+    fn toggle_problems(&mut self) {
+        // Show or hide the list of problems below the documents
+        self.listing = !self.listing;
+        if self.listing && self.problems.is_empty() {
+            self.listing = false;
+            self.doc[self.tab].set_command_line(
+                "The last build or search found nothing".to_string(),
+                Type::Info,
+            );
+        }
+        self.resize_panes();
+        self.fit_panes();
+    }
+    fn panel_height(&self) -> usize {
+        // How many rows the list of problems takes up, including its divider
+        let rows = self.term.size.height.saturating_sub(OFFSET + 2);
+        if self.listing && !self.problems.is_empty() && rows >= 9 {
+            cmp::min(self.problems.len(), rows / 4) + 1
+        } else {
+            0
+        }
+    }
+    fn problems_shown(&self) -> std::ops::Range<usize> {
+        // Which problems fit in the list, keeping the one gone to last in view
+        let rows = self.panel_height().saturating_sub(1);
+        let start = self
+            .problem
+            .map_or(0, |index| (index + 1).saturating_sub(rows));
+        start..cmp::min(start + rows, self.problems.len())
+    }
+    fn problem_lines(&self, width: usize) -> Vec<String> {
+        // Render the problems in view, each with its place and what was wrong
+        let theme = &self.config.theme;
+        let shown = self.problems_shown();
+        let first = shown.start;
+        shown
+            .map(|index| {
+                let problem = &self.problems[index];
+                // Places in the same file as the one above leave its name out, grouping them
+                let path = if index > first && self.problems[index - 1].path == problem.path {
+                    " ".repeat(problem.path.width())
+                } else {
+                    problem.path.clone()
+                };
+                let place = format!("{}:{}:{} ", path, problem.line, problem.column);
+                let text = trim_end(&format!("{}{}", place, problem.message), width);
+                let place = trim_end(&place, width);
+                let message = &text[place.len()..];
+                format!(
+                    "{}{}{}{}{}{}{}{}",
+                    if self.problem == Some(index) {
+                        Reader::rgb_bg(theme.selection_bg)
+                    } else {
+                        Reader::rgb_bg(theme.editor_bg)
+                    },
+                    Reader::rgb_fg(theme.line_number_fg),
+                    place,
+                    Reader::rgb_fg(theme.editor_fg),
+                    message,
+                    " ".repeat(width.saturating_sub(text.width())),
+                    RESET_FG,
+                    RESET_BG,
+                )
+            })
+            .collect()
+    }
+    fn console_size(&self) -> (usize, usize) {
+        // The columns and rows the shell has to draw in
+        (
+            self.term.size.width.saturating_sub(self.sidebar_width()),
+            self.console_height().saturating_sub(1),
+        )
+    }
+    fn process_explorer(&mut self, key: KeyBinding) -> bool {
+        // Browse the file tree while it has focus, returning false to let keys through
+        let explorer = match &mut self.explorer {
+            Some(explorer) if explorer.focus => explorer,
+            _ => return false,
+        };
+        match key {
+            KeyBinding::Raw(RawKey::Up) => explorer.up(),
+            KeyBinding::Raw(RawKey::Down) => explorer.down(),
+            KeyBinding::Raw(RawKey::Left) => explorer.collapse(),
+            KeyBinding::Raw(RawKey::Right) => explorer.expand(),
+            KeyBinding::Raw(RawKey::Esc) => explorer.focus = false,
+            KeyBinding::Raw(RawKey::Enter) => {
+                if let Some(path) = explorer.activate() {
+                    self.show_file(&path);
+                }
+            }
+            KeyBinding::Raw(_) | KeyBinding::Shift(_) => (),
+            _ => return false,
+        }
+        true
+    }
+    fn show_file(&mut self, path: &Path) {
+        // Show a file that was picked from a list, switching to it if it's open already
+        let path = env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf());
+        let path = path.to_string_lossy().to_string();
+        if let Some(explorer) = &mut self.explorer {
+            explorer.focus = false;
+        }
+        if let Some(index) = self.doc.iter().position(|doc| doc.path == path) {
+            self.tab = index;
+        } else {
+            self.open_document(Some(path));
+        }
+    }
+    fn preview_width(&self) -> usize {
+        // How many columns the Markdown preview takes up, including its divider
+        if self.preview.is_some() && self.split.is_none() {
+            let width = self.term.size.width.saturating_sub(self.sidebar_width());
+            width - width.saturating_sub(1) / 2
+        } else {
+            0
+        }
+    }
+    fn toggle_preview(&mut self) {
+        // Show a Markdown document formatted beside its source, or stop showing it
+        if self.preview.take().is_none() {
+            if self.doc[self.tab].kind != "Markdown" {
+                self.doc[self.tab].set_command_line(
+                    "Only Markdown documents can be previewed".to_string(),
+                    Type::Error,
+                );
+                return;
+            }
+            self.split = None;
+            self.preview = Some(Preview {
+                tab: self.tab,
+                edits: usize::MAX,
+                width: 0,
+                lines: vec![],
+            });
+        }
+        self.resize_panes();
+        self.fit_panes();
+    }
+    fn sync_preview(&mut self) {
+        // Follow the focused document and draw it again whenever it changes
+        let markdown = self.doc[self.tab].kind == "Markdown";
+        if self.split.is_some() || !markdown {
+            if self.preview.take().is_some() {
+                self.resize_panes();
+                self.fit_panes();
+            }
+            return;
+        }
+        let width = self.preview_width().saturating_sub(1);
+        let Some(preview) = &mut self.preview else {
+            return;
+        };
+        let doc = &self.doc[self.tab];
+        if (preview.tab, preview.edits, preview.width) == (self.tab, doc.edits, width) {
+            return;
+        }
+        let theme = &self.config.theme;
+        let highlights = self.config.highlights.get(&self.theme);
+        let colour = |name: &str| {
+            highlights
+                .and_then(|highlights| highlights.get(name).copied())
+                .unwrap_or(theme.editor_fg)
+        };
+        let palette = Palette {
+            text: theme.editor_fg,
+            background: theme.editor_bg,
+            heading: colour("headers"),
+            code: colour("strings"),
+            quote: colour("comments"),
+            link: colour("functions"),
+            marker: colour("keywords"),
+        };
+        let source: Vec<&str> = doc.rows.iter().map(|row| row.string.as_str()).collect();
+        preview.lines = markdown::render(&source, width, &palette);
+        preview.tab = self.tab;
+        preview.edits = doc.edits;
+        preview.width = width;
+    }
+    fn sidebar_width(&self) -> usize {
+        // How many columns the file tree takes up, including its divider
+        if self.explorer.is_some() {
+            explorer::WIDTH.min(self.term.size.width / 3) + 1
+        } else {
+            0
+        }
+    }
+    fn sidebar(&mut self, height: usize) -> Vec<String> {
+        // Render the rows of the file tree followed by its divider
+        let width = self.sidebar_width().saturating_sub(1);
+        let theme = &self.config.theme;
+        let background = if theme.transparent_editor {
+            RESET_BG.to_string()
+        } else {
+            Reader::rgb_bg(theme.editor_bg).to_string()
+        };
+        let (focus, mut lines) = match &mut self.explorer {
+            Some(explorer) => (explorer.focus, explorer.lines(height)),
+            None => return vec![],
+        };
+        lines.resize(height, (String::new(), false));
+        lines
+            .into_iter()
+            .map(|(text, selected)| {
+                let text = trim_end(&text, width);
+                let padding = " ".repeat(width.saturating_sub(text.width()));
+                format!(
+                    "{}{}{}{}{}{}\u{2502}{}{}",
+                    if selected && focus {
+                        Reader::rgb_bg(theme.selection_bg).to_string()
+                    } else {
+                        background.clone()
+                    },
+                    Reader::rgb_fg(theme.editor_fg),
+                    text,
+                    padding,
+                    background,
+                    Reader::rgb_fg(theme.line_number_fg),
+                    RESET_FG,
+                    RESET_BG,
+                )
+            })
+            .collect()
+    }
+    fn fit_panes(&mut self) {
+        // Make sure the cursors of the visible documents fit in their panes
+        for (index, size, _) in self.panes() {
+            self.doc[index].fit(&self.config, &size);
+            self.doc[index].mass_redraw();
+        }
+    }
+    fn resize_panes(&mut self) -> bool {
+        // Work out the size of the focused pane, dropping a split that no longer makes sense
+        let (width, height) = self.console_size();
+        if let Some(console) = &mut self.console {
+            console.resize(width, height);
+        }
+        let fits = |size: &Size| size.width >= 10 && size.height > 4;
+        if let Some(split) = &self.split {
+            if split.other == self.tab
+                || split.other >= self.doc.len()
+                || !self.panes().iter().all(|pane| fits(&pane.1))
+            {
+                self.split = None;
+                self.size = self.panes()[0].1;
+                return false;
+            }
+        }
+        self.size = self
+            .panes()
+            .iter()
+            .find(|pane| pane.0 == self.tab)
+            .map_or(self.term.size, |pane| pane.1);
+        true
+    }
+    fn panes(&self) -> Vec<(usize, Size, Position)> {
+        // List the documents on screen, with the size and screen origin of their panes
+        let sidebar = self.sidebar_width();
+        let width = self
+            .term
+            .size
+            .width
+            .saturating_sub(sidebar + self.preview_width());
+        let height = self
+            .term
+            .size
+            .height
+            .saturating_sub(self.console_height() + self.panel_height());
+        // The scrollbar takes the last column of each pane, just to the right of its text
+        let bar = usize::from(self.config.general.scrollbar);
+        let Some(split) = &self.split else {
+            return vec![(
+                self.tab,
+                Size {
+                    width: width.saturating_sub(bar),
+                    height,
+                },
+                Position { x: sidebar, y: 0 },
+            )];
+        };
+        let (first, second) = if split.first {
+            (self.tab, split.other)
+        } else {
+            (split.other, self.tab)
+        };
+        if split.vertical {
+            // Leave a column between the panes for the divider
+            let left = width.saturating_sub(1) / 2;
+            vec![
+                (
+                    first,
+                    Size {
+                        width: left.saturating_sub(bar),
+                        height,
+                    },
+                    Position { x: sidebar, y: 0 },
+                ),
+                (
+                    second,
+                    Size {
+                        width: width.saturating_sub(left + 1 + bar),
+                        height,
+                    },
+                    Position {
+                        x: sidebar + left + 1,
+                        y: 0,
+                    },
+                ),
+            ]
+        } else {
+            // Leave a row between the panes for the divider
+            let rows = height.saturating_sub(OFFSET + 2);
+            let bottom = rows.saturating_sub(1) / 2;
+            let top = rows.saturating_sub(bottom + 1);
+            vec![
+                (
+                    first,
+                    Size {
+                        width: width.saturating_sub(bar),
+                        height: top + OFFSET + 2,
+                    },
+                    Position { x: sidebar, y: 0 },
+                ),
+                (
+                    second,
+                    Size {
+                        width: width.saturating_sub(bar),
+                        height: bottom + OFFSET + 2,
+                    },
+                    Position {
+                        x: sidebar,
+                        y: top + 1,
+                    },
+                ),
+            ]
+        }
+    }
+    fn next_tab(&mut self) {
+        // Move to the next tab
+        if self.tab.saturating_add(1) < self.doc.len() {
+            self.tab = self.tab.saturating_add(1);
+        }
+    }
+    fn prev_tab(&mut self) {
+        // Move to the previous tab
+        self.tab = self.tab.saturating_sub(1);
+    }
+    pub fn shell(&mut self, mut command: String, substitution: bool, root: bool, confirm: bool) {
+        if substitution {
+            let file =
+                self.doc[self.tab].render(self.doc[self.tab].tabs, self.doc[self.tab].tab_width);
+            command = command.replacen("%F", &self.doc[self.tab].path, 1);
+            command = command.replacen("%C", &file, 1);
+        }
+        shell!(self.term, &command, confirm, root);
+        // The command's output was written over the screen
+        self.term.forget();
+    }
+    fn filter(&mut self, command: &str, piped: bool) {
+        // Run a command on the selection or the whole document, or with no input at all,
+        // and put what it writes in place of that text
+        if command.trim().is_empty() {
+            self.doc[self.tab].set_command_line("No command was given".to_string(), Type::Error);
+            return;
+        }
+        let doc = &self.doc[self.tab];
+        let current = self.current();
+        let whole = piped && doc.selection().is_none();
+        let (start, input) = if !piped {
+            (current, String::new())
+        } else if let Some(selected) = doc.selected_text() {
+            selected
+        } else {
+            let last = doc.rows.len().saturating_sub(1);
+            let end = Position {
+                x: doc.rows[last].length(),
+                y: last,
+            };
+            (
+                Position { x: 0, y: 0 },
+                text_between(&doc.rows, Position { x: 0, y: 0 }, end),
+            )
+        };
+        let output = match run_filter(command, &input) {
+            Ok(output) => output,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't run {command}: {error}"), Type::Error);
+                return;
+            }
+        };
+        let errors = String::from_utf8_lossy(&output.stderr);
+        let said = errors
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or(String::new(), |line| format!(": {}", line.trim()));
+        let status = output
+            .status
+            .code()
+            .map_or("a signal".to_string(), |code| format!("status {code}"));
+        if !output.status.success() {
+            // A failed command leaves the text as it was
+            self.doc[self.tab]
+                .set_command_line(format!("{command} failed with {status}{said}"), Type::Error);
+            return;
+        }
+        let text = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+        let mut text = tabs_to_spaces(&text, self.doc[self.tab].tab_width);
+        // Text that didn't end a line doesn't get the newline commands put at the end
+        if !input.ends_with('\n') && text.ends_with('\n') {
+            text.pop();
+        }
+        let lines = text.lines().count();
+        if text != input {
+            let doc = &mut self.doc[self.tab];
+            doc.anchor = None;
+            doc.undo_stack.commit();
+            if !input.is_empty() {
+                self.execute(Event::Deletion(start, input), false);
+            }
+            if !text.is_empty() {
+                self.execute(Event::Insertion(start, text), false);
+            }
+            self.doc[self.tab].undo_stack.commit();
+            if whole {
+                // The cursor stays where it was in the document rather than going to the end
+                let doc = &mut self.doc[self.tab];
+                let y = cmp::min(current.y, doc.rows.len().saturating_sub(1));
+                let x = cmp::min(current.x, doc.rows[y].length());
+                doc.goto(Position { x, y }, &self.size);
+                doc.recalculate_graphemes();
+            }
+        }
+        self.doc[self.tab].set_command_line(
+            format!(
+                "{} finished with {}, {} line{}{}",
+                command,
+                status,
+                lines,
+                if lines == 1 { "" } else { "s" },
+                said
+            ),
+            Type::Info,
+        );
+    }
+    fn format_on_save(&self, tab: usize) -> bool {
+        // Whether a document is formatted before it is saved, which its type of file can say
+        self.config
+            .filetypes
+            .get(&self.doc[tab].filetype)
+            .and_then(|filetype| filetype.format_on_save)
+            .unwrap_or(self.config.general.format_on_save)
+    }
+    fn formatter(&self) -> Option<String> {
+        // The formatter of the current document's type of file, with the file put in for %F
+        let doc = &self.doc[self.tab];
+        let filetype = self.config.filetypes.get(&doc.filetype)?;
+        let command = filetype.formatter.as_ref()?.trim();
+        (!command.is_empty()).then(|| command.replace("%F", &doc.path))
+    }
+    fn format(&mut self, saving: bool) {
+        // Put what the formatter makes of the document in its place, keeping the text if it fails
+        let Some(command) = self.formatter() else {
+            if !saving {
+                let kind = self.doc[self.tab].kind.clone();
+                self.doc[self.tab]
+                    .set_command_line(format!("No formatter is set for {kind}"), Type::Warning);
+            }
+            return;
+        };
+        if self.read_whole(self.tab).is_none() {
+            return;
+        }
+        let doc = &self.doc[self.tab];
+        let input = doc.render(doc.tabs, doc.tab_width) + "\n";
+        let output = match run_filter(&command, &input) {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                let errors = String::from_utf8_lossy(&output.stderr);
+                let said = errors
+                    .lines()
+                    .find(|line| !line.trim().is_empty())
+                    .map_or(String::new(), |line| format!(": {}", line.trim()));
+                self.doc[self.tab].set_command_line(format!("{command} failed{said}"), Type::Error);
+                return;
+            }
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't run {command}: {error}"), Type::Error);
+                return;
+            }
+        };
+        let text = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+        let doc = &self.doc[self.tab];
+        let text = tabs_to_spaces(&text, doc.tab_width);
+        let text = text.strip_suffix('\n').unwrap_or(&text).to_string();
+        let last = doc.rows.len().saturating_sub(1);
+        let end = Position {
+            x: doc.rows[last].length(),
+            y: last,
+        };
+        let before = text_between(&doc.rows, Position { x: 0, y: 0 }, end);
+        if text.trim().is_empty() && !before.trim().is_empty() {
+            // Nothing coming back is taken as the formatter going wrong
+            self.doc[self.tab]
+                .set_command_line(format!("{command} gave back nothing"), Type::Error);
+            return;
+        }
+        if text == before {
+            if !saving {
+                self.doc[self.tab].set_command_line("Already formatted".to_string(), Type::Info);
+            }
+            return;
+        }
+        // The cursor goes after as many of the characters that aren't spaces as it was before
+        let current = self.current();
+        let rows: Vec<&str> = before.split('\n').collect();
+        let mut visible: usize = rows[..current.y]
+            .iter()
+            .map(|row| row.chars().filter(|c| !c.is_whitespace()).count())
+            .sum();
+        visible += rows[current.y]
+            .chars()
+            .take(current.x)
+            .filter(|c| !c.is_whitespace())
+            .count();
+        let mut place = Position { x: 0, y: 0 };
+        for (y, row) in text.split('\n').enumerate() {
+            place = Position { x: 0, y };
+            for c in row.chars() {
+                if visible == 0 {
+                    break;
+                }
+                if !c.is_whitespace() {
+                    visible -= 1;
+                }
+                place.x += 1;
+            }
+            if visible == 0 {
+                break;
+            }
+        }
+        let doc = &mut self.doc[self.tab];
+        doc.anchor = None;
+        doc.undo_stack.commit();
+        self.execute(Event::Deletion(Position { x: 0, y: 0 }, before), false);
+        self.execute(Event::Insertion(Position { x: 0, y: 0 }, text), false);
+        self.doc[self.tab].undo_stack.commit();
+        let doc = &mut self.doc[self.tab];
+        doc.goto(place, &self.size);
+        doc.recalculate_graphemes();
+        doc.set_command_line(format!("Formatted with {command}"), Type::Info);
+    }
+    fn substitute(&mut self, pattern: &str, with: &str, (from, to): (usize, usize), global: bool) {
+        // Replace the first match on each of the lines, or every match, as one edit
+        let Ok(re) = Regex::new(pattern) else {
+            self.doc[self.tab]
+                .set_command_line(format!("{pattern} isn't a valid regex"), Type::Error);
+            return;
+        };
+        let doc = &mut self.doc[self.tab];
+        let lines = from..cmp::min(to + 1, doc.rows.len());
+        doc.undo_stack.commit();
+        let mut count = 0;
+        for y in lines {
+            let row = &doc.rows[y].string;
+            let after = if global {
+                re.replace_all(row, with)
+            } else {
+                re.replace(row, with)
+            }
+            .to_string();
+            if after == doc.rows[y].string {
+                continue;
+            }
+            let before = doc.rows[y].clone();
+            let after = Row::from(after.as_str());
+            doc.rows[y] = after.clone();
+            doc.undo_stack.push(Event::UpdateLine(
+                Position { x: 0, y },
+                0,
+                Box::new(before),
+                Box::new(after),
+            ));
+            count += 1;
+        }
+        if count == 0 {
+            doc.set_command_line(format!("Nothing matched {pattern}"), Type::Info);
+            return;
+        }
+        doc.undo_stack.commit();
+        doc.dirty = true;
+        doc.edits += 1;
+        doc.mass_redraw();
+        doc.snap_cursor(&self.size);
+        doc.recalculate_graphemes();
+        doc.set_command_line(
+            format!(
+                "Changed {} line{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+            Type::Info,
+        );
+    }
+    fn range_text(&self, from: usize, to: usize) -> (usize, usize, String) {
+        // The lines of a range that are there, as text ending in a newline
+        let rows = &self.doc[self.tab].rows;
+        let to = cmp::min(to, rows.len().saturating_sub(1));
+        let from = cmp::min(from, to);
+        let mut text = rows[from..=to]
+            .iter()
+            .map(|row| row.string.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        text.push('\n');
+        (from, to, text)
+    }
+    fn delete_lines(&mut self, from: usize, to: usize, register: Option<char>) {
+        // Cut whole lines as one edit, keeping them like yanked lines
+        let (from, to, text) = self.range_text(from, to);
+        let rows = &self.doc[self.tab].rows;
+        let last = rows.len().saturating_sub(1);
+        // The newline after the lines goes with them, or the one before them at the end
+        let (start, end) = if to < last {
+            (Position { x: 0, y: from }, Position { x: 0, y: to + 1 })
+        } else if from > 0 {
+            (
+                Position {
+                    x: rows[from - 1].length(),
+                    y: from - 1,
+                },
+                Position {
+                    x: rows[to].length(),
+                    y: to,
+                },
+            )
+        } else {
+            (
+                Position { x: 0, y: 0 },
+                Position {
+                    x: rows[to].length(),
+                    y: to,
+                },
+            )
+        };
+        let removed = text_between(rows, start, end);
+        self.store(text, register, true);
+        self.doc[self.tab].undo_stack.commit();
+        self.execute(Event::Deletion(start, removed), false);
+        self.doc[self.tab].undo_stack.commit();
+        let doc = &mut self.doc[self.tab];
+        let y = cmp::min(from, doc.rows.len().saturating_sub(1));
+        doc.goto(Position { x: 0, y }, &self.size);
+        doc.recalculate_graphemes();
+        if register.is_none() {
+            let count = to - from + 1;
+            doc.set_command_line(
+                format!("Cut {} line{}", count, if count == 1 { "" } else { "s" }),
+                Type::Info,
+            );
+        }
+    }
+    fn yank_lines(&mut self, from: usize, to: usize, register: Option<char>) {
+        // Copy whole lines without moving the cursor
+        let (from, to, text) = self.range_text(from, to);
+        self.store(text, register, false);
+        if register.is_none() {
+            let count = to - from + 1;
+            self.doc[self.tab].set_command_line(
+                format!("Copied {} line{}", count, if count == 1 { "" } else { "s" }),
+                Type::Info,
+            );
+        }
+    }
+    fn transform(&mut self, how: Transform, lines: Option<(usize, usize)>) {
+        // Change the selection or the lines given, or else the document or the current line
+        let doc = &self.doc[self.tab];
+        let current = self.current();
+        let last = doc.rows.len().saturating_sub(1);
+        let whole = matches!(
+            how,
+            Transform::Sort(..) | Transform::Reverse | Transform::Unique
+        );
+        let span = |from: usize, to: usize| {
+            (
+                Position { x: 0, y: from },
+                Position {
+                    x: doc.rows[to].length(),
+                    y: to,
+                },
+            )
+        };
+        let (start, end) = match (lines, doc.selection()) {
+            (Some((from, to)), _) => span(cmp::min(from, last), cmp::min(to, last)),
+            (None, Some((start, end))) if !whole => (start, end),
+            // Lines are taken whole, leaving out one the selection only reaches the start of
+            (None, Some((start, end))) => {
+                span(start.y, end.y - usize::from(end.x == 0 && end.y > start.y))
+            }
+            (None, None) if whole => span(0, last),
+            (None, None) => span(current.y, current.y),
+        };
+        let before = text_between(&doc.rows, start, end);
+        let after = transform(&before, how);
+        if after == before {
+            self.doc[self.tab].set_command_line("Nothing changed".to_string(), Type::Info);
+            return;
+        }
+        let doc = &mut self.doc[self.tab];
+        doc.anchor = None;
+        doc.undo_stack.commit();
+        self.execute(Event::Deletion(start, before), false);
+        self.execute(Event::Insertion(start, after), false);
+        self.doc[self.tab].undo_stack.commit();
+        // The cursor stays where it was rather than going to the end of the new text
+        let doc = &mut self.doc[self.tab];
+        let y = cmp::min(current.y, doc.rows.len().saturating_sub(1));
+        let x = cmp::min(current.x, doc.rows[y].length());
+        doc.goto(Position { x, y }, &self.size);
+        doc.recalculate_graphemes();
+        let count = end.y - start.y + 1;
+        doc.set_command_line(
+            format!(
+                "Changed {} line{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+            Type::Info,
+        );
+    }
+    fn increment(&mut self, amount: i128) {
+        // Add to the number under or after the cursor, keeping how it is written
+        let current = self.current();
+        let doc = &self.doc[self.tab];
+        let row = &doc.rows[current.y];
+        let cursor = row.byte_index(current.x);
+        let numbers = Regex::new(r"0[xX][0-9a-fA-F]+|-?[0-9]+").unwrap();
+        let found = numbers.find_iter(&row.string).find(|m| m.end() > cursor);
+        let (x, before) = if let Some(number) = found {
+            let x = UnicodeWidthStr::width(&row.string[..number.start()]);
+            (x, number.as_str().to_string())
+        } else {
+            self.doc[self.tab]
+                .set_command_line("No number on this line".to_string(), Type::Warning);
+            return;
+        };
+        let Some(changed) = bump(&before, amount) else {
+            self.doc[self.tab].set_command_line(format!("{before} is too large"), Type::Warning);
+            return;
+        };
+        let start = Position { x, y: current.y };
+        let doc = &mut self.doc[self.tab];
+        doc.anchor = None;
+        doc.undo_stack.commit();
+        self.execute(Event::Deletion(start, before), false);
+        self.execute(Event::Insertion(start, changed.clone()), false);
+        self.doc[self.tab].undo_stack.commit();
+        // The cursor goes to the last digit, so the same number is changed again next time
+        let doc = &mut self.doc[self.tab];
+        let x = x + changed.len() - 1;
+        doc.goto(Position { x, y: current.y }, &self.size);
+        doc.recalculate_graphemes();
+    }
+    #[allow(clippy::too_many_lines)] // One arm per event
+    pub fn execute(&mut self, event: Event, reversed: bool) {
+        // Event executor
+        log!(debug, "Event", format!("{:?}", event));
+        let edits = will_edit(&event)
+            || matches!(
+                event,
+                Event::Undo
+                    | Event::Redo
+                    | Event::RedoBranch(_)
+                    | Event::History
+                    | Event::Save(None, false)
+                    | Event::LineEndings(_)
+                    | Event::Encoding(_)
+                    | Event::Comment
+                    | Event::Filter(_, _)
+                    | Event::Transform(_, _)
+                    | Event::Increment(_)
+                    | Event::DeleteLines(_, _, _)
+                    | Event::Format
+            );
+        if self.doc[self.tab].read_only && edits {
+            let name = self.doc[self.tab].name.clone();
+            self.doc[self.tab].set_command_line(format!("{name} is read only"), Type::Warning);
+            return;
+        }
+        // Large files are read in whole before they change, or are saved with only part of them
+        let event =
+            if self.doc[self.tab].lazy.is_some() && (edits || matches!(event, Event::Save(..))) {
+                match self.read_whole(self.tab) {
+                    Some(lines) => shifted(event, lines),
+                    None => return,
+                }
+            } else {
+                event
+            };
+        if !matches!(event, Event::Scroll(_)) {
+            // Bring the cursor back into view
+            self.doc[self.tab].peek = None;
+        }
+        if will_edit(&event) && !reversed {
+            self.edited = true;
+        }
+        if will_edit(&event) || matches!(event, Event::Undo | Event::Redo) {
+            // Editing the text ends the selection, and language servers need to see it again
+            self.doc[self.tab].anchor = None;
+            self.doc[self.tab].edits += 1;
+        }
+        match event {
+            Event::New => self.new_document(),
+            Event::Open(file) => self.open_document(file),
+            Event::Recent => self.open_recent(),
+            Event::Jump(place) => self.jump(place),
+            Event::Save(file, prompt) => self.save_document(file, prompt),
+            Event::SaveAll => self.save_every_document(),
+            Event::Quit(force) => self.quit_document(force),
+            Event::QuitAll(force) => self.quit_all(force),
+            Event::NextTab => self.next_tab(),
+            Event::PrevTab => self.prev_tab(),
+            Event::SplitView(vertical) => self.split_view(vertical),
+            Event::Unsplit => self.unsplit(),
+            Event::Explorer => self.toggle_explorer(),
+            Event::Console => self.toggle_console(),
+            Event::Diff => self.toggle_diff(),
+            Event::Preview => self.toggle_preview(),
+            Event::Export(file) => self.export(file),
+            Event::Build(command) => self.build(command),
+            Event::Problem(forward) => self.next_problem(forward),
+            Event::Problems => self.toggle_problems(),
+            Event::Find => self.find_file(),
+            Event::Complete => self.complete(),
+            Event::Spell => self.spell(),
+            Event::Messages => self.show_messages(),
+            Event::Help => self.help(),
+            Event::Grep(term) => self.grep(term),
+            Event::GrepReplace => self.grep_replace(),
+            Event::Definition(symbol) => self.go_to_definition(symbol),
+            Event::DefinitionBack => self.tag_back(),
+            Event::Hunk(forward) => self.next_hunk(forward),
+            Event::Plugin(name, args) => self.run_plugin(&name, &args),
+            Event::Words => {
+                if !self.complete_word() {
+                    self.doc[self.tab]
+                        .set_command_line("No words to complete with".to_string(), Type::Info);
+                }
+            }
+            Event::Hex(file) => {
+                let path = file.unwrap_or_else(|| self.doc[self.tab].path.clone());
+                if path.is_empty() {
+                    self.doc[self.tab].set_command_line(
+                        "Save the document before showing it as bytes".to_string(),
+                        Type::Error,
+                    );
+                } else {
+                    self.hex_edit(&path);
+                }
+            }
+            Event::SwitchPane => self.switch_pane(),
+            Event::Search => self.search(),
+            Event::Replace => self.replace(),
+            Event::ReplaceAll => self.replace_all(),
+            Event::Substitute(pattern, with, lines, global) => {
+                self.substitute(&pattern, &with, lines, global);
+            }
+            Event::DeleteLines(from, to, register) => self.delete_lines(from, to, register),
+            Event::YankLines(from, to, register) => self.yank_lines(from, to, register),
+            Event::Cmd => self.cmd(),
+            Event::Filter(command, piped) => self.filter(&command, piped),
+            Event::Transform(how, lines) => self.transform(how, lines),
+            Event::Increment(amount) => self.increment(amount),
+            Event::Format => self.format(false),
+            Event::Shell(command, confirm, substitution, root) => {
+                self.shell(command, confirm, substitution, root);
+            }
+            Event::ReloadConfig => {
+                let config = Reader::read(&self.config_path);
+                self.config = config.0;
+                for doc in &mut self.doc {
+                    doc.scroll_off = self.config.general.scroll_off;
+                    let settings = doc.settings(&self.config);
+                    doc.configure(&self.config, &settings);
+                }
+                let loaded = Document::config_to_commandline(&config.1);
+                self.doc[self.tab].set_command_line(loaded.text, loaded.msg);
+            }
+            Event::Theme(name) => {
+                // Switch the colour scheme and / or the syntax highlighting colours
+                // A base16 scheme or VS Code theme can be named by its path to take it in
+                let name = if !self.config.themes.contains_key(&name) && Path::new(&name).is_file()
+                {
+                    match scheme::import(&mut self.config, Path::new(&name)) {
+                        Ok(imported) => imported,
+                        Err(error) => {
+                            self.doc[self.tab].set_command_line(error, Type::Error);
+                            return;
+                        }
+                    }
+                } else {
+                    name
+                };
+                let found = if let Some(theme) = self.config.themes.get(&name) {
+                    if self.config.highlights.contains_key(&theme.default_theme) {
+                        self.theme.clone_from(&theme.default_theme);
+                    }
+                    self.config.theme = theme.clone();
+                    true
+                } else if self.config.highlights.contains_key(&name) {
+                    self.theme.clone_from(&name);
+                    true
+                } else {
+                    false
+                };
+                if found {
+                    self.doc[self.tab].mass_redraw();
+                    self.update();
+                } else {
+                    self.doc[self.tab]
+                        .set_command_line(format!("Theme {name} not found"), Type::Error);
+                }
+            }
+            Event::Option(name, value) => match self.config.general.set(&name, value.as_deref()) {
+                Ok(message) => {
+                    for doc in &mut self.doc {
+                        doc.scroll_off = self.config.general.scroll_off;
+                        let settings = doc.settings(&self.config);
+                        doc.configure(&self.config, &settings);
+                    }
+                    self.doc[self.tab].mass_redraw();
+                    self.doc[self.tab].set_command_line(message, Type::Info);
+                }
+                Err(message) => self.doc[self.tab].set_command_line(message, Type::Error),
+            },
+            Event::MoveWord(direction) => match direction {
+                Direction::Left => self.doc[self.tab].word_left(&self.size),
+                Direction::Right => self.doc[self.tab].word_right(&self.size),
+                _ => {}
+            },
+            Event::MoveParagraph(direction) => {
+                self.doc[self.tab].paragraph(matches!(direction, Direction::Down), &self.size);
+            }
+            Event::MoveBlock(end) => self.doc[self.tab].block_edge(end, &self.size),
+            Event::GotoCursor(pos) => {
+                let rows = &self.doc[self.tab].rows;
+                if rows.len() > pos.y && rows[pos.y].length() >= pos.x {
+                    self.doc[self.tab].goto(pos, &self.size);
+                }
+            }
+            Event::MoveCursor(magnitude, direction) => {
+                for _ in 0..magnitude {
+                    self.doc[self.tab].move_cursor(
+                        direction,
+                        &self.size,
+                        self.config.general.wrap_cursor,
+                    );
+                }
+            }
+            Event::Commit => self.doc[self.tab].undo_stack.commit(),
+            Event::Store(kind, bank) => {
+                let cursor = self.doc[self.tab].cursor;
+                let offset = self.doc[self.tab].offset;
+                let current = Position {
+                    x: cursor.x + offset.x,
+                    y: cursor.y + offset.y - OFFSET,
+                };
+                match kind {
+                    BankType::Cursor => {
+                        self.position_bank.insert(bank, current);
+                    }
+                    BankType::Line => {
+                        self.row_bank
+                            .insert(bank, self.doc[self.tab].rows[current.y].clone());
+                    }
+                }
+            }
+            Event::Load(kind, bank) => {
+                let cursor = self.doc[self.tab].cursor;
+                let offset = self.doc[self.tab].offset;
+                let current = Position {
+                    x: cursor.x + offset.x,
+                    y: cursor.y + offset.y - OFFSET,
+                };
+                match kind {
+                    BankType::Cursor => {
+                        let cursor = *self.position_bank.get(&bank).unwrap_or(&current);
+                        self.doc[self.tab].goto(cursor, &self.size);
+                    }
+                    BankType::Line => {
+                        if let Some(row) = self.row_bank.get(&bank) {
+                            self.doc[self.tab].rows[current.y] = row.clone();
+                        }
+                    }
+                }
+            }
+            Event::ToggleWrap => self.doc[self.tab].toggle_wrap(&self.size),
+            Event::ToggleRelative => {
+                let relative = &mut self.config.general.relative_line_numbers;
+                *relative = !*relative;
+            }
+            Event::ToggleBlame => {
+                let blame = &mut self.config.general.blame;
+                *blame = !*blame;
+            }
+            Event::Encoding(encoding) => {
+                let doc = &mut self.doc[self.tab];
+                if doc.encoding != encoding {
+                    doc.encoding = encoding;
+                    doc.dirty = true;
+                }
+                doc.set_command_line(
+                    format!("The file will be saved as {}", encoding.name()),
+                    Type::Info,
+                );
+            }
+            Event::LineEndings(crlf) => {
+                let doc = &mut self.doc[self.tab];
+                if doc.crlf != crlf {
+                    doc.crlf = crlf;
+                    doc.dirty = true;
+                }
+                let ending = if crlf { "CRLF" } else { "LF" };
+                doc.set_command_line(
+                    format!("Lines will end with {ending} when saved"),
+                    Type::Info,
+                );
+            }
+            Event::ToggleReadOnly => {
+                let doc = &mut self.doc[self.tab];
+                doc.read_only = !doc.read_only;
+                let state = if doc.read_only {
+                    "is now read only"
+                } else {
+                    "can be edited again"
+                };
+                doc.set_command_line(format!("{} {}", doc.name, state), Type::Info);
+            }
+            Event::Scroll(direction) => self.doc[self.tab].scroll(direction),
+            Event::Copy(register) => self.copy(register),
+            Event::Paste(register) => self.paste(register),
+            Event::Cut(register) => self.cut(register),
+            Event::Registers => self.show_registers(),
+            Event::Select => {
+                // Drop an anchor at the cursor, or pick it back up
+                let current = self.current();
+                let anchor = &mut self.doc[self.tab].anchor;
+                *anchor = if anchor.is_some() {
+                    None
+                } else {
+                    Some(current)
+                };
+            }
+            Event::Record => self.record(),
+            Event::MatchBracket => {
+                let current = self.current();
+                if let Some((_, other)) = self.doc[self.tab].matching_bracket(&current) {
+                    self.doc[self.tab].record_jump(current);
+                    self.doc[self.tab].goto(other, &self.size);
+                    self.doc[self.tab].recalculate_graphemes();
+                } else {
+                    self.doc[self.tab]
+                        .set_command_line("No matching bracket".to_string(), Type::Warning);
+                }
+            }
+            Event::Repeat => self.repeat_edit(),
+            Event::Replay => {
+                // Queue the recording up to be read as if it were typed again
+                if !self.replaying {
+                    self.playback.extend(self.recorded.iter().copied());
+                }
+            }
+            Event::Statistics => {
+                let (words, chars, lines) = self.doc[self.tab].statistics();
+                self.doc[self.tab].set_command_line(
+                    format!("{words} words, {chars} characters, {lines} lines"),
+                    Type::Info,
+                );
+            }
+            Event::Home => self.doc[self.tab].leap_cursor(Leap::Home, &self.size),
+            Event::End => self.doc[self.tab].leap_cursor(Leap::End, &self.size),
+            Event::Center => self.doc[self.tab].center(&self.size),
+            Event::AddCursor(direction) => self.add_cursor(direction),
+            Event::AddNextCursor => self.add_cursor_at_next(),
+            Event::Mark(set, letter) => self.mark(set, letter),
+            Event::Fold(all) => {
+                if !self.doc[self.tab].fold(all, &self.size) {
+                    self.doc[self.tab]
+                        .set_command_line("Nothing to fold here".to_string(), Type::Info);
+                }
+            }
+            Event::Unfold(all) => {
+                if !self.doc[self.tab].unfold(all) {
+                    self.doc[self.tab]
+                        .set_command_line("Nothing is folded here".to_string(), Type::Info);
+                }
+            }
+            Event::Comment => self.toggle_comment(),
+            Event::Suspend => self.suspend(),
+            Event::Retrace(back) => {
+                let current = self.current();
+                if !self.doc[self.tab].retrace(back, current, &self.size) {
+                    let message = if back {
+                        "No earlier jumps"
+                    } else {
+                        "No later jumps"
+                    };
+                    self.doc[self.tab].set_command_line(message.to_string(), Type::Info);
+                }
+            }
+            Event::SelectBlock => {
+                // Drop a corner at the cursor, or pick it back up
+                let current = self.current();
+                let doc = &mut self.doc[self.tab];
+                doc.anchor = None;
+                doc.cursors.clear();
+                doc.block = if doc.block.is_some() {
+                    None
+                } else {
+                    Some(current)
+                };
+            }
+            Event::PageUp => self.doc[self.tab].leap_cursor(Leap::PageUp, &self.size),
+            Event::PageDown => self.doc[self.tab].leap_cursor(Leap::PageDown, &self.size),
+            Event::Undo => self.undo(),
+            Event::Redo => self.redo(),
+            Event::RedoBranch(branch) => self.redo_branch(branch),
+            Event::History => self.browse_history(),
+            // Event is a document event, send to current document
+            _ => self.doc[self.tab].execute(event, reversed, &self.size, &self.config),
+        }
+        self.doc[self.tab].recalculate_graphemes();
+    }
+    fn find_file(&mut self) {
+        // Narrow down the files under the working directory as a pattern is typed
+        let root = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let mut finder = Finder::new(root);
+        let mut pattern = String::new();
+        let mut selected = 0;
+        let mut redraw = true;
+        let chosen = loop {
+            redraw |= finder.receive();
+            if redraw {
+                let height = self.term.size.height.saturating_sub(OFFSET + 2);
+                let results = finder.matches(&pattern, height);
+                selected = selected.min(results.len().saturating_sub(1));
+                let progress = if finder.done { "" } else { "..." };
+                self.doc[self.tab].set_prompt_line(
+                    format!("Find ({}{}): {}", finder.files.len(), progress, pattern),
+                    Type::Info,
+                );
+                self.update();
+                self.draw_results(&results, selected);
+                redraw = false;
+            }
+            // Keep checking on the search while waiting for keys, until it has finished
+            let waiting = self.playback.is_empty();
+            if waiting && !finder.done && !self.term.poll(Duration::from_millis(16)) {
+                continue;
+            }
+            let InputEvent::Key(key) = self.read_event() else {
+                continue;
+            };
+            redraw = true;
+            match Editor::key_event_to_ox_key(key.code, key.modifiers) {
+                KeyBinding::Raw(RawKey::Enter) => {
+                    let height = self.term.size.height.saturating_sub(OFFSET + 2);
+                    break finder
+                        .matches(&pattern, height)
+                        .get(selected)
+                        .map(ToString::to_string);
+                }
+                KeyBinding::Raw(RawKey::Esc) => break None,
+                KeyBinding::Raw(RawKey::Up) => selected = selected.saturating_sub(1),
+                KeyBinding::Raw(RawKey::Down) => selected += 1,
+                KeyBinding::Raw(RawKey::Backspace) => {
+                    pattern.pop();
+                    selected = 0;
+                }
+                KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
+                    pattern.push(c);
+                    selected = 0;
+                }
+                _ => (),
+            }
+        };
+        self.doc[self.tab].set_command_line(String::new(), Type::Info);
+        if let Some(path) = chosen {
+            self.show_file(Path::new(&path));
+        }
+    }
+    fn draw_results(&mut self, results: &[&str], selected: usize) {
+        // Show the matching files over the top of the documents
+        if self.batch.is_some() {
+            return;
+        }
+        self.term.hide_cursor();
+        let width = self.term.size.width;
+        let theme = &self.config.theme;
+        for (row, result) in results.iter().enumerate() {
+            self.term.goto(&Position {
+                x: 0,
+                y: row + OFFSET,
+            });
+            let text = trim_end(&format!(" {result}"), width);
+            self.term.write(&format!(
+                "{}{}{}{}{}{}",
+                if row == selected {
+                    Reader::rgb_bg(theme.selection_bg)
+                } else {
+                    Reader::rgb_bg(theme.status_bg)
+                },
+                Reader::rgb_fg(theme.status_fg),
+                text,
+                " ".repeat(width.saturating_sub(text.width())),
+                RESET_FG,
+                RESET_BG,
+            ));
+        }
+        // The documents under the results have to be drawn again next time
+        self.term.forget_rows(OFFSET..results.len() + OFFSET);
+        self.term.flush();
+    }
+    fn complete(&mut self) {
+        // Offer what the language server suggests in place of the word before the cursor
+        let Some((language, _)) = self.server_for(self.tab) else {
+            self.doc[self.tab].set_command_line(
+                "There is no language server for this document".to_string(),
+                Type::Warning,
+            );
+            return;
+        };
+        self.lsp_sync();
+        let current = self.current();
+        let row = &self.doc[self.tab].rows[current.y];
+        let before = &row.string[..row.byte_index(current.x)];
+        let uri = lsp::uri(&self.doc[self.tab].path);
+        let completions = match self.servers.get_mut(&language) {
+            Some(Some(server)) if server.alive => {
+                server.complete(&uri, (current.y, lsp::to_units(before)))
+            }
+            _ => {
+                self.doc[self.tab].set_command_line(
+                    format!("The {language} language server isn't running"),
+                    Type::Error,
+                );
+                return;
+            }
+        };
+        if completions.is_empty() {
+            self.doc[self.tab].set_command_line("No completions".to_string(), Type::Info);
+            return;
+        }
+        if let Some(index) = self.popup(&completions) {
+            self.replace_word(completions[index].clone());
+        }
+    }
+    fn complete_word(&mut self) -> bool {
+        // Offer the words in the open documents that start with the one before the cursor
+        let prefix = self.word_before();
+        if prefix.is_empty() {
+            return false;
+        }
+        let mut words: Vec<&str> = self
+            .doc
+            .iter()
+            .flat_map(|doc| &doc.rows)
+            .flat_map(|row| row.string.split(|c: char| !c.is_alphanumeric() && c != '_'))
+            .filter(|word| word.starts_with(&prefix) && word.len() > prefix.len())
+            .collect();
+        if words.is_empty() {
+            return false;
+        }
+        words.sort_unstable();
+        words.dedup();
+        let words: Vec<String> = words.into_iter().map(ToString::to_string).collect();
+        if let Some(index) = self.popup(&words) {
+            self.replace_word(words[index].clone());
+        }
+        true
+    }
+    fn expand_snippet(&mut self) -> bool {
+        // Put the snippet named by the word before the cursor in its place, if there is one
+        let word = self.word_before();
+        let ext = &self.doc[self.tab].filetype;
+        let body = match Reader::get_language(&self.config, ext)
+            .and_then(|lang| lang.snippets.get(&word))
+        {
+            Some(body) if !word.is_empty() => body.clone(),
+            _ => return false,
+        };
+        let current = self.current();
+        let start = Position {
+            x: current.x - word.width(),
+            y: current.y,
+        };
+        // Lines after the first line up with the line the snippet was expanded on
+        let row = &self.doc[self.tab].rows[current.y];
+        let indent = " ".repeat(row.string.chars().take_while(|c| *c == ' ').count());
+        let tab = " ".repeat(self.doc[self.tab].tab_width);
+        let (text, stops) = Editor::parse_snippet(&body.replace('\t', &tab), start, &indent);
+        self.replace_word(text);
+        self.stops = stops;
+        self.stop = 0;
+        self.doc[self.tab].goto(self.stops[0], &self.size);
+        self.doc[self.tab].recalculate_graphemes();
+        if self.stops.len() == 1 {
+            // There is nothing else to fill in
+            self.stops.clear();
+        }
+        true
+    }
+    fn parse_snippet(body: &str, start: Position, indent: &str) -> (String, Vec<Position>) {
+        // Take the places out of a snippet, returning its text and where the places end up
+        let mut text = String::new();
+        let mut places: Vec<(usize, Position)> = vec![];
+        let mut at = start;
+        let mut chars = body.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '$' && chars.peek().is_some_and(char::is_ascii_digit) {
+                let mut number = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    number = number * 10 + digit as usize;
+                    chars.next();
+                }
+                places.push((number, at));
+            } else if c == '\n' {
+                text.push('\n');
+                text.push_str(indent);
+                at = Position {
+                    x: indent.width(),
+                    y: at.y + 1,
+                };
+            } else {
+                text.push(c);
+                at.x += c.to_string().width();
+            }
+        }
+        // $0 comes last, and snippets without one finish after their text
+        if !places.iter().any(|(number, _)| *number == 0) {
+            places.push((0, at));
         }
+        places.sort_by_key(|(number, _)| number.checked_sub(1).unwrap_or(usize::MAX));
+        (text, places.into_iter().map(|(_, at)| at).collect())
     }
-    fn quit_all(&mut self, force: bool) {
-        // Quit all the documents in the editor
-        self.tab = 0;
-        while !self.quit {
-            self.execute(Event::Quit(force), false);
+    fn back_tab(&mut self) {
+        // Shift + Tab moves back through snippets, and otherwise takes away indentation
+        if !self.next_stop(false) {
+            self.dedent();
         }
     }
-    fn next_tab(&mut self) {
-        // Move to the next tab
-        if self.tab.saturating_add(1) < self.doc.len() {
-            self.tab = self.tab.saturating_add(1);
+    fn next_stop(&mut self, forward: bool) -> bool {
+        // Move on to the next or previous place of the snippet being filled in
+        if self.stops.is_empty() {
+            return false;
         }
+        if forward {
+            self.stop += 1;
+        } else {
+            self.stop = self.stop.saturating_sub(1);
+        }
+        // The text may have changed around the place since it was found
+        let doc = &self.doc[self.tab];
+        let y = self.stops[self.stop].y.min(doc.rows.len() - 1);
+        let x = self.stops[self.stop].x.min(doc.rows[y].length());
+        self.doc[self.tab].goto(Position { x, y }, &self.size);
+        self.doc[self.tab].recalculate_graphemes();
+        if self.stop + 1 == self.stops.len() {
+            // Reaching the last place finishes the snippet
+            self.stops.clear();
+        }
+        true
     }
-    fn prev_tab(&mut self) {
-        // Move to the previous tab
-        self.tab = self.tab.saturating_sub(1);
+    fn word_before(&self) -> String {
+        // The part of a word that is before the cursor
+        let current = self.current();
+        let row = &self.doc[self.tab].rows[current.y];
+        let word: Vec<char> = row.string[..row.byte_index(current.x)]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        word.into_iter().rev().collect()
     }
-    pub fn shell(&mut self, mut command: String, substitution: bool, root: bool, confirm: bool) {
-        if substitution {
-            let file =
-                self.doc[self.tab].render(self.doc[self.tab].tabs, self.config.general.tab_width);
-            command = command.replacen("%F", &self.doc[self.tab].path, 1);
-            command = command.replacen("%C", &file, 1);
+    fn replace_word(&mut self, with: String) {
+        // Put text in place of the word before the cursor
+        let current = self.current();
+        let word = self.word_before();
+        let start = Position {
+            x: current.x - word.width(),
+            y: current.y,
+        };
+        self.replace_text(start, word, with);
+    }
+    fn replace_text(&mut self, start: Position, word: String, with: String) {
+        // Swap the text at a position for something else, as a single edit
+        self.doc[self.tab].undo_stack.commit();
+        if !word.is_empty() {
+            self.execute(Event::Deletion(start, word), false);
         }
-        shell!(&command, confirm, root);
+        self.execute(Event::Insertion(start, with), false);
+        self.doc[self.tab].undo_stack.commit();
     }
-    pub fn execute(&mut self, event: Event, reversed: bool) {
-        // Event executor
-        if self.doc[self.tab].read_only && Editor::will_edit(&event) {
-            return;
+    fn load_dictionary(&mut self) -> bool {
+        // Read the words for spell checking the first time they are needed
+        if self.dictionary.is_some() {
+            return true;
         }
-        match event {
-            Event::New => self.new_document(),
-            Event::Open(file) => self.open_document(file),
-            Event::Save(file, prompt) => self.save_document(file, prompt),
-            Event::SaveAll => self.save_every_document(),
-            Event::Quit(force) => self.quit_document(force),
-            Event::QuitAll(force) => self.quit_all(force),
-            Event::NextTab => self.next_tab(),
-            Event::PrevTab => self.prev_tab(),
-            Event::Search => self.search(),
-            Event::Replace => self.replace(),
-            Event::ReplaceAll => self.replace_all(),
-            Event::Cmd => self.cmd(),
-            Event::Shell(command, confirm, substitution, root) => {
-                self.shell(command, confirm, substitution, root)
+        let path = self.config.general.dictionary.clone();
+        match Dictionary::open(&path) {
+            Ok(dictionary) => self.dictionary = Some(dictionary),
+            Err(error) => {
+                self.config.general.spell_check = false;
+                self.doc[self.tab].set_command_line(
+                    format!("Couldn't read the dictionary \"{path}\": {error}"),
+                    Type::Error,
+                );
             }
-            Event::ReloadConfig => {
-                let config = Reader::read(&self.config_path);
-                self.config = config.0;
-                self.doc[self.tab].cmd_line = Document::config_to_commandline(&config.1);
+        }
+        self.dictionary.is_some()
+    }
+    fn spell(&mut self) {
+        // Offer known words in place of the misspelt one at the cursor
+        if !self.load_dictionary() {
+            return;
+        }
+        let current = self.current();
+        let row = &self.doc[self.tab].rows[current.y];
+        let word = spell::words(&row.string)
+            .into_iter()
+            .find(|(start, word)| (*start..=start + word.width()).contains(&current.x));
+        let (start, word) = if let Some((start, word)) = word {
+            (start, word.to_string())
+        } else {
+            self.doc[self.tab]
+                .set_command_line("There is no word at the cursor".to_string(), Type::Info);
+            return;
+        };
+        let dictionary = self.dictionary.as_ref().unwrap();
+        let suggestions = dictionary.suggest(&word, 10);
+        if dictionary.knows(&word) {
+            self.doc[self.tab].set_command_line(format!("{word} is spelt correctly"), Type::Info);
+        } else if suggestions.is_empty() {
+            self.doc[self.tab].set_command_line(format!("No suggestions for {word}"), Type::Info);
+        } else if let Some(index) = self.popup(&suggestions) {
+            let start = Position {
+                x: start,
+                y: current.y,
+            };
+            self.replace_text(start, word, suggestions[index].clone());
+        }
+    }
+    fn is_prose(path: &str) -> bool {
+        // Whether a file is writing rather than code, so that all of it is spell checked
+        path.is_empty()
+            || matches!(
+                path.split('.').next_back(),
+                Some("md" | "markdown" | "txt" | "rst" | "tex")
+            )
+    }
+    fn popup(&mut self, items: &[String]) -> Option<usize> {
+        // Pick from a list by the cursor, any other key closes it and goes on to the document
+        if self.batch.is_some() {
+            return None;
+        }
+        let origin = self
+            .panes()
+            .iter()
+            .find(|pane| pane.0 == self.tab)
+            .map(|pane| pane.2)?;
+        let cursor = self.doc[self.tab].screen_cursor(&self.size)?;
+        let at = Position {
+            x: cursor.x + origin.x,
+            y: cursor.y + origin.y,
+        };
+        let (screen_width, screen_height) = (self.term.size.width, self.term.size.height);
+        let height = items.len().min(10).min(screen_height.saturating_sub(3));
+        let width = items.iter().map(|item| item.width()).max().unwrap_or(0) + 2;
+        let width = width.min(screen_width);
+        // The list goes under the cursor when there is room, otherwise over it
+        let top = if at.y + 1 + height <= screen_height.saturating_sub(2) {
+            at.y + 1
+        } else {
+            at.y.saturating_sub(height)
+        };
+        let left = at.x.min(screen_width - width);
+        let mut selected = 0;
+        let mut scroll = 0;
+        let choice = loop {
+            if selected < scroll {
+                scroll = selected;
+            } else if selected >= scroll + height {
+                scroll = selected + 1 - height;
             }
-            Event::Theme(name) => {
-                self.theme = name;
-                self.doc[self.tab].mass_redraw();
-                self.update();
+            let theme = &self.config.theme;
+            self.term.hide_cursor();
+            for (row, item) in items[scroll..scroll + height].iter().enumerate() {
+                self.term.goto(&Position {
+                    x: left,
+                    y: top + row,
+                });
+                let text = trim_end(&format!(" {item}"), width);
+                self.term.write(&format!(
+                    "{}{}{}{}{}{}",
+                    if row + scroll == selected {
+                        Reader::rgb_bg(theme.selection_bg)
+                    } else {
+                        Reader::rgb_bg(theme.status_bg)
+                    },
+                    Reader::rgb_fg(theme.status_fg),
+                    text,
+                    " ".repeat(width.saturating_sub(text.width())),
+                    RESET_FG,
+                    RESET_BG,
+                ));
             }
-            Event::MoveWord(direction) => match direction {
-                Direction::Left => self.doc[self.tab].word_left(&self.term.size),
-                Direction::Right => self.doc[self.tab].word_right(&self.term.size),
-                _ => {},
-            },
-            Event::GotoCursor(pos) => {
-                let rows = &self.doc[self.tab].rows;
-                if rows.len() > pos.y && rows[pos.y].length() >= pos.x {
-                    self.doc[self.tab].goto(pos, &self.term.size);
+            self.term.goto(&at);
+            self.term.show_cursor();
+            self.term.flush();
+            match self.read_event() {
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Up,
+                    modifiers: KeyModifiers::NONE,
+                }) => selected = selected.checked_sub(1).unwrap_or(items.len() - 1),
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Down,
+                    modifiers: KeyModifiers::NONE,
+                }) => selected = (selected + 1) % items.len(),
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Enter | KeyCode::Tab,
+                    modifiers: KeyModifiers::NONE,
+                }) => break Some(selected),
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Esc, ..
+                }) => break None,
+                event => {
+                    self.playback.push_front(event);
+                    break None;
                 }
             }
-            Event::MoveCursor(magnitude, direction) => {
-                for _ in 0..magnitude {
-                    self.doc[self.tab].move_cursor(
-                        match direction {
-                            Direction::Up => KeyCode::Up,
-                            Direction::Down => KeyCode::Down,
-                            Direction::Left => KeyCode::Left,
-                            Direction::Right => KeyCode::Right,
-                        },
-                        &self.term.size,
-                        self.config.general.wrap_cursor,
+        };
+        // The documents under the list have to be drawn again next time
+        self.term.forget_rows(top..top + height);
+        choice
+    }
+    fn is_binary(path: &str, config: &Reader) -> bool {
+        // Whether a file exists but doesn't look like text, going by the start of large files
+        let bytes = if lazy::is_large(path, config.general.large_file_size) {
+            lazy::sample(path)
+        } else {
+            fs::read(path)
+        };
+        // Compressed files are opened as what they hold
+        bytes.is_ok_and(|bytes| {
+            Encoding::detect(&bytes).is_none() && Compression::detect(path, &bytes).is_none()
+        })
+    }
+    fn hex_edit(&mut self, path: &str) {
+        // Show the bytes of a file, changing them by typing hex digits, until closed
+        let mut hex = match Hex::open(path) {
+            Ok(hex) => hex,
+            Err(error) => {
+                self.doc[self.tab]
+                    .set_command_line(format!("Couldn't open {path}: {error}"), Type::Error);
+                return;
+            }
+        };
+        let help = "Type hex digits to change bytes, Ctrl + S saves and Esc closes";
+        self.doc[self.tab].set_command_line(help.to_string(), Type::Info);
+        let mut warned = false;
+        loop {
+            let per_line = Hex::per_line(self.term.size.width);
+            let height = self.term.size.height.saturating_sub(OFFSET + 2);
+            self.draw_hex(&mut hex, per_line, height);
+            let key = match self.read_event() {
+                InputEvent::Key(key) => key,
+                InputEvent::Resize(width, height) => {
+                    self.term.size = Size {
+                        width: width as usize,
+                        height: height as usize,
+                    };
+                    continue;
+                }
+                InputEvent::Mouse(_) => continue,
+            };
+            let closing = matches!(
+                Editor::key_event_to_ox_key(key.code, key.modifiers),
+                KeyBinding::Raw(RawKey::Esc) | KeyBinding::Ctrl(RawKey::Char('q'))
+            );
+            match Editor::key_event_to_ox_key(key.code, key.modifiers) {
+                _ if closing && hex.dirty && !warned => {
+                    self.doc[self.tab].set_command_line(
+                        "Unsaved Changes! Press again to close without saving".to_string(),
+                        Type::Warning,
                     );
                 }
-            }
-            Event::Commit => self.doc[self.tab].undo_stack.commit(),
-            Event::Store(kind, bank) => {
-                let cursor = self.doc[self.tab].cursor;
-                let offset = self.doc[self.tab].offset;
-                let current = Position {
-                    x: cursor.x + offset.x,
-                    y: cursor.y + offset.y - OFFSET,
-                };
-                match kind {
-                    BankType::Cursor => {
-                        self.position_bank.insert(bank, current);
-                    }
-                    BankType::Line => {
-                        self.row_bank
-                            .insert(bank, self.doc[self.tab].rows[current.y].clone());
+                _ if closing => break,
+                KeyBinding::Ctrl(RawKey::Char('s')) => {
+                    let message = match hex.save() {
+                        Ok(bytes) => (format!("Saved {bytes} bytes to \"{path}\""), Type::Info),
+                        Err(error) => (format!("Failed to save \"{path}\": {error}"), Type::Error),
+                    };
+                    self.doc[self.tab].set_command_line(message.0, message.1);
+                }
+                KeyBinding::Raw(RawKey::Left) => hex.left(),
+                KeyBinding::Raw(RawKey::Right) => hex.right(),
+                KeyBinding::Raw(RawKey::Up) => hex.up(per_line),
+                KeyBinding::Raw(RawKey::Down) => hex.down(per_line),
+                KeyBinding::Raw(RawKey::PageUp) => hex.up(per_line * height),
+                KeyBinding::Raw(RawKey::PageDown) => hex.down(per_line * height),
+                KeyBinding::Raw(RawKey::Home) => hex.home(per_line),
+                KeyBinding::Raw(RawKey::End) => hex.end(per_line),
+                KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
+                    if let Some(digit) = c.to_digit(16) {
+                        hex.type_digit(digit as u8);
                     }
                 }
+                _ => (),
             }
-            Event::Load(kind, bank) => {
-                let cursor = self.doc[self.tab].cursor;
-                let offset = self.doc[self.tab].offset;
-                let current = Position {
-                    x: cursor.x + offset.x,
-                    y: cursor.y + offset.y - OFFSET,
+            warned = closing;
+        }
+        self.doc[self.tab].set_command_line(String::new(), Type::Info);
+        self.term.forget();
+    }
+    fn draw_hex(&mut self, hex: &mut Hex, per_line: usize, height: usize) {
+        // Draw the offsets, bytes and characters of a file over the whole screen
+        if self.batch.is_some() {
+            return;
+        }
+        let theme = &self.config.theme;
+        let normal = if theme.transparent_editor {
+            RESET_BG.to_string()
+        } else {
+            Reader::rgb_bg(theme.editor_bg).to_string()
+        };
+        let selected = Reader::rgb_bg(theme.selection_bg).to_string();
+        let (offset_fg, text_fg) = (
+            Reader::rgb_fg(theme.line_number_fg),
+            Reader::rgb_fg(theme.editor_fg),
+        );
+        let width = self.term.size.width;
+        let name = Path::new(&hex.path)
+            .file_name()
+            .map_or(hex.path.clone(), |name| name.to_string_lossy().to_string());
+        let title = format!(" {name} (bytes)");
+        let mut rows = vec![format!(
+            "{}{}{}{}{}{}",
+            Reader::rgb_fg(theme.active_tab_fg),
+            Reader::rgb_bg(theme.active_tab_bg),
+            trim_end(&title, width),
+            " ".repeat(width.saturating_sub(title.width())),
+            RESET_FG,
+            RESET_BG
+        )];
+        let cursor = hex.cursor;
+        for (offset, bytes) in hex.lines(height, per_line) {
+            let mut line = format!("{offset_fg}{offset:08x}{text_fg}  ");
+            let mut text = String::new();
+            for (index, byte) in bytes.iter().enumerate() {
+                let (start, end) = if offset + index == cursor {
+                    (selected.as_str(), normal.as_str())
+                } else {
+                    ("", "")
                 };
-                match kind {
-                    BankType::Cursor => {
-                        let cursor = *self.position_bank.get(&bank).unwrap_or(&current);
-                        self.doc[self.tab].goto(cursor, &self.term.size);
-                    }
-                    BankType::Line => {
-                        if let Some(row) = self.row_bank.get(&bank) {
-                            self.doc[self.tab].rows[current.y] = row.clone();
-                        }
-                    }
-                }
+                let _ = write!(line, "{start}{byte:02x}{end} ");
+                let c = if byte.is_ascii_graphic() || *byte == b' ' {
+                    char::from(*byte)
+                } else {
+                    '.'
+                };
+                let _ = write!(text, "{start}{c}{end}");
             }
-            Event::Home => self.doc[self.tab].leap_cursor(KeyCode::Home, &self.term.size),
-            Event::End => self.doc[self.tab].leap_cursor(KeyCode::End, &self.term.size),
-            Event::PageUp => self.doc[self.tab].leap_cursor(KeyCode::PageUp, &self.term.size),
-            Event::PageDown => self.doc[self.tab].leap_cursor(KeyCode::PageDown, &self.term.size),
-            Event::Undo => self.undo(),
-            Event::Redo => self.redo(),
-            // Event is a document event, send to current document
-            _ => self.doc[self.tab].execute(event, reversed, &self.term.size, &self.config),
+            line.push_str(&"   ".repeat(per_line - bytes.len()));
+            let _ = write!(line, " {text}{RESET_FG}");
+            rows.push(self.add_background(&line, width));
+        }
+        while rows.len() <= height {
+            rows.push(self.add_background("", width));
+        }
+        // Show where the cursor is in the file along the status line
+        let left = format!(" {}{} │ Bytes", name, if hex.dirty { "[+]" } else { "" });
+        let right = format!("{:#x} / {:#x} ", cursor, hex.bytes.len());
+        rows.push(format!(
+            "{}{}{}{}{}{}{}",
+            Attribute::Bold,
+            Reader::rgb_fg(theme.status_fg),
+            Reader::rgb_bg(theme.status_bg),
+            trim_end(
+                &format!("{}{}{}", left, self.term.align_break(&left, &right), right),
+                width
+            ),
+            RESET_BG,
+            RESET_FG,
+            Attribute::Reset,
+        ));
+        rows.push(self.command_line());
+        self.term.hide_cursor();
+        self.term.draw(rows);
+        self.term.flush();
+    }
+    fn jump(&mut self, place: Option<String>) {
+        // Go to a line and column, asking for them when they aren't given
+        let place = if let Some(place) = place {
+            place
+        } else if let Some(place) = self.prompt("Go to", " (line:column): ", &|_, _, _| {}) {
+            place
+        } else {
+            return;
+        };
+        if let Some((line, column)) = line_column(&place) {
+            let current = self.current();
+            self.doc[self.tab].record_jump(current);
+            self.doc[self.tab].jump(line, column, &self.size);
+        } else {
+            self.doc[self.tab]
+                .set_command_line(format!("{place} isn't a line number"), Type::Error);
         }
-        self.doc[self.tab].recalculate_graphemes();
     }
     fn cmd(&mut self) {
         // Recieve macro command
         if let Some(command) = self.prompt(":", "", &|s, e, _| {
-            if let PromptEvent::KeyPress(KeyCode::Esc) = e {
-                s.doc[s.tab].set_command_line("".to_string(), Type::Info);
+            if let PromptEvent::KeyPress(KeyCode::Esc, _) = e {
+                s.doc[s.tab].set_command_line(String::new(), Type::Info);
             }
         }) {
             // Parse and Lex instruction
             for command in command.split('|') {
-                self.text_to_event(command);
+                if line_column(command).is_some() {
+                    // Jump to a line number and maybe a column
+                    self.execute(Event::Jump(Some(command.to_string())), false);
+                } else {
+                    self.text_to_event(command);
+                }
             }
         }
     }
@@ -687,7 +5666,11 @@ impl Editor {
         let command = command.trim_start_matches(' ');
         let mut cmd = command.split(' ');
         let actual_command;
-        let times = if let Ok(repeat) = cmd.next().unwrap_or_default().parse::<usize>() {
+        // A number on its own is the line to go to rather than how many times to repeat
+        let times = if let (Ok(repeat), true) = (
+            cmd.next().unwrap_or_default().parse::<usize>(),
+            command.contains(' '),
+        ) {
             actual_command = cmd.collect::<Vec<_>>().join(" ");
             repeat
         } else {
@@ -698,49 +5681,51 @@ impl Editor {
             if self.config.macros.contains_key(&actual_command) {
                 self.execute_macro(&actual_command);
             } else {
+                // Lines are counted from the top of a large file, not of the part of it held
+                if self.doc[self.tab].lazy.is_some()
+                    && has_range(&actual_command)
+                    && self.read_whole(self.tab).is_none()
+                {
+                    return;
+                }
                 let cursor = self.doc[self.tab].cursor;
                 let offset = self.doc[self.tab].offset;
+                let cursor = Position {
+                    x: cursor.x + offset.x,
+                    y: cursor.y + offset.y - OFFSET,
+                };
+                let doc = &self.doc[self.tab];
+                // Ranges that reach outside the document are refused rather than cut short
+                if let Some(error) = range_error(&actual_command, &cursor, &doc.rows, &doc.marks) {
+                    self.doc[self.tab].set_command_line(error, Type::Error);
+                    return;
+                }
                 let instruction = interpret_line(
                     &actual_command,
-                    &Position {
-                        x: cursor.x + offset.x,
-                        y: cursor.y + offset.y - OFFSET,
-                    },
+                    &cursor,
                     self.doc[self.tab].graphemes,
                     &self.doc[self.tab].rows,
+                    &self.doc[self.tab].marks,
                 );
                 // Execute the instruction
                 if let Some(instruct) = instruction {
                     for i in instruct {
-                        if Editor::will_edit(&i) {
-                            self.doc[self.tab].redo_stack.empty();
-                        }
                         self.execute(i, false);
                     }
                     self.doc[self.tab].undo_stack.commit();
+                } else if !actual_command.is_empty() {
+                    self.doc[self.tab].set_command_line(
+                        format!("Unknown command: {actual_command}"),
+                        Type::Error,
+                    );
                 }
             }
         }
     }
-    pub fn will_edit(event: &Event) -> bool {
-        matches!(event, Event::SpliceUp(_, _)
-            | Event::SplitDown(_, _)
-            | Event::InsertLineAbove(_)
-            | Event::InsertLineBelow(_)
-            | Event::Deletion(_, _)
-            | Event::Insertion(_, _)
-            | Event::InsertTab(_)
-            | Event::DeleteTab(_)
-            | Event::DeleteLine(_, _, _)
-            | Event::UpdateLine(_, _, _, _)
-            | Event::ReplaceAll
-            | Event::Replace
-            | Event::Overwrite(_, _))
-    }
     pub fn undo(&mut self) {
         self.doc[self.tab].undo_stack.commit();
         if let Some(events) = self.doc[self.tab].undo_stack.pop() {
-            for event in events.clone() {
+            for event in events {
                 if let Some(reversed) = reverse(event, self.doc[self.tab].rows.len()) {
                     for i in reversed {
                         self.execute(i, true);
@@ -748,25 +5733,276 @@ impl Editor {
                     self.update();
                 }
             }
-            self.doc[self.tab]
-                .redo_stack
-                .append(events.into_iter().rev().collect());
+            // Going back to where the history splits gives a choice of what to redo
+            let branches = self.doc[self.tab].undo_stack.branches();
+            if branches > 1 {
+                self.doc[self.tab].set_command_line(
+                    format!("{branches} branches to redo, redo 1 to {branches} picks one"),
+                    Type::Info,
+                );
+            }
         } else {
             self.doc[self.tab].set_command_line("Empty Undo Stack".to_string(), Type::Error);
         }
-        if self.doc[self.tab].undo_stack.len() == self.doc[self.tab].last_save_index {
-            self.doc[self.tab].dirty = false;
-        }
+        self.mark_saved();
     }
     pub fn redo(&mut self) {
-        if let Some(events) = self.doc[self.tab].redo_stack.pop() {
-            for event in events {
-                self.execute(event, false);
+        // The events are applied again as they were, without being recorded a second time
+        if let Some(events) = self.doc[self.tab].undo_stack.forward() {
+            for event in events.into_iter().rev() {
+                self.execute(event, true);
                 self.update();
             }
         } else {
             self.doc[self.tab].set_command_line("Empty Redo Stack".to_string(), Type::Error);
         }
+        self.mark_saved();
+    }
+    fn redo_branch(&mut self, branch: usize) {
+        // Redo along one of the branches of the history, counting from the oldest
+        self.doc[self.tab].undo_stack.commit();
+        if branch > 0 && self.doc[self.tab].undo_stack.choose(branch - 1) {
+            self.execute(Event::Redo, false);
+        } else {
+            let branches = self.doc[self.tab].undo_stack.branches();
+            self.doc[self.tab].set_command_line(
+                format!("There are {branches} branches to redo here"),
+                Type::Error,
+            );
+        }
+    }
+    fn mark_saved(&mut self) {
+        // The document is unchanged exactly when it is back in the state it was saved in
+        let doc = &mut self.doc[self.tab];
+        doc.dirty = doc.undo_stack.state() != doc.last_save_index;
+    }
+    fn travel(&mut self, state: usize) {
+        // Undo and redo through the history until the document is in the given state
+        let path = self.doc[self.tab].undo_stack.path(state);
+        while !path.contains(&self.doc[self.tab].undo_stack.state()) {
+            self.execute(Event::Undo, false);
+        }
+        let here = self.doc[self.tab].undo_stack.state();
+        let ahead = path.iter().skip_while(|node| **node != here).skip(1);
+        for node in ahead.copied().collect::<Vec<_>>() {
+            self.doc[self.tab].undo_stack.aim(node);
+            self.execute(Event::Redo, false);
+        }
+    }
+    fn browse_history(&mut self) {
+        // Show the history as a tree and move the document through it as the selection moves
+        self.doc[self.tab].undo_stack.commit();
+        let start = self.doc[self.tab].undo_stack.state();
+        let chosen = loop {
+            let tree = self.doc[self.tab].undo_stack.tree();
+            let here = self.doc[self.tab].undo_stack.state();
+            let selected = tree.iter().position(|line| line.0 == here).unwrap_or(0);
+            // Keep the selected state in view in the top half of the screen
+            let height = self.term.size.height.saturating_sub(OFFSET + 2) / 2;
+            let first = (selected + 1).saturating_sub(height.max(1));
+            let lines: Vec<&str> = tree
+                .iter()
+                .skip(first)
+                .take(height)
+                .map(|line| line.1.as_str())
+                .collect();
+            self.doc[self.tab].set_prompt_line(
+                "History: \u{2191} and \u{2193} move, Enter keeps, Esc goes back".to_string(),
+                Type::Info,
+            );
+            self.update();
+            self.draw_results(&lines, selected - first);
+            let InputEvent::Key(key) = self.read_event() else {
+                continue;
+            };
+            let target = match Editor::key_event_to_ox_key(key.code, key.modifiers) {
+                KeyBinding::Raw(RawKey::Enter) => break true,
+                KeyBinding::Raw(RawKey::Esc) => break false,
+                KeyBinding::Raw(RawKey::Up) => selected.checked_sub(1),
+                KeyBinding::Raw(RawKey::Down) => Some(selected + 1),
+                _ => None,
+            };
+            if let Some((state, _)) = target.and_then(|target| tree.get(target)) {
+                self.travel(*state);
+            }
+        };
+        if !chosen {
+            self.travel(start);
+        }
+        self.doc[self.tab].set_command_line(String::new(), Type::Info);
+    }
+    fn show_messages(&mut self) {
+        // List the messages of every open document, newest at the bottom, until a key goes back
+        let mut messages: Vec<CommandLine> = self
+            .doc
+            .iter()
+            .flat_map(|doc| doc.messages.iter().cloned())
+            .collect();
+        if messages.is_empty() {
+            self.doc[self.tab].set_command_line("No messages yet".to_string(), Type::Info);
+            return;
+        }
+        messages.sort_by_key(|message| message.posted);
+        let mut selected = messages.len() - 1;
+        loop {
+            let height = self.term.size.height.saturating_sub(OFFSET + 2).max(1);
+            let first = (selected + 1).saturating_sub(height);
+            let lines: Vec<String> = messages
+                .iter()
+                .skip(first)
+                .take(height)
+                .map(|message| {
+                    let age = message
+                        .posted
+                        .map_or(0, |posted| posted.elapsed().as_secs());
+                    let age = match age {
+                        0..=59 => format!("{age}s"),
+                        60..=3599 => format!("{}m", age / 60),
+                        _ => format!("{}h", age / 3600),
+                    };
+                    let kind = match message.msg {
+                        Type::Error => "error",
+                        Type::Warning => "warning",
+                        Type::Info => "info",
+                    };
+                    format!("{:>4} ago  {:<7}  {}", age, kind, message.text)
+                })
+                .collect();
+            let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+            self.doc[self.tab].set_prompt_line(
+                "Messages: \u{2191} and \u{2193} move, any other key goes back".to_string(),
+                Type::Info,
+            );
+            self.update();
+            self.draw_results(&lines, selected - first);
+            let InputEvent::Key(key) = self.read_event() else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected = cmp::min(selected + 1, messages.len() - 1),
+                _ => break,
+            }
+        }
+        self.doc[self.tab].set_command_line(String::new(), Type::Info);
+    }
+    fn explain(&self, commands: &[String]) -> String {
+        // Say what the commands run by a key do, going by the command list and the macros
+        commands
+            .iter()
+            .map(|command| {
+                // A number in front only repeats the command
+                let command = command.trim_start_matches(' ');
+                let command = match command.split_once(' ') {
+                    Some((times, rest)) if times.parse::<usize>().is_ok() => rest,
+                    _ => command,
+                };
+                if let Some(steps) = self.config.macros.get(command) {
+                    format!("Macro: {}", steps.join(", "))
+                } else {
+                    oxa::describe(command).unwrap_or("").to_string()
+                }
+            })
+            .filter(|description| !description.is_empty())
+            .collect::<Vec<_>>()
+            .join(", then ")
+    }
+    fn help(&mut self) {
+        // Open a read only document listing the keys and commands as they are set up right now
+        let row = |key: &str, commands: &str, description: &str| {
+            // Lists without commands to show give their names the extra room
+            let line = if commands.is_empty() {
+                format!("  {key:<45} {description}")
+            } else {
+                format!("  {key:<20} {commands:<24} {description}")
+            };
+            line.trim_end().to_string()
+        };
+        let mut text = vec![
+            format!(
+                "Ox {} help, made from the keys and commands set up now",
+                VERSION
+            ),
+            String::new(),
+            "Keys".to_string(),
+        ];
+        let mut keys: Vec<(String, String, String)> = self
+            .config
+            .keys
+            .iter()
+            .map(|(key, commands)| (key.describe(), commands.join("; "), self.explain(commands)))
+            .collect();
+        keys.sort();
+        text.extend(
+            keys.iter()
+                .map(|(key, commands, description)| row(key, commands, description)),
+        );
+        let mut chords: Vec<(String, String, String)> = self
+            .config
+            .chords
+            .iter()
+            .map(|(keys, commands)| {
+                let keys: Vec<String> = keys.iter().map(|key| key.describe()).collect();
+                (keys.join(" "), commands.join("; "), self.explain(commands))
+            })
+            .collect();
+        chords.sort();
+        if !chords.is_empty() {
+            text.extend([String::new(), "Chords".to_string()]);
+            text.extend(
+                chords
+                    .iter()
+                    .map(|(keys, commands, description)| row(keys, commands, description)),
+            );
+        }
+        text.extend([String::new(), "Built in keys".to_string()]);
+        text.extend(
+            BUILT_IN_KEYS
+                .iter()
+                .map(|(key, description)| row(key, "", description)),
+        );
+        let mut macros: Vec<(&String, &Vec<String>)> = self.config.macros.iter().collect();
+        macros.sort();
+        if !macros.is_empty() {
+            text.extend([String::new(), "Macros".to_string()]);
+            text.extend(
+                macros
+                    .iter()
+                    .map(|(name, steps)| row(name, "", &steps.join(", "))),
+            );
+        }
+        let mut abbreviations: Vec<(&String, &String)> = self.config.abbreviations.iter().collect();
+        abbreviations.sort();
+        if !abbreviations.is_empty() {
+            text.extend([String::new(), "Abbreviations".to_string()]);
+            text.extend(
+                abbreviations
+                    .iter()
+                    .map(|(short, long)| row(short, "", &long.replace('\n', "\u{21b5}"))),
+            );
+        }
+        text.extend([String::new(), "Commands".to_string()]);
+        text.extend(
+            oxa::COMMANDS
+                .iter()
+                .map(|(usage, description)| row(usage, "", description)),
+        );
+        text.push(String::new());
+        let mut doc =
+            Document::from_text(&self.config, &self.status, text.join("\n").as_bytes(), true);
+        doc.name = "[Help]".to_string();
+        doc.dirty = false;
+        self.doc.push(doc);
+        self.tab = self.doc.len().saturating_sub(1);
+    }
+    fn message_update(&mut self) -> bool {
+        // Take down the messages that have been up long enough, giving whether any went
+        let timeout = self.config.general.message_timeout;
+        let mut changed = false;
+        for doc in &mut self.doc {
+            changed |= doc.expire_message(timeout);
+        }
+        changed
     }
     fn refresh_view(&mut self) {
         let offset = self.doc[self.tab].offset.y;
@@ -779,8 +6015,67 @@ impl Editor {
             o.updated = true;
         }
     }
+    fn search_pattern(&self, term: &str) -> String {
+        // Turn a search term into the pattern to look for, following the search options
+        if term.is_empty() {
+            return String::new();
+        }
+        let general = &self.config.general;
+        let ignore = if general.smart_case {
+            !term.chars().any(char::is_uppercase)
+        } else {
+            !general.case_sensitive
+        };
+        let pattern = if general.whole_word {
+            format!(r"\b(?:{term})\b")
+        } else {
+            term.to_string()
+        };
+        if ignore {
+            format!("(?i){pattern}")
+        } else {
+            pattern
+        }
+    }
+    fn search_options(&self) -> String {
+        // Describe the search options in use for the command line
+        let general = &self.config.general;
+        let case = if general.smart_case {
+            "[smart case]"
+        } else if general.case_sensitive {
+            "[match case]"
+        } else {
+            "[ignore case]"
+        };
+        if general.whole_word {
+            format!("{case} [whole word]")
+        } else {
+            case.to_string()
+        }
+    }
+    fn toggle_search_option(&mut self, key: KeyCode) -> bool {
+        // Flip a search option from the search prompt, giving whether the key was one
+        let general = &mut self.config.general;
+        match key {
+            KeyCode::Char('c') => {
+                // Choosing the case by hand stops it following the search term
+                general.case_sensitive = general.smart_case || !general.case_sensitive;
+                general.smart_case = false;
+            }
+            KeyCode::Char('s') => general.smart_case = !general.smart_case,
+            KeyCode::Char('w') => general.whole_word = !general.whole_word,
+            _ => return false,
+        }
+        true
+    }
     fn highlight_bg_tokens(&mut self, t: &str, current: Position) -> Option<()> {
-        let occurances = self.doc[self.tab].find_all(t)?;
+        self.matches = None;
+        let occurances = self.doc[self.tab].find_all(&self.search_pattern(t))?;
+        // Count the matches for the command line, noting which one the cursor is on
+        if !t.is_empty() {
+            let index = occurances.iter().position(|o| *o == current);
+            self.matches = Some((index.map_or(0, |index| index + 1), occurances.len()));
+        }
         for i in &mut self.doc[self.tab].rows {
             i.bg_syntax.clear();
         }
@@ -799,6 +6094,7 @@ impl Editor {
                             }],
                         )
                         .to_string(),
+                        name: "search".to_string(),
                         priority: 10,
                     },
                 );
@@ -815,26 +6111,42 @@ impl Editor {
             y: initial_cursor.y + initial_offset.y - OFFSET,
         };
         // Ask for a search term after saving the current cursor position
-        self.prompt("Search", ": ", &|s, e, t| {
+        // The arrow keys move between the matches instead of along the search term
+        self.read_prompt("Search", ": ", false, false, &|s, e, t| {
             // Find all occurances in the document
             let current = Position {
                 x: s.doc[s.tab].cursor.x + s.doc[s.tab].offset.x,
                 y: s.doc[s.tab].cursor.y + s.doc[s.tab].offset.y - OFFSET,
             };
+            let pattern = s.search_pattern(t);
             match e {
-                PromptEvent::KeyPress(c) => match c {
+                PromptEvent::KeyPress(c, m) if m == KeyModifiers::ALT => {
+                    // Search again from the start with the new options
+                    if s.toggle_search_option(c) {
+                        let pattern = s.search_pattern(t);
+                        if let Some(p) = s.doc[s.tab].find_next(&pattern, &initial) {
+                            s.doc[s.tab].goto(p, &s.size);
+                            s.refresh_view();
+                            s.highlight_bg_tokens(t, p);
+                        } else {
+                            s.doc[s.tab].goto(initial, &s.size);
+                            s.highlight_bg_tokens(t, initial);
+                        }
+                    }
+                }
+                PromptEvent::KeyPress(c, _) => match c {
                     KeyCode::Up | KeyCode::Left => {
-                        if let Some(p) = s.doc[s.tab].find_prev(t, &current) {
-                            s.doc[s.tab].goto(p, &s.term.size);
+                        if let Some(p) = s.doc[s.tab].find_prev(&pattern, &current) {
+                            s.doc[s.tab].goto(p, &s.size);
                             s.refresh_view();
-                            s.highlight_bg_tokens(&t, p);
+                            s.highlight_bg_tokens(t, p);
                         }
                     }
                     KeyCode::Down | KeyCode::Right => {
-                        if let Some(p) = s.doc[s.tab].find_next(t, &current) {
-                            s.doc[s.tab].goto(p, &s.term.size);
+                        if let Some(p) = s.doc[s.tab].find_next(&pattern, &current) {
+                            s.doc[s.tab].goto(p, &s.size);
                             s.refresh_view();
-                            s.highlight_bg_tokens(&t, p);
+                            s.highlight_bg_tokens(t, p);
                         }
                     }
                     KeyCode::Esc => {
@@ -846,24 +6158,28 @@ impl Editor {
                 PromptEvent::CharPress(backspace) => {
                     // Highlight the tokens
                     if backspace {
-                        s.highlight_bg_tokens(&t, initial);
+                        s.highlight_bg_tokens(t, initial);
                     }
-                    if let Some(p) = s.doc[s.tab].find_next(t, &initial) {
-                        s.doc[s.tab].goto(p, &s.term.size);
+                    if let Some(p) = s.doc[s.tab].find_next(&pattern, &initial) {
+                        s.doc[s.tab].goto(p, &s.size);
                         s.refresh_view();
-                        s.highlight_bg_tokens(&t, p);
+                        s.highlight_bg_tokens(t, p);
                     } else {
-                        s.doc[s.tab].goto(initial, &s.term.size);
-                        s.highlight_bg_tokens(&t, initial);
+                        s.doc[s.tab].goto(initial, &s.size);
+                        s.highlight_bg_tokens(t, initial);
                     }
                 }
                 PromptEvent::Update => (),
             }
         });
         // User cancelled or found what they were looking for
+        if self.current() != initial {
+            self.doc[self.tab].record_jump(initial);
+        }
         for i in &mut self.doc[self.tab].rows {
             i.bg_syntax.clear();
         }
+        self.matches = None;
         self.doc[self.tab].set_command_line("Search exited".to_string(), Type::Info);
     }
     fn replace(&mut self) {
@@ -876,24 +6192,27 @@ impl Editor {
         };
         // After saving the cursor position, ask the user for the information
         if let Some(target) = self.prompt("Replace", ": ", &|_, _, _| {}) {
-            let re = if let Ok(re) = Regex::new(&target) {
-                re
-            } else {
+            let pattern = self.search_pattern(&target);
+            let Ok(re) = Regex::new(&pattern) else {
                 self.doc[self.tab].set_command_line("Invalid Regex".to_string(), Type::Error);
                 return;
             };
             self.highlight_bg_tokens(&target, current);
             if let Some(arrow) = self.prompt("With", ": ", &|_, _, _| {}) {
-                if let Some(p) = self.doc[self.tab].find_next(&target, &current) {
-                    self.doc[self.tab].goto(p, &self.term.size);
+                // Start from the first match at or after the cursor
+                let first = self.doc[self.tab]
+                    .find_all(&pattern)
+                    .into_iter()
+                    .flatten()
+                    .find(|p| p.y > current.y || (p.y == current.y && p.x >= current.x));
+                if let Some(p) = first {
+                    self.doc[self.tab].goto(p, &self.size);
                     self.highlight_bg_tokens(&target, p);
                     self.update();
                 }
                 loop {
                     // Read an event
-                    let key = if let InputEvent::Key(key) = self.read_event() {
-                        key
-                    } else {
+                    let InputEvent::Key(key) = self.read_event() else {
                         continue;
                     };
                     let current = Position {
@@ -902,32 +6221,48 @@ impl Editor {
                     };
                     match key.code {
                         KeyCode::Up | KeyCode::Left => {
-                            if let Some(p) = self.doc[self.tab].find_prev(&target, &current) {
-                                self.doc[self.tab].goto(p, &self.term.size);
+                            if let Some(p) = self.doc[self.tab].find_prev(&pattern, &current) {
+                                self.doc[self.tab].goto(p, &self.size);
                                 self.highlight_bg_tokens(&target, p);
                             }
                         }
                         KeyCode::Down | KeyCode::Right => {
-                            if let Some(p) = self.doc[self.tab].find_next(&target, &current) {
-                                self.doc[self.tab].goto(p, &self.term.size);
+                            if let Some(p) = self.doc[self.tab].find_next(&pattern, &current) {
+                                self.doc[self.tab].goto(p, &self.size);
                                 self.highlight_bg_tokens(&target, p);
                             }
                         }
-                        KeyCode::Char('y') | KeyCode::Enter | KeyCode::Char(' ') => {
+                        KeyCode::Char('y' | ' ') | KeyCode::Enter => {
+                            // Replace this match and move on to the next one
                             self.doc[self.tab].undo_stack.commit();
-                            let before = self.doc[self.tab].rows[current.y].clone();
-                            let after = Row::from(&*re.replace_all(&before.string, &arrow[..]));
-                            self.doc[self.tab].rows[current.y] = after.clone();
-                            self.highlight_bg_tokens(&target, current);
-                            if before.string != after.string {
-                                self.doc[self.tab].undo_stack.push(Event::UpdateLine(
-                                    current,
-                                    0,
-                                    Box::new(before),
-                                    Box::new(after),
-                                ));
+                            let mut next = current;
+                            if let Some(end) = self.doc[self.tab].replace_at(&re, &current, &arrow)
+                            {
+                                // Don't match inside of the replacement text
+                                next.x = end.saturating_sub(1);
+                            }
+                            if let Some(p) = self.doc[self.tab].find_next(&pattern, &next) {
+                                self.doc[self.tab].goto(p, &self.size);
+                                self.highlight_bg_tokens(&target, p);
+                            } else {
+                                self.highlight_bg_tokens(&target, current);
                             }
                         }
+                        KeyCode::Char('n') => {
+                            // Skip this match
+                            if let Some(p) = self.doc[self.tab].find_next(&pattern, &current) {
+                                self.doc[self.tab].goto(p, &self.size);
+                                self.highlight_bg_tokens(&target, p);
+                            }
+                        }
+                        KeyCode::Char('a') => {
+                            // Replace every match that is left
+                            self.doc[self.tab].undo_stack.commit();
+                            self.replace_every(&re, &pattern, &arrow);
+                            self.doc[self.tab]
+                                .set_command_line("Replace finished".to_string(), Type::Info);
+                            break;
+                        }
                         KeyCode::Esc => {
                             self.doc[self.tab].cursor = initial_cursor;
                             self.doc[self.tab].offset = initial_offset;
@@ -943,75 +6278,87 @@ impl Editor {
             for i in &mut self.doc[self.tab].rows {
                 i.bg_syntax.clear();
             }
+            self.matches = None;
         }
     }
     fn replace_all(&mut self) {
         // Replace all occurances of a substring
         if let Some(target) = self.prompt("Replace all", ": ", &|_, _, _| {}) {
-            let re = if let Ok(re) = Regex::new(&target) {
-                re
-            } else {
+            let pattern = self.search_pattern(&target);
+            let Ok(re) = Regex::new(&pattern) else {
                 return;
             };
             if let Some(arrow) = self.prompt("With", ": ", &|_, _, _| {}) {
-                // Find all occurances
-                let search_points = if let Some(t) = self.doc[self.tab].find_all(&target) {
-                    t
-                } else {
-                    vec![]
-                };
-                for p in search_points {
-                    let before = self.doc[self.tab].rows[p.y].clone();
-                    let after = Row::from(&*re.replace_all(&before.string, &arrow[..]));
-                    self.doc[self.tab].rows[p.y] = after.clone();
-                    if before.string != after.string {
-                        self.doc[self.tab].undo_stack.push(Event::UpdateLine(
-                            Position { x: 0, y: p.y },
-                            0,
-                            Box::new(before),
-                            Box::new(after),
-                        ));
-                    }
-                }
+                self.replace_every(&re, &pattern, &arrow);
             }
         }
         // Exit message
         self.doc[self.tab].set_command_line("Replace finished".to_string(), Type::Info);
     }
+    fn replace_every(&mut self, re: &Regex, target: &str, arrow: &str) {
+        // Find all the lines with occurances, each line is only replaced once
+        let mut lines: Vec<usize> = self.doc[self.tab]
+            .find_all(target)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.y)
+            .collect();
+        lines.dedup();
+        for y in lines {
+            let before = self.doc[self.tab].rows[y].clone();
+            let after = Row::from(&*re.replace_all(&before.string, arrow));
+            self.doc[self.tab].rows[y] = after.clone();
+            if before.string != after.string {
+                self.doc[self.tab].dirty = true;
+                self.doc[self.tab].undo_stack.push(Event::UpdateLine(
+                    Position { x: 0, y },
+                    0,
+                    Box::new(before),
+                    Box::new(after),
+                ));
+            }
+        }
+    }
+    fn confirm(&mut self, question: &str) -> bool {
+        // Ask a yes or no question on the command line, taking anything but yes as no
+        self.doc[self.tab].set_prompt_line(question.to_string(), Type::Warning);
+        self.update();
+        loop {
+            if let InputEvent::Key(key) = self.read_event() {
+                return key.code == KeyCode::Char('y');
+            }
+        }
+    }
     fn dirty_prompt(&mut self, key: KeyBinding, subject: &str) -> bool {
-        // For events that require changes to the document
-        if self.doc[self.tab].dirty {
-            // Handle unsaved changes
-            self.doc[self.tab].set_command_line(
-                format!("Unsaved Changes! {:?} to force {}", key, subject),
+        // For events that would throw away the changes to the document, returning whether to go on
+        if !self.doc[self.tab].dirty {
+            return true;
+        }
+        let name = self.doc[self.tab].name.clone();
+        loop {
+            self.doc[self.tab].set_prompt_line(
+                format!(
+                    "{name} has unsaved changes! (s)ave, (d)iscard or (c)ancel? {key:?} to force {subject}"
+                ),
                 Type::Warning,
             );
             self.update();
-            if let InputEvent::Key(KeyEvent {
-                code: c,
-                modifiers: m,
-            }) = self.read_event()
-            {
-                let ox_key = Editor::key_event_to_ox_key(c, m);
-                match ox_key {
-                    KeyBinding::Raw(RawKey::Enter) => return true,
-                    KeyBinding::Ctrl(_) | KeyBinding::Alt(_) => {
-                        if ox_key == key {
-                            return true;
-                        } else {
-                            self.doc[self.tab].set_command_line(
-                                format!("{} cancelled", title(subject)),
-                                Type::Info,
-                            );
-                        }
-                    }
-                    _ => self.doc[self.tab]
-                        .set_command_line(format!("{} cancelled", title(subject)), Type::Info),
+            let InputEvent::Key(KeyEvent { code, modifiers }) = self.read_event() else {
+                continue;
+            };
+            match code {
+                KeyCode::Char('s') if modifiers.is_empty() => {
+                    self.save_document(None, false);
+                    // Only go on if the save went through
+                    return !self.doc[self.tab].dirty;
                 }
+                KeyCode::Char('d') if modifiers.is_empty() => return true,
+                KeyCode::Char('c') | KeyCode::Esc if modifiers.is_empty() => break,
+                _ if Editor::key_event_to_ox_key(code, modifiers) == key => return true,
+                _ => (),
             }
-        } else {
-            return true;
         }
+        self.doc[self.tab].set_command_line(format!("{} cancelled", title(subject)), Type::Info);
         false
     }
     fn prompt(
@@ -1020,73 +6367,202 @@ impl Editor {
         ending: &str,
         func: &dyn Fn(&mut Self, PromptEvent, &str),
     ) -> Option<String> {
-        // Create a new prompt
-        self.doc[self.tab].set_command_line(format!("{}{}", prompt, ending), Type::Info);
-        self.update();
-        let mut result = String::new();
-        'p: loop {
+        // Ask for a line of input
+        self.read_prompt(prompt, ending, true, false, func)
+    }
+    fn path_prompt(&mut self, prompt: &str) -> Option<String> {
+        // Ask for the path of a file, which tab completes
+        self.read_prompt(prompt, ": ", true, true, &|_, _, _| {})
+    }
+    fn read_prompt(
+        &mut self,
+        prompt: &str,
+        ending: &str,
+        arrows: bool,
+        paths: bool,
+        func: &dyn Fn(&mut Self, PromptEvent, &str),
+    ) -> Option<String> {
+        // Create a new prompt, leaving the arrow keys to the caller unless told otherwise
+        // Prompts for paths complete them with tab, reading directories in the background
+        let history = self.histories.get(prompt).cloned().unwrap_or_default();
+        let mut input = Prompt::new(history);
+        input.paths = paths;
+        let start = format!("{prompt}{ending}").width();
+        let submitted = loop {
+            input.receive();
+            let room = self
+                .term
+                .size
+                .width
+                .saturating_sub(start + input.text.width());
+            self.doc[self.tab].set_prompt_line(
+                format!("{}{}{}{}", prompt, ending, input.text, input.hint(room)),
+                Type::Info,
+            );
+            self.prompting = Some(start + input.cursor());
+            self.completing = input.waiting();
+            self.update();
             if let InputEvent::Key(KeyEvent {
                 code: c,
                 modifiers: m,
             }) = self.read_event()
             {
-                match Editor::key_event_to_ox_key(c, m) {
-                    KeyBinding::Raw(RawKey::Enter) => {
-                        // Exit on enter key
-                        break 'p;
-                    }
-                    KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
-                        // Update the prompt contents
-                        result.push(c);
-                        func(self, PromptEvent::CharPress(false), &result)
-                    }
-                    KeyBinding::Raw(RawKey::Backspace) => {
-                        // Handle backspace event
-                        result.pop();
-                        func(self, PromptEvent::CharPress(true), &result)
-                    }
-                    KeyBinding::Raw(RawKey::Esc) => {
-                        // Handle escape key
-                        func(self, PromptEvent::KeyPress(c), &result);
-                        return None;
+                let key = Editor::key_event_to_ox_key(c, m);
+                let arrow = matches!(
+                    key,
+                    KeyBinding::Raw(RawKey::Up | RawKey::Down | RawKey::Left | RawKey::Right)
+                );
+                let handled = if arrow && !arrows {
+                    Input::Ignored
+                } else {
+                    input.handle(key)
+                };
+                match handled {
+                    Input::Submit => break true,
+                    Input::Cancel => {
+                        func(self, PromptEvent::KeyPress(c, m), &input.text);
+                        break false;
                     }
-                    _ => func(self, PromptEvent::KeyPress(c), &result),
+                    Input::Typed => func(self, PromptEvent::CharPress(false), &input.text),
+                    Input::Removed => func(self, PromptEvent::CharPress(true), &input.text),
+                    Input::Kept => (),
+                    Input::Ignored => func(self, PromptEvent::KeyPress(c, m), &input.text),
                 }
             }
-            self.doc[self.tab]
-                .set_command_line(format!("{}{}{}", prompt, ending, result), Type::Info);
-            func(self, PromptEvent::Update, &result);
-            self.update();
+            func(self, PromptEvent::Update, &input.text);
+        };
+        self.prompting = None;
+        self.completing = false;
+        let text = input.text.clone();
+        self.histories.insert(prompt.to_string(), input.finish());
+        if submitted {
+            Some(text)
+        } else {
+            None
         }
-        Some(result)
     }
     fn update(&mut self) {
-        // Move the cursor and render the screen
-        Terminal::hide_cursor();
-        Terminal::goto(&Position { x: 0, y: 0 });
-        self.doc[self.tab].recalculate_offset(&self.config);
-        self.render();
-        Terminal::goto(&Position {
-            x: self.doc[self.tab]
-                .cursor
-                .x
-                .saturating_add(self.doc[self.tab].line_offset),
-            y: self.doc[self.tab].cursor.y,
-        });
-        Terminal::show_cursor();
-        Terminal::flush();
+        // Move the cursor and render the screen, which scripts don't have
+        if self.batch.is_some() {
+            return;
+        }
+        self.lsp_sync();
+        if self.config.general.spell_check {
+            self.load_dictionary();
+        }
+        let started = Instant::now();
+        self.term.hide_cursor();
+        self.term.goto(&Position { x: 0, y: 0 });
+        self.sync_preview();
+        self.resize_panes();
+        let panes = self.panes();
+        for (index, size, _) in &panes {
+            self.doc[*index].page_in(size);
+            self.doc[*index].recalculate_offset(&self.config, size);
+            self.doc[*index].wrap_scroll(size);
+        }
+        self.sync_diff();
+        self.render(&panes);
+        // Leave the cursor hidden when it has been scrolled out of view or the file tree has focus
+        let browsing = self
+            .explorer
+            .as_ref()
+            .is_some_and(|explorer| explorer.focus);
+        let console = self.console.as_ref().filter(|console| console.focus);
+        if self.config.general.cursor_shapes {
+            let shape = self.cursor_shape(console.is_some());
+            self.term.set_shape(shape);
+        }
+        if let Some(column) = self.prompting {
+            // The cursor sits in what is being typed on the command line
+            self.term.goto(&Position {
+                x: column.min(self.term.size.width.saturating_sub(1)),
+                y: self.term.size.height.saturating_sub(1),
+            });
+            self.term.show_cursor();
+        } else if let Some(console) = console {
+            // The cursor sits where the shell last left it
+            if console.screen.shown {
+                let (width, height) = self.console_size();
+                self.term.goto(&Position {
+                    x: self.sidebar_width() + console.screen.x.min(width.saturating_sub(1)),
+                    y: self
+                        .term
+                        .size
+                        .height
+                        .saturating_sub(2 + height + self.panel_height())
+                        + console.screen.y,
+                });
+                self.term.show_cursor();
+            }
+        } else if let Some((_, _, origin)) =
+            panes.iter().find(|pane| pane.0 == self.tab && !browsing)
+        {
+            if let Some(cursor) = self.doc[self.tab].screen_cursor(&self.size) {
+                self.term.goto(&Position {
+                    x: cursor.x + origin.x,
+                    y: cursor.y + origin.y,
+                });
+                self.term.show_cursor();
+            }
+        }
+        self.term.flush();
+        log!(debug, "Render", format!("Drawn in {:?}", started.elapsed()));
+    }
+    fn cursor_shape(&self, console: bool) -> Shape {
+        // A bar where keys type text, a block where they run commands or act on a selection
+        // and an underline where typing goes over the text
+        if self.prompting.is_some() || console {
+            return Shape::Bar;
+        }
+        match self.mode {
+            Mode::Normal | Mode::Visual if self.config.general.modal => Shape::Block,
+            Mode::Replace if self.config.general.modal => Shape::Underline,
+            _ if self.doc[self.tab].anchor.is_some() => Shape::Block,
+            _ => Shape::Bar,
+        }
+    }
+    fn recent_line(&self, tab: usize, row: usize, size: &Size) -> Option<String> {
+        // The recent files under the welcome message, with an arrow by the one picked
+        let first = (size.height / 4 + 7).checked_sub(OFFSET)?;
+        let index = row.checked_sub(first)?;
+        if !self.doc[tab].show_welcome || self.recent.is_empty() || index > RECENT_SHOWN {
+            return None;
+        }
+        if index == 0 {
+            return Some(self.welcome_message(
+                "Recent files",
+                Reader::rgb_fg(self.config.theme.status_fg),
+                size.width,
+            ));
+        }
+        let path = self.recent.get(index - 1)?;
+        let chosen = self.recent_chosen == Some(index - 1);
+        Some(self.welcome_message(
+            &format!(
+                "{} {}",
+                if chosen { ">" } else { " " },
+                Editor::recent_name(path)
+            ),
+            Reader::rgb_fg(if chosen {
+                self.config.theme.editor_fg
+            } else {
+                self.config.theme.line_number_fg
+            }),
+            size.width,
+        ))
     }
-    fn welcome_message(&self, text: &str, colour: SetForegroundColor) -> String {
+    fn welcome_message(&self, text: &str, colour: SetForegroundColor, width: usize) -> String {
         // Render the welcome message
         let pad = " ".repeat(
-            (self.term.size.width / 2)
+            (width / 2)
                 .saturating_sub(text.len() / 2)
                 .saturating_sub(self.config.general.line_number_padding_right)
                 .saturating_sub(self.config.general.line_number_padding_left)
                 .saturating_sub(1),
         );
         let pad_right = " ".repeat(
-            (self.term.size.width.saturating_sub(1))
+            (width.saturating_sub(1))
                 .saturating_sub(text.len() + pad.len())
                 .saturating_sub(self.config.general.line_number_padding_left)
                 .saturating_sub(self.config.general.line_number_padding_right),
@@ -1108,21 +6584,40 @@ impl Editor {
             } else {
                 Reader::rgb_bg(self.config.theme.editor_bg)
             },
-            trim_end(
-                &format!("{}{}", pad, text),
-                self.term.size.width.saturating_sub(4)
-            ),
+            trim_end(&format!("{pad}{text}"), width.saturating_sub(4)),
             pad_right,
             RESET_FG,
             RESET_BG,
         )
     }
+    fn format_tabs(&self, template: &str) -> String {
+        // Fill in the tab related values of a template
+        template
+            .replace("%t", &format!("{}", self.tab.saturating_add(1)))
+            .replace("%T", &format!("{}", self.doc.len()))
+            .replace(
+                "%m",
+                &if self.config.general.modal {
+                    format!("{} ", self.mode.name())
+                } else {
+                    String::new()
+                },
+            )
+    }
     fn status_line(&mut self) -> String {
         // Produce the status line
         // Create the left part of the status line
-        let left = self.doc[self.tab].format(&self.config.general.status_left);
+        let left = self.doc[self.tab].format(&self.format_tabs(&self.config.general.status_left));
+        // Create the middle part of the status line
+        let center =
+            self.doc[self.tab].format(&self.format_tabs(&self.config.general.status_center));
         // Create the right part of the status line
-        let right = self.doc[self.tab].format(&self.config.general.status_right);
+        let right = self.doc[self.tab].format(&self.format_tabs(&self.config.general.status_right));
+        // Centre the middle part, keeping it clear of the left part when the line is tight
+        let width = self.term.size.width;
+        let start = width.saturating_sub(center.width()) / 2;
+        let gap = " ".repeat(start.saturating_sub(left.width()));
+        let left = format!("{left}{gap}{center}");
         // Get the padding value
         let padding = self.term.align_break(&left, &right);
         // Generate it
@@ -1131,16 +6626,13 @@ impl Editor {
             Attribute::Bold,
             Reader::rgb_fg(self.config.theme.status_fg),
             Reader::rgb_bg(self.config.theme.status_bg),
-            trim_end(
-                &format!("{}{}{}", left, padding, right),
-                self.term.size.width
-            ),
+            trim_end(&format!("{left}{padding}{right}"), self.term.size.width),
             RESET_BG,
             RESET_FG,
             Attribute::Reset,
         )
     }
-    fn add_background(&self, text: &str) -> String {
+    fn add_background(&self, text: &str, width: usize) -> String {
         // Add a background colour to a line
         if self.config.theme.transparent_editor {
             text.to_string()
@@ -1149,38 +6641,101 @@ impl Editor {
                 "{}{}{}{}",
                 Reader::rgb_bg(self.config.theme.editor_bg),
                 text,
-                self.term.align_left(&text),
+                self.term.pad(text, width),
+                RESET_BG
+            )
+        }
+    }
+    fn tint_line(&self, text: &str, width: usize, colour: (u8, u8, u8)) -> String {
+        // Swap the editor background of a line for another colour
+        let highlight = Reader::rgb_bg(colour).to_string();
+        if self.config.theme.transparent_editor {
+            format!(
+                "{}{}{}{}",
+                highlight,
+                text.replace(&RESET_BG.to_string(), &highlight),
+                self.term.pad(text, width),
                 RESET_BG
             )
+        } else {
+            let editor_bg = Reader::rgb_bg(self.config.theme.editor_bg).to_string();
+            text.replace(&editor_bg, &highlight)
         }
     }
     fn command_line(&self) -> String {
         // Render the command line
-        let line = &self.doc[self.tab].cmd_line.text;
-        // Add the correct styling
-        match self.doc[self.tab].cmd_line.msg {
-            Type::Error => self.add_background(&format!(
-                "{}{}{}{}{}",
-                Attribute::Bold,
-                Reader::rgb_fg(self.config.theme.error_fg),
-                self.add_background(&trim_end(&line, self.term.size.width)),
-                RESET_FG,
-                Attribute::Reset
-            )),
-            Type::Warning => self.add_background(&format!(
-                "{}{}{}{}{}",
-                Attribute::Bold,
-                Reader::rgb_fg(self.config.theme.warning_fg),
-                self.add_background(&trim_end(&line, self.term.size.width)),
-                RESET_FG,
-                Attribute::Reset
-            )),
-            Type::Info => self.add_background(&format!(
+        let mut line = &self.doc[self.tab].cmd_line.text;
+        let mut msg = &self.doc[self.tab].cmd_line.msg;
+        let width = self.term.size.width;
+        // Problems on the cursor's line are shown over messages that are just information
+        let y = self.current().y;
+        if let (Type::Info, Some(diagnostic)) = (
+            msg,
+            self.doc[self.tab]
+                .diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic.start.0 == y),
+        ) {
+            line = &diagnostic.message;
+            msg = if diagnostic.error {
+                &Type::Error
+            } else {
+                &Type::Warning
+            };
+        }
+        // How far through the matches a search is goes at the right while searching
+        let with_count;
+        if let (Type::Info, Some((index, total))) = (msg, self.matches) {
+            let counter = match (index, total) {
+                (_, 0) => "no matches".to_string(),
+                (0, 1) => "1 match".to_string(),
+                (0, _) => format!("{total} matches"),
+                _ => format!("match {index} of {total}"),
+            };
+            let counter = format!("{} {}", self.search_options(), counter);
+            let room = width.saturating_sub(counter.width() + 1);
+            let text = trim_end(line, room);
+            with_count = format!(
                 "{}{}{}",
-                Reader::rgb_fg(self.config.theme.info_fg),
-                self.add_background(&trim_end(&line, self.term.size.width)),
-                RESET_FG,
-            )),
+                text,
+                " ".repeat(width.saturating_sub(text.width() + counter.width())),
+                counter
+            );
+            line = &with_count;
+        }
+        // Add the correct styling
+        match msg {
+            Type::Error => self.add_background(
+                &format!(
+                    "{}{}{}{}{}",
+                    Attribute::Bold,
+                    Reader::rgb_fg(self.config.theme.error_fg),
+                    self.add_background(&trim_end(line, width), width),
+                    RESET_FG,
+                    Attribute::Reset
+                ),
+                width,
+            ),
+            Type::Warning => self.add_background(
+                &format!(
+                    "{}{}{}{}{}",
+                    Attribute::Bold,
+                    Reader::rgb_fg(self.config.theme.warning_fg),
+                    self.add_background(&trim_end(line, width), width),
+                    RESET_FG,
+                    Attribute::Reset
+                ),
+                width,
+            ),
+            Type::Info => self.add_background(
+                &format!(
+                    "{}{}{}",
+                    Reader::rgb_fg(self.config.theme.info_fg),
+                    self.add_background(&trim_end(line, width), width),
+                    RESET_FG,
+                ),
+                width,
+            ),
         }
     }
     fn tab_line(&mut self) -> String {
@@ -1203,7 +6758,7 @@ impl Editor {
                         active_foreground,
                     )
                 } else {
-                    format!("{}{}", inactive_background, inactive_foreground,)
+                    format!("{inactive_background}{inactive_foreground}")
                 },
                 self.doc[num].format(&self.config.general.tab),
                 Attribute::Reset,
@@ -1239,94 +6794,656 @@ impl Editor {
             RESET_BG,
         )
     }
-    fn render(&mut self) {
+    #[allow(clippy::too_many_lines)] // Draws every pane, bar and prompt in a single frame
+    fn render(&mut self, panes: &[(usize, Size, Position)]) {
         // Draw the screen to the terminal
-        let offset = self.doc[self.tab].offset;
-        let mut frame = vec![self.tab_line()];
-        let rendered = self.doc[self.tab].render(TabType::Spaces, 0);
-        let reg = self.doc[self.tab].regex.clone();
+        let height = self.term.size.height.saturating_sub(OFFSET);
+        let views: Vec<Vec<String>> = panes
+            .iter()
+            .map(|(index, size, _)| self.view(*index, size))
+            .collect();
+        let sidebar = self.sidebar(height.saturating_sub(2));
         if self.config.theme.transparent_editor {
             // Prevent garbage characters spamming the screen
-            Terminal::clear();
+            self.term.clear();
+            self.term.forget();
         }
-        for row in OFFSET..self.term.size.height {
-            // Clear the current line
-            let row = row.saturating_sub(OFFSET);
-            if let Some(r) = self.doc[self.tab].rows.get_mut(offset.y + row) {
-                if r.updated {
-                    r.update_syntax(&self.config, &reg, &rendered, offset.y + row, &self.theme);
-                    r.updated = false;
-                }
-            }
+        let divider = format!(
+            "{}{}",
+            Reader::rgb_fg(self.config.theme.line_number_fg),
+            if self.config.theme.transparent_editor {
+                RESET_BG
+            } else {
+                Reader::rgb_bg(self.config.theme.editor_bg)
+            },
+        );
+        // The shell and the problems the last build found sit below the panes, under dividers
+        let width = self.console_size().0;
+        let rule = format!(
+            "{}{}{}{}",
+            divider,
+            "\u{2500}".repeat(width),
+            RESET_FG,
+            RESET_BG
+        );
+        let mut below = vec![];
+        if let Some(console) = self.console.as_ref().filter(|_| self.console_height() > 0) {
+            let theme = &self.config.theme;
+            below.push(rule.clone());
+            below.extend(console.screen.lines(theme.editor_fg, theme.editor_bg));
+        }
+        if self.panel_height() > 0 {
+            below.push(rule);
+            below.extend(self.problem_lines(width));
+        }
+        let below_top = height.saturating_sub(2 + below.len());
+        // The preview starts at the first thing drawn from the line at the top of the document
+        let top = self.doc[self.tab].top();
+        let preview: Option<Vec<String>> = self
+            .preview
+            .as_ref()
+            .filter(|_| self.preview_width() > 0)
+            .map(|preview| {
+                let first = preview.lines.partition_point(|(source, _)| *source < top);
+                preview.lines[first..]
+                    .iter()
+                    .take(height)
+                    .map(|line| line.1.clone())
+                    .collect()
+            });
+        let blank = format!(
+            "{}{}{}",
+            Reader::rgb_bg(self.config.theme.editor_bg),
+            " ".repeat(self.preview_width().saturating_sub(1)),
+            RESET_BG
+        );
+        let bar = usize::from(self.config.general.scrollbar);
+        let mut rows = vec![self.tab_line()];
+        for row in 0..height {
             if row == self.term.size.height - 1 - OFFSET {
                 // Render command line
-                frame.push(self.command_line());
+                rows.push(self.command_line());
+                continue;
             } else if row == self.term.size.height - 2 - OFFSET {
                 // Render status line
-                frame.push(self.status_line());
-            } else if row == self.term.size.height / 4 - OFFSET && self.doc[self.tab].show_welcome {
-                frame.push(self.welcome_message(
-                    &format!("Ox editor  v{}", VERSION),
+                rows.push(self.status_line());
+                continue;
+            }
+            // Render the file tree to the left of the panes
+            let mut line = sidebar.get(row).cloned().unwrap_or_default();
+            if row >= below_top {
+                line.push_str(&below[row - below_top]);
+            } else if let [first, second] = &views[..] {
+                let vertical = self.split.as_ref().is_some_and(|split| split.vertical);
+                if vertical {
+                    // Render both panes side by side with a divider between them
+                    let _ = write!(
+                        line,
+                        "{}{}\u{2502}{}{}{}",
+                        first[row], divider, RESET_FG, RESET_BG, second[row]
+                    );
+                } else if row < first.len() {
+                    line.push_str(&first[row]);
+                } else if row == first.len() {
+                    // Render the divider between the top and bottom panes
+                    let _ = write!(
+                        line,
+                        "{}{}{}{}",
+                        divider,
+                        "\u{2500}".repeat(panes[0].1.width + bar),
+                        RESET_FG,
+                        RESET_BG
+                    );
+                } else {
+                    line.push_str(&second[row - first.len() - 1]);
+                }
+            } else {
+                line.push_str(&views[0][row]);
+                if let Some(preview) = &preview {
+                    // Render the preview beside the document, kept level with its top line
+                    let text = preview.get(row).unwrap_or(&blank);
+                    let _ = write!(line, "{divider}\u{2502}{RESET_FG}{RESET_BG}{text}");
+                }
+            }
+            rows.push(line);
+        }
+        self.term.draw(rows);
+    }
+    #[allow(clippy::too_many_lines)] // Builds each visible row with gutter and wrapping
+    fn view(&mut self, tab: usize, size: &Size) -> Vec<String> {
+        // Render the lines of a document that fit in a pane
+        let offset = self.doc[tab].offset;
+        let layout = self.doc[tab].layout(size);
+        // What git said about the cursor's line, while it is still the line that was asked about
+        let blame = self
+            .blame
+            .as_ref()
+            .filter(|(key, _)| tab == self.tab && Some(key) == self.blame_key().as_ref())
+            .and_then(|(_, text)| text.clone());
+        let current = self.doc[tab].cursor.y + offset.y - OFFSET;
+        let top = self.doc[tab].top();
+        let height = size.height.saturating_sub(OFFSET + 2);
+        // Folded lines are skipped over, so the lines shown can reach past the height
+        let bottom = layout.last().map_or(top, |row| row.0 + 1);
+        // Bring the syntax highlighting of the visible lines up to date
+        let doc = &mut self.doc[tab];
+        let visible = top..bottom;
+        if doc.rows[visible.clone()].iter().any(|r| r.updated) {
+            // Only render the whole document when there is something to highlight
+            let rendered = doc.render(TabType::Spaces, 0);
+            for index in visible {
+                let r = &mut doc.rows[index];
+                if r.updated {
+                    r.update_syntax(&self.config, &doc.regex, &rendered, index, &self.theme);
+                    r.updated = false;
+                }
+            }
+        }
+        // Paint the selection onto the visible lines, keyed apart from search matches
+        let selection = doc.selection();
+        // A block selection covers the same columns on each of its lines, at least one wide
+        let column = doc.cursor.x + offset.x;
+        let block = doc.block.map(|corner| {
+            let (left, right) = (cmp::min(corner.x, column), cmp::max(corner.x, column));
+            (
+                cmp::min(corner.y, current)..=cmp::max(corner.y, current),
+                left,
+                right.max(left + 1),
+            )
+        });
+        for index in top..bottom {
+            let row = &mut doc.rows[index];
+            row.bg_syntax.remove(&usize::MAX);
+            let span = if let Some((lines, left, right)) = &block {
+                lines.contains(&index).then_some((*left, *right))
+            } else {
+                selection
+                    .filter(|(s, e)| (s.y..=e.y).contains(&index))
+                    .map(|(start, end)| {
+                        let from = if index == start.y { start.x } else { 0 };
+                        let to = if index == end.y { end.x } else { row.length() };
+                        (from, to)
+                    })
+            };
+            if let Some((from, to)) = span {
+                row.bg_syntax.insert(
+                    usize::MAX,
+                    Token {
+                        span: (from, to),
+                        data: String::new(),
+                        kind: Reader::rgb_bg(self.config.theme.selection_bg).to_string(),
+                        name: "selection".to_string(),
+                        priority: 0,
+                    },
+                );
+            }
+        }
+        // Mark the bracket under the cursor and the one it pairs with
+        let brackets = doc.matching_bracket(&Position {
+            x: doc.cursor.x + offset.x,
+            y: current,
+        });
+        for index in top..bottom {
+            let row = &mut doc.rows[index];
+            row.bg_syntax.remove(&(usize::MAX - 1));
+            row.bg_syntax.remove(&(usize::MAX - 2));
+        }
+        if let Some((first, second)) = brackets {
+            for (key, at) in [(usize::MAX - 1, first), (usize::MAX - 2, second)] {
+                let row = &mut doc.rows[at.y];
+                let width = row.slice(at.x, at.x + 1).width();
+                row.bg_syntax.insert(
+                    key,
+                    Token {
+                        span: (at.x, at.x + width),
+                        data: String::new(),
+                        kind: Reader::rgb_bg(self.config.theme.bracket_bg).to_string(),
+                        name: "bracket".to_string(),
+                        priority: 0,
+                    },
+                );
+            }
+        }
+        // Show the extra cursors as reversed characters
+        for index in top..bottom {
+            doc.rows[index]
+                .bg_syntax
+                .retain(|_, token| token.name != "cursor");
+        }
+        for pos in &doc.cursors {
+            if let Some(row) = doc.rows.get_mut(pos.y) {
+                let width = cmp::max(row.slice(pos.x, pos.x + 1).width(), 1);
+                row.bg_syntax.insert(
+                    usize::MAX / 4 + pos.x,
+                    Token {
+                        span: (pos.x, pos.x + width),
+                        data: String::new(),
+                        kind: Attribute::Reverse.to_string(),
+                        name: "cursor".to_string(),
+                        priority: 0,
+                    },
+                );
+            }
+        }
+        // Underline the problems language servers found and mark the lines they start on
+        let shown = top..bottom;
+        for index in shown.clone() {
+            let row = &mut doc.rows[index];
+            row.bg_syntax.retain(|_, token| token.name != "diagnostic");
+            row.mark = None;
+            row.bookmark = doc
+                .marks
+                .iter()
+                .filter(|(_, mark)| mark.y == index)
+                .map(|(letter, _)| *letter)
+                .min();
+            row.change = if self.config.general.git_gutter {
+                doc.git_changes.get(&index).copied()
+            } else {
+                None
+            };
+            row.symbols = row.find_symbols(
+                self.config.general.indent_guides,
+                self.config.general.show_whitespace && matches!(doc.tabs, TabType::Tabs),
+                self.config.general.show_whitespace,
+                doc.tab_width,
+            );
+        }
+        for (number, diagnostic) in doc.diagnostics.iter().enumerate() {
+            let (first, last) = (diagnostic.start.0, diagnostic.end.0);
+            for index in cmp::max(first, shown.start)..=cmp::min(last, shown.end.saturating_sub(1))
+            {
+                let row = &mut doc.rows[index];
+                let from = if index == first {
+                    lsp::to_column(&row.string, diagnostic.start.1)
+                } else {
+                    0
+                };
+                let to = if index == last {
+                    lsp::to_column(&row.string, diagnostic.end.1)
+                } else {
+                    row.length()
+                };
+                // Problems with no width still underline the character they are at
+                let to = cmp::min(cmp::max(to, from + 1), row.length());
+                if from < to {
+                    row.bg_syntax.insert(
+                        usize::MAX - 3 - number,
+                        Token {
+                            span: (from, to),
+                            data: String::new(),
+                            kind: Attribute::Underlined.to_string(),
+                            name: "diagnostic".to_string(),
+                            priority: 0,
+                        },
+                    );
+                }
+                if index == first {
+                    row.mark = Some(row.mark == Some(true) || diagnostic.error);
+                }
+            }
+        }
+        // Underline misspelt words, only in comments and strings when the file is code
+        let prose = Editor::is_prose(&doc.path);
+        let spell_check = self.config.general.spell_check;
+        let dictionary = self.dictionary.as_ref().filter(|_| spell_check);
+        for index in shown {
+            let row = &mut doc.rows[index];
+            row.bg_syntax.retain(|_, token| token.name != "spelling");
+            for (start, end) in dictionary.map_or(vec![], |d| d.mistakes(&row.string)) {
+                let checked = prose
+                    || row.syntax.values().any(|token| {
+                        matches!(token.name.as_str(), "comments" | "strings")
+                            && token.span.0 <= start
+                            && end <= token.span.1
+                    });
+                if checked {
+                    row.bg_syntax.insert(
+                        usize::MAX / 2 + start,
+                        Token {
+                            span: (start, end),
+                            data: String::new(),
+                            kind: Attribute::Underlined.to_string(),
+                            name: "spelling".to_string(),
+                            priority: 0,
+                        },
+                    );
+                }
+            }
+        }
+        let mut lines = Vec::with_capacity(height);
+        for row in 0..height {
+            if row == size.height / 4 - OFFSET && self.doc[tab].show_welcome {
+                lines.push(self.welcome_message(
+                    &format!("Ox editor  v{VERSION}"),
                     Reader::rgb_fg(self.config.theme.editor_fg),
+                    size.width,
                 ));
-            } else if row == (self.term.size.height / 4).saturating_add(1) - OFFSET
-                && self.doc[self.tab].show_welcome
+            } else if row == (size.height / 4).saturating_add(1) - OFFSET
+                && self.doc[tab].show_welcome
             {
-                frame.push(self.welcome_message(
+                lines.push(self.welcome_message(
                     "A Rust powered editor by Luke",
                     Reader::rgb_fg(self.config.theme.editor_fg),
+                    size.width,
                 ));
-            } else if row == (self.term.size.height / 4).saturating_add(3) - OFFSET
-                && self.doc[self.tab].show_welcome
+            } else if row == (size.height / 4).saturating_add(3) - OFFSET
+                && self.doc[tab].show_welcome
             {
-                frame.push(self.welcome_message(
+                lines.push(self.welcome_message(
                     "To access the wiki: Press F1",
                     Reader::rgb_fg(self.config.theme.status_fg),
+                    size.width,
                 ));
-            } else if row == (self.term.size.height / 4).saturating_add(5) - OFFSET
-                && self.doc[self.tab].show_welcome
+            } else if row == (size.height / 4).saturating_add(5) - OFFSET
+                && self.doc[tab].show_welcome
             {
-                frame.push(self.welcome_message(
+                lines.push(self.welcome_message(
                     "Start typing to begin",
                     Reader::rgb_fg(self.config.theme.status_fg),
+                    size.width,
                 ));
-            } else if let Some(line) = self.doc[self.tab]
-                .rows
-                .get(self.doc[self.tab].offset.y + row)
-            {
+            } else if let Some(line) = self.recent_line(tab, row, size) {
+                lines.push(line);
+            } else if let Some(&(index, start, width, number)) = layout.get(row) {
                 // Render lines of code
-                frame.push(self.add_background(&line.render(
-                    self.doc[self.tab].offset.x,
-                    self.term.size.width,
-                    self.doc[self.tab].offset.y + row,
-                    self.doc[self.tab].line_offset,
+                let label = if self.config.general.relative_line_numbers && index != current {
+                    index.abs_diff(current)
+                } else {
+                    // Large files number their lines from where the lines held start
+                    index + 1 + self.doc[tab].lazy.as_ref().map_or(0, |lazy| lazy.start)
+                };
+                let mut line = self.doc[tab].rows[index].render(
+                    start,
+                    width,
+                    label,
+                    self.doc[tab].line_offset,
                     &self.config,
-                )));
+                    number,
+                );
+                // A folded line ends with how many lines are folded under it
+                let last = layout.get(row + 1).is_none_or(|next| next.0 != index);
+                let folded = self.doc[tab]
+                    .folds
+                    .iter()
+                    .filter(|fold| fold.0 == index)
+                    .map(|fold| fold.1 - fold.0)
+                    .max();
+                if let (Some(folded), true) = (folded, last) {
+                    let _ = write!(
+                        line,
+                        "{} \u{22ef} {} lines{}",
+                        Reader::rgb_fg(self.config.theme.line_number_fg),
+                        folded,
+                        Reader::rgb_fg(self.config.theme.editor_fg),
+                    );
+                }
+                // The blame of the cursor's line goes after the end of it, when there is room
+                if let (Some(blame), true) = (&blame, index == current && last) {
+                    let room = size.width.saturating_sub(self.exp.ansi_len(&line) + 4);
+                    if room > 0 {
+                        let _ = write!(
+                            line,
+                            "{}    {}{}",
+                            Reader::rgb_fg(self.config.theme.line_number_fg),
+                            trim_end(blame, room),
+                            Reader::rgb_fg(self.config.theme.editor_fg),
+                        );
+                    }
+                }
+                let line = self.add_background(&line, size.width);
+                // Lines that differ from the other file being compared stand out
+                let theme = &self.config.theme;
+                let tint = match self.diff.as_ref().and_then(|diff| diff.change(tab, index)) {
+                    Some(Change::Added) => Some(theme.diff_added_bg),
+                    Some(Change::Removed) => Some(theme.diff_removed_bg),
+                    Some(Change::Modified) => Some(theme.diff_modified_bg),
+                    None if self.config.general.highlight_line && index == current => {
+                        Some(theme.line_highlight_bg)
+                    }
+                    None => None,
+                };
+                let mut line = if let Some(tint) = tint {
+                    self.tint_line(&line, size.width, tint)
+                } else {
+                    line
+                };
+                // Rulers are drawn over everything else, past the end of short lines too
+                let ruler = Reader::rgb_bg(theme.ruler_bg).to_string();
+                for column in &self.config.general.rulers {
+                    let at = (column + self.doc[tab].line_offset).checked_sub(start + 1);
+                    if let Some(at) =
+                        at.filter(|at| (self.doc[tab].line_offset..size.width).contains(at))
+                    {
+                        line = self.exp.paint_column(&line, at, &ruler);
+                    }
+                }
+                lines.push(line);
             } else {
                 // Render empty lines
-                let o = self.doc[self.tab].line_offset.saturating_sub(
-                    1 + self.config.general.line_number_padding_right
-                        + self.config.general.line_number_padding_left,
-                );
-                frame.push(format!(
+                let (left, right) = if self.config.general.line_numbers {
+                    (
+                        self.config.general.line_number_padding_left,
+                        self.config.general.line_number_padding_right,
+                    )
+                } else {
+                    (0, 0)
+                };
+                let o = self.doc[tab].line_offset.saturating_sub(1 + left + right);
+                lines.push(format!(
                     "{}{}{}",
                     Reader::rgb_fg(self.config.theme.line_number_fg),
-                    self.add_background(&format!(
-                        "{}{}~{}{}{}",
-                        if self.config.theme.transparent_editor {
-                            RESET_BG
-                        } else {
-                            Reader::rgb_bg(self.config.theme.line_number_bg)
-                        },
-                        " ".repeat(self.config.general.line_number_padding_left),
-                        " ".repeat(self.config.general.line_number_padding_right),
-                        " ".repeat(o),
-                        Reader::rgb_bg(self.config.theme.editor_bg),
-                    )),
+                    self.add_background(
+                        &format!(
+                            "{}{}~{}{}{}",
+                            if self.config.theme.transparent_editor {
+                                RESET_BG
+                            } else {
+                                Reader::rgb_bg(self.config.theme.line_number_bg)
+                            },
+                            " ".repeat(left),
+                            " ".repeat(right),
+                            " ".repeat(o),
+                            Reader::rgb_bg(self.config.theme.editor_bg),
+                        ),
+                        size.width
+                    ),
                     RESET_FG
                 ));
             }
         }
-        print!("{}", frame.join("\r\n"));
+        if self.config.general.scrollbar {
+            let thumb = self.thumb(tab, height);
+            for (row, line) in lines.iter_mut().enumerate() {
+                line.push_str(&self.scrollbar_cell(thumb.contains(&row)));
+            }
+        }
+        lines
+    }
+    fn thumb(&self, tab: usize, height: usize) -> Range<usize> {
+        // The rows of the scrollbar that stand for the lines in view
+        let doc = &self.doc[tab];
+        let total = cmp::max(doc.rows.len(), 1);
+        let shown = cmp::min(height * height / total, height).max(1);
+        // The thumb reaches the bottom once the last lines fill the pane
+        let scrollable = total.saturating_sub(height);
+        let start = (cmp::min(doc.top(), scrollable) * height.saturating_sub(shown))
+            .checked_div(scrollable)
+            .unwrap_or(0);
+        start..start + shown
+    }
+    fn scrollbar_cell(&self, thumb: bool) -> String {
+        // A cell of the scrollbar, the thumb drawn as a slim block down its right side
+        let theme = &self.config.theme;
+        format!(
+            "{}{}{}{}{}",
+            if theme.transparent_editor {
+                RESET_BG.to_string()
+            } else {
+                Reader::rgb_bg(theme.editor_bg).to_string()
+            },
+            Reader::rgb_fg(theme.line_number_fg),
+            if thumb { '\u{2590}' } else { ' ' },
+            RESET_FG,
+            RESET_BG
+        )
+    }
+}
+
+fn run_filter(command: &str, input: &str) -> io::Result<Output> {
+    // Run a command in the shell with some text as its input, collecting what it writes
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("bash")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?
+    };
+    // The input is written from another thread so a command writing as it reads can't get stuck
+    let mut stdin = child.stdin.take();
+    let input = input.to_string();
+    let writer = thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let output = child.wait_with_output();
+    let _ = writer.join();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::Headless;
+
+    fn editor(width: u16, height: u16, name: &str) -> Editor {
+        // An editor over a file that doesn't exist yet, drawing on a terminal that is never shown
+        let path = env::temp_dir().join(name);
+        let _ = fs::remove_file(&path);
+        let config = env::temp_dir().join("ox-test-missing.ron");
+        let args = crate::cli("").get_matches_from(vec![
+            "ox",
+            "--no-restore",
+            "--config",
+            &config.to_string_lossy(),
+            &path.to_string_lossy(),
+        ]);
+        Editor::new(&args, Terminal::new(Box::new(Headless::new(width, height))))
+    }
+
+    fn lines(editor: &Editor) -> Vec<String> {
+        editor.doc[editor.tab]
+            .rows
+            .iter()
+            .map(|row| row.string.clone())
+            .collect()
+    }
+
+    fn shown(editor: &mut Editor) -> Position {
+        // Draw the editor, giving where the text cursor ends up on the screen
+        editor.update();
+        editor.term.cursor.expect("the cursor is shown")
+    }
+
+    #[test]
+    fn typing_and_deleting() {
+        let mut e = editor(80, 24, "ox-test-typing.txt");
+        for c in "fn f(".chars() {
+            e.insert_char(c);
+        }
+        // The bracket is closed for it, and typing the closer goes over it
+        assert_eq!(lines(&e), ["fn f()"]);
+        assert_eq!(e.current(), Position { x: 5, y: 0 });
+        e.insert_char(')');
+        assert_eq!(lines(&e), ["fn f()"]);
+        e.insert_char(' ');
+        e.insert_char('{');
+        e.insert_newline();
+        assert_eq!(lines(&e), ["fn f() {", "    ", "}"]);
+        assert_eq!(e.current(), Position { x: 4, y: 1 });
+        e.insert_char('x');
+        e.delete_backward();
+        assert_eq!(lines(&e), ["fn f() {", "    ", "}"]);
+        assert_eq!(e.current(), Position { x: 4, y: 1 });
+        // The indent goes a character at a time, then the line joins onto the one above
+        for _ in 0..5 {
+            e.delete_backward();
+        }
+        assert_eq!(lines(&e), ["fn f() {", "}"]);
+        assert_eq!(e.current(), Position { x: 8, y: 0 });
+        // Going back to the start takes away everything typed
+        for _ in 0..20 {
+            e.undo();
+        }
+        assert_eq!(lines(&e), [""]);
+        assert_eq!(e.current(), Position { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn reaching_the_end_of_lines_around_the_width() {
+        let mut e = editor(40, 10, "ox-test-width.txt");
+        shown(&mut e);
+        let columns = e.size.width - e.doc[e.tab].line_offset;
+        for length in [columns - 1, columns, columns + 1] {
+            let mut e = editor(40, 10, "ox-test-width.txt");
+            for _ in 0..length {
+                e.insert_char('x');
+            }
+            let end = Position { x: length, y: 0 };
+            // End, moving right and going to a place all stop on the last character
+            e.execute(Event::Home, false);
+            e.execute(Event::End, false);
+            assert_eq!(e.current(), end, "end of a line of {length}");
+            assert!(shown(&mut e).x < e.size.width);
+            e.execute(Event::Home, false);
+            for _ in 0..length {
+                e.move_cursor(Direction::Right);
+            }
+            assert_eq!(e.current(), end, "right along a line of {length}");
+            assert!(shown(&mut e).x < e.size.width);
+            e.execute(Event::Home, false);
+            e.doc[e.tab].goto(end, &e.size);
+            assert_eq!(e.current(), end, "going to the end of a line of {length}");
+            assert!(shown(&mut e).x < e.size.width);
+            // The last character can be taken back out from there
+            e.delete_backward();
+            assert_eq!(lines(&e), ["x".repeat(length - 1)]);
+        }
+    }
+
+    #[test]
+    fn cursor_keeps_up_with_the_gutter() {
+        let mut e = editor(40, 10, "ox-test-gutter.txt");
+        let padding =
+            e.config.general.line_number_padding_left + e.config.general.line_number_padding_right;
+        for c in "line".chars() {
+            e.insert_char(c);
+        }
+        // Growing past 9 and then 99 lines each takes another column for the numbers
+        for lines in 2..=101 {
+            e.insert_newline();
+            for c in "line".chars() {
+                e.insert_char(c);
+            }
+            let x = shown(&mut e).x;
+            let gutter = e.doc[e.tab].line_offset;
+            assert_eq!(
+                gutter,
+                lines.to_string().len() + padding,
+                "gutter at {lines} lines"
+            );
+            assert_eq!(x, gutter + 4, "cursor at {lines} lines");
+        }
     }
 }
@@ -0,0 +1,190 @@
+// Editorconfig.rs - Following the settings .editorconfig files give the files under them
+use crate::encoding::Encoding;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// What the .editorconfig files say about a file, for the things they say anything about
+#[derive(Debug, Default, Clone)]
+pub struct Settings {
+    pub tabs: Option<bool>,                     // Whether to indent with tabs
+    pub indent_size: Option<usize>,             // How many columns a level of indentation is
+    pub tab_width: Option<usize>,               // How many columns a tab is shown as
+    pub crlf: Option<bool>,                     // Whether lines end with \r\n
+    pub trim_trailing_whitespace: Option<bool>, // Whether spaces at the ends of lines go on save
+    pub insert_final_newline: Option<bool>,     // Whether saved files end with a newline
+    pub charset: Option<Encoding>,              // The encoding to save in
+    pub filetype: Option<String>,               // The type of file, which only modelines give
+    pub soft_wrap: Option<bool>, // Whether long lines wrap, which only the config gives
+}
+
+impl Settings {
+    pub fn or(self, base: Settings) -> Settings {
+        // Fill in what these settings don't say from others
+        Settings {
+            tabs: self.tabs.or(base.tabs),
+            indent_size: self.indent_size.or(base.indent_size),
+            tab_width: self.tab_width.or(base.tab_width),
+            crlf: self.crlf.or(base.crlf),
+            trim_trailing_whitespace: self
+                .trim_trailing_whitespace
+                .or(base.trim_trailing_whitespace),
+            insert_final_newline: self.insert_final_newline.or(base.insert_final_newline),
+            charset: self.charset.or(base.charset),
+            filetype: self.filetype.or(base.filetype),
+            soft_wrap: self.soft_wrap.or(base.soft_wrap),
+        }
+    }
+    pub fn width(&self) -> Option<usize> {
+        // Ox has one width for tabs and indentation, so take the one that matters more
+        if self.tabs == Some(true) {
+            self.tab_width.or(self.indent_size)
+        } else {
+            self.indent_size.or(self.tab_width)
+        }
+    }
+    fn set(&mut self, key: &str, value: &str) {
+        // Take in a property, where unset and values that make no sense go back to nothing
+        let flag = match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        let number = value.parse().ok().filter(|number| *number > 0);
+        match key {
+            "indent_style" => {
+                self.tabs = match value {
+                    "tab" => Some(true),
+                    "space" => Some(false),
+                    _ => None,
+                };
+            }
+            // An indent_size of tab means the tab_width, which width already falls back on
+            "indent_size" => self.indent_size = number,
+            "tab_width" => self.tab_width = number,
+            "end_of_line" => {
+                self.crlf = match value {
+                    "crlf" => Some(true),
+                    "lf" => Some(false),
+                    _ => None,
+                };
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = flag,
+            "insert_final_newline" => self.insert_final_newline = flag,
+            "charset" => self.charset = Encoding::from_name(value),
+            _ => (),
+        }
+    }
+}
+
+fn pattern(glob: &str) -> Option<Regex> {
+    // Turn the name of a section into a regex for paths from the directory of its file
+    let anchored = glob.contains('/');
+    let chars: Vec<char> = glob.trim_start_matches('/').chars().collect();
+    let mut re = String::new();
+    let mut braces = 0;
+    let mut index = 0;
+    while index < chars.len() {
+        match chars[index] {
+            '*' if chars.get(index + 1) == Some(&'*') => {
+                re.push_str(".*");
+                index += 1;
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => {
+                if let Some(end) = chars[index..].iter().position(|c| *c == ']') {
+                    let class: String = chars[index + 1..index + end].iter().collect();
+                    let class = class
+                        .strip_prefix('!')
+                        .map_or(class.clone(), |rest| format!("^{rest}"));
+                    re.push('[');
+                    re.push_str(&class.replace('\\', "\\\\"));
+                    re.push(']');
+                    index += end;
+                } else {
+                    re.push_str("\\[");
+                }
+            }
+            '{' => {
+                braces += 1;
+                re.push_str("(?:");
+            }
+            ',' if braces > 0 => re.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                re.push(')');
+            }
+            '\\' => {
+                index += 1;
+                if let Some(c) = chars.get(index) {
+                    re.push_str(&regex::escape(&c.to_string()));
+                }
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        index += 1;
+    }
+    // Names without a slash match files of that name in any directory below
+    let start = if anchored { "^" } else { "^(?:.*/)?" };
+    Regex::new(&format!("{start}{re}$")).ok()
+}
+
+fn is_root(text: &str) -> bool {
+    // Whether a file says not to look any further up, which it does before its first section
+    text.lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| {
+            key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+        })
+}
+
+fn apply(settings: &mut Settings, text: &str, relative: &str) {
+    // Take the properties of every section of a file that matches, later ones winning
+    let mut matched = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            matched = pattern(&line[1..line.len() - 1]).is_some_and(|re| re.is_match(relative));
+        } else if let (true, Some((key, value))) = (matched, line.split_once('=')) {
+            settings.set(&key.trim().to_lowercase(), &value.trim().to_lowercase());
+        }
+    }
+}
+
+pub fn settings(path: &Path) -> Settings {
+    // Gather what the .editorconfig files above a file say about it, the nearest having the last word
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(path)
+    };
+    let mut files = vec![];
+    for dir in path.ancestors().skip(1) {
+        if let Ok(text) = fs::read_to_string(dir.join(".editorconfig")) {
+            let root = is_root(&text);
+            files.push((dir, text));
+            if root {
+                break;
+            }
+        }
+    }
+    let mut settings = Settings::default();
+    for (dir, text) in files.iter().rev() {
+        if let Ok(relative) = path.strip_prefix(dir) {
+            apply(
+                &mut settings,
+                text,
+                &relative.to_string_lossy().replace('\\', "/"),
+            );
+        }
+    }
+    settings
+}
@@ -0,0 +1,118 @@
+// Encoding.rs - Reading and writing files that aren't plain UTF-8
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind};
+use std::iter;
+
+// The character encodings files can be read and saved in
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        // Work out the encoding from a byte order mark, or failing that from the bytes
+        if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+            return Some(Self::Utf8Bom);
+        } else if bytes.starts_with(&[0xff, 0xfe]) {
+            return Some(Self::Utf16Le);
+        } else if bytes.starts_with(&[0xfe, 0xff]) {
+            return Some(Self::Utf16Be);
+        }
+        // Text in UTF-16 without a mark has a zero byte in half of most characters
+        let half = |start: usize| {
+            bytes
+                .iter()
+                .skip(start)
+                .step_by(2)
+                .filter(|b| **b == 0)
+                .count()
+        };
+        let pairs = bytes.len() / 2;
+        if bytes.len().is_multiple_of(2) && pairs > 0 {
+            let (even, odd) = (half(0), half(1));
+            if odd * 10 > pairs * 4 && even * 10 < pairs {
+                return Some(Self::Utf16Le);
+            } else if even * 10 > pairs * 4 && odd * 10 < pairs {
+                return Some(Self::Utf16Be);
+            }
+        }
+        if bytes.contains(&0) {
+            // Zero bytes don't turn up in text, so this is a binary file
+            None
+        } else if std::str::from_utf8(bytes).is_ok() {
+            Some(Self::Utf8)
+        } else {
+            Some(Self::Latin1)
+        }
+    }
+    pub fn decode(self, bytes: &[u8]) -> String {
+        // Turn the contents of a file into text
+        let units = |bytes: &[u8], read: fn([u8; 2]) -> u16| -> Vec<u16> {
+            bytes
+                .chunks_exact(2)
+                .map(|pair| read([pair[0], pair[1]]))
+                .collect()
+        };
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            Self::Utf8Bom => String::from_utf8_lossy(&bytes[3..]).to_string(),
+            Self::Utf16Le => {
+                let bytes = bytes.strip_prefix(&[0xff, 0xfe]).unwrap_or(bytes);
+                String::from_utf16_lossy(&units(bytes, u16::from_le_bytes))
+            }
+            Self::Utf16Be => {
+                let bytes = bytes.strip_prefix(&[0xfe, 0xff]).unwrap_or(bytes);
+                String::from_utf16_lossy(&units(bytes, u16::from_be_bytes))
+            }
+            Self::Latin1 => bytes.iter().map(|b| char::from(*b)).collect(),
+        }
+    }
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>> {
+        // Turn text back into the bytes of a file
+        Ok(match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => [&[0xef, 0xbb, 0xbf], text.as_bytes()].concat(),
+            Self::Utf16Le => iter::once(0xfeff)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Self::Utf16Be => iter::once(0xfeff)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Self::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)))
+                .collect::<Result<_, _>>()
+                .map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidData, "Some characters aren't in Latin-1")
+                })?,
+        })
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        // Find an encoding by what it's usually called
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(Self::Utf8Bom),
+            "utf-16le" | "utf-16" | "utf16le" | "utf16" => Some(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Self::Utf16Be),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        // The name shown in the status line
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Explorer.rs - A sidebar for browsing the files under the working directory
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The widest the sidebar gets, it is narrower on small terminals
+pub const WIDTH: usize = 30;
+
+// A file or directory shown in the tree
+pub struct Entry {
+    pub path: PathBuf, // Where the entry is on disk
+    pub depth: usize,  // How many directories deep it is
+    pub dir: bool,     // Whether it can be expanded
+}
+
+pub struct Explorer {
+    entries: Vec<Entry>,        // Everything visible in the tree, top to bottom
+    selected: usize,            // The entry under the cursor
+    pub focus: bool,            // Whether keys go to the sidebar
+    top: usize,                 // The first entry in view
+    root: PathBuf,              // The directory at the top of the tree
+    expanded: HashSet<PathBuf>, // Directories that show their contents
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        // Show the contents of a directory
+        let mut explorer = Self {
+            entries: vec![],
+            selected: 0,
+            focus: true,
+            top: 0,
+            root,
+            expanded: HashSet::new(),
+        };
+        explorer.refresh();
+        explorer
+    }
+    pub fn refresh(&mut self) {
+        // Read the tree from disk again
+        self.entries.clear();
+        let root = self.root.clone();
+        self.list(&root, 0);
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+    fn list(&mut self, dir: &Path, depth: usize) {
+        // Add the contents of a directory, directories first
+        let mut children: Vec<(bool, PathBuf)> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| (entry.path().is_dir(), entry.path()))
+                    .filter(|(_, path)| path.file_name() != Some(OsStr::new(".git")))
+                    .collect()
+            })
+            .unwrap_or_default();
+        children.sort_by_key(|(dir, path)| (!dir, path.to_string_lossy().to_lowercase()));
+        for (dir, path) in children {
+            let open = dir && self.expanded.contains(&path);
+            self.entries.push(Entry {
+                path: path.clone(),
+                depth,
+                dir,
+            });
+            if open {
+                self.list(&path, depth + 1);
+            }
+        }
+    }
+    pub fn up(&mut self) {
+        // Move the cursor to the entry above
+        self.selected = self.selected.saturating_sub(1);
+    }
+    pub fn down(&mut self) {
+        // Move the cursor to the entry below
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+    pub fn activate(&mut self) -> Option<PathBuf> {
+        // Open or close the selected directory, or hand back the selected file
+        let entry = self.entries.get(self.selected)?;
+        if !entry.dir {
+            return Some(entry.path.clone());
+        }
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.refresh();
+        None
+    }
+    pub fn expand(&mut self) {
+        // Show the contents of the selected directory
+        if let Some(entry) = self.entries.get(self.selected) {
+            if entry.dir && self.expanded.insert(entry.path.clone()) {
+                self.refresh();
+            }
+        }
+    }
+    pub fn collapse(&mut self) {
+        // Hide the selected directory, or move up to the directory holding the entry
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        if entry.dir && self.expanded.remove(&entry.path) {
+            self.refresh();
+        } else if let Some(parent) = entry.path.parent() {
+            if let Some(index) = self.entries.iter().position(|e| e.path == parent) {
+                self.selected = index;
+            }
+        }
+    }
+    pub fn click(&mut self, row: usize) -> Option<PathBuf> {
+        // Select the entry on a row of the sidebar and act on it
+        if self.top + row >= self.entries.len() {
+            return None;
+        }
+        self.selected = self.top + row;
+        self.activate()
+    }
+    pub fn scroll(&mut self, up: bool, height: usize) {
+        // Move the view without going past either end
+        let max = self.entries.len().saturating_sub(height);
+        self.top = if up {
+            self.top.saturating_sub(1)
+        } else {
+            (self.top + 1).min(max)
+        };
+        self.selected = self
+            .selected
+            .clamp(self.top, self.top + height.saturating_sub(1));
+    }
+    pub fn lines(&mut self, height: usize) -> Vec<(String, bool)> {
+        // The text of the rows in view, and whether each one is selected
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + height {
+            self.top = self.selected + 1 - height;
+        }
+        self.entries
+            .iter()
+            .enumerate()
+            .skip(self.top)
+            .take(height)
+            .map(|(index, entry)| {
+                let icon = if !entry.dir {
+                    "  "
+                } else if self.expanded.contains(&entry.path) {
+                    "\u{25be} "
+                } else {
+                    "\u{25b8} "
+                };
+                let name = entry
+                    .path
+                    .file_name()
+                    .map_or(String::new(), |name| name.to_string_lossy().to_string());
+                let text = format!(" {}{}{}", "  ".repeat(entry.depth), icon, name);
+                (text, index == self.selected)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+// Export.rs - Turning a highlighted document into a web page
+use crate::config::Reader;
+use crate::document::{Document, TabType};
+use crate::highlight::{highlight, remove_nested_tokens};
+use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    // The CSS form of a colour
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape(text: &str) -> String {
+    // Keep text from being read as markup
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn html(doc: &Document, config: &Reader, theme: &str) -> String {
+    // Write a standalone page showing the document the way it is highlighted in the editor
+    let colours = &config.highlights[theme];
+    let rendered = doc.render(TabType::Spaces, 0);
+    let mut body = String::new();
+    for (index, row) in doc.rows.iter().enumerate() {
+        // The tokens are found the same way as when the line is drawn
+        let tokens = remove_nested_tokens(
+            &highlight(&row.string, &rendered, index, &doc.regex, colours),
+            &row.string,
+        );
+        let mut column = 0;
+        let mut end: Option<usize> = None;
+        for grapheme in row.string.graphemes(true) {
+            if end.is_some_and(|end| end <= column) {
+                body.push_str("</span>");
+                end = None;
+            }
+            if let (None, Some(token)) = (end, tokens.get(&column)) {
+                if let Some(colour) = colours.get(&token.name) {
+                    let _ = write!(body, "<span style=\"color: {}\">", hex(*colour));
+                    end = Some(token.span.1);
+                }
+            }
+            body.push_str(&escape(grapheme));
+            column += grapheme.width();
+        }
+        if end.is_some() {
+            body.push_str("</span>");
+        }
+        body.push('\n');
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<pre style=\"background: {}; color: {}; padding: 1em\">\n{}</pre>\n</body>\n</html>\n",
+        escape(&doc.name),
+        hex(config.theme.editor_bg),
+        hex(config.theme.editor_fg),
+        body,
+    )
+}
@@ -0,0 +1,111 @@
+// Filetype.rs - Working out what type of file a document is, which the rest of Ox goes by
+use crate::config::Reader;
+use crate::editorconfig::Settings;
+use std::path::Path;
+
+// Files known by their whole name, and the type they are
+const NAMES: &[(&str, &str)] = &[
+    ("Makefile", "Makefile"),
+    ("makefile", "Makefile"),
+    ("GNUmakefile", "Makefile"),
+    ("Dockerfile", "Dockerfile"),
+    ("Containerfile", "Dockerfile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Rakefile", "rb"),
+    ("Gemfile", "rb"),
+    ("Cargo.lock", "toml"),
+    (".bashrc", "sh"),
+    (".bash_profile", "sh"),
+    (".profile", "sh"),
+    (".zshrc", "sh"),
+];
+
+// Programs named by a #! line, and the type of the scripts they run
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "py"),
+    ("bash", "sh"),
+    ("sh", "sh"),
+    ("zsh", "sh"),
+    ("dash", "sh"),
+    ("fish", "fish"),
+    ("ruby", "rb"),
+    ("node", "js"),
+    ("perl", "pl"),
+    ("lua", "lua"),
+    ("crystal", "cr"),
+];
+
+fn shebang(line: &str) -> Option<&'static str> {
+    // The type of a script from its #! line, looking past env to the program it runs
+    let command = line.strip_prefix("#!")?;
+    let mut words = command.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    INTERPRETERS
+        .iter()
+        .find(|(name, _)| {
+            // Versions such as python3 and python3.11 count as the program
+            program
+                .strip_prefix(name)
+                .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        })
+        .map(|(_, filetype)| *filetype)
+}
+
+pub fn detect(path: &str, first_line: &str) -> String {
+    // The type of a file from its name, then its extension, then its #! line
+    // Types are named by their usual extension, which is what languages in the config list
+    let name = Path::new(path)
+        .file_name()
+        .map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+    if let Some((_, filetype)) = NAMES.iter().find(|(known, _)| *known == name) {
+        return filetype.to_string();
+    }
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => extension.to_string(),
+        _ => shebang(first_line).map_or_else(
+            || name.trim_start_matches('.').to_string(),
+            ToString::to_string,
+        ),
+    }
+}
+
+pub fn from_name(config: &Reader, name: &str) -> String {
+    // The type of file a language is, named the way Vim or Emacs would name it
+    let name = name.trim_end_matches("-mode").to_lowercase();
+    let name = match name.as_str() {
+        "c++" => "cpp",
+        "shell" | "bash" | "zsh" => "sh",
+        "javascript" | "js2" => "js",
+        "markdown" | "gfm" => "md",
+        "python3" => "py",
+        "ruby" => "rb",
+        "make" | "makefile" => "Makefile",
+        "dockerfile" => "Dockerfile",
+        other => other,
+    };
+    config
+        .languages
+        .iter()
+        .find(|lang| {
+            lang.name.to_lowercase() == name || lang.extensions.iter().any(|ext| ext == name)
+        })
+        .and_then(|lang| lang.extensions.first().cloned())
+        .unwrap_or_else(|| name.to_string())
+}
+
+pub fn settings(config: &Reader, filetype: &str) -> Settings {
+    // What the config says about a type of file, for .editorconfig files and modelines to go over
+    let filetype = config.filetypes.get(filetype).cloned().unwrap_or_default();
+    Settings {
+        tabs: filetype.expand_tabs.map(|expand| !expand),
+        indent_size: filetype.tab_width,
+        tab_width: filetype.tab_width,
+        trim_trailing_whitespace: filetype.trim_trailing_whitespace,
+        insert_final_newline: filetype.ensure_final_newline,
+        soft_wrap: filetype.soft_wrap,
+        ..Settings::default()
+    }
+}
@@ -0,0 +1,122 @@
+// Finder.rs - Fuzzy matching of the paths of files under the working directory
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+// Directories that are too big or too rarely wanted to be worth searching
+const SKIPPED: [&str; 2] = ["target", "node_modules"];
+
+pub struct Finder {
+    pub files: Vec<String>,          // Every path found so far
+    pub done: bool,                  // Whether the walk has finished
+    receiver: Receiver<Vec<String>>, // Paths coming in from the walking thread
+}
+
+impl Finder {
+    pub fn new(root: PathBuf) -> Self {
+        // Start walking a directory in the background so the editor stays responsive
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || walk(&root, &root, &sender));
+        Self {
+            files: vec![],
+            done: false,
+            receiver,
+        }
+    }
+    pub fn receive(&mut self) -> bool {
+        // Take in the paths found since last time, returning whether there were any
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(mut files) => {
+                    self.files.append(&mut files);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    changed |= !self.done;
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+    pub fn matches(&self, pattern: &str, limit: usize) -> Vec<&str> {
+        // The paths that match a pattern best, best first
+        let mut scored: Vec<(isize, &str)> = self
+            .files
+            .iter()
+            .filter_map(|file| score(pattern, file).map(|score| (score, file.as_str())))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.len().cmp(&b.1.len())));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, file)| file)
+            .collect()
+    }
+}
+
+pub fn files(root: &Path) -> Vec<String> {
+    // Every file under a directory that would be offered, found all at once
+    let (sender, receiver) = mpsc::channel();
+    walk(root, root, &sender);
+    drop(sender);
+    receiver.into_iter().flatten().collect()
+}
+
+fn walk(root: &Path, dir: &Path, sender: &Sender<Vec<String>>) -> bool {
+    // Send the files in a directory and below, stopping once nobody is listening
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+    let mut files = vec![];
+    let mut dirs = vec![];
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Symbolic links aren't followed so that loops can't trap the walk
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            if !name.starts_with('.') && !SKIPPED.contains(&name.as_str()) {
+                dirs.push(path);
+            }
+        } else if let (false, Ok(relative)) = (path.is_dir(), path.strip_prefix(root)) {
+            files.push(relative.to_string_lossy().to_string());
+        }
+    }
+    if sender.send(files).is_err() {
+        return false;
+    }
+    dirs.iter().all(|dir| walk(root, dir, sender))
+}
+
+pub fn score(pattern: &str, path: &str) -> Option<isize> {
+    // Match the characters of a pattern in order, preferring runs and word starts
+    let path: Vec<char> = path.chars().collect();
+    let mut score = 0;
+    let mut index = 0;
+    let mut last = None;
+    for wanted in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let wanted = wanted.to_lowercase().next().unwrap_or(wanted);
+        let found = (index..path.len()).find(|&i| path[i].to_lowercase().next() == Some(wanted))?;
+        score += 1;
+        if last.is_some_and(|last| last + 1 == found) {
+            // Consecutive characters count for more
+            score += 4;
+        }
+        if found == 0 || matches!(path[found - 1], '/' | '_' | '-' | '.' | ' ') {
+            // So do the first characters of words and file names
+            score += 3;
+        }
+        last = Some(found);
+        index = found + 1;
+    }
+    // Matches in the file name beat ones in the directories above
+    let name = path.iter().rposition(|c| *c == '/').map_or(0, |i| i + 1);
+    if last.is_some_and(|last| last >= name) {
+        score += 2;
+    }
+    Some(score)
+}
@@ -0,0 +1,336 @@
+// Git.rs - Comparing documents with the copies of them that git holds
+use crate::encoding::Encoding;
+use crate::util::tabs_to_spaces;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// How a line differs from the one git has
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Change {
+    Added,    // The line is new
+    Modified, // The line replaced one or more lines
+    Removed,  // Lines were taken out just before this one
+}
+
+// How the lines of two files differ, and which lines sit across from each other
+#[derive(Debug, Default)]
+pub struct Comparison {
+    pub old: HashMap<usize, Change>, // Lines of the first file that were removed or modified
+    pub new: HashMap<usize, Change>, // Lines of the second file that were added or modified
+    pub pairs: Vec<(usize, usize)>,  // Lines of the two that match up, in order
+}
+
+// The branch a repository is on, and whether the files it tracks differ from the last commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub name: String,
+    pub dirty: bool,
+}
+
+// A step in turning the lines git has into the lines of the document
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Edit {
+    Same,
+    Delete,
+    Insert,
+}
+
+pub fn base(path: &str, encoding: Encoding, tab_width: usize) -> Option<Vec<String>> {
+    // The lines of a file as they are in the git index, if it is tracked
+    let path = fs::canonicalize(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path.parent()?)
+        .arg("show")
+        .arg(format!(":./{name}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // The lines are made to look like the rows of a document so they can be compared
+    let text = tabs_to_spaces(&encoding.decode(&output.stdout), tab_width);
+    Some(
+        text.lines()
+            .map(|line| line.trim_end_matches('\r').to_string())
+            .collect(),
+    )
+}
+
+pub fn changes(old: &[String], new: &[&str]) -> HashMap<usize, Change> {
+    // Work out which rows of a document differ from the lines git has
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| a.as_str() == **b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a.as_str() == **b)
+        .count();
+    let script = diff(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let mut changes = HashMap::new();
+    let mut row = prefix;
+    let mut deleted = 0;
+    let mut inserted = vec![];
+    for edit in script.into_iter().chain(std::iter::once(Edit::Same)) {
+        match edit {
+            Edit::Delete => deleted += 1,
+            Edit::Insert => {
+                inserted.push(row);
+                row += 1;
+            }
+            Edit::Same => {
+                if inserted.is_empty() && deleted > 0 && !new.is_empty() {
+                    changes.insert(row.min(new.len() - 1), Change::Removed);
+                }
+                for (n, index) in inserted.drain(..).enumerate() {
+                    let change = if n < deleted {
+                        Change::Modified
+                    } else {
+                        Change::Added
+                    };
+                    changes.insert(index, change);
+                }
+                deleted = 0;
+                row += 1;
+            }
+        }
+    }
+    changes
+}
+
+pub fn compare(old: &[String], new: &[&str]) -> Comparison {
+    // Work out how two files differ line by line, for showing them side by side
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| a.as_str() == **b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a.as_str() == **b)
+        .count();
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    let script = diff(&old[prefix..old_end], &new[prefix..new_end]);
+    let mut comparison = Comparison::default();
+    comparison
+        .pairs
+        .extend((0..prefix).map(|line| (line, line)));
+    let (mut x, mut y) = (prefix, prefix);
+    let (mut deleted, mut inserted) = (vec![], vec![]);
+    for edit in script.into_iter().chain(std::iter::once(Edit::Same)) {
+        match edit {
+            Edit::Delete => {
+                deleted.push(x);
+                x += 1;
+            }
+            Edit::Insert => {
+                inserted.push(y);
+                y += 1;
+            }
+            Edit::Same => {
+                // Lines taken out where others were put in are changes of each other
+                for n in 0..deleted.len().max(inserted.len()) {
+                    match (deleted.get(n), inserted.get(n)) {
+                        (Some(&a), Some(&b)) => {
+                            comparison.old.insert(a, Change::Modified);
+                            comparison.new.insert(b, Change::Modified);
+                            comparison.pairs.push((a, b));
+                        }
+                        (Some(&a), None) => {
+                            comparison.old.insert(a, Change::Removed);
+                        }
+                        (None, Some(&b)) => {
+                            comparison.new.insert(b, Change::Added);
+                        }
+                        (None, None) => (),
+                    }
+                }
+                deleted.clear();
+                inserted.clear();
+                if x < old_end && y < new_end {
+                    comparison.pairs.push((x, y));
+                    x += 1;
+                    y += 1;
+                }
+            }
+        }
+    }
+    comparison
+        .pairs
+        .extend((0..suffix).map(|line| (old_end + line, new_end + line)));
+    comparison
+}
+
+pub fn branch(path: &str) -> Option<Branch> {
+    // The branch of the repository a file is in and whether it has changes, if it is in one
+    let path = fs::canonicalize(path).ok()?;
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path.parent()?)
+        .args(["status", "--porcelain", "--branch", "--untracked-files=no"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // The first line is the branch, like ## main...origin/main [ahead 1], and each after is a file
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines();
+    let head = lines.next()?.strip_prefix("## ")?;
+    let branch = if let Some(branch) = head
+        .strip_prefix("No commits yet on ")
+        .or_else(|| head.strip_prefix("Initial commit on "))
+    {
+        branch
+    } else if head.starts_with("HEAD (no branch)") {
+        "HEAD"
+    } else {
+        head.split("...").next()?
+    };
+    Some(Branch {
+        name: branch.to_string(),
+        dirty: lines.next().is_some(),
+    })
+}
+
+pub fn blame(path: &str, line: usize, contents: &[u8]) -> Option<String> {
+    // Who last changed a line and why, going by the document as it is rather than as saved
+    let path = fs::canonicalize(path).ok()?;
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(path.parent()?)
+        .args(["blame", "--porcelain", "-L"])
+        .arg(format!("{},+1", line + 1))
+        .args(["--contents", "-", "--"])
+        .arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(contents).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines();
+    let commit = lines.next()?.split(' ').next()?;
+    if commit.bytes().all(|byte| byte == b'0') {
+        return Some("Not committed yet".to_string());
+    }
+    let (mut author, mut time, mut summary) = (None, None, None);
+    for line in lines {
+        if let Some(value) = line.strip_prefix("author ") {
+            author = Some(value);
+        } else if let Some(value) = line.strip_prefix("author-time ") {
+            time = value.parse().ok();
+        } else if let Some(value) = line.strip_prefix("summary ") {
+            summary = Some(value);
+        }
+    }
+    Some(format!("{}, {} \u{2022} {}", author?, ago(time?), summary?))
+}
+
+fn ago(time: u64) -> String {
+    // Describe how long ago a moment given in seconds since 1970 was
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(time, |now| now.as_secs());
+    let elapsed = now.saturating_sub(time);
+    let units = [
+        ("year", 365 * 86400),
+        ("month", 30 * 86400),
+        ("week", 7 * 86400),
+        ("day", 86400),
+        ("hour", 3600),
+        ("minute", 60),
+    ];
+    units.iter().find(|(_, length)| elapsed >= *length).map_or(
+        "just now".to_string(),
+        |(unit, length)| {
+            let count = elapsed / length;
+            format!(
+                "{} {}{} ago",
+                count,
+                unit,
+                if count == 1 { "" } else { "s" }
+            )
+        },
+    )
+}
+
+fn diff(old: &[String], new: &[&str]) -> Vec<Edit> {
+    // Find the fewest lines to delete and insert with the Myers algorithm, where the
+    // furthest point reached on each diagonal k = x - y is kept at index k + offset
+    let (end_x, end_y) = (old.len(), new.len());
+    let offset = end_x + end_y + 1;
+    let mut furthest = vec![0; 2 * offset + 1];
+    let mut trace = vec![];
+    'search: for d in 0..=end_x + end_y {
+        trace.push(furthest.clone());
+        for k in (offset - d..=offset + d).step_by(2) {
+            let mut x = if k == offset - d || (k != offset + d && furthest[k - 1] < furthest[k + 1])
+            {
+                furthest[k + 1]
+            } else {
+                furthest[k - 1] + 1
+            };
+            let mut y = x + offset - k;
+            while x < end_x && y < end_y && old[x] == new[y] {
+                x += 1;
+                y += 1;
+            }
+            furthest[k] = x;
+            if x >= end_x && y >= end_y {
+                break 'search;
+            }
+        }
+    }
+    // Walk back from the end to the start to recover the edits
+    let mut script = vec![];
+    let (mut x, mut y) = (end_x, end_y);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        if d == 0 {
+            script.extend((0..x).map(|_| Edit::Same));
+            break;
+        }
+        let k = x + offset - y;
+        let previous = if k == offset - d || (k != offset + d && furthest[k - 1] < furthest[k + 1])
+        {
+            k + 1
+        } else {
+            k - 1
+        };
+        let px = furthest[previous];
+        let py = px + offset - previous;
+        while x > px && y > py {
+            script.push(Edit::Same);
+            x -= 1;
+            y -= 1;
+        }
+        script.push(if x == px { Edit::Insert } else { Edit::Delete });
+        x = px;
+        y = py;
+    }
+    script.reverse();
+    script
+}
@@ -0,0 +1,122 @@
+// Hex.rs - Viewing and changing the bytes of files that aren't text
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Hex {
+    pub path: String,   // The file being edited
+    pub bytes: Vec<u8>, // The contents of the file
+    pub cursor: usize,  // The byte under the cursor
+    high: bool,         // Whether the next digit typed is the first half of the byte
+    top: usize,         // The first line in view
+    pub dirty: bool,    // Whether there are changes that haven't been saved
+}
+
+impl Hex {
+    pub fn open(path: &str) -> io::Result<Self> {
+        // Read in the bytes of a file
+        Ok(Self {
+            path: path.to_string(),
+            bytes: fs::read(path)?,
+            cursor: 0,
+            high: true,
+            top: 0,
+            dirty: false,
+        })
+    }
+    pub fn per_line(width: usize) -> usize {
+        // Show as many bytes on each line as fit, 16 or 8
+        if width >= 76 {
+            16
+        } else {
+            8
+        }
+    }
+    pub fn left(&mut self) {
+        // Move to the byte before
+        self.cursor = self.cursor.saturating_sub(1);
+        self.high = true;
+    }
+    pub fn right(&mut self) {
+        // Move to the byte after
+        self.cursor = (self.cursor + 1).min(self.bytes.len().saturating_sub(1));
+        self.high = true;
+    }
+    pub fn up(&mut self, by: usize) {
+        // Move up by a number of bytes, staying put at the top
+        if self.cursor >= by {
+            self.cursor -= by;
+        }
+        self.high = true;
+    }
+    pub fn down(&mut self, by: usize) {
+        // Move down by a number of bytes, staying put at the bottom
+        if self.cursor + by < self.bytes.len() {
+            self.cursor += by;
+        }
+        self.high = true;
+    }
+    pub fn home(&mut self, per_line: usize) {
+        // Move to the first byte on the line
+        self.cursor -= self.cursor % per_line;
+        self.high = true;
+    }
+    pub fn end(&mut self, per_line: usize) {
+        // Move to the last byte on the line
+        self.cursor = (self.cursor - self.cursor % per_line + per_line - 1)
+            .min(self.bytes.len().saturating_sub(1));
+        self.high = true;
+    }
+    pub fn type_digit(&mut self, digit: u8) {
+        // Change half of the byte under the cursor, moving on once both halves are typed
+        if let Some(byte) = self.bytes.get_mut(self.cursor) {
+            if self.high {
+                *byte = (digit << 4) | (*byte & 0x0f);
+                self.high = false;
+            } else {
+                *byte = (*byte & 0xf0) | digit;
+                self.right();
+            }
+            self.dirty = true;
+        }
+    }
+    pub fn save(&mut self) -> io::Result<usize> {
+        // Write the bytes out through a temporary file, like documents are saved
+        let target = fs::canonicalize(&self.path).unwrap_or_else(|_| PathBuf::from(&self.path));
+        let name = target
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+        let temp = target.with_file_name(format!(".{name}.ox"));
+        let write = |temp: &Path| -> io::Result<()> {
+            let mut file = fs::File::create(temp)?;
+            file.write_all(&self.bytes)?;
+            file.sync_all()?;
+            if let Ok(metadata) = fs::metadata(&target) {
+                fs::set_permissions(temp, metadata.permissions())?;
+            }
+            fs::rename(temp, &target)
+        };
+        if let Err(error) = write(&temp) {
+            let _ = fs::remove_file(&temp);
+            return Err(error);
+        }
+        self.dirty = false;
+        Ok(self.bytes.len())
+    }
+    pub fn lines(&mut self, height: usize, per_line: usize) -> Vec<(usize, &[u8])> {
+        // The offsets and bytes of the lines in view, scrolling to keep the cursor in sight
+        let line = self.cursor / per_line;
+        if line < self.top {
+            self.top = line;
+        } else if line >= self.top + height {
+            self.top = line + 1 - height;
+        }
+        self.bytes
+            .chunks(per_line)
+            .enumerate()
+            .skip(self.top)
+            .take(height)
+            .map(|(index, chunk)| (index * per_line, chunk))
+            .collect()
+    }
+}
@@ -4,11 +4,13 @@ use std::collections::HashMap;
 use unicode_width::UnicodeWidthStr;
 
 // Tokens for storing syntax highlighting info
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Token {
     pub span: (usize, usize),
     pub data: String,
     pub kind: String,
+    pub name: String,
     pub priority: usize,
 }
 
@@ -32,7 +34,7 @@ fn bounds(reg: &regex::Match, line: &str) -> (usize, usize) {
 
 fn multi_to_single(doc: &str, m: &regex::Match) -> ((usize, usize), (usize, usize)) {
     // Multiline tokens to single line tokens
-    let b = bounds(&m, &doc);
+    let b = bounds(m, doc);
     let start_y = doc[..m.start()].matches('\n').count();
     let end_y = doc[..m.end()].matches('\n').count();
     let start_x = b.0
@@ -63,12 +65,13 @@ pub fn highlight(
                         // Locate keywords
                         for cap in kw.captures_iter(row) {
                             let cap = cap.get(cap.len().saturating_sub(1)).unwrap();
-                            let boundaries = bounds(&cap, &row);
+                            let boundaries = bounds(&cap, row);
                             cine(
                                 &Token {
                                     span: boundaries,
                                     data: cap.as_str().to_string(),
                                     kind: Reader::rgb_fg(highlights["keywords"]).to_string(),
+                                    name: "keywords".to_string(),
                                     priority: 0,
                                 },
                                 &mut syntax,
@@ -80,12 +83,13 @@ pub fn highlight(
                         // Locate expressions
                         for cap in exp.captures_iter(row) {
                             let cap = cap.get(cap.len().saturating_sub(1)).unwrap();
-                            let boundaries = bounds(&cap, &row);
+                            let boundaries = bounds(&cap, row);
                             cine(
                                 &Token {
                                     span: boundaries,
                                     data: cap.as_str().to_string(),
                                     kind: Reader::rgb_fg(highlights[name]).to_string(),
+                                    name: name.clone(),
                                     priority: 1,
                                 },
                                 &mut syntax,
@@ -99,7 +103,7 @@ pub fn highlight(
                 for exp in regex {
                     for cap in exp.captures_iter(doc) {
                         let cap = cap.get(cap.len().saturating_sub(1)).unwrap();
-                        let ((start_x, start_y), (end_x, end_y)) = multi_to_single(&doc, &cap);
+                        let ((start_x, start_y), (end_x, end_y)) = multi_to_single(doc, &cap);
                         if start_y == index {
                             cine(
                                 &Token {
@@ -113,30 +117,33 @@ pub fn highlight(
                                     ),
                                     data: row.to_string(),
                                     kind: Reader::rgb_fg(highlights[name]).to_string(),
+                                    name: name.clone(),
                                     priority: 2,
                                 },
                                 &mut syntax,
-                            )
+                            );
                         } else if end_y == index {
                             cine(
                                 &Token {
                                     span: (0, end_x),
                                     data: row.to_string(),
                                     kind: Reader::rgb_fg(highlights[name]).to_string(),
+                                    name: name.clone(),
                                     priority: 2,
                                 },
                                 &mut syntax,
-                            )
+                            );
                         } else if (start_y..=end_y).contains(&index) {
                             cine(
                                 &Token {
                                     span: (0, UnicodeWidthStr::width(row)),
                                     data: row.to_string(),
                                     kind: Reader::rgb_fg(highlights[name]).to_string(),
+                                    name: name.clone(),
                                     priority: 2,
                                 },
                                 &mut syntax,
-                            )
+                            );
                         }
                     }
                 }
@@ -0,0 +1,579 @@
+// Input.rs - Reading keys, the mouse and pasted text from the terminal
+use crossterm::event::{Event as InputEvent, KeyCode, KeyEvent, KeyModifiers};
+#[cfg(not(unix))]
+use {crossterm::event, std::collections::VecDeque, std::io, std::time::Duration};
+#[cfg(unix)]
+use {
+    crossterm::event::{MouseButton, MouseEvent},
+    crossterm::terminal,
+    std::collections::VecDeque,
+    std::convert::TryFrom,
+    std::env,
+    std::fs::{File, OpenOptions},
+    std::io::{self, Read},
+    std::os::unix::io::{AsRawFd, FromRawFd},
+    std::ptr,
+    std::sync::atomic::{AtomicI32, Ordering},
+    std::time::Duration,
+    term::terminfo::TermInfo,
+};
+
+// Without markers from the terminal, more typed keys than this arriving at once are a paste
+const BURST: usize = 16;
+
+// Terminals known to mark pasted text, by the start of $TERM
+#[cfg(unix)]
+const BRACKETED_TERMS: [&str; 9] = [
+    "xterm",
+    "tmux",
+    "rxvt",
+    "alacritty",
+    "kitty",
+    "foot",
+    "wezterm",
+    "st",
+    "vte",
+];
+
+// What the terminal marks the end of pasted text with, the start being ESC [ 200 ~
+#[cfg(unix)]
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+// Where the signal handler says the terminal changed size, as it can't do much else
+#[cfg(unix)]
+static RESIZED: AtomicI32 = AtomicI32::new(-1);
+
+// Something that came from the terminal
+#[derive(Debug, Clone)]
+pub enum Input {
+    Event(InputEvent), // A key, the mouse or the terminal changing size
+    Paste(String),     // Text pasted in all at once
+}
+
+pub fn typed(key: KeyEvent) -> Option<char> {
+    // The character a key puts into the text, if it is one that types
+    match (key.code, key.modifiers) {
+        (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => Some(c),
+        (KeyCode::Enter, KeyModifiers::NONE) => Some('\n'),
+        (KeyCode::Tab, KeyModifiers::NONE) => Some('\t'),
+        _ => None,
+    }
+}
+
+pub fn keys(text: &str) -> impl Iterator<Item = InputEvent> + '_ {
+    // The keys that would type out some text
+    text.chars().map(|c| {
+        InputEvent::Key(match c {
+            '\n' => KeyCode::Enter.into(),
+            '\t' => KeyCode::Tab.into(),
+            c if c.is_uppercase() => KeyEvent::new(KeyCode::Char(c), KeyModifiers::SHIFT),
+            c => KeyCode::Char(c).into(),
+        })
+    })
+}
+
+fn burst(events: &[InputEvent]) -> Option<String> {
+    // Take keys that all arrived at once as pasted text, when there are more than would
+    // be typed that quickly and they aren't just one key repeating
+    if events.len() <= BURST || events.iter().all(|event| *event == events[0]) {
+        return None;
+    }
+    events
+        .iter()
+        .map(|event| match event {
+            InputEvent::Key(key) => typed(*key),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn bracketed() -> bool {
+    // Work out whether the terminal marks pasted text, which terminfo calls BE
+    if TermInfo::from_env().is_ok_and(|info| info.strings.contains_key("BE")) {
+        return true;
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    BRACKETED_TERMS.iter().any(|known| term.starts_with(known))
+        || env::var("TERM_PROGRAM").is_ok()
+        || env::var("VTE_VERSION").is_ok()
+}
+
+#[cfg(unix)]
+extern "C" fn resized(_: libc::c_int) {
+    // Wake up whatever is waiting for input, to pick up the new size
+    let pipe = RESIZED.load(Ordering::SeqCst);
+    if pipe >= 0 {
+        unsafe {
+            libc::write(pipe, [0_u8].as_ptr().cast(), 1);
+        }
+    }
+}
+
+// What a run of bytes from the terminal turned out to be
+#[cfg(unix)]
+enum Step {
+    Wait,              // Part of something, more bytes are needed
+    Skip,              // Nothing Ox knows, to be thrown away
+    Event(InputEvent), // A whole key or mouse event
+    Paste,             // The start of pasted text
+}
+
+#[cfg(unix)]
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Step {
+    Step::Event(InputEvent::Key(KeyEvent::new(code, modifiers)))
+}
+
+#[cfg(unix)]
+fn parse(buffer: &[u8], more: bool) -> Step {
+    // Turn the bytes of one key or mouse event into it, the same way crossterm does
+    match buffer {
+        [] | [b'\x1b', b'O'] => Step::Wait,
+        [b'\x1b'] if more => Step::Wait,
+        [b'\x1b'] | [b'\x1b', b'\x1b', ..] => key(KeyCode::Esc, KeyModifiers::NONE),
+        [b'\x1b', b'O', c @ b'P'..=b'S', ..] => key(KeyCode::F(1 + c - b'P'), KeyModifiers::NONE),
+        [b'\x1b', b'O', ..] => Step::Skip,
+        [b'\x1b', b'[', ..] => parse_csi(buffer),
+        [b'\x1b', rest @ ..] => parse_char(rest, |c| key(KeyCode::Char(c), KeyModifiers::ALT)),
+        [b'\r'] => key(KeyCode::Enter, KeyModifiers::NONE),
+        [b'\t'] => key(KeyCode::Tab, KeyModifiers::NONE),
+        [b'\x7f'] => key(KeyCode::Backspace, KeyModifiers::NONE),
+        [c @ b'\x01'..=b'\x1a'] => key(
+            KeyCode::Char((c - 0x1 + b'a') as char),
+            KeyModifiers::CONTROL,
+        ),
+        [c @ b'\x1c'..=b'\x1f'] => key(
+            KeyCode::Char((c - 0x1c + b'4') as char),
+            KeyModifiers::CONTROL,
+        ),
+        [b'\0'] => key(KeyCode::Char(' '), KeyModifiers::CONTROL),
+        _ => parse_char(buffer, |c| {
+            let shift = if c.is_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            key(KeyCode::Char(c), shift)
+        }),
+    }
+}
+
+#[cfg(unix)]
+fn parse_char(buffer: &[u8], key: impl Fn(char) -> Step) -> Step {
+    // Read a character, which may take up to four bytes
+    if let Ok(text) = std::str::from_utf8(buffer) {
+        return text.chars().next().map_or(Step::Skip, key);
+    }
+    let needed = match buffer[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Step::Skip,
+    };
+    if buffer.len() < needed
+        && buffer[1..]
+            .iter()
+            .all(|byte| byte & 0b1100_0000 == 0b1000_0000)
+    {
+        Step::Wait
+    } else {
+        Step::Skip
+    }
+}
+
+#[cfg(unix)]
+fn numbers(text: &[u8]) -> Vec<u16> {
+    // The numbers of a control sequence, separated by semicolons
+    String::from_utf8_lossy(text)
+        .split(';')
+        .map_while(|number| number.parse().ok())
+        .collect()
+}
+
+#[cfg(unix)]
+fn modifiers(mask: u16) -> KeyModifiers {
+    // The modifiers that keys with them pass as one more than a mask
+    let mask = mask.saturating_sub(1);
+    let mut modifiers = KeyModifiers::NONE;
+    if mask & 1 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if mask & 2 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if mask & 4 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
+
+#[cfg(unix)]
+fn mouse_modifiers(button: u16) -> KeyModifiers {
+    // The modifiers held with the mouse, which are kept in the bits of the button
+    let mut modifiers = KeyModifiers::NONE;
+    if button & 0b0000_0100 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if button & 0b0000_1000 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if button & 0b0001_0000 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
+
+#[cfg(unix)]
+fn parse_csi(buffer: &[u8]) -> Step {
+    // Read a control sequence, which starts with ESC [
+    let Some(&kind) = buffer.get(2) else {
+        return Step::Wait;
+    };
+    let code = match kind {
+        b'[' => {
+            return match buffer.get(3) {
+                None => Step::Wait,
+                Some(c @ b'A'..=b'E') => key(KeyCode::F(1 + c - b'A'), KeyModifiers::NONE),
+                Some(_) => Step::Skip,
+            }
+        }
+        b'D' => KeyCode::Left,
+        b'C' => KeyCode::Right,
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'Z' => return key(KeyCode::BackTab, KeyModifiers::SHIFT),
+        b'M' => return parse_x10_mouse(buffer),
+        b'<' => return parse_sgr_mouse(buffer),
+        b'0'..=b'9' => {
+            // Numbered sequences go on until a byte between @ and ~
+            let last = buffer[buffer.len() - 1];
+            if buffer.len() == 3 || !(64..=126).contains(&last) {
+                return Step::Wait;
+            }
+            let numbers = numbers(&buffer[2..buffer.len() - 1]);
+            return match last {
+                b'M' => parse_rxvt_mouse(&numbers),
+                b'~' => parse_special(&numbers),
+                b'R' => Step::Skip,
+                _ => parse_modified(&numbers, last),
+            };
+        }
+        _ => return Step::Skip,
+    };
+    key(code, KeyModifiers::NONE)
+}
+
+#[cfg(unix)]
+fn parse_special(numbers: &[u16]) -> Step {
+    // Read keys that end in ~, like ESC [ 3 ~ for delete, and the start of a paste
+    let Some(&first) = numbers.first() else {
+        return Step::Skip;
+    };
+    let code = match first {
+        1 | 7 => KeyCode::Home,
+        2 => KeyCode::Insert,
+        3 => KeyCode::Delete,
+        4 | 8 => KeyCode::End,
+        5 => KeyCode::PageUp,
+        6 => KeyCode::PageDown,
+        v @ 11..=15 => KeyCode::F((v - 10) as u8),
+        v @ 17..=21 => KeyCode::F((v - 11) as u8),
+        v @ 23..=24 => KeyCode::F((v - 12) as u8),
+        200 => return Step::Paste,
+        _ => return Step::Skip,
+    };
+    key(
+        code,
+        numbers.get(1).map_or(KeyModifiers::NONE, |&m| modifiers(m)),
+    )
+}
+
+#[cfg(unix)]
+fn parse_modified(numbers: &[u16], last: u8) -> Step {
+    // Read keys held with modifiers, like ESC [ 1 ; 3 A for alt and up
+    let code = match last {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'F' => KeyCode::End,
+        b'H' => KeyCode::Home,
+        b'P' => KeyCode::F(1),
+        b'Q' => KeyCode::F(2),
+        b'R' => KeyCode::F(3),
+        b'S' => KeyCode::F(4),
+        _ => return Step::Skip,
+    };
+    key(code, modifiers(numbers.last().copied().unwrap_or(1)))
+}
+
+#[cfg(unix)]
+fn parse_x10_mouse(buffer: &[u8]) -> Step {
+    // Read the oldest mouse encoding, ESC [ M and a byte each for the button, x and y
+    if buffer.len() < 6 {
+        return Step::Wait;
+    }
+    let button = u16::from(buffer[3].saturating_sub(32));
+    let x = u16::from(buffer[4].saturating_sub(32)).saturating_sub(1);
+    let y = u16::from(buffer[5].saturating_sub(32)).saturating_sub(1);
+    let modifiers = mouse_modifiers(button);
+    let scroll = button & 0b0100_0000 != 0;
+    Step::Event(InputEvent::Mouse(match (button & 0b0000_0011, scroll) {
+        (0, true) => MouseEvent::ScrollUp(x, y, modifiers),
+        (1, true) => MouseEvent::ScrollDown(x, y, modifiers),
+        (0, false) => MouseEvent::Down(MouseButton::Left, x, y, modifiers),
+        (1, false) => MouseEvent::Down(MouseButton::Middle, x, y, modifiers),
+        (2, _) => MouseEvent::Down(MouseButton::Right, x, y, modifiers),
+        _ => MouseEvent::Up(MouseButton::Left, x, y, modifiers),
+    }))
+}
+
+#[cfg(unix)]
+fn parse_rxvt_mouse(numbers: &[u16]) -> Step {
+    // Read the mouse as rxvt sends it, ESC [ button ; x ; y M
+    let [button, x, y] = numbers else {
+        return Step::Skip;
+    };
+    let (x, y) = (x.saturating_sub(1), y.saturating_sub(1));
+    let modifiers = mouse_modifiers(*button);
+    if button & 0b0110_0000 == 0b0110_0000 {
+        return Step::Event(InputEvent::Mouse(if button & 1 == 0 {
+            MouseEvent::ScrollUp(x, y, modifiers)
+        } else {
+            MouseEvent::ScrollDown(x, y, modifiers)
+        }));
+    }
+    let drag = button & 0b0100_0000 != 0;
+    let pressed = match button & 0b0000_0011 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        2 => MouseButton::Right,
+        _ if !drag => {
+            return Step::Event(InputEvent::Mouse(MouseEvent::Up(
+                MouseButton::Left,
+                x,
+                y,
+                modifiers,
+            )))
+        }
+        _ => return Step::Skip,
+    };
+    Step::Event(InputEvent::Mouse(if drag {
+        MouseEvent::Drag(pressed, x, y, modifiers)
+    } else {
+        MouseEvent::Down(pressed, x, y, modifiers)
+    }))
+}
+
+#[cfg(unix)]
+fn parse_sgr_mouse(buffer: &[u8]) -> Step {
+    // Read the mouse as most terminals send it, ESC [ < button ; x ; y and M or m on release
+    let last = buffer[buffer.len() - 1];
+    if last != b'M' && last != b'm' {
+        return Step::Wait;
+    }
+    let [button, x, y] = numbers(&buffer[3..buffer.len() - 1])[..] else {
+        return Step::Skip;
+    };
+    let (x, y) = (x.saturating_sub(1), y.saturating_sub(1));
+    let modifiers = mouse_modifiers(button);
+    if button & 0b0100_0000 != 0 {
+        return Step::Event(InputEvent::Mouse(if button & 1 == 0 {
+            MouseEvent::ScrollUp(x, y, modifiers)
+        } else {
+            MouseEvent::ScrollDown(x, y, modifiers)
+        }));
+    }
+    let pressed = match button & 0b0000_0011 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        2 => MouseButton::Right,
+        _ => return Step::Skip,
+    };
+    Step::Event(InputEvent::Mouse(if last == b'm' {
+        MouseEvent::Up(pressed, x, y, modifiers)
+    } else if button & 0b0010_0000 != 0 {
+        MouseEvent::Drag(pressed, x, y, modifiers)
+    } else {
+        MouseEvent::Down(pressed, x, y, modifiers)
+    }))
+}
+
+// Reads from the terminal itself, as crossterm throws away the marks around pasted text
+#[cfg(unix)]
+pub struct Reader {
+    tty: File,               // The terminal, even when text is piped into standard input
+    wake: File,              // The end of the pipe the signal handler writes to
+    sequence: Vec<u8>,       // The bytes of the key or mouse event being read
+    paste: Option<Vec<u8>>,  // The text being pasted, when inside a paste
+    inputs: VecDeque<Input>, // What has been read and not yet handed out
+    bracketed: bool,         // Whether the terminal marks pastes, so bursts are typed
+}
+
+#[cfg(unix)]
+impl Reader {
+    pub fn new() -> io::Result<Self> {
+        // Open the terminal, and hear about it changing size through a pipe
+        let tty = OpenOptions::new().read(true).open("/dev/tty")?;
+        let mut pipe = [0; 2];
+        if unsafe { libc::pipe(pipe.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        for end in pipe {
+            unsafe {
+                libc::fcntl(end, libc::F_SETFL, libc::O_NONBLOCK);
+            }
+        }
+        // The writing end is left open for as long as the editor runs
+        let wake = unsafe { File::from_raw_fd(pipe[0]) };
+        RESIZED.store(pipe[1], Ordering::SeqCst);
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = resized as *const () as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigaction(libc::SIGWINCH, ptr::addr_of!(action), ptr::null_mut());
+        }
+        Ok(Self {
+            tty,
+            wake,
+            sequence: vec![],
+            paste: None,
+            inputs: VecDeque::new(),
+            bracketed: bracketed(),
+        })
+    }
+    pub fn poll(&mut self, wait: Duration) -> bool {
+        // Wait for input for a while, giving whether any came
+        if self.inputs.is_empty() {
+            let wait = wait.as_micros().div_ceil(1000);
+            self.fill(i32::try_from(wait).unwrap_or(i32::MAX));
+        }
+        !self.inputs.is_empty()
+    }
+    pub fn read(&mut self) -> Option<Input> {
+        // Wait for a key, the mouse, the terminal changing size or pasted text
+        loop {
+            if let Some(input) = self.inputs.pop_front() {
+                return Some(input);
+            }
+            if !self.fill(-1) {
+                return None;
+            }
+        }
+    }
+    fn fill(&mut self, wait: i32) -> bool {
+        // Wait up to some milliseconds for the terminal, reading what it sent,
+        // giving whether it can still be read from
+        let mut fds = [self.tty.as_raw_fd(), self.wake.as_raw_fd()].map(|fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        if unsafe { libc::poll(fds.as_mut_ptr(), 2, wait) } < 0 {
+            return io::Error::last_os_error().kind() == io::ErrorKind::Interrupted;
+        }
+        if fds[1].revents & libc::POLLIN != 0 {
+            let _ = self.wake.read(&mut [0; 64]);
+            if let Ok((width, height)) = terminal::size() {
+                self.inputs
+                    .push_back(Input::Event(InputEvent::Resize(width, height)));
+            }
+        }
+        if fds[0].revents & libc::POLLIN != 0 {
+            let mut chunk = [0; 1024];
+            match self.tty.read(&mut chunk) {
+                Ok(0) => return false,
+                Ok(read) => self.take(&chunk[..read], read == chunk.len()),
+                Err(error) => return error.kind() == io::ErrorKind::Interrupted,
+            }
+        } else if fds[0].revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+            return false;
+        }
+        true
+    }
+    fn take(&mut self, chunk: &[u8], full: bool) {
+        // Turn what was read into keys, mouse events and pasted text
+        let start = self.inputs.len();
+        for (index, &byte) in chunk.iter().enumerate() {
+            if let Some(paste) = &mut self.paste {
+                paste.push(byte);
+                if paste.ends_with(PASTE_END) {
+                    paste.truncate(paste.len() - PASTE_END.len());
+                    let text = String::from_utf8_lossy(paste).replace("\r\n", "\n");
+                    self.inputs
+                        .push_back(Input::Paste(text.replace('\r', "\n")));
+                    self.paste = None;
+                }
+                continue;
+            }
+            self.sequence.push(byte);
+            match parse(&self.sequence, full || index + 1 < chunk.len()) {
+                Step::Wait => continue,
+                Step::Skip => (),
+                Step::Event(event) => self.inputs.push_back(Input::Event(event)),
+                Step::Paste => {
+                    // Once the terminal is seen marking a paste, bursts of keys are typed
+                    self.bracketed = true;
+                    self.paste = Some(vec![]);
+                }
+            }
+            self.sequence.clear();
+        }
+        if !self.bracketed {
+            let events: Vec<InputEvent> = self
+                .inputs
+                .range(start..)
+                .filter_map(|input| match input {
+                    Input::Event(event) => Some(*event),
+                    Input::Paste(_) => None,
+                })
+                .collect();
+            if let Some(text) = burst(&events) {
+                self.inputs.truncate(start);
+                self.inputs.push_back(Input::Paste(text));
+            }
+        }
+    }
+}
+
+// Reads through crossterm, where the terminal can't mark pasted text
+#[cfg(not(unix))]
+pub struct Reader {
+    events: VecDeque<InputEvent>, // Keys read along with others that turned out not to be a paste
+}
+
+#[cfg(not(unix))]
+impl Reader {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            events: VecDeque::new(),
+        })
+    }
+    pub fn poll(&mut self, wait: Duration) -> bool {
+        !self.events.is_empty() || matches!(event::poll(wait), Ok(true))
+    }
+    pub fn read(&mut self) -> Option<Input> {
+        // Read a key, taking the keys waiting along with it as a paste when there are enough
+        if let Some(event) = self.events.pop_front() {
+            return Some(Input::Event(event));
+        }
+        let mut events = vec![event::read().ok()?];
+        while matches!(events.last(), Some(InputEvent::Key(key)) if typed(*key).is_some())
+            && matches!(event::poll(Duration::ZERO), Ok(true))
+        {
+            let Ok(event) = event::read() else {
+                break;
+            };
+            events.push(event);
+        }
+        if let Some(text) = burst(&events) {
+            return Some(Input::Paste(text));
+        }
+        let first = events.remove(0);
+        self.events.extend(events);
+        Some(Input::Event(first))
+    }
+}
@@ -0,0 +1,93 @@
+// Lazy.rs - Reading files too large to hold at once a window of lines at a time
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+// How many lines are held at once
+pub const WINDOW: usize = 10_000;
+// How many lines apart the places lines start at are remembered
+const STEP: usize = 1_000;
+// How much of the start of a large file is looked at to tell what it holds
+const SAMPLE: u64 = 1 << 16;
+
+pub fn is_large(path: &str, megabytes: u64) -> bool {
+    // Whether a file is over the size above which it is read a part at a time, 0 being no size
+    let limit = megabytes.saturating_mul(1 << 20);
+    limit > 0 && fs::metadata(path).is_ok_and(|meta| meta.len() > limit)
+}
+
+pub fn sample(path: &str) -> io::Result<Vec<u8>> {
+    // The start of a file, which is all that is looked at before reading it a part at a time
+    let mut bytes = vec![];
+    File::open(path)?.take(SAMPLE).read_to_end(&mut bytes)?;
+    // A character cut off at the end doesn't make it look like something other than UTF-8
+    if let Err(error) = std::str::from_utf8(&bytes) {
+        if error.error_len().is_none() {
+            bytes.truncate(error.valid_up_to());
+        }
+    }
+    Ok(bytes)
+}
+
+// A file shown a window of lines at a time, found by where every so many lines start
+pub struct Lazy {
+    path: String,     // The file the lines come from
+    index: Vec<u64>,  // Where every STEP-th line starts in the file, as far as it was read
+    pub start: usize, // The line the window starts at
+    pub byte: u64,    // Where that line starts in the file
+    pub end: bool,    // Whether the window reaches the end of the file
+    pub length: u64,  // How large the file is
+}
+
+impl Lazy {
+    pub fn new(path: &str) -> io::Result<Self> {
+        // Get ready to read a file, without reading any of it yet
+        Ok(Self {
+            path: path.to_string(),
+            index: vec![0],
+            start: 0,
+            byte: 0,
+            end: false,
+            length: File::open(path)?.metadata()?.len(),
+        })
+    }
+    pub fn read(&mut self, start: usize) -> io::Result<Vec<String>> {
+        // Read the window of lines from a line, or the last lines when the file ends before it
+        let step = cmp::min(start / STEP, self.index.len() - 1);
+        let mut file = BufReader::new(File::open(&self.path)?);
+        let mut byte = file.seek(SeekFrom::Start(self.index[step]))?;
+        let mut line = step * STEP;
+        let mut lines = vec![];
+        let mut buffer = vec![];
+        while lines.len() < WINDOW {
+            if line == self.index.len() * STEP {
+                self.index.push(byte);
+            }
+            if line == start {
+                self.byte = byte;
+            }
+            buffer.clear();
+            let read = file.read_until(b'\n', &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            if line >= start {
+                let text = String::from_utf8_lossy(&buffer);
+                lines.push(text.trim_end_matches(['\n', '\r']).to_string());
+            }
+            byte += read as u64;
+            line += 1;
+        }
+        // When the file ends early the window is filled from the lines before instead
+        let last = line.saturating_sub(WINDOW);
+        if lines.len() < WINDOW && last < start {
+            return self.read(last);
+        }
+        self.start = start;
+        self.end = lines.len() < WINDOW || file.fill_buf()?.is_empty();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Ok(lines)
+    }
+}
@@ -0,0 +1,346 @@
+// Lsp.rs - Talking to language servers for diagnostics and completion
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+// The id of the request that starts every session
+const INITIALIZE: u64 = 0;
+
+// A problem that a language server found in a document
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub start: (usize, usize), // The line and UTF-16 offset the problem starts at
+    pub end: (usize, usize),   // The line and UTF-16 offset just after it
+    pub error: bool,           // Whether it is an error rather than a warning or a hint
+    pub message: String,       // What the server said about it
+}
+
+pub struct Server {
+    child: Child,                                      // The running server
+    stdin: ChildStdin,                                 // Where messages to the server go
+    receiver: Receiver<Value>,                         // Messages coming in from the reading thread
+    next_id: u64,                                      // The id to give the next request
+    ready: bool,                                       // Whether the server has been initialised
+    queue: Vec<Value>,                                 // Messages held back until it has
+    responses: HashMap<u64, Value>,                    // Answers to requests that nobody took yet
+    pub alive: bool,                                   // Whether the server is still running
+    pub diagnostics: HashMap<String, Vec<Diagnostic>>, // The latest problems in each document
+}
+
+impl Server {
+    pub fn start(command: &str) -> io::Result<Self> {
+        // Run a server and start the session, rooted at the working directory
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "No command given"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || read(stdout, &sender));
+        let mut server = Self {
+            child,
+            stdin,
+            receiver,
+            next_id: INITIALIZE + 1,
+            ready: false,
+            queue: vec![],
+            responses: HashMap::new(),
+            alive: true,
+            diagnostics: HashMap::new(),
+        };
+        let root = env::current_dir()?;
+        server.write(&json!({
+            "jsonrpc": "2.0",
+            "id": INITIALIZE,
+            "method": "initialize",
+            "params": {
+                "processId": std::process::id(),
+                "rootUri": uri(&root.to_string_lossy()),
+                "clientInfo": {"name": "ox", "version": crate::VERSION},
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": {"didSave": true},
+                        "completion": {"completionItem": {"snippetSupport": false}},
+                        "publishDiagnostics": {},
+                    },
+                },
+            },
+        }))?;
+        Ok(server)
+    }
+    fn write(&mut self, message: &Value) -> io::Result<()> {
+        // Send a message with the header that says how long it is
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()
+    }
+    fn send(&mut self, message: Value) {
+        // Send a message, or hold on to it while the server is starting
+        if !self.ready {
+            self.queue.push(message);
+        } else if self.write(&message).is_err() {
+            self.alive = false;
+        }
+    }
+    fn notify(&mut self, method: &str, params: &Value) {
+        // Send a message that doesn't get an answer
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+    fn request(&mut self, method: &str, params: &Value) -> u64 {
+        // Send a message that gets an answer, returning the id to look for
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+        id
+    }
+    pub fn did_open(&mut self, uri: &str, language: &str, version: usize, text: &str) {
+        // Tell the server a document was opened
+        self.notify(
+            "textDocument/didOpen",
+            &json!({"textDocument": {
+                "uri": uri,
+                "languageId": language,
+                "version": version,
+                "text": text,
+            }}),
+        );
+    }
+    pub fn did_change(&mut self, uri: &str, version: usize, text: &str) {
+        // Hand the server the whole of a changed document
+        self.notify(
+            "textDocument/didChange",
+            &json!({
+                "textDocument": {"uri": uri, "version": version},
+                "contentChanges": [{"text": text}],
+            }),
+        );
+    }
+    pub fn did_save(&mut self, uri: &str) {
+        // Tell the server a document was saved, which some only check on
+        self.notify(
+            "textDocument/didSave",
+            &json!({"textDocument": {"uri": uri}}),
+        );
+    }
+    pub fn did_close(&mut self, uri: &str) {
+        // Tell the server a document was closed
+        self.diagnostics.remove(uri);
+        self.notify(
+            "textDocument/didClose",
+            &json!({"textDocument": {"uri": uri}}),
+        );
+    }
+    pub fn complete(&mut self, uri: &str, (line, character): (usize, usize)) -> Vec<String> {
+        // Ask for the completions at a position, giving up if they take too long
+        let id = self.request(
+            "textDocument/completion",
+            &json!({
+                "textDocument": {"uri": uri},
+                "position": {"line": line, "character": character},
+            }),
+        );
+        let result = self.wait(id, Duration::from_secs(2)).unwrap_or(Value::Null);
+        let items = result
+            .get("items")
+            .or(Some(&result))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let mut completions: Vec<(String, String)> = items
+            .iter()
+            .filter_map(|item| {
+                let text = item
+                    .pointer("/textEdit/newText")
+                    .or_else(|| item.get("insertText"))
+                    .or_else(|| item.get("label"))?
+                    .as_str()?;
+                let order = item
+                    .get("sortText")
+                    .or_else(|| item.get("label"))
+                    .and_then(Value::as_str)
+                    .unwrap_or(text);
+                Some((order.to_string(), text.to_string()))
+            })
+            .collect();
+        completions.sort();
+        completions.dedup_by(|a, b| a.1 == b.1);
+        completions.into_iter().map(|(_, text)| text).collect()
+    }
+    fn wait(&mut self, id: u64, timeout: Duration) -> Option<Value> {
+        // Handle messages until the answer to a request comes in
+        let deadline = Instant::now() + timeout;
+        while self.alive {
+            if let Some(response) = self.responses.remove(&id) {
+                return response.get("result").cloned();
+            }
+            match self
+                .receiver
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(message) => {
+                    self.handle(message);
+                }
+                Err(RecvTimeoutError::Timeout) => return None,
+                Err(RecvTimeoutError::Disconnected) => self.alive = false,
+            }
+        }
+        None
+    }
+    pub fn receive(&mut self) -> bool {
+        // Handle the messages that came in since last time, returning whether diagnostics changed
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => changed |= self.handle(message),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.alive = false;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+    fn handle(&mut self, message: Value) -> bool {
+        // Act on a message from the server, returning whether diagnostics changed
+        let method = message.get("method").and_then(Value::as_str);
+        match (method, message.get("id")) {
+            (Some("textDocument/publishDiagnostics"), None) => {
+                let params = &message["params"];
+                if let Some(uri) = params["uri"].as_str() {
+                    let diagnostics = params["diagnostics"]
+                        .as_array()
+                        .map(|list| list.iter().filter_map(diagnostic).collect())
+                        .unwrap_or_default();
+                    self.diagnostics.insert(uri.to_string(), diagnostics);
+                    return true;
+                }
+            }
+            (Some(_), Some(id)) => {
+                // Requests from the server aren't supported, but still need an answer
+                let reply = json!({"jsonrpc": "2.0", "id": id, "result": null});
+                self.send(reply);
+            }
+            (None, Some(id)) if id.as_u64() == Some(INITIALIZE) => {
+                // Everything that was held back can go now the server is ready
+                self.ready = true;
+                self.notify("initialized", &json!({}));
+                for message in std::mem::take(&mut self.queue) {
+                    self.send(message);
+                }
+            }
+            (None, Some(id)) => {
+                if let Some(id) = id.as_u64() {
+                    self.responses.insert(id, message);
+                }
+            }
+            _ => (),
+        }
+        false
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        // Ask the server to shut down, and stop it if it doesn't
+        if self.alive && self.ready {
+            let id = self.request("shutdown", &Value::Null);
+            self.wait(id, Duration::from_millis(500));
+            self.notify("exit", &Value::Null);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn read(stdout: ChildStdout, sender: &Sender<Value>) -> Option<()> {
+    // Pass on each message from the server until it closes its output
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            } else if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().ok();
+            }
+        }
+        let mut body = vec![0; length?];
+        reader.read_exact(&mut body).ok()?;
+        if let Ok(message) = serde_json::from_slice(&body) {
+            sender.send(message).ok()?;
+        }
+    }
+}
+
+fn diagnostic(value: &Value) -> Option<Diagnostic> {
+    // Read a diagnostic, counting hints and information along with warnings
+    let position = |at: &str| -> Option<(usize, usize)> {
+        let position = value.pointer(&format!("/range/{at}"))?;
+        Some((
+            usize::try_from(position["line"].as_u64()?).ok()?,
+            usize::try_from(position["character"].as_u64()?).ok()?,
+        ))
+    };
+    Some(Diagnostic {
+        start: position("start")?,
+        end: position("end")?,
+        error: value["severity"]
+            .as_u64()
+            .is_none_or(|severity| severity == 1),
+        message: value["message"].as_str()?.lines().next()?.to_string(),
+    })
+}
+
+pub fn uri(path: &str) -> String {
+    // Turn a path into the address servers know files by
+    let path = fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            let _ = write!(uri, "%{byte:02X}");
+        }
+    }
+    uri
+}
+
+pub fn to_column(line: &str, units: usize) -> usize {
+    // Find the display column of a UTF-16 offset into a line
+    let mut count = 0;
+    for (index, ch) in line.char_indices() {
+        if count >= units {
+            return line[..index].width();
+        }
+        count += ch.len_utf16();
+    }
+    line.width()
+}
+
+pub fn to_units(line: &str) -> usize {
+    // Count the UTF-16 code units in the part of a line before a position
+    line.encode_utf16().count()
+}
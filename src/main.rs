@@ -20,62 +20,139 @@
 */
 
 // Bring in the external modules
+mod clipboard;
+mod compress;
 mod config;
+mod console;
+mod cursor;
 mod document;
 mod editor;
+mod editorconfig;
+mod encoding;
+mod explorer;
+mod export;
+mod filetype;
+mod finder;
+mod git;
+mod hex;
 mod highlight;
+mod input;
+mod lazy;
+mod lsp;
+mod markdown;
+mod mode;
+mod modeline;
 mod oxa;
+mod plugin;
+mod prompt;
+mod quickfix;
+mod remote;
 mod row;
+mod scheme;
+mod spell;
+mod ssh;
+mod tags;
 mod terminal;
 mod undo;
 mod util;
 
 use clap::{App, Arg};
+use cursor::{Direction, Position, Size};
 use directories::BaseDirs;
 use document::Document;
-use editor::{Direction, Editor, Position};
-use oxa::Variable;
+use editor::Editor;
+use oxa::{Transform, Variable};
 use row::Row;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{env, panic};
-use terminal::{Size, Terminal};
-use undo::{Event, EventStack};
+use terminal::{Crossterm, Terminal};
+use undo::{Event, EventStack, History};
 
-// Create log macro
+// Create log macro, where messages without a level are info
 #[macro_export]
 macro_rules! log {
     ($type:literal, $msg:expr) => {
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(std::env::temp_dir().join("ox.log"));
-        if let Ok(mut log) = file {
-            writeln!(log, "{}: {}", $type, $msg).unwrap();
-        } else {
-            panic!("{:?}", file);
+        $crate::log!(info, $type, $msg)
+    };
+    ($level:ident, $type:literal, $msg:expr) => {
+        if $crate::logging(stringify!($level)) {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(std::env::temp_dir().join("ox.log"));
+            if let Ok(mut log) = file {
+                writeln!(log, "[{}] {}: {}", stringify!($level), $type, $msg).unwrap();
+            } else {
+                panic!("{:?}", file);
+            }
         }
     };
 }
 
+// The levels of the log, each taking in the ones before it
+const LOG_LEVELS: [&str; 3] = ["error", "info", "debug"];
+
+// How many of the levels are written to the log, none unless asked for
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+fn set_log_level(level: &str) {
+    // Start writing the messages of a level and the ones before it to the log
+    let level = LOG_LEVELS
+        .iter()
+        .position(|name| *name == level)
+        .map_or(0, |index| index + 1);
+    LOG_LEVEL.store(level, Ordering::Relaxed);
+}
+
+fn logging(level: &str) -> bool {
+    // Whether messages of a level go into the log
+    LOG_LEVELS
+        .iter()
+        .position(|name| *name == level)
+        .is_some_and(|index| index < LOG_LEVEL.load(Ordering::Relaxed))
+}
+
 // Get the current version of Ox
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+#[allow(clippy::too_many_lines)] // Argument parsing and every startup mode
 fn main() {
-    log!("Ox started", "Ox has just been started");
     // Set up panic hook in case of unexpected crash
     panic::set_hook(Box::new(|e| {
-        // Reenter canonical mode
-        Terminal::exit();
         // Set hook to log crash reason
-        log!("Unexpected panic", e);
+        log!(error, "Unexpected panic", e);
+        // Threads working in the background can go without taking the editor down with them
+        if std::thread::current().name() != Some("main") {
+            return;
+        }
+        // Reenter canonical mode, with the cursor and mouse back to normal
+        terminal::leave();
         // Print panic info
-        eprintln!("{}", e);
+        eprintln!("Ox crashed, {e}");
+        eprintln!("Opening the files again offers to recover what their swap files kept");
     }));
     // Attempt to start an editor instance
     let config_dir = load_config().unwrap_or_else(|| " ~/.config/ox/ox.ron".to_string());
+    let args = cli(&config_dir).get_matches();
+    // Set up terminal, unless a script is to be run over the files instead
+    let term = if args.is_present("batch") {
+        Terminal::headless()
+    } else {
+        // Fire up the editor, ensuring that no start up problems occured
+        let Ok(backend) = Crossterm::new() else {
+            return;
+        };
+        Terminal::new(Box::new(backend))
+    };
+    Editor::new(&args, term).run();
+}
+
+#[allow(clippy::too_many_lines)] // Every option is spelled out with its help
+fn cli(config_dir: &str) -> App<'_, '_> {
     // Gather the command line arguments
-    let cli = App::new("Ox")
+    App::new("Ox")
         .version(VERSION)
         .author("Author: Luke <https://github.com/curlpipe>")
         .about("An independent Rust powered text editor")
@@ -84,9 +161,11 @@ fn main() {
                 .multiple(true)
                 .takes_value(true)
                 .help(
-                    r#"The files you wish to edit
+                    r"The files you wish to edit
 You can also provide the line number to jump to by doing this:
-file.txt:100 (This will go to line 100 in file.txt)"#,
+file.txt:100 or file.txt +100 (This will go to line 100 in file.txt)
+A column can follow the line, like file.txt:100:8 or file.txt +100:8
+Use - to edit the text piped into Ox",
                 ),
         )
         .arg(
@@ -97,25 +176,87 @@ file.txt:100 (This will go to line 100 in file.txt)"#,
                 .required(false)
                 .help("Enable read only mode"),
         )
+        .arg(
+            Arg::with_name("view")
+                .short("R")
+                .takes_value(false)
+                .required(false)
+                .help("Open the files read only, like --readonly"),
+        )
+        .arg(
+            Arg::with_name("hex")
+                .long("hex")
+                .takes_value(false)
+                .required(false)
+                .help("Show the files as bytes to view and change"),
+        )
+        .arg(
+            Arg::with_name("diff")
+                .long("diff")
+                .value_names(&["old", "new"])
+                .takes_value(true)
+                .number_of_values(2)
+                .required(false)
+                .conflicts_with("files")
+                .help("Compare two files side by side"),
+        )
+        .arg(
+            Arg::with_name("restore")
+                .long("restore")
+                .takes_value(false)
+                .required(false)
+                .help("Reopen the files that were open when Ox was last closed"),
+        )
+        .arg(
+            Arg::with_name("no-restore")
+                .long("no-restore")
+                .takes_value(false)
+                .required(false)
+                .help("Don't restore cursor positions from the last session"),
+        )
+        .arg(
+            Arg::with_name("batch")
+                .long("batch")
+                .value_name("script")
+                .takes_value(true)
+                .number_of_values(1)
+                .required(false)
+                .conflicts_with("diff")
+                .help("Run the commands of a script over the files without opening the editor"),
+        )
+        .arg(
+            Arg::with_name("log")
+                .long("log")
+                .value_name("level")
+                .takes_value(true)
+                .number_of_values(1)
+                .possible_values(&LOG_LEVELS)
+                .required(false)
+                .help(
+                    "Write what happens to ox.log in the temporary directory, debug being the most",
+                ),
+        )
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .value_name("socket")
+                .takes_value(true)
+                .required(false)
+                .help("Let other programs control Ox through a socket at this path"),
+        )
         .arg(
             Arg::with_name("config")
                 .long("config")
                 .short("c")
+                .value_name("path")
                 .takes_value(true)
-                .default_value(&config_dir)
-                .help("The directory of the config file"),
-        );
-    // Fire up the editor, ensuring that no start up problems occured
-    if let Ok(mut editor) = Editor::new(cli) {
-        editor.run();
-    }
+                .default_value(config_dir)
+                .help("The path of the config file"),
+        )
 }
 
 fn load_config() -> Option<String> {
     // Load the configuration file
     let base_dirs = BaseDirs::new()?;
-    Some(format!(
-        "{}/ox/ox.ron",
-        base_dirs.config_dir().to_str()?.to_string()
-    ))
+    Some(format!("{}/ox/ox.ron", base_dirs.config_dir().to_str()?))
 }
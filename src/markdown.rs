@@ -0,0 +1,287 @@
+// Markdown.rs - Drawing Markdown in the terminal the way it would look formatted
+use crate::config::Reader;
+use crossterm::style::Attribute;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// The colours a preview is drawn in
+pub struct Palette {
+    pub text: (u8, u8, u8),       // Plain text
+    pub background: (u8, u8, u8), // Behind everything
+    pub heading: (u8, u8, u8),    // Headings
+    pub code: (u8, u8, u8),       // Code spans and blocks
+    pub quote: (u8, u8, u8),      // Block quotes and rules
+    pub link: (u8, u8, u8),       // The text of links
+    pub marker: (u8, u8, u8),     // List bullets and numbers
+}
+
+// How a piece of text is drawn
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    colour: Colour,
+}
+
+// Which colour of the palette a piece of text is drawn in
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+enum Colour {
+    #[default]
+    Text,
+    Heading,
+    Code,
+    Quote,
+    Link,
+    Marker,
+}
+
+// A piece of a line, drawn all in one style
+type Span = (String, Style);
+
+fn inline(text: &str, base: Style) -> Vec<Span> {
+    // Split a line into runs of emphasis, code and links
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = vec![];
+    let mut style = base;
+    let mut current = String::new();
+    let mut index = 0;
+    let flush = |current: &mut String, style: Style, spans: &mut Vec<Span>| {
+        if !current.is_empty() {
+            spans.push((std::mem::take(current), style));
+        }
+    };
+    while index < chars.len() {
+        let rest: String = chars[index..].iter().collect();
+        if chars[index] == '`' {
+            // Code is shown as written, emphasis markers and all
+            if let Some(end) = rest[1..].find('`') {
+                flush(&mut current, style, &mut spans);
+                let code = &rest[1..=end];
+                let colour = Colour::Code;
+                spans.push((code.to_string(), Style { colour, ..style }));
+                index += code.chars().count() + 2;
+                continue;
+            }
+        } else if chars[index] == '[' {
+            // Links show their text and leave out where they go
+            if let Some(close) = rest.find("](") {
+                if let Some(end) = rest[close..].find(')') {
+                    flush(&mut current, style, &mut spans);
+                    let link = Style {
+                        underline: true,
+                        colour: Colour::Link,
+                        ..style
+                    };
+                    spans.push((rest[1..close].to_string(), link));
+                    index += rest[..=close + end].chars().count();
+                    continue;
+                }
+            }
+        } else if rest.starts_with("**") || rest.starts_with("__") {
+            flush(&mut current, style, &mut spans);
+            style.bold = !style.bold;
+            index += 2;
+            continue;
+        } else if matches!(chars[index], '*' | '_')
+            && (style.italic || chars.get(index + 1).is_some_and(|c| !c.is_whitespace()))
+            && !(index > 0 && chars[index - 1].is_alphanumeric() && chars[index] == '_')
+        {
+            // Underscores inside words are left alone
+            flush(&mut current, style, &mut spans);
+            style.italic = !style.italic;
+            index += 1;
+            continue;
+        }
+        current.push(chars[index]);
+        index += 1;
+    }
+    flush(&mut current, style, &mut spans);
+    spans
+}
+
+fn wrap(spans: &[Span], width: usize, first: &[Span], rest: &[Span]) -> Vec<Vec<Span>> {
+    // Break styled text into lines that fit, starting them with the prefixes given
+    let lead = |prefix: &[Span]| prefix.iter().map(|(text, _)| text.width()).sum::<usize>();
+    let mut lines = vec![];
+    let mut line = first.to_vec();
+    let mut used = lead(first);
+    let mut start = used;
+    // Words keep the spaces before them, which are dropped where a line breaks
+    let mut words: Vec<Span> = vec![];
+    for (text, style) in spans {
+        let mut word = String::new();
+        for c in text.chars() {
+            if c == ' ' && !word.trim().is_empty() {
+                words.push((std::mem::take(&mut word), *style));
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push((word, *style));
+        }
+    }
+    for (word, style) in words {
+        if used + word.width() > width && used > start {
+            lines.push(std::mem::replace(&mut line, rest.to_vec()));
+            used = lead(rest);
+            start = used;
+        }
+        let word = if used == start && !lines.is_empty() {
+            word.trim_start()
+        } else {
+            &word
+        };
+        // Words too long for a line are cut off at the edge
+        let mut fitted = String::new();
+        for c in word.chars() {
+            let size = c.width().unwrap_or(0);
+            if used + size > width {
+                break;
+            }
+            fitted.push(c);
+            used += size;
+        }
+        line.push((fitted, style));
+    }
+    lines.push(line);
+    lines
+}
+
+fn paint(style: Style, palette: &Palette) -> String {
+    // The escape codes that switch to a style
+    let colour = match style.colour {
+        Colour::Text => palette.text,
+        Colour::Heading => palette.heading,
+        Colour::Code => palette.code,
+        Colour::Quote => palette.quote,
+        Colour::Link => palette.link,
+        Colour::Marker => palette.marker,
+    };
+    let mut codes = format!(
+        "{}{}{}",
+        Attribute::Reset,
+        Reader::rgb_bg(palette.background),
+        Reader::rgb_fg(colour)
+    );
+    for (on, attribute) in [
+        (style.bold, Attribute::Bold),
+        (style.italic, Attribute::Italic),
+        (style.underline, Attribute::Underlined),
+    ] {
+        if on {
+            codes.push_str(&attribute.to_string());
+        }
+    }
+    codes
+}
+
+fn finish(line: &[Span], width: usize, palette: &Palette) -> String {
+    // Draw a laid out line, padded to the width
+    let mut result = String::new();
+    let mut used = 0;
+    for (text, style) in line {
+        result.push_str(&paint(*style, palette));
+        result.push_str(text);
+        used += text.width();
+    }
+    format!(
+        "{}{}{}{}",
+        result,
+        paint(Style::default(), palette),
+        " ".repeat(width.saturating_sub(used)),
+        Attribute::Reset
+    )
+}
+
+fn list_item(line: &str) -> Option<(String, &str)> {
+    // The bullet to show and the text of a list item
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        return Some(("\u{2022}".to_string(), item));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        return Some((line[..=digits].to_string(), &rest[2..]));
+    }
+    None
+}
+
+pub fn render(source: &[&str], width: usize, palette: &Palette) -> Vec<(usize, String)> {
+    // Lay out a document as formatted lines, each with the line of the source it came from
+    let quote = Style {
+        colour: Colour::Quote,
+        ..Style::default()
+    };
+    let mut lines = vec![];
+    let mut fence: Option<&str> = None;
+    for (index, text) in source.iter().enumerate() {
+        let trimmed = text.trim_start();
+        let indent = text.len() - trimmed.len();
+        let laid: Vec<Vec<Span>> = if let Some(opening) = fence {
+            // Code blocks are shown as they are, cut off at the edge
+            if trimmed.starts_with(opening) {
+                fence = None;
+                continue;
+            }
+            let code = Style {
+                colour: Colour::Code,
+                ..Style::default()
+            };
+            let mut used = 2;
+            let text: String = text
+                .replace('\t', "    ")
+                .chars()
+                .take_while(|c| {
+                    used += c.width().unwrap_or(0);
+                    used <= width
+                })
+                .collect();
+            vec![vec![("  ".to_string(), code), (text, code)]]
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        } else if trimmed.starts_with('#') {
+            // Headings lose their hashes and stand out, the top level most of all
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let heading = Style {
+                bold: true,
+                underline: level == 1,
+                colour: Colour::Heading,
+                ..Style::default()
+            };
+            wrap(&inline(trimmed[level..].trim(), heading), width, &[], &[])
+        } else if trimmed.chars().all(|c| matches!(c, '-' | '*' | '_' | ' '))
+            && trimmed.chars().filter(|c| !c.is_whitespace()).count() >= 3
+        {
+            vec![vec![("\u{2500}".repeat(width), quote)]]
+        } else if let Some(quoted) = trimmed.strip_prefix('>') {
+            let bar = [("\u{2502} ".to_string(), quote)];
+            let italic = Style {
+                italic: true,
+                ..Style::default()
+            };
+            wrap(&inline(quoted.trim_start(), italic), width, &bar, &bar)
+        } else if let Some((bullet, item)) = list_item(trimmed) {
+            // Items hang off their bullet, indented as deep as they are nested
+            let marker = Style {
+                colour: Colour::Marker,
+                ..Style::default()
+            };
+            let first = [
+                (" ".repeat(indent), Style::default()),
+                (format!("{bullet} "), marker),
+            ];
+            let rest = [(" ".repeat(indent + bullet.width() + 1), Style::default())];
+            wrap(&inline(item, Style::default()), width, &first, &rest)
+        } else {
+            wrap(&inline(trimmed, Style::default()), width, &[], &[])
+        };
+        for line in laid {
+            lines.push((index, finish(&line, width, palette)));
+        }
+    }
+    lines
+}
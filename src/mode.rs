@@ -0,0 +1,123 @@
+// Mode.rs - Optional vim style modal editing that sits between keys and the editor
+
+// The editing modes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,  // Keys run commands
+    Insert,  // Keys type text
+    Visual,  // Keys extend and act on a selection
+    Replace, // Keys type over the text
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        // The name shown in the status line
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::Replace => "REPLACE",
+        }
+    }
+}
+
+// Operators that act on the text between two cursor positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Yank,
+    Change,
+}
+
+// What the keys typed so far in normal or visual mode ask for
+#[derive(Debug)]
+pub enum Action {
+    Pending,                         // More keys are needed
+    Unknown,                         // The keys don't mean anything
+    Run(Vec<&'static str>),          // Run Oxa commands, once per count
+    Enter(Vec<&'static str>, Mode),  // Run Oxa commands and then switch mode
+    Append,                          // Insert after the character under the cursor
+    Goto(bool),                      // Jump to the first or last line, or to the count
+    Operate(Operator, &'static str), // Apply an operator over a motion
+    Line(Operator),                  // Apply an operator to the current line
+    Selection(Operator),             // Apply an operator to the selection
+    Register(char),                  // Name the register the next command uses
+}
+
+fn motion(key: char) -> Option<&'static str> {
+    // The Oxa commands of the keys that move the cursor
+    Some(match key {
+        'h' => "move 1 left",
+        'j' => "move 1 down",
+        'k' => "move 1 up",
+        'l' => "move 1 right",
+        'w' => "move word right",
+        'b' => "move word left",
+        '0' => "move home",
+        '$' => "move end",
+        '{' => "move paragraph up",
+        '}' => "move paragraph down",
+        '[' => "move block start",
+        ']' => "move block end",
+        _ => return None,
+    })
+}
+
+fn operator(key: char) -> Option<Operator> {
+    // The keys that start an operator
+    Some(match key {
+        'd' => Operator::Delete,
+        'y' => Operator::Yank,
+        'c' => Operator::Change,
+        _ => return None,
+    })
+}
+
+pub fn interpret(keys: &str, mode: Mode) -> Action {
+    // Work out what a sequence of keys in normal or visual mode does
+    let mut keys = keys.chars();
+    let Some(first) = keys.next() else {
+        return Action::Pending;
+    };
+    let second = keys.next();
+    if let Some(op) = operator(first) {
+        if mode == Mode::Visual {
+            return Action::Selection(op);
+        }
+        return match second {
+            None => Action::Pending,
+            Some(key) if key == first => Action::Line(op),
+            Some(key) => motion(key).map_or(Action::Unknown, |m| Action::Operate(op, m)),
+        };
+    }
+    match (first, second) {
+        ('g' | '"', None) => Action::Pending,
+        ('"', Some(name)) if name.is_ascii_alphanumeric() => Action::Register(name),
+        ('g', Some('g')) => Action::Goto(true),
+        (key, None) => {
+            if let Some(m) = motion(key) {
+                return Action::Run(vec![m]);
+            }
+            match key {
+                'x' if mode == Mode::Visual => Action::Selection(Operator::Delete),
+                'x' => Action::Run(vec!["delete"]),
+                'p' => Action::Run(vec!["paste"]),
+                'u' => Action::Run(vec!["undo"]),
+                '.' => Action::Run(vec!["repeat"]),
+                '%' => Action::Run(vec!["bracket"]),
+                'G' => Action::Goto(false),
+                'i' => Action::Enter(vec![], Mode::Insert),
+                'R' => Action::Enter(vec![], Mode::Replace),
+                'a' => Action::Append,
+                'A' => Action::Enter(vec!["move end"], Mode::Insert),
+                'I' => Action::Enter(vec!["move home"], Mode::Insert),
+                'o' => Action::Enter(vec!["line below", "move 1 down", "move home"], Mode::Insert),
+                'O' => Action::Enter(vec!["line above", "move 1 up", "move home"], Mode::Insert),
+                'v' if mode == Mode::Visual => Action::Enter(vec![], Mode::Normal),
+                'v' => Action::Enter(vec![], Mode::Visual),
+                _ => Action::Unknown,
+            }
+        }
+        _ => Action::Unknown,
+    }
+}
@@ -0,0 +1,110 @@
+// Modeline.rs - Reading the settings Vim and Emacs modelines give the files they are in
+use crate::editorconfig::Settings;
+
+// How many lines at each end of a file Vim looks at for a modeline
+const LINES: usize = 5;
+
+fn number(value: &str) -> Option<usize> {
+    // A width, where nothing and zero mean there isn't one
+    value.trim().parse().ok().filter(|number| *number > 0)
+}
+
+fn vim(settings: &mut Settings, line: &str) -> bool {
+    // Take in the options of a line like "# vim: ts=4 sw=4 et" or "/* vim: set ts=8: */"
+    let start = ["vim:", "vi:", "ex:", "Vim:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| {
+                // The marker has to start the line or come after a space
+                *index == 0 || line[..*index].ends_with(char::is_whitespace)
+            })
+            .map(|(index, _)| index + marker.len())
+    });
+    let Some(start) = start else {
+        return false;
+    };
+    let rest = line[start..].trim_start();
+    // With "set" the options end at the next colon, otherwise colons separate them too
+    let options: Vec<&str> = if let Some(set) = rest
+        .strip_prefix("set ")
+        .or_else(|| rest.strip_prefix("se "))
+    {
+        set.split(':')
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect()
+    } else {
+        rest.split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|option| !option.is_empty())
+            .collect()
+    };
+    for option in options {
+        let (name, value) = option.split_once('=').unwrap_or((option, ""));
+        match name {
+            "ts" | "tabstop" => settings.tab_width = number(value).or(settings.tab_width),
+            "sw" | "shiftwidth" | "sts" | "softtabstop" => {
+                settings.indent_size = number(value).or(settings.indent_size);
+            }
+            "et" | "expandtab" => settings.tabs = Some(false),
+            "noet" | "noexpandtab" => settings.tabs = Some(true),
+            "ft" | "filetype" | "syn" | "syntax" if !value.is_empty() => {
+                settings.filetype = Some(value.to_string());
+            }
+            _ => (),
+        }
+    }
+    true
+}
+
+fn emacs(settings: &mut Settings, line: &str) -> bool {
+    // Take in the variables of a line like "# -*- mode: python; tab-width: 4 -*-"
+    let inside = if let Some((_, rest)) = line.split_once("-*-") {
+        rest.split_once("-*-").map(|(inside, _)| inside)
+    } else {
+        None
+    };
+    let Some(inside) = inside else {
+        return false;
+    };
+    for variable in inside.split(';').map(str::trim) {
+        match variable.split_once(':') {
+            Some((name, value)) => {
+                let value = value.trim();
+                match name.trim().to_lowercase().as_str() {
+                    "mode" => settings.filetype = Some(value.to_lowercase()),
+                    "tab-width" => settings.tab_width = number(value).or(settings.tab_width),
+                    "indent-tabs-mode" => settings.tabs = Some(value != "nil"),
+                    name if name.ends_with("-indent-offset")
+                        || name.ends_with("-basic-offset")
+                        || name == "indent-level" =>
+                    {
+                        settings.indent_size = number(value).or(settings.indent_size);
+                    }
+                    _ => (),
+                }
+            }
+            // A line of just "-*- python -*-" names the mode alone
+            None if !variable.is_empty() => settings.filetype = Some(variable.to_lowercase()),
+            None => (),
+        }
+    }
+    true
+}
+
+pub fn apply(settings: &mut Settings, lines: &[&str]) {
+    // Go over settings with what the modelines at either end of a file say
+    // Emacs only looks at the first line, or the second after a #! line
+    let shebang = lines.first().is_some_and(|line| line.starts_with("#!"));
+    for line in lines.iter().take(if shebang { 2 } else { 1 }) {
+        if emacs(settings, line) {
+            break;
+        }
+    }
+    let end = lines
+        .len()
+        .saturating_sub(LINES)
+        .max(LINES.min(lines.len()));
+    for line in lines[..LINES.min(lines.len())].iter().chain(&lines[end..]) {
+        vim(settings, line);
+    }
+}
@@ -7,27 +7,400 @@
 
     An example usage could be writing a macro to delete the current line
 */
+use crate::cursor::{word_after, word_before};
+use crate::encoding::Encoding;
 use crate::undo::BankType;
-use crate::util::line_offset;
+use crate::util::{line_offset, text_between};
 use crate::{Direction, Event, Position, Row};
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 #[derive(Debug, Copy, Clone)]
 pub enum Variable {
     Saved,
 }
 
+// Ways of changing the text of the selection or a range of lines
+#[derive(Debug, Copy, Clone)]
+pub enum Transform {
+    Sort(bool, bool), // Sort the lines, dropping repeats and backwards when asked
+    Upper,            // Make the letters capitals
+    Lower,            // Make the letters small
+    Title,            // Start each word with a capital
+    Reverse,          // Put the lines in the opposite order
+    Unique,           // Drop lines that came before, keeping the first of each
+}
+
+// Every command with what it takes and what it does, for the help screen
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("new", "Create a new document"),
+    (
+        "open [file]",
+        "Open a document, asking for it when not given",
+    ),
+    ("recent", "Pick a file opened lately to open again"),
+    ("save", "Save the current document"),
+    ("save ?", "Save the current document under another name"),
+    ("save *", "Save every open document"),
+    ("save <file>", "Save the current document to a file"),
+    ("wq [file]", "Save the current document and close it"),
+    ("quit", "Close the current document"),
+    (
+        "quit !",
+        "Close the current document, throwing away its changes",
+    ),
+    ("quit *", "Close every document"),
+    (
+        "q!",
+        "Close the current document, throwing away its changes",
+    ),
+    ("prev", "Move to the previous tab"),
+    ("next", "Move to the next tab"),
+    ("undo", "Undo the last edit"),
+    ("undo tree", "Browse every state the document has been in"),
+    (
+        "redo [branch]",
+        "Redo the last edit undone, along a branch when given",
+    ),
+    ("commit", "Finish the edit being made, so undo stops there"),
+    (
+        "suspend",
+        "Stop the editor and go back to the shell, fg to return",
+    ),
+    ("reload", "Reload the configuration file"),
+    ("cmd", "Open the command line"),
+    ("help", "Show the keys, chords and commands"),
+    (
+        "messages",
+        "Look back over the messages shown on the command line",
+    ),
+    ("search", "Search the document"),
+    ("replace", "Replace matches one at a time"),
+    ("replace *", "Replace every match"),
+    (
+        "substitute [*] /<regex>/<text>/[g]",
+        "Replace the first match on the line, on every line with *, every match with g",
+    ),
+    (
+        "<from>,<to>d [register]",
+        "Cut lines, given by number, ., $, 'mark or % for all",
+    ),
+    ("<from>,<to>y [register]", "Copy lines"),
+    (
+        "<from>,<to>s/<regex>/<text>/[g]",
+        "Replace the first match on each line, or every match with g",
+    ),
+    ("goto [y] [x]", "Go to a line and column"),
+    ("goto ?", "Go to a line and column, asking for them"),
+    (
+        "move <n> <direction>",
+        "Move the cursor up, down, left or right",
+    ),
+    (
+        "move word <direction>",
+        "Move the cursor to the start or end of a word",
+    ),
+    ("move word left", "Move to the start of the previous word"),
+    ("move word right", "Move to the end of the next word"),
+    (
+        "move paragraph <direction>",
+        "Move the cursor to the blank line up or down",
+    ),
+    (
+        "move paragraph up",
+        "Move to the blank line before the paragraph",
+    ),
+    (
+        "move paragraph down",
+        "Move to the blank line after the paragraph",
+    ),
+    (
+        "move block start",
+        "Move to the first line of the indented block",
+    ),
+    (
+        "move block end",
+        "Move to the last line of the indented block",
+    ),
+    (
+        "move line <direction>",
+        "Swap the current line with the one above or below",
+    ),
+    ("move line up", "Move the current line up"),
+    ("move line down", "Move the current line down"),
+    ("move home", "Move to the start of the line"),
+    ("move end", "Move to the end of the line"),
+    ("move pageup", "Move up a screen"),
+    ("move pagedown", "Move down a screen"),
+    ("scroll up", "Scroll the view up"),
+    ("scroll down", "Scroll the view down"),
+    (
+        "center",
+        "Scroll the line the cursor is on to the middle of the screen",
+    ),
+    ("put <text>", "Insert text at the cursor"),
+    ("delete", "Delete the character at the cursor"),
+    ("delete word", "Delete the word the cursor is in"),
+    ("delete word left", "Delete the word before the cursor"),
+    ("delete word right", "Delete the word after the cursor"),
+    ("delete end", "Delete to the end of the line"),
+    ("delete <line>", "Delete a line, counting from the cursor's"),
+    ("line below", "Open a new line below"),
+    ("line above", "Open a new line above"),
+    ("line duplicate", "Copy the current line below itself"),
+    ("line join", "Join the next line onto the current one"),
+    ("line delete", "Delete the current line"),
+    (
+        "increment [amount]",
+        "Add to the number under or after the cursor",
+    ),
+    (
+        "decrement [amount]",
+        "Take away from the number under or after the cursor",
+    ),
+    ("set <text>", "Replace the current line"),
+    ("overwrite <text>", "Replace the whole document"),
+    (
+        "store <cursor|line> <bank>",
+        "Keep the cursor or line in a bank",
+    ),
+    (
+        "load <cursor|line> <bank>",
+        "Bring back the cursor or line kept in a bank",
+    ),
+    ("cursor above", "Add a cursor on the line above"),
+    ("cursor below", "Add a cursor on the line below"),
+    (
+        "cursor next",
+        "Add a cursor where the word at the cursor appears next",
+    ),
+    ("select", "Start or stop selecting text"),
+    ("select block", "Start or stop selecting a block of columns"),
+    (
+        "copy",
+        "Copy the selection or current line to the clipboard",
+    ),
+    ("paste", "Paste from the clipboard"),
+    ("cut", "Cut the selection to the clipboard"),
+    (
+        "copy <letter>",
+        "Copy the selection or current line into a register, a capital adding to it",
+    ),
+    (
+        "paste <register>",
+        "Paste a register, 1 to 9 being the text cut most recently",
+    ),
+    (
+        "cut <letter>",
+        "Cut the selection into a register, a capital adding to it",
+    ),
+    ("registers", "Pick from what the registers hold to paste it"),
+    ("bracket", "Jump to the matching bracket"),
+    ("mark [letter]", "Go to a mark, asking which when not given"),
+    (
+        "mark set [letter]",
+        "Set a mark at the cursor, asking its name when not given",
+    ),
+    (
+        "jump back",
+        "Go back to where the last large jump was made from",
+    ),
+    ("jump forward", "Go forward again through the jumps"),
+    ("fold", "Fold away the block the cursor is in"),
+    ("fold all", "Fold away every outermost block"),
+    ("unfold", "Open the fold on the cursor's line"),
+    ("unfold all", "Open every fold"),
+    (
+        "comment",
+        "Comment out the selected lines, or uncomment them",
+    ),
+    ("record", "Start or stop recording keys"),
+    ("replay", "Replay the recorded keys"),
+    ("repeat", "Make the last edit again at the cursor"),
+    ("wrap", "Toggle soft wrapping of long lines"),
+    ("relative", "Toggle relative line numbers"),
+    ("readonly", "Refuse or allow changes to the document"),
+    ("count", "Show word, character and line counts"),
+    ("vsplit", "Show the next tab beside this one"),
+    ("hsplit", "Show the next tab below this one"),
+    ("unsplit", "Go back to one pane"),
+    ("pane", "Switch between split panes"),
+    ("diff", "Compare the two panes"),
+    ("explorer", "Show, focus or hide the file tree"),
+    (
+        "console",
+        "Open a shell below the documents, or move focus to and from it",
+    ),
+    (
+        "preview",
+        "Show or hide a formatted preview of a Markdown document",
+    ),
+    (
+        "export [file]",
+        "Write the highlighted document to a web page",
+    ),
+    ("find", "Find a file to open by its path"),
+    (
+        "grep [pattern]",
+        "Search every file under the working directory",
+    ),
+    (
+        "grep replace",
+        "Replace in every file, going through the changes first",
+    ),
+    (
+        "build [command]",
+        "Run the build command of the file's language",
+    ),
+    ("problem next", "Go to the next problem the build found"),
+    (
+        "problem previous",
+        "Go to the previous problem the build found",
+    ),
+    ("problem list", "Show or hide the problems the build found"),
+    (
+        "hunk next",
+        "Go to the next run of lines changed since git saw them",
+    ),
+    ("hunk prev", "Go to the previous run of changed lines"),
+    (
+        "blame",
+        "Show or hide the blame of the line the cursor is on",
+    ),
+    (
+        "complete",
+        "Complete the word at the cursor with the language server",
+    ),
+    (
+        "words",
+        "Complete the word at the cursor from the open documents",
+    ),
+    ("spell", "Suggest spellings for the word at the cursor"),
+    (
+        "tag [symbol]",
+        "Go to the definition of a symbol from the tags file",
+    ),
+    (
+        "tag back",
+        "Go back to where the definition was gone to from",
+    ),
+    ("hex [file]", "Show the bytes of a file"),
+    ("plugin <name> [command]", "Hand a command to a plugin"),
+    (
+        "option <name> [value]",
+        "Change an option of the general section",
+    ),
+    ("theme <name>", "Switch to another theme"),
+    (
+        "encoding <name>",
+        "Change the character encoding of the document",
+    ),
+    (
+        "endings <lf|crlf>",
+        "Change the line endings of the document",
+    ),
+    ("is saved", "Mark the document as saved"),
+    ("sh <command>", "Run a shell command"),
+    (
+        "shc <command>",
+        "Run a shell command and show what it wrote",
+    ),
+    (
+        "shs <command>",
+        "Run a shell command with the document's values filled in",
+    ),
+    (
+        "shcs <command>",
+        "Run a shell command with values filled in and show what it wrote",
+    ),
+    (
+        "read <command>",
+        "Insert what a shell command writes at the cursor",
+    ),
+    (
+        "filter <command>",
+        "Pass the selection or document through a shell command",
+    ),
+    (
+        "format",
+        "Run the formatter of the file type over the document",
+    ),
+    (
+        "sort [unique] [reverse] [from-to]",
+        "Sort the selected lines, the lines given or the document",
+    ),
+    (
+        "reverse [from-to]",
+        "Reverse the order of the selected lines, the lines given or the document",
+    ),
+    (
+        "unique [from-to]",
+        "Remove repeated lines from the selection, the lines given or the document",
+    ),
+    (
+        "upper [from-to]",
+        "Make the selection, the lines given or the current line capitals",
+    ),
+    (
+        "lower [from-to]",
+        "Make the selection, the lines given or the current line small letters",
+    ),
+    (
+        "title [from-to]",
+        "Start each word of the selection, the lines given or the current line with a capital",
+    ),
+];
+
+pub fn describe(command: &str) -> Option<&'static str> {
+    // Find what a command does, by the most specific entry its words start with
+    let words: Vec<&str> = command.split(' ').collect();
+    COMMANDS
+        .iter()
+        .filter_map(|(usage, description)| {
+            let fixed: Vec<&str> = usage
+                .split(' ')
+                .take_while(|word| !word.starts_with('<') && !word.starts_with('['))
+                .collect();
+            // Entries that take a value need one to be given
+            let takes = usage
+                .split(' ')
+                .nth(fixed.len())
+                .is_some_and(|word| word.starts_with('<'));
+            (words.starts_with(&fixed) && (!takes || words.len() > fixed.len()))
+                .then_some((fixed.len(), *description))
+        })
+        .max_by_key(|(length, _)| *length)
+        .map(|(_, description)| description)
+}
+
+#[allow(clippy::too_many_lines)] // One arm per command
 pub fn interpret_line(
     line: &str,
     cursor: &Position,
     graphemes: usize,
     rows: &[Row],
+    marks: &HashMap<char, Position>,
 ) -> Option<Vec<Event>> {
     // Take an instruction of Oxa and interpret it
+    if let Some((lines, command)) = range(line, cursor, rows, marks) {
+        return Some(vec![ranged_command(lines, command.trim_start())?]);
+    }
+    // s/foo/bar/ takes its delimiter straight after the name, as it does after a range
+    let name = line
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .map_or(line, |end| &line[..end]);
+    let rest = &line[name.len()..];
+    if matches!(name, "s" | "substitute")
+        && rest.starts_with(|c: char| !c.is_alphanumeric() && !c.is_whitespace())
+    {
+        return Some(vec![substitution(rest, (cursor.y, cursor.y))?]);
+    }
     let mut events = vec![];
     let mut line = line.split(' ');
     if let Some(instruction) = line.next() {
         let mut args: Vec<&str> = line.collect();
-        let root = if let Some(&"sudo") = args.get(0) {
+        let root = if let Some(&"sudo") = args.first() {
             args.remove(0);
             true
         } else {
@@ -35,20 +408,98 @@ pub fn interpret_line(
         };
         match instruction {
             "new" => events.push(Event::New),
-            "open" => events.push(open_command(&args)),
+            "open" | "e" => events.push(open_command(&args)),
+            "recent" => events.push(Event::Recent),
+            "undo" if args.first() == Some(&"tree") => events.push(Event::History),
             "undo" => events.push(Event::Undo),
             "commit" => events.push(Event::Commit),
-            "redo" => events.push(Event::Redo),
-            "quit" => events.push(quit_command(&args)),
+            "redo" => events.push(match args.first() {
+                Some(branch) => Event::RedoBranch(branch.parse().ok()?),
+                None => Event::Redo,
+            }),
+            "quit" | "q" => events.push(quit_command(&args)),
             "prev" => events.push(Event::PrevTab),
             "next" => events.push(Event::NextTab),
-            "set" => events.push(set_command(&args, &cursor, &rows)),
+            "set" => events.push(set_command(&args, cursor, rows)),
             "split" => events.push(Event::SplitDown(*cursor, *cursor)),
             "splice" => events.push(Event::SpliceUp(*cursor, *cursor)),
             "search" => events.push(Event::Search),
             "reload" => events.push(Event::ReloadConfig),
             "cmd" => events.push(Event::Cmd),
+            "center" => events.push(Event::Center),
+            "cursor" => events.push(match args.first() {
+                Some(&"above") => Event::AddCursor(Direction::Up),
+                Some(&"below") => Event::AddCursor(Direction::Down),
+                Some(&"next") => Event::AddNextCursor,
+                _ => return None,
+            }),
+            "wrap" => events.push(Event::ToggleWrap),
+            "relative" => events.push(Event::ToggleRelative),
+            "blame" => events.push(Event::ToggleBlame),
+            "readonly" => events.push(Event::ToggleReadOnly),
+            "count" => events.push(Event::Statistics),
+            "copy" => events.push(register_command(&args, false, Event::Copy)?),
+            "paste" => events.push(register_command(&args, true, Event::Paste)?),
+            "cut" => events.push(register_command(&args, false, Event::Cut)?),
+            "registers" => events.push(Event::Registers),
+            "select" => events.push(if args.first() == Some(&"block") {
+                Event::SelectBlock
+            } else {
+                Event::Select
+            }),
+            "bracket" => events.push(Event::MatchBracket),
+            "mark" => events.push(mark_command(&args)?),
+            "comment" => events.push(Event::Comment),
+            "suspend" => events.push(Event::Suspend),
+            "fold" => events.push(Event::Fold(args.first() == Some(&"all"))),
+            "unfold" => events.push(Event::Unfold(args.first() == Some(&"all"))),
+            "jump" => events.push(match args.first() {
+                Some(&"back") => Event::Retrace(true),
+                Some(&"forward") => Event::Retrace(false),
+                _ => return None,
+            }),
+            "record" => events.push(Event::Record),
+            "replay" => events.push(Event::Replay),
+            "repeat" => events.push(Event::Repeat),
+            "vsplit" => events.push(Event::SplitView(true)),
+            "hsplit" => events.push(Event::SplitView(false)),
+            "unsplit" => events.push(Event::Unsplit),
+            "pane" => events.push(Event::SwitchPane),
+            "explorer" => events.push(Event::Explorer),
+            "console" => events.push(Event::Console),
+            "diff" => events.push(Event::Diff),
+            "preview" => events.push(Event::Preview),
+            "export" => events.push(Event::Export(args.first().map(ToString::to_string))),
+            "build" if args.is_empty() => events.push(Event::Build(None)),
+            "build" => events.push(Event::Build(Some(args.join(" ")))),
+            "problem" => events.push(match args.first() {
+                Some(&"list") => Event::Problems,
+                Some(&"previous" | &"prev") => Event::Problem(false),
+                _ => Event::Problem(true),
+            }),
+            "messages" => events.push(Event::Messages),
+            "help" => events.push(Event::Help),
+            "find" => events.push(Event::Find),
+            "grep" if args.is_empty() => events.push(Event::Grep(None)),
+            "grep" if args == ["replace"] => events.push(Event::GrepReplace),
+            "grep" => events.push(Event::Grep(Some(args.join(" ")))),
+            "complete" => events.push(Event::Complete),
+            "words" => events.push(Event::Words),
+            "spell" => events.push(Event::Spell),
+            "tag" => events.push(match args.first() {
+                Some(&"back") => Event::DefinitionBack,
+                Some(symbol) => Event::Definition(Some((*symbol).to_string())),
+                None => Event::Definition(None),
+            }),
+            "hunk" => events.push(Event::Hunk(args.first() != Some(&"prev"))),
+            "plugin" => events.push(Event::Plugin(
+                args.first().map_or(String::new(), ToString::to_string),
+                args.iter().skip(1).copied().collect::<Vec<_>>().join(" "),
+            )),
+            "hex" => events.push(Event::Hex(args.first().map(ToString::to_string))),
             "replace" => events.push(replace_command(&args)),
+            "substitute" | "s" => events.push(substitute_command(&args, cursor, rows)?),
+            "q!" => events.push(Event::Quit(true)),
             // Shell with substitution and no confirm
             "shs" => events.push(Event::Shell(args.join(" "), false, true, root)),
             // Shell with substitution and confirm
@@ -57,37 +508,76 @@ pub fn interpret_line(
             "shc" => events.push(Event::Shell(args.join(" "), true, false, root)),
             // Shell with no confirm nor substitution
             "sh" => events.push(Event::Shell(args.join(" "), false, false, root)),
+            // Insert the output of a command at the cursor
+            "read" => events.push(Event::Filter(args.join(" "), false)),
+            // Replace the selection, or the whole document, with what a command makes of it
+            "filter" => events.push(Event::Filter(args.join(" "), true)),
+            "format" => events.push(Event::Format),
+            "increment" | "decrement" => {
+                let amount = args.first().map_or(Some(1), |n| n.parse::<i128>().ok())?;
+                events.push(Event::Increment(if instruction == "decrement" {
+                    -amount
+                } else {
+                    amount
+                }));
+            }
+            "sort" | "reverse" | "unique" | "upper" | "lower" | "title" => {
+                events.push(transform_command(instruction, &args)?);
+            }
             "is" => {
                 if let Some(set) = is_command(&args) {
-                    events.push(set)
+                    events.push(set);
+                } else {
+                    return None;
+                }
+            }
+            "option" => {
+                if let Some(option) = option_command(&args) {
+                    events.push(option);
+                } else {
+                    return None;
+                }
+            }
+            "encoding" => {
+                if let Some(encoding) = args.first().and_then(|name| Encoding::from_name(name)) {
+                    events.push(Event::Encoding(encoding));
+                } else {
+                    return None;
+                }
+            }
+            "endings" => {
+                if let Some(endings) = endings_command(&args) {
+                    events.push(endings);
                 } else {
                     return None;
                 }
             }
             "theme" => {
                 if let Some(theme) = theme_command(&args) {
-                    events.push(theme)
+                    events.push(theme);
                 } else {
                     return None;
                 }
             }
             "line" => {
-                if let Some(line) = line_command(&args, &cursor) {
-                    events.push(line);
+                if let Some(mut line) = line_command(&args, cursor, rows) {
+                    events.append(&mut line);
                 } else {
                     return None;
                 }
             }
             _ => {
                 let i = match instruction {
-                    "save" => save_command(&args),
+                    "save" | "w" | "write" => Some(save_command(&args)),
+                    "wq" => Some(write_quit_command(&args)),
                     "goto" => goto_command(&args),
-                    "move" => move_command(&args),
-                    "put" => put_command(&args, &cursor),
-                    "delete" => delete_command(&args, &cursor, graphemes, &rows),
+                    "move" => move_command(&args, cursor),
+                    "scroll" => scroll_command(&args),
+                    "put" => put_command(&args, cursor),
+                    "delete" => delete_command(&args, cursor, graphemes, rows),
                     "load" => load_command(&args),
                     "store" => store_command(&args),
-                    "overwrite" => overwrite_command(&args, &rows),
+                    "overwrite" => Some(overwrite_command(&args, rows)),
                     _ => return None,
                 };
                 if let Some(mut command) = i {
@@ -103,7 +593,7 @@ pub fn interpret_line(
 
 fn is_command(args: &[&str]) -> Option<Event> {
     Some(Event::Set(
-        match &args[0][..] {
+        match args[0] {
             "saved" => Variable::Saved,
             _ => return None,
         },
@@ -111,6 +601,62 @@ fn is_command(args: &[&str]) -> Option<Event> {
     ))
 }
 
+fn transform_command(instruction: &str, args: &[&str]) -> Option<Event> {
+    // The lines to change are given as from-to, counting from 1
+    let mut lines = None;
+    let (mut unique, mut reverse) = (false, false);
+    for arg in args {
+        match *arg {
+            "unique" if instruction == "sort" => unique = true,
+            "reverse" if instruction == "sort" => reverse = true,
+            range => {
+                let (from, to) = range.split_once('-')?;
+                let (from, to): (usize, usize) = (from.parse().ok()?, to.parse().ok()?);
+                lines = Some((cmp::min(from, to).max(1) - 1, cmp::max(from, to).max(1) - 1));
+            }
+        }
+    }
+    let transform = match instruction {
+        "sort" => Transform::Sort(unique, reverse),
+        "reverse" => Transform::Reverse,
+        "unique" => Transform::Unique,
+        "upper" => Transform::Upper,
+        "lower" => Transform::Lower,
+        _ => Transform::Title,
+    };
+    Some(Event::Transform(transform, lines))
+}
+
+fn register_command(args: &[&str], read: bool, event: fn(Option<char>) -> Event) -> Option<Event> {
+    // The register a command names is a letter or, to read from, a digit
+    let Some(name) = args.first() else {
+        return Some(event(None));
+    };
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() || (read && matches!(c, '1'..='9')) => {
+            Some(event(Some(c)))
+        }
+        _ => None,
+    }
+}
+
+fn option_command(args: &[&str]) -> Option<Event> {
+    let name = args.first()?;
+    Some(Event::Option(
+        name.to_string(),
+        args.get(1).map(ToString::to_string),
+    ))
+}
+
+fn endings_command(args: &[&str]) -> Option<Event> {
+    match *args.first()? {
+        "lf" => Some(Event::LineEndings(false)),
+        "crlf" => Some(Event::LineEndings(true)),
+        _ => None,
+    }
+}
+
 fn theme_command(args: &[&str]) -> Option<Event> {
     if args.is_empty() {
         None
@@ -127,6 +673,168 @@ fn replace_command(args: &[&str]) -> Event {
     }
 }
 
+fn substitute_command(args: &[&str], cursor: &Position, rows: &[Row]) -> Option<Event> {
+    let all = args.first() == Some(&"*");
+    let lines = if all {
+        (0, rows.len().saturating_sub(1))
+    } else {
+        (cursor.y, cursor.y)
+    };
+    substitution(&args[usize::from(all)..].join(" "), lines)
+}
+
+fn substitution(text: &str, lines: (usize, usize)) -> Option<Event> {
+    // The pattern and replacement are split by whatever character comes first, as in sed
+    // Only the first match on each line is replaced, unless a g on the end asks for every one
+    let delimiter = text.chars().next()?;
+    let mut parts = text[delimiter.len_utf8()..].splitn(3, delimiter);
+    let pattern = parts.next().filter(|pattern| !pattern.is_empty())?;
+    let with = parts.next()?;
+    let global = match parts.next().unwrap_or_default().trim() {
+        "" => false,
+        "g" => true,
+        _ => return None,
+    };
+    Some(Event::Substitute(
+        pattern.to_string(),
+        with.to_string(),
+        lines,
+        global,
+    ))
+}
+
+fn address<'a>(
+    text: &'a str,
+    cursor: &Position,
+    rows: &[Row],
+    marks: &HashMap<char, Position>,
+) -> Option<(i128, &'a str)> {
+    // Read a line from the start of the text: a number counting from 1, . for the cursor's
+    // line, $ for the last or 'a for a mark, with any +n or -n after it, which may not be a
+    // line of the document
+    let last = rows.len().saturating_sub(1) as i128;
+    let (mut line, mut rest) = match text.chars().next()? {
+        '.' => (cursor.y as i128, &text[1..]),
+        '$' => (last, &text[1..]),
+        '\'' => {
+            let letter = text[1..].chars().next()?;
+            (
+                marks.get(&letter)?.y as i128,
+                &text[1 + letter.len_utf8()..],
+            )
+        }
+        c if c.is_ascii_digit() => {
+            let digits = text
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(text.len());
+            (text[..digits].parse::<i128>().ok()? - 1, &text[digits..])
+        }
+        // An offset alone counts from the cursor's line
+        '+' | '-' => (cursor.y as i128, text),
+        _ => return None,
+    };
+    while let Some(sign) = rest.chars().next().filter(|c| matches!(c, '+' | '-')) {
+        let digits = rest[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |end| end + 1);
+        let amount = if digits == 1 {
+            1
+        } else {
+            rest[1..digits].parse::<i128>().ok()?
+        };
+        line += if sign == '+' { amount } else { -amount };
+        rest = &rest[digits..];
+    }
+    Some((line, rest))
+}
+
+fn range<'a>(
+    text: &'a str,
+    cursor: &Position,
+    rows: &[Row],
+    marks: &HashMap<char, Position>,
+) -> Option<((usize, usize), &'a str)> {
+    // Read the lines an ex style command starts with, where % is every line
+    let ((from, to), rest) = bounds(text, cursor, rows, marks)?;
+    let inside = |line: i128| usize::try_from(line).ok().filter(|&line| line < rows.len());
+    Some(((inside(from)?, inside(to)?), rest))
+}
+
+fn bounds<'a>(
+    text: &'a str,
+    cursor: &Position,
+    rows: &[Row],
+    marks: &HashMap<char, Position>,
+) -> Option<((i128, i128), &'a str)> {
+    // Read the first and last lines of a range, whether or not the document has them
+    if let Some(rest) = text.strip_prefix('%') {
+        return Some(((0, rows.len().saturating_sub(1) as i128), rest));
+    }
+    let (from, rest) = address(text, cursor, rows, marks)?;
+    let (to, rest) = if let Some(rest) = rest.strip_prefix(',') {
+        address(rest, cursor, rows, marks)?
+    } else {
+        (from, rest)
+    };
+    Some(((cmp::min(from, to), cmp::max(from, to)), rest))
+}
+
+pub fn has_range(text: &str) -> bool {
+    // Whether a command starts with the lines it works on
+    text.starts_with(|c: char| c.is_ascii_digit() || "%.$'+-".contains(c))
+}
+
+pub fn range_error(
+    text: &str,
+    cursor: &Position,
+    rows: &[Row],
+    marks: &HashMap<char, Position>,
+) -> Option<String> {
+    // Say which line of the range a command starts with isn't in the document, if one isn't
+    let ((from, to), _) = bounds(text, cursor, rows, marks)?;
+    let length = rows.len() as i128;
+    let line = [from, to]
+        .iter()
+        .copied()
+        .find(|&line| line < 0 || line >= length)?;
+    Some(if line < 0 {
+        format!("Line {} is before the first line, which is 1", line + 1)
+    } else {
+        format!(
+            "Line {} is past the end, which is line {}",
+            line + 1,
+            length
+        )
+    })
+}
+
+fn ranged_command((from, to): (usize, usize), command: &str) -> Option<Event> {
+    // Run a command over the lines of a range, or go to its last line when there isn't one
+    let name = command
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .map_or(command, |end| &command[..end]);
+    let rest = command[name.len()..].trim();
+    let register = || match rest.chars().collect::<Vec<_>>()[..] {
+        [] => Some(None),
+        [name] if name.is_ascii_alphanumeric() => Some(Some(name)),
+        _ => None,
+    };
+    Some(match name {
+        "" if rest.is_empty() => Event::GotoCursor(Position { x: 0, y: to }),
+        "d" | "delete" => Event::DeleteLines(from, to, register()?),
+        "y" | "yank" => Event::YankLines(from, to, register()?),
+        "s" | "substitute" => substitution(rest, (from, to))?,
+        "sort" | "reverse" | "unique" | "upper" | "lower" | "title" => {
+            let args: Vec<&str> = rest.split_whitespace().collect();
+            match transform_command(name, &args)? {
+                Event::Transform(how, None) => Event::Transform(how, Some((from, to))),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    })
+}
+
 fn open_command(args: &[&str]) -> Event {
     Event::Open(if args.is_empty() {
         None
@@ -135,6 +843,17 @@ fn open_command(args: &[&str]) -> Event {
     })
 }
 
+fn mark_command(args: &[&str]) -> Option<Event> {
+    let set = args.first() == Some(&"set");
+    let args = if set { &args[1..] } else { args };
+    let letter = match args {
+        [] => None,
+        [letter] if letter.chars().count() == 1 => letter.chars().next(),
+        _ => return None,
+    };
+    Some(Event::Mark(set, letter))
+}
+
 fn quit_command(args: &[&str]) -> Event {
     if args.contains(&"*") {
         Event::QuitAll(args.contains(&"!"))
@@ -143,17 +862,46 @@ fn quit_command(args: &[&str]) -> Event {
     }
 }
 
-fn line_command(args: &[&str], cursor: &Position) -> Option<Event> {
-    if args.is_empty() {
-        return None;
-    } else if let Some(dir) = args.get(0) {
-        return match *dir {
-            "below" => Some(Event::InsertLineBelow(*cursor)),
-            "above" => Some(Event::InsertLineAbove(*cursor)),
-            _ => None,
-        };
-    }
-    None
+fn line_command(args: &[&str], cursor: &Position, rows: &[Row]) -> Option<Vec<Event>> {
+    let row = &rows[cursor.y];
+    let end = Position {
+        x: row.length(),
+        y: cursor.y,
+    };
+    Some(match *args.first()? {
+        "below" => vec![Event::InsertLineBelow(*cursor)],
+        "above" => vec![Event::InsertLineAbove(*cursor)],
+        "duplicate" => vec![
+            // Copy the line below itself, keeping the cursor in the same column
+            Event::Insertion(end, format!("\n{}", row.string)),
+            Event::GotoCursor(Position {
+                x: cursor.x,
+                y: cursor.y + 1,
+            }),
+        ],
+        "join" => {
+            // Bring the next line up, with a single space in place of its indentation
+            let next = &rows.get(cursor.y + 1)?.string;
+            let content = next.trim_start();
+            let mut events = vec![Event::Deletion(
+                end,
+                format!("\n{}", &next[..next.len() - content.len()]),
+            )];
+            if row.string.ends_with(|c: char| !c.is_whitespace()) && !content.is_empty() {
+                events.push(Event::Insertion(end, " ".to_string()));
+            }
+            events
+        }
+        "delete" if rows.len() > 1 => vec![Event::DeleteLine(*cursor, 0, Box::new(row.clone()))],
+        // The only line can't go, so it is emptied instead
+        "delete" => vec![Event::UpdateLine(
+            *cursor,
+            0,
+            Box::new(row.clone()),
+            Box::new(Row::from("")),
+        )],
+        _ => return None,
+    })
 }
 
 fn set_command(args: &[&str], cursor: &Position, rows: &[Row]) -> Event {
@@ -174,8 +922,8 @@ fn set_command(args: &[&str], cursor: &Position, rows: &[Row]) -> Event {
     }
 }
 
-fn overwrite_command(args: &[&str], rows: &[Row]) -> Option<Vec<Event>> {
-    Some(vec![if args.is_empty() {
+fn overwrite_command(args: &[&str], rows: &[Row]) -> Vec<Event> {
+    vec![if args.is_empty() {
         Event::Overwrite(rows.to_vec(), vec![Row::from("")])
     } else {
         Event::Overwrite(
@@ -185,13 +933,13 @@ fn overwrite_command(args: &[&str], rows: &[Row]) -> Option<Vec<Event>> {
                 .map(Row::from)
                 .collect::<Vec<_>>(),
         )
-    }])
+    }]
 }
 
-fn save_command(args: &[&str]) -> Option<Vec<Event>> {
+fn save_command(args: &[&str]) -> Vec<Event> {
     let mut events = vec![];
     if args.is_empty() {
-        events.push(Event::Save(None, false))
+        events.push(Event::Save(None, false));
     } else {
         events.push(if args[0] == "*" {
             Event::SaveAll
@@ -199,16 +947,22 @@ fn save_command(args: &[&str]) -> Option<Vec<Event>> {
             Event::Save(None, true)
         } else {
             Event::Save(Some(args[0].to_string()), false)
-        })
+        });
     }
-    Some(events)
+    events
+}
+
+fn write_quit_command(args: &[&str]) -> Vec<Event> {
+    let mut events = save_command(args);
+    events.push(Event::Quit(false));
+    events
 }
 
 fn store_command(args: &[&str]) -> Option<Vec<Event>> {
     let mut events = vec![];
     if args.len() == 2 {
         if let Ok(bank) = args[1].parse::<usize>() {
-            if let Some(kind) = args.get(0) {
+            if let Some(kind) = args.first() {
                 match *kind {
                     "cursor" => events.push(Event::Store(BankType::Cursor, bank)),
                     "line" => events.push(Event::Store(BankType::Line, bank)),
@@ -228,7 +982,7 @@ fn load_command(args: &[&str]) -> Option<Vec<Event>> {
     let mut events = vec![];
     if args.len() == 2 {
         if let Ok(bank) = args[1].parse::<usize>() {
-            if let Some(kind) = args.get(0) {
+            if let Some(kind) = args.first() {
                 match *kind {
                     "cursor" => events.push(Event::Load(BankType::Cursor, bank)),
                     "line" => events.push(Event::Load(BankType::Line, bank)),
@@ -246,6 +1000,9 @@ fn load_command(args: &[&str]) -> Option<Vec<Event>> {
 
 fn goto_command(args: &[&str]) -> Option<Vec<Event>> {
     let mut events = vec![];
+    if args == ["?"] {
+        return Some(vec![Event::Jump(None)]);
+    }
     match args.len() {
         0 => events.push(Event::GotoCursor(Position { x: 0, y: 0 })),
         1 => {
@@ -274,24 +1031,17 @@ fn goto_command(args: &[&str]) -> Option<Vec<Event>> {
 }
 
 fn put_command(args: &[&str], cursor: &Position) -> Option<Vec<Event>> {
+    // There has to be something to put
     let mut events = vec![];
-    if args[0] == "\\t" {
+    if *args.first()? == "\\t" {
         events.push(Event::InsertTab(*cursor));
     } else {
-        for (c, ch) in args.join(" ").chars().enumerate() {
-            events.push(Event::Insertion(
-                Position {
-                    x: cursor.x.saturating_add(c),
-                    y: cursor.y,
-                },
-                ch,
-            ))
-        }
+        events.push(Event::Insertion(*cursor, args.join(" ")));
     }
     Some(events)
 }
 
-fn move_command(args: &[&str]) -> Option<Vec<Event>> {
+fn move_command(args: &[&str], cursor: &Position) -> Option<Vec<Event>> {
     let mut events = vec![];
     if args.len() == 2 {
         if let Ok(magnitude) = args[0].parse::<usize>() {
@@ -312,10 +1062,31 @@ fn move_command(args: &[&str]) -> Option<Vec<Event>> {
                 "right" => Direction::Right,
                 _ => return None,
             }));
+        } else if args[0] == "paragraph" {
+            events.push(Event::MoveParagraph(match args[1] {
+                "up" => Direction::Up,
+                "down" => Direction::Down,
+                _ => return None,
+            }));
+        } else if args[0] == "block" {
+            events.push(Event::MoveBlock(match args[1] {
+                "start" => false,
+                "end" => true,
+                _ => return None,
+            }));
+        } else if args[0] == "line" {
+            events.push(Event::SwapLine(
+                *cursor,
+                match args[1] {
+                    "up" => Direction::Up,
+                    "down" => Direction::Down,
+                    _ => return None,
+                },
+            ));
         } else {
             return None;
         }
-    } else if let Some(direction) = args.get(0) {
+    } else if let Some(direction) = args.first() {
         events.push(match *direction {
             "home" => Event::Home,
             "end" => Event::End,
@@ -329,6 +1100,14 @@ fn move_command(args: &[&str]) -> Option<Vec<Event>> {
     Some(events)
 }
 
+fn scroll_command(args: &[&str]) -> Option<Vec<Event>> {
+    Some(vec![Event::Scroll(match *args.first()? {
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        _ => return None,
+    })])
+}
+
 fn delete_command(
     args: &[&str],
     cursor: &Position,
@@ -338,16 +1117,36 @@ fn delete_command(
     // Handle the delete command (complicated)
     let mut events = vec![];
     if args.is_empty() {
-        if let Some(ch) = rows[cursor.y]
-            .string
-            .chars()
-            .collect::<Vec<_>>()
-            .get(graphemes)
-        {
-            events.push(Event::Deletion(*cursor, *ch));
+        if let Some(ch) = rows[cursor.y].chars().get(graphemes) {
+            events.push(Event::Deletion(*cursor, ch.to_string()));
         }
     } else if args[0] == "word" {
-        events.push(Event::DeleteWord(*cursor, "egg".to_string()));
+        // Delete to the start or end of a word, or the whole word the cursor is in
+        let (start, end) = match args.get(1) {
+            Some(&"left") => (word_before(rows, *cursor), *cursor),
+            Some(&"right") => (*cursor, word_after(rows, *cursor)),
+            _ => {
+                events.push(Event::DeleteWord(*cursor));
+                return Some(events);
+            }
+        };
+        if start != end {
+            events.push(Event::Deletion(start, text_between(rows, start, end)));
+        }
+    } else if args[0] == "end" {
+        // Delete to the end of the line, or join the next line onto it when already there
+        let length = rows[cursor.y].length();
+        let end = if cursor.x < length {
+            Position {
+                x: length,
+                y: cursor.y,
+            }
+        } else {
+            word_after(rows, *cursor)
+        };
+        if *cursor != end {
+            events.push(Event::Deletion(*cursor, text_between(rows, *cursor, end)));
+        }
     } else if let Ok(line) = args[0].parse::<i128>() {
         events.push(Event::DeleteLine(
             *cursor,
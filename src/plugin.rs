@@ -0,0 +1,113 @@
+// Plugin.rs - Running the programs that extend the editor and talking to them
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+pub struct Plugin {
+    pub name: String,          // The file name of the plugin, used to run its commands
+    child: Child,              // The running plugin
+    stdin: ChildStdin,         // Where messages to the plugin go
+    receiver: Receiver<Value>, // Messages coming in from the reading thread
+    pub alive: bool,           // Whether the plugin is still running
+}
+
+impl Plugin {
+    pub fn start(path: &Path) -> io::Result<Self> {
+        // Run a plugin, with its messages read on another thread
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || read(stdout, &sender));
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+        Ok(Self {
+            name,
+            child,
+            stdin,
+            receiver,
+            alive: true,
+        })
+    }
+    pub fn send(&mut self, message: &Value) {
+        // Send a message on a line of its own
+        if writeln!(self.stdin, "{message}")
+            .and_then(|()| self.stdin.flush())
+            .is_err()
+        {
+            self.alive = false;
+        }
+    }
+    pub fn reply(&mut self, id: &Value, result: Result<Value, String>) {
+        // Answer a request from the plugin
+        self.send(&match result {
+            Ok(result) => json!({"id": id, "result": result}),
+            Err(error) => json!({"id": id, "error": error}),
+        });
+    }
+    pub fn receive(&mut self, timeout: Option<Duration>) -> Option<Value> {
+        // Take the next message, waiting for one for a while if asked to
+        let message = match timeout {
+            Some(timeout) => self
+                .receiver
+                .recv_timeout(timeout)
+                .map_err(|error| matches!(error, RecvTimeoutError::Disconnected)),
+            None => self
+                .receiver
+                .try_recv()
+                .map_err(|error| matches!(error, TryRecvError::Disconnected)),
+        };
+        match message {
+            Ok(message) => Some(message),
+            Err(closed) => {
+                self.alive &= !closed;
+                None
+            }
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        // Stop the plugin along with the editor
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn read(stdout: ChildStdout, sender: &Sender<Value>) {
+    // Pass on each line the plugin writes that holds a message
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else { return };
+        if let Ok(message) = serde_json::from_str(&line) {
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+pub fn find(dir: &Path) -> Vec<PathBuf> {
+    // The programs in the plugin folder, in the order of their names
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    found.sort();
+    found
+}
@@ -0,0 +1,286 @@
+// Prompt.rs - Reading a line of input on the command line, with earlier input to go back to
+use crate::config::{KeyBinding, RawKey};
+use std::fs;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use unicode_width::UnicodeWidthStr;
+
+// How many earlier inputs each prompt remembers
+const HISTORY: usize = 100;
+
+// What a key did to a prompt
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Input {
+    Submit,  // The input was finished with enter
+    Cancel,  // The prompt was left with escape
+    Typed,   // A character was added to the text
+    Removed, // Text was taken away or replaced
+    Kept,    // The text stayed the same, though the cursor may have moved
+    Ignored, // The key isn't one the prompt uses
+}
+
+// How many names the thread reading a directory sends at a time
+const BATCH: usize = 256;
+
+// The files that could finish the path being typed
+struct Completion {
+    dir: String,                             // The directory part of the text, as typed
+    names: Vec<String>, // What it has that starts with the rest, directories ending in /
+    chosen: Option<usize>, // Which of the names is in the text, once cycling
+    receiver: Option<Receiver<Vec<String>>>, // Names still coming in from the reading thread
+}
+
+pub struct Prompt {
+    pub text: String,               // What has been typed so far
+    pub paths: bool,                // Whether tab completes the paths of files
+    cursor: usize,                  // How many characters of the text are before the cursor
+    history: Vec<String>,           // Earlier input to the prompt, oldest first
+    recalled: Option<usize>,        // Which earlier input is being shown, if any
+    draft: String,                  // What was typed before going back through the history
+    completion: Option<Completion>, // The paths tab goes through, if it was pressed
+}
+
+impl Prompt {
+    pub fn new(history: Vec<String>) -> Self {
+        // Start with nothing typed
+        Self {
+            text: String::new(),
+            paths: false,
+            cursor: 0,
+            history,
+            recalled: None,
+            draft: String::new(),
+            completion: None,
+        }
+    }
+    pub fn handle(&mut self, key: KeyBinding) -> Input {
+        // Act on a key pressed while the prompt is open
+        let back = matches!(
+            key,
+            KeyBinding::Raw(RawKey::BackTab) | KeyBinding::Shift(RawKey::BackTab)
+        );
+        if !back && key != KeyBinding::Raw(RawKey::Tab) {
+            // Anything but tab leaves the paths behind, to be looked at afresh next time
+            self.completion = None;
+        }
+        match key {
+            KeyBinding::Raw(RawKey::Tab) if self.paths => self.complete(true),
+            _ if back && self.paths => self.complete(false),
+            KeyBinding::Raw(RawKey::Enter) => Input::Submit,
+            KeyBinding::Raw(RawKey::Esc) => Input::Cancel,
+            KeyBinding::Raw(RawKey::Char(c)) | KeyBinding::Shift(RawKey::Char(c)) => {
+                let index = self.index();
+                self.text.insert(index, c);
+                self.cursor += 1;
+                Input::Typed
+            }
+            KeyBinding::Raw(RawKey::Backspace) if self.cursor > 0 => {
+                self.cursor -= 1;
+                let index = self.index();
+                self.text.remove(index);
+                Input::Removed
+            }
+            KeyBinding::Raw(RawKey::Delete) if self.cursor < self.text.chars().count() => {
+                let index = self.index();
+                self.text.remove(index);
+                Input::Removed
+            }
+            KeyBinding::Raw(RawKey::Left) => {
+                self.cursor = self.cursor.saturating_sub(1);
+                Input::Kept
+            }
+            KeyBinding::Raw(RawKey::Right) => {
+                self.cursor = (self.cursor + 1).min(self.text.chars().count());
+                Input::Kept
+            }
+            KeyBinding::Raw(RawKey::Home) => {
+                self.cursor = 0;
+                Input::Kept
+            }
+            KeyBinding::Raw(RawKey::End) => {
+                self.cursor = self.text.chars().count();
+                Input::Kept
+            }
+            KeyBinding::Raw(RawKey::Up) => self.recall(true),
+            KeyBinding::Raw(RawKey::Down) => self.recall(false),
+            KeyBinding::Raw(RawKey::Backspace | RawKey::Delete) => Input::Kept,
+            _ => Input::Ignored,
+        }
+    }
+    pub fn cursor(&self) -> usize {
+        // How far along the text the cursor appears
+        self.text[..self.index()].width()
+    }
+    pub fn waiting(&self) -> bool {
+        // Whether a directory is still being read for paths to complete
+        self.completion
+            .as_ref()
+            .is_some_and(|completion| completion.receiver.is_some())
+    }
+    pub fn receive(&mut self) -> bool {
+        // Take in the names read since last time, filling in what they all start with once done
+        let Some(completion) = &mut self.completion else {
+            return false;
+        };
+        if let Some(receiver) = &completion.receiver {
+            loop {
+                match receiver.try_recv() {
+                    Ok(mut names) => completion.names.append(&mut names),
+                    Err(TryRecvError::Empty) => return false,
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            }
+        } else {
+            return false;
+        }
+        completion.receiver = None;
+        if completion.names.is_empty() {
+            // Nothing in the directory fits, so the text stays as it is
+            self.completion = None;
+            return true;
+        }
+        completion.names.sort();
+        let common =
+            completion
+                .names
+                .iter()
+                .skip(1)
+                .fold(completion.names[0].clone(), |common, name| {
+                    common
+                        .chars()
+                        .zip(name.chars())
+                        .take_while(|(a, b)| a == b)
+                        .map(|(c, _)| c)
+                        .collect()
+                });
+        let text = format!("{}{}", completion.dir, common);
+        if completion.names.len() < 2 {
+            // Nothing is left to choose between
+            self.completion = None;
+        }
+        self.text = text;
+        self.cursor = self.text.chars().count();
+        true
+    }
+    pub fn hint(&self, width: usize) -> String {
+        // The paths tab goes through, with the one in the text in brackets, as far as they fit
+        let Some(completion) = &self.completion else {
+            return String::new();
+        };
+        if completion.receiver.is_some() {
+            return "  …".to_string();
+        }
+        let mut hint = String::new();
+        for (index, name) in completion.names.iter().enumerate() {
+            let name = if completion.chosen == Some(index) {
+                format!("  [{name}]")
+            } else {
+                format!("  {name}")
+            };
+            if hint.width() + name.width() + 3 > width {
+                hint.push_str("  …");
+                break;
+            }
+            hint.push_str(&name);
+        }
+        hint
+    }
+    fn complete(&mut self, forward: bool) -> Input {
+        // Go to the next or previous path, reading the directory in the background first
+        if let Some(completion) = &mut self.completion {
+            let count = completion.names.len();
+            if completion.receiver.is_some() || count == 0 {
+                return Input::Kept;
+            }
+            let chosen = match (completion.chosen, forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(index), true) => (index + 1) % count,
+                (Some(index), false) => (index + count - 1) % count,
+            };
+            completion.chosen = Some(chosen);
+            self.text = format!("{}{}", completion.dir, completion.names[chosen]);
+            self.cursor = self.text.chars().count();
+            return Input::Removed;
+        }
+        let split = self.text.rfind('/').map_or(0, |index| index + 1);
+        let (dir, prefix) = (
+            self.text[..split].to_string(),
+            self.text[split..].to_string(),
+        );
+        let path = if dir.is_empty() {
+            ".".to_string()
+        } else {
+            shellexpand::tilde(&dir).to_string()
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let Ok(entries) = fs::read_dir(path) else {
+                return;
+            };
+            let mut names = vec![];
+            for entry in entries.filter_map(Result::ok) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // Hidden files only come up when a dot is typed
+                if !name.starts_with(&prefix) || (name.starts_with('.') && !prefix.starts_with('.'))
+                {
+                    continue;
+                }
+                if entry.path().is_dir() {
+                    names.push(format!("{name}/"));
+                } else {
+                    names.push(name);
+                }
+                // Stop as soon as the prompt no longer wants them
+                if names.len() == BATCH && sender.send(std::mem::take(&mut names)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(names);
+        });
+        self.completion = Some(Completion {
+            dir,
+            names: vec![],
+            chosen: None,
+            receiver: Some(receiver),
+        });
+        Input::Kept
+    }
+    pub fn finish(mut self) -> Vec<String> {
+        // The history with the input added to the end of it
+        let text = self.text;
+        if !text.is_empty() {
+            self.history.retain(|earlier| *earlier != text);
+            self.history.push(text);
+            let extra = self.history.len().saturating_sub(HISTORY);
+            self.history.drain(..extra);
+        }
+        self.history
+    }
+    fn recall(&mut self, older: bool) -> Input {
+        // Show the input before or after the one shown, coming back to what was typed
+        let recalled = match (self.recalled, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (_, false) => None,
+        };
+        if recalled == self.recalled {
+            return Input::Kept;
+        }
+        if self.recalled.is_none() {
+            self.draft = self.text.clone();
+        }
+        self.recalled = recalled;
+        self.text = recalled.map_or(self.draft.clone(), |index| self.history[index].clone());
+        self.cursor = self.text.chars().count();
+        Input::Removed
+    }
+    fn index(&self) -> usize {
+        // The byte in the text the cursor is at
+        self.text
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+}
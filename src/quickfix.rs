@@ -0,0 +1,131 @@
+// Quickfix.rs - Running a build or a search and collecting the places it points at
+use crate::finder;
+use regex::Regex;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// A place in the code that a build complained about
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub path: String,    // The file, as the build named it
+    pub line: usize,     // The line, counting from 1
+    pub column: usize,   // The column, counting from 1
+    pub message: String, // What was wrong there
+}
+
+pub fn run(command: &str) -> io::Result<(Option<i32>, String)> {
+    // Run a build to the end, giving its exit status and everything it wrote in order
+    let output = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(format!("{command} 2>&1"))
+            .stdin(Stdio::null())
+            .output()?
+    } else {
+        Command::new("bash")
+            .arg("-c")
+            .arg(format!("exec 2>&1\n{command}"))
+            .stdin(Stdio::null())
+            .output()?
+    };
+    Ok((
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    ))
+}
+
+pub fn parse(output: &str) -> Vec<Problem> {
+    // Find the file, line and column of each error and warning in what a build wrote
+    let plain = Regex::new(r"^([^\s:][^:]*):(\d+):(?:(\d+):)?\s*(.*)$").unwrap();
+    let arrow = Regex::new(r"^\s*--> ([^:]+):(\d+):(\d+)").unwrap();
+    let traceback = Regex::new(r#"^\s*File "([^"]+)", line (\d+)"#).unwrap();
+    let ansi = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    let mut problems: Vec<Problem> = vec![];
+    let mut heading = String::new();
+    for line in output.lines() {
+        let line = ansi.replace_all(line, "");
+        let number = |text: Option<regex::Match>| {
+            text.and_then(|text| text.as_str().parse().ok())
+                .unwrap_or(1)
+        };
+        if let Some(found) = arrow.captures(&line) {
+            // Rust gives the message first and the place on the line after
+            problems.push(Problem {
+                path: found[1].to_string(),
+                line: number(found.get(2)),
+                column: number(found.get(3)),
+                message: heading.clone(),
+            });
+        } else if let Some(found) = traceback.captures(&line) {
+            // Python names the places on the way to the error, the last one being where it was
+            problems.push(Problem {
+                path: found[1].to_string(),
+                line: number(found.get(2)),
+                column: 1,
+                message: String::new(),
+            });
+        } else if let Some(found) = plain.captures(&line) {
+            problems.push(Problem {
+                path: found[1].to_string(),
+                line: number(found.get(2)),
+                column: number(found.get(3)),
+                message: found[4].trim().to_string(),
+            });
+        } else if line.starts_with("error") || line.starts_with("warning") {
+            heading = line.trim().to_string();
+        } else if !line.starts_with(' ') && !line.trim().is_empty() {
+            // The line after a traceback says what the error was
+            for problem in problems.iter_mut().rev() {
+                if !problem.message.is_empty() {
+                    break;
+                }
+                problem.message = line.trim().to_string();
+            }
+        }
+    }
+    problems
+}
+
+pub fn search(pattern: &str, root: &Path) -> io::Result<Vec<Problem>> {
+    // Find every line under a directory that matches, with ripgrep when it is there
+    let output = Command::new("rg")
+        .args(["--vimgrep", "--color", "never", "--regexp", pattern])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        // Ripgrep finishes with 1 when nothing matched and 2 when something went wrong
+        Ok(output) if output.status.code() == Some(2) => Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+        Ok(output) => Ok(parse(&String::from_utf8_lossy(&output.stdout))),
+        Err(error) if error.kind() == ErrorKind::NotFound => walk(pattern, root),
+        Err(error) => Err(error),
+    }
+}
+
+fn walk(pattern: &str, root: &Path) -> io::Result<Vec<Problem>> {
+    // Look through the files one by one, skipping the ones that aren't text
+    let re = Regex::new(pattern).map_err(|error| io::Error::new(ErrorKind::InvalidInput, error))?;
+    let mut files = finder::files(root);
+    files.sort();
+    let mut found = vec![];
+    for path in files {
+        let Ok(text) = fs::read_to_string(root.join(&path)) else {
+            continue;
+        };
+        for (index, line) in text.lines().enumerate() {
+            if let Some(m) = re.find(line) {
+                found.push(Problem {
+                    path: path.clone(),
+                    line: index + 1,
+                    column: line[..m.start()].chars().count() + 1,
+                    message: line.trim().to_string(),
+                });
+            }
+        }
+    }
+    Ok(found)
+}
@@ -0,0 +1,140 @@
+// Remote.rs - Letting other programs drive the editor through a local socket
+use serde_json::Value;
+use std::io::{self, ErrorKind};
+#[cfg(unix)]
+use {
+    serde_json::json,
+    std::fs,
+    std::io::{Read, Write},
+    std::os::unix::fs::FileTypeExt,
+    std::os::unix::net::{UnixListener, UnixStream},
+    std::path::PathBuf,
+    std::time::Duration,
+};
+
+#[cfg(unix)]
+struct Client {
+    stream: UnixStream, // The connection to the program
+    buffer: Vec<u8>,    // What it sent that doesn't make up a whole line yet
+    open: bool,         // Whether the program is still connected
+}
+
+#[cfg(unix)]
+pub struct Remote {
+    path: PathBuf,          // Where the socket is
+    listener: UnixListener, // The socket that programs connect to
+    clients: Vec<Client>,   // The programs that are connected
+}
+
+#[cfg(unix)]
+impl Remote {
+    pub fn listen(path: &str) -> io::Result<Self> {
+        // Open a socket, replacing one that an earlier session left behind
+        let path = PathBuf::from(path);
+        if fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            path,
+            listener,
+            clients: vec![],
+        })
+    }
+    pub fn receive(&mut self) -> Vec<(usize, Value)> {
+        // Take in new programs and the messages sent since last time, with who sent them
+        while let Ok((stream, _)) = self.listener.accept() {
+            // Answers wait a little for programs that are slow to read them, then give up
+            let ready = stream.set_nonblocking(true).is_ok()
+                && stream
+                    .set_write_timeout(Some(Duration::from_secs(1)))
+                    .is_ok();
+            if ready {
+                self.clients.push(Client {
+                    stream,
+                    buffer: vec![],
+                    open: true,
+                });
+            }
+        }
+        let mut messages = vec![];
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            let mut chunk = [0; 4096];
+            loop {
+                match client.stream.read(&mut chunk) {
+                    Ok(0) => client.open = false,
+                    Ok(read) => {
+                        client.buffer.extend_from_slice(&chunk[..read]);
+                        continue;
+                    }
+                    Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                    Err(error) => client.open &= error.kind() == ErrorKind::WouldBlock,
+                }
+                break;
+            }
+            while let Some(end) = client.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = client.buffer.drain(..=end).collect();
+                match serde_json::from_slice(&line) {
+                    Ok(message) => messages.push((index, message)),
+                    Err(error) if !line.trim_ascii().is_empty() => {
+                        client.send(&json!({"id": null, "error": error.to_string()}));
+                    }
+                    Err(_) => (),
+                }
+            }
+        }
+        messages
+    }
+    pub fn reply(&mut self, client: usize, id: &Value, result: Result<Value, String>) {
+        // Answer a request from a program
+        let reply = match result {
+            Ok(result) => json!({"id": id, "result": result}),
+            Err(error) => json!({"id": id, "error": error}),
+        };
+        if let Some(client) = self.clients.get_mut(client) {
+            client.send(&reply);
+        }
+    }
+    pub fn forget(&mut self) {
+        // Let go of the programs that have disconnected
+        self.clients.retain(|client| client.open);
+    }
+}
+
+#[cfg(unix)]
+impl Client {
+    fn send(&mut self, message: &Value) {
+        // Write a message on a line of its own, letting go of programs that can't take it
+        let _ = self.stream.set_nonblocking(false);
+        self.open &= writeln!(self.stream, "{message}").is_ok();
+        let _ = self.stream.set_nonblocking(true);
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Remote {
+    fn drop(&mut self) {
+        // Take the socket away with the editor
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(not(unix))]
+pub struct Remote;
+
+#[cfg(not(unix))]
+impl Remote {
+    pub fn listen(_: &str) -> io::Result<Self> {
+        // Sockets like these are only found on Unix systems
+        Err(io::Error::new(
+            ErrorKind::Other,
+            "Listening for other programs needs a Unix system",
+        ))
+    }
+    pub fn receive(&mut self) -> Vec<(usize, Value)> {
+        vec![]
+    }
+    pub fn reply(&mut self, _: usize, _: &Value, _: Result<Value, String>) {}
+    pub fn forget(&mut self) {}
+}
@@ -1,8 +1,10 @@
 // Row.rs - Handling the rows of a document and their appearance
 use crate::config::{Reader, TokenType};
 use crate::editor::{RESET_BG, RESET_FG};
+use crate::git::Change;
 use crate::highlight::{highlight, remove_nested_tokens, Token};
 use crate::util::{safe_ansi_insert, Exp};
+use crossterm::style::Attribute;
 use std::collections::HashMap;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
@@ -14,6 +16,10 @@ pub struct Row {
     pub syntax: HashMap<usize, Token>,    // Hashmap for syntax
     pub bg_syntax: HashMap<usize, Token>, // Hashmap for background syntax colour
     pub updated: bool,                    // Line needs to be redrawn
+    pub mark: Option<bool>,               // Whether a language server found an error or a warning
+    pub change: Option<Change>,           // How the line differs from the one git has
+    pub bookmark: Option<char>,           // The letter of a mark set on the line
+    pub symbols: HashMap<usize, char>,    // Symbols drawn over spaces, such as indent guides
     regex: Exp,                           // For holding the regex expression
 }
 
@@ -27,57 +33,140 @@ impl From<&str> for Row {
             bg_syntax: HashMap::new(),
             regex: Exp::new(),
             updated: true,
+            mark: None,
+            change: None,
+            bookmark: None,
+            symbols: HashMap::new(),
         }
     }
 }
 
 // Add methods to the Row struct / class
 impl Row {
-    pub fn render_line_number(config: &Reader, offset: usize, index: usize) -> String {
+    pub fn render_line_number(
+        config: &Reader,
+        offset: usize,
+        index: &str,
+        mark: Option<bool>,
+        change: Option<Change>,
+        bookmark: Option<char>,
+    ) -> String {
+        let editor_bg = if config.theme.transparent_editor {
+            RESET_BG.to_string()
+        } else {
+            Reader::rgb_bg(config.theme.editor_bg).to_string()
+        };
+        if !config.general.line_numbers {
+            // Only set up the colours for the text
+            return format!("{}{}", Reader::rgb_fg(config.theme.editor_fg), editor_bg);
+        }
         let post_padding = offset.saturating_sub(
-            index.to_string().len() +         // Length of the number
+            index.len() +                     // Length of the number
             config.general.line_number_padding_right + // Length of the right padding
             config.general.line_number_padding_left, // Length of the left padding
         );
+        // Problems are marked in the first space of the padding, or else a mark's letter
+        let mut padding = " ".repeat(config.general.line_number_padding_left + post_padding);
+        if let (None, Some(letter), false) = (mark, bookmark, padding.is_empty()) {
+            padding.replace_range(
+                ..1,
+                &format!(
+                    "{}{}{}",
+                    Reader::rgb_fg(config.theme.bookmark_fg),
+                    letter,
+                    Reader::rgb_fg(config.theme.line_number_fg)
+                ),
+            );
+        } else if let (Some(error), false) = (mark, padding.is_empty()) {
+            let colour = if error {
+                config.theme.error_fg
+            } else {
+                config.theme.warning_fg
+            };
+            padding.replace_range(
+                ..1,
+                &format!(
+                    "{}\u{25cf}{}",
+                    Reader::rgb_fg(colour),
+                    Reader::rgb_fg(config.theme.line_number_fg)
+                ),
+            );
+        }
+        // Changes since git last saw the line are marked just after the number
+        let mut right = " ".repeat(config.general.line_number_padding_right);
+        if let (Some(change), false) = (change, right.is_empty()) {
+            let (symbol, colour) = match change {
+                Change::Added => ('+', config.theme.git_added_fg),
+                Change::Modified => ('~', config.theme.git_modified_fg),
+                Change::Removed => ('-', config.theme.git_removed_fg),
+            };
+            right.replace_range(..1, &format!("{}{}", Reader::rgb_fg(colour), symbol));
+        }
         format!(
-            "{}{}{}{}{}{}{}{}",
+            "{}{}{}{}{}{}{}",
             if config.theme.transparent_editor {
                 RESET_BG
             } else {
                 Reader::rgb_bg(config.theme.line_number_bg)
             },
             Reader::rgb_fg(config.theme.line_number_fg),
-            " ".repeat(config.general.line_number_padding_left),
-            " ".repeat(post_padding),
+            padding,
             index,
-            " ".repeat(config.general.line_number_padding_right),
+            right,
             Reader::rgb_fg(config.theme.editor_fg),
-            if config.theme.transparent_editor {
-                RESET_BG
-            } else {
-                Reader::rgb_bg(config.theme.editor_bg)
-            },
+            editor_bg,
         )
     }
+    #[allow(clippy::too_many_lines)] // Highlighting, selection and whitespace in a single pass
     pub fn render(
         &self,
         mut start: usize,
         width: usize,
-        index: usize,
+        label: usize,
         offset: usize,
         config: &Reader,
+        number: bool,
     ) -> String {
         // Render the row by trimming it to the correct size
-        let index = index.saturating_add(1);
         // Padding to align line numbers to the right
         // Assemble the line number data
-        let line_number = Row::render_line_number(config, offset, index);
+        let mut index = label.to_string();
+        if !number {
+            // Wrapped continuation of a line, leave the line number blank
+            index = " ".repeat(index.len());
+        }
+        let (mark, change, bookmark) = if number {
+            (self.mark, self.change, self.bookmark)
+        } else {
+            (None, None, None)
+        };
+        let line_number = Row::render_line_number(config, offset, &index, mark, change, bookmark);
         // Strip ANSI values from the line
         let line_number_len = self.regex.ansi_len(&line_number);
         let width = width.saturating_sub(line_number_len);
         let reset_foreground = RESET_FG.to_string();
         let reset_background = RESET_BG.to_string();
         let editor_bg = Reader::rgb_bg(config.theme.editor_bg).to_string();
+        let no_underline = Attribute::NoUnderline.to_string();
+        let no_reverse = Attribute::NoReverse.to_string();
+        // Symbols over spaces are coloured apart from the text around them
+        let text_fg = Reader::rgb_fg(config.theme.editor_fg);
+        let drawn: HashMap<usize, String> = self
+            .symbols
+            .iter()
+            .map(|(column, symbol)| {
+                let colour = if *symbol == '\u{2502}' {
+                    config.theme.guide_fg
+                } else {
+                    config.theme.whitespace_fg
+                };
+                (
+                    *column,
+                    format!("{}{}{}", Reader::rgb_fg(colour), symbol, text_fg),
+                )
+            })
+            .collect();
+        let origin = start;
         let mut initial = start;
         let mut result = vec![];
         // Ensure that the render isn't impossible
@@ -121,7 +210,7 @@ impl Row {
                         result.push(" ");
                         break 'a;
                     }
-                    result.push(ch);
+                    result.push(drawn.get(&start).map_or(ch, String::as_str));
                     start += UnicodeWidthStr::width(*ch);
                 } else {
                     // The quota has been used up
@@ -132,7 +221,7 @@ impl Row {
             let initial_initial = initial; // Terrible variable naming, I know
             if initial > 0 {
                 // Calculate the last token start boundary
-                while self.syntax.get(&initial).is_none() && initial > 0 {
+                while !self.syntax.contains_key(&initial) && initial > 0 {
                     initial -= 1;
                 }
                 // Verify that the token actually exists
@@ -154,25 +243,42 @@ impl Row {
                     }
                 }
             }
-            // Insert background tokens
-            for b in &self.bg_syntax {
-                let bg = if config.theme.transparent_editor {
+            // Insert background tokens, their spans are relative to the start of the row
+            for b in self.bg_syntax.values() {
+                let bg = if matches!(b.name.as_str(), "diagnostic" | "spelling") {
+                    // Problems underline the text rather than colouring behind it
+                    &no_underline
+                } else if b.name == "cursor" {
+                    &no_reverse
+                } else if config.theme.transparent_editor {
                     &reset_background
                 } else {
                     &editor_bg
                 };
-                if let Some(a) = safe_ansi_insert(b.1.span.0, &result, &self.regex.ansi) {
-                    if a < result.len() {
-                        result.insert(a, &b.1.kind);
-                    }
-                };
-                if let Some(a) = safe_ansi_insert(b.1.span.1, &result, &self.regex.ansi) {
-                    if a < result.len() {
-                        result.insert(a, bg);
-                    }
-                };
+                if b.span.1 <= initial_initial {
+                    // Token is scrolled off to the left
+                    continue;
+                }
+                let end = b.span.1 - initial_initial;
+                if let Some(a) = safe_ansi_insert(end, &result, &self.regex.ansi) {
+                    result.insert(a, bg);
+                }
+                let begin = b.span.0.saturating_sub(initial_initial);
+                if let Some(a) = safe_ansi_insert(begin, &result, &self.regex.ansi) {
+                    result.insert(a, &b.kind);
+                }
             }
         }
+        // A cursor just past the end of the line is shown on a space
+        let length = self.length();
+        let after = self
+            .bg_syntax
+            .values()
+            .any(|b| b.name == "cursor" && b.span.0 == length);
+        let end_cursor = format!("{} {}", Attribute::Reverse, no_reverse);
+        if after && length >= origin && length < origin + width {
+            result.push(&end_cursor);
+        }
         // Return the full line string to be rendered
         line_number + &result.join("")
     }
@@ -186,16 +292,52 @@ impl Row {
     ) {
         // Update the syntax highlighting indices for this row
         self.syntax = remove_nested_tokens(
-            &highlight(
-                &self.string,
-                &doc,
-                index,
-                &syntax,
-                &config.highlights[theme],
-            ),
+            &highlight(&self.string, doc, index, syntax, &config.highlights[theme]),
             &self.string,
         );
     }
+    pub fn find_symbols(
+        &self,
+        guides: bool,
+        tabs: bool,
+        trailing: bool,
+        tab_width: usize,
+    ) -> HashMap<usize, char> {
+        // Work out what to draw over the spaces of the row rather than leaving them blank
+        let mut symbols = HashMap::new();
+        let content = self.string.trim_end_matches(' ');
+        let indent = content.len() - content.trim_start_matches(' ').len();
+        if guides || tabs {
+            // Each level of indentation starts with a guide, or an arrow where it was a tab
+            for column in (0..indent).step_by(tab_width.max(1)) {
+                symbols.insert(column, if tabs { '\u{2192}' } else { '\u{2502}' });
+            }
+        }
+        if trailing {
+            for column in content.width()..self.length() {
+                symbols.insert(column, '\u{b7}');
+            }
+        }
+        symbols
+    }
+    pub fn wrap_points(&self, width: usize) -> Vec<usize> {
+        // Find the columns where this row breaks onto a new screen row
+        let mut points = vec![0];
+        let mut column = 0;
+        for ch in self.string.graphemes(true) {
+            let start = *points.last().unwrap();
+            let ch = UnicodeWidthStr::width(ch);
+            if column + ch > start + width && column > start {
+                points.push(column);
+            }
+            column += ch;
+        }
+        // Leave room for the cursor at the end of a full screen row
+        if column >= *points.last().unwrap() + width {
+            points.push(column);
+        }
+        points
+    }
     pub fn length(&self) -> usize {
         // Get the current length of the row
         UnicodeWidthStr::width(&self.string[..])
@@ -230,31 +372,49 @@ impl Row {
         }
         result
     }
-    pub fn insert(&mut self, ch: char, pos: usize) {
-        // Insert a character
-        self.updated = true;
-        let mut before: String = self.string.graphemes(true).take(pos as usize).collect();
-        let after: String = self.string.graphemes(true).skip(pos as usize).collect();
-        before.push(ch);
-        before.push_str(&after);
-        self.string = before;
+    pub fn brackets(&self) -> Vec<(usize, char)> {
+        // Find the brackets in the row that aren't in strings or comments
+        self.boundaries()
+            .into_iter()
+            .zip(self.chars())
+            .filter_map(|(column, ch)| match ch {
+                "(" | ")" | "[" | "]" | "{" | "}" => Some((column, ch.chars().next()?)),
+                _ => None,
+            })
+            .filter(|(column, _)| {
+                !self.syntax.values().any(|token| {
+                    (token.span.0..token.span.1).contains(column)
+                        && matches!(token.name.as_str(), "strings" | "comments" | "characters")
+                })
+            })
+            .collect()
     }
-    pub fn delete(&mut self, pos: usize) -> Option<char> {
-        // Remove a character
-        self.updated = true;
-        let before: String = self.string.graphemes(true).take(pos as usize).collect();
-        let after: String = self.string.graphemes(true).skip(1 + pos as usize).collect();
-        let result: Option<char>;
-        if let Some(c) = self.chars().get(pos) {
-            if let Ok(c) = c.parse() {
-                result = Some(c);
-            } else {
-                result = None;
+    pub fn byte_index(&self, column: usize) -> usize {
+        // Find where the character at a display column starts in the string
+        let mut width = 0;
+        for (index, ch) in self.string.grapheme_indices(true) {
+            if width >= column {
+                return index;
             }
-        } else {
-            result = None;
+            width += UnicodeWidthStr::width(ch);
         }
-        self.string = before + &after;
-        result
+        self.string.len()
+    }
+    pub fn slice(&self, start: usize, end: usize) -> &str {
+        // Get the characters between two display columns
+        &self.string[self.byte_index(start)..self.byte_index(end)]
+    }
+    pub fn insert(&mut self, text: &str, column: usize) -> usize {
+        // Insert text, returning the column just after it
+        self.updated = true;
+        let index = self.byte_index(column);
+        self.string.insert_str(index, text);
+        UnicodeWidthStr::width(&self.string[..index + text.len()])
+    }
+    pub fn delete(&mut self, start: usize, end: usize) -> String {
+        // Remove the characters between two display columns
+        self.updated = true;
+        let range = self.byte_index(start)..self.byte_index(end);
+        self.string.drain(range).collect()
     }
 }
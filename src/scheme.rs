@@ -0,0 +1,356 @@
+// Scheme.rs - Taking in base16 schemes and VS Code colour themes as Ox themes
+use crate::config::{Reader, Theme};
+use crate::log;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+type Colour = (u8, u8, u8);
+
+// The scopes of VS Code themes that colour each kind of token, the most fitting first
+const SCOPES: &[(&str, &[&str])] = &[
+    ("comments", &["comment"]),
+    ("keywords", &["keyword.control", "keyword", "storage"]),
+    (
+        "namespaces",
+        &["entity.name.namespace", "entity.name.module", "storage"],
+    ),
+    ("references", &["variable.other", "variable"]),
+    ("strings", &["string.quoted", "string"]),
+    ("characters", &["constant.character", "string"]),
+    ("digits", &["constant.numeric", "constant"]),
+    ("booleans", &["constant.language", "constant"]),
+    ("functions", &["entity.name.function", "support.function"]),
+    (
+        "structs",
+        &["entity.name.type", "entity.name.class", "support.type"],
+    ),
+    (
+        "macros",
+        &[
+            "entity.name.function.macro",
+            "entity.name.function.preprocessor",
+            "entity.name.function",
+        ],
+    ),
+    (
+        "attributes",
+        &["meta.attribute", "entity.other.attribute-name"],
+    ),
+    ("headers", &["markup.heading", "entity.name.section"]),
+    ("symbols", &["punctuation"]),
+    ("global", &["variable.other.constant", "constant"]),
+    ("operators", &["keyword.operator", "keyword"]),
+    ("regex", &["string.regexp", "string"]),
+];
+
+fn hex(text: &str) -> Option<(Colour, u8)> {
+    // Read a colour like #rgb, #rrggbb or either with an alpha on the end, the # being optional
+    let digits = text.trim().trim_matches('"').trim_start_matches('#');
+    let expanded: String = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => digits.to_string(),
+        _ => return None,
+    };
+    let channel = |index: usize| u8::from_str_radix(expanded.get(index..index + 2)?, 16).ok();
+    let alpha = if expanded.len() == 8 {
+        channel(6)?
+    } else {
+        255
+    };
+    Some(((channel(0)?, channel(2)?, channel(4)?), alpha))
+}
+
+fn blend(under: Colour, over: Colour, amount: u8) -> Colour {
+    // Lay one colour over another, as much as the amount out of 255 lets through
+    let mix = |under: u8, over: u8| {
+        let amount = u16::from(amount);
+        ((u16::from(under) * (255 - amount) + u16::from(over) * amount) / 255) as u8
+    };
+    (
+        mix(under.0, over.0),
+        mix(under.1, over.1),
+        mix(under.2, over.2),
+    )
+}
+
+fn theme(name: &str, colour: &dyn Fn(&str, Colour) -> Colour, bg: Colour, fg: Colour) -> Theme {
+    // Fill in a theme from the colours a scheme names, falling back on mixes of its text and
+    // background for the ones it leaves out
+    let shade = |amount: u8| blend(bg, fg, amount);
+    let status_bg = colour("statusBar.background", shade(25));
+    let error = colour("editorError.foreground", (224, 113, 113));
+    let warning = colour("editorWarning.foreground", (208, 164, 79));
+    let added = colour("editorGutter.addedBackground", (35, 240, 144));
+    let modified = colour("editorGutter.modifiedBackground", (130, 170, 255));
+    let removed = colour("editorGutter.deletedBackground", error);
+    Theme {
+        transparent_editor: false,
+        editor_bg: bg,
+        editor_fg: fg,
+        status_bg,
+        status_fg: colour("statusBar.foreground", fg),
+        line_number_fg: colour("editorLineNumber.foreground", shade(90)),
+        line_number_bg: colour("editorGutter.background", bg),
+        line_highlight_bg: colour("editor.lineHighlightBackground", shade(15)),
+        selection_bg: colour("editor.selectionBackground", shade(50)),
+        bracket_bg: colour("editorBracketMatch.background", shade(70)),
+        git_added_fg: added,
+        git_modified_fg: modified,
+        git_removed_fg: removed,
+        bookmark_fg: colour("editorBookmark.foreground", modified),
+        diff_added_bg: colour("diffEditor.insertedTextBackground", blend(bg, added, 50)),
+        diff_removed_bg: colour("diffEditor.removedTextBackground", blend(bg, removed, 50)),
+        diff_modified_bg: colour("diffEditor.modifiedTextBackground", blend(bg, modified, 50)),
+        guide_fg: colour("editorIndentGuide.background", shade(40)),
+        whitespace_fg: colour("editorWhitespace.foreground", shade(70)),
+        ruler_bg: blend(bg, colour("editorRuler.foreground", shade(90)), 80),
+        inactive_tab_fg: colour("tab.inactiveForeground", shade(180)),
+        inactive_tab_bg: colour("tab.inactiveBackground", status_bg),
+        active_tab_fg: colour("tab.activeForeground", fg),
+        active_tab_bg: colour("tab.activeBackground", bg),
+        warning_fg: warning,
+        error_fg: error,
+        info_fg: fg,
+        default_theme: name.to_string(),
+        fallback: false,
+    }
+}
+
+fn base16(name: &str, text: &str) -> Result<(Theme, HashMap<String, Colour>), String> {
+    // Read a base16 scheme, a YAML file of sixteen named colours, flat or under a palette
+    let mut base = HashMap::new();
+    for line in text.lines() {
+        let line = line.split(" #").next().unwrap_or_default();
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim().to_lowercase();
+            if key.len() == 6 && key.starts_with("base") {
+                if let Some((colour, _)) = hex(value) {
+                    base.insert(key[4..].to_string(), colour);
+                }
+            }
+        }
+    }
+    let mut colours = vec![];
+    for index in 0..16 {
+        let key = format!("{index:02x}");
+        colours.push(
+            *base
+                .get(&key)
+                .ok_or_else(|| format!("The scheme has no base{}", key.to_uppercase()))?,
+        );
+    }
+    let c = |index: usize| colours[index];
+    // Each colour has a part it plays, which the base16 styling guidelines lay out
+    let ui: HashMap<&str, Colour> = [
+        ("statusBar.background", c(1)),
+        ("statusBar.foreground", c(4)),
+        ("editorLineNumber.foreground", c(3)),
+        ("editor.lineHighlightBackground", c(1)),
+        ("editor.selectionBackground", c(2)),
+        ("editorBracketMatch.background", c(3)),
+        ("editorGutter.addedBackground", c(11)),
+        ("editorGutter.modifiedBackground", c(14)),
+        ("editorGutter.deletedBackground", c(8)),
+        ("editorBookmark.foreground", c(13)),
+        ("editorIndentGuide.background", c(2)),
+        ("editorWhitespace.foreground", c(3)),
+        ("tab.inactiveForeground", c(4)),
+        ("tab.inactiveBackground", c(1)),
+        ("editorWarning.foreground", c(10)),
+        ("editorError.foreground", c(8)),
+    ]
+    .iter()
+    .copied()
+    .collect();
+    let colour = |key: &str, default: Colour| ui.get(key).copied().unwrap_or(default);
+    let theme = theme(name, &colour, c(0), c(5));
+    let highlights = [
+        ("comments", c(3)),
+        ("keywords", c(14)),
+        ("namespaces", c(14)),
+        ("references", c(8)),
+        ("strings", c(11)),
+        ("characters", c(11)),
+        ("digits", c(9)),
+        ("booleans", c(9)),
+        ("functions", c(13)),
+        ("structs", c(10)),
+        ("macros", c(12)),
+        ("attributes", c(15)),
+        ("headers", c(13)),
+        ("symbols", c(5)),
+        ("global", c(8)),
+        ("operators", c(12)),
+        ("regex", c(12)),
+        ("search_active", blend(c(0), c(10), 110)),
+        ("search_inactive", blend(c(0), c(10), 55)),
+    ];
+    Ok((
+        theme,
+        highlights
+            .iter()
+            .map(|(group, colour)| ((*group).to_string(), *colour))
+            .collect(),
+    ))
+}
+
+fn strip_comments(text: &str) -> String {
+    // VS Code allows comments and commas before closing brackets, which JSON itself doesn't
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut index = 0;
+    let mut string = false;
+    while index < chars.len() {
+        let (c, next) = (chars[index], chars.get(index + 1).copied());
+        if string {
+            result.push(c);
+            if c == '\\' {
+                if let Some(next) = next {
+                    result.push(next);
+                    index += 1;
+                }
+            } else if c == '"' {
+                string = false;
+            }
+        } else if c == '/' && next == Some('/') {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        } else if c == '/' && next == Some('*') {
+            index += 2;
+            while index < chars.len()
+                && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+            {
+                index += 1;
+            }
+            index += 1;
+        } else {
+            if matches!(c, '}' | ']') && result.trim_end().ends_with(',') {
+                // A closing bracket takes away the comma before it
+                result.truncate(result.trim_end().len() - 1);
+            }
+            string = c == '"';
+            result.push(c);
+        }
+        index += 1;
+    }
+    result
+}
+
+fn vscode(name: &str, text: &str) -> Result<(Theme, HashMap<String, Colour>), String> {
+    // Read a VS Code colour theme, mapping the scopes of its token colours onto Ox's kinds
+    let json: Value = serde_json::from_str(&strip_comments(text))
+        .map_err(|error| format!("The theme isn't valid JSON: {error}"))?;
+    let colours = json["colors"].as_object().cloned().unwrap_or_default();
+    let rules = json["tokenColors"].as_array().cloned().unwrap_or_default();
+    // Rules without a scope set the text and background of the whole editor
+    let global = rules
+        .iter()
+        .find(|rule| rule.get("scope").is_none())
+        .map(|rule| &rule["settings"]);
+    let light = json["type"].as_str() == Some("light");
+    let read = |key: &str| colours.get(key).and_then(Value::as_str).and_then(hex);
+    let setting = |key: &str| global.and_then(|s| s[key].as_str()).and_then(hex);
+    let bg = read("editor.background")
+        .or_else(|| setting("background"))
+        .map_or(if light { (255, 255, 255) } else { (30, 30, 30) }, |c| c.0);
+    let fg = read("editor.foreground")
+        .or_else(|| setting("foreground"))
+        .map_or(if light { (0, 0, 0) } else { (212, 212, 212) }, |c| c.0);
+    // Colours that can be seen through are laid over the background
+    let colour = |key: &str, default: Colour| {
+        read(key).map_or(default, |(colour, alpha)| blend(bg, colour, alpha))
+    };
+    let theme = theme(name, &colour, bg, fg);
+    // The rule for a scope is the most specific one wholly covering it, as VS Code does
+    let mut painted: Vec<(String, Colour)> = vec![];
+    for rule in &rules {
+        let foreground = rule["settings"]["foreground"].as_str().and_then(hex);
+        let scopes: Vec<String> = match &rule["scope"] {
+            Value::String(scopes) => scopes.split(',').map(|s| s.trim().to_string()).collect(),
+            Value::Array(scopes) => scopes
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|s| s.trim().to_string())
+                .collect(),
+            _ => vec![],
+        };
+        if let Some((foreground, alpha)) = foreground {
+            for scope in scopes {
+                painted.push((scope, blend(bg, foreground, alpha)));
+            }
+        }
+    }
+    let find = |wanted: &str| {
+        painted
+            .iter()
+            .filter(|(scope, _)| wanted == scope || wanted.starts_with(&format!("{scope}.")))
+            .max_by_key(|(scope, _)| scope.len())
+            .map(|(_, colour)| *colour)
+    };
+    let mut highlights: HashMap<String, Colour> = SCOPES
+        .iter()
+        .map(|(group, wanted)| {
+            let found = wanted.iter().find_map(|scope| find(scope)).unwrap_or(fg);
+            ((*group).to_string(), found)
+        })
+        .collect();
+    highlights.insert(
+        "search_active".to_string(),
+        colour("editor.findMatchBackground", blend(bg, fg, 80)),
+    );
+    highlights.insert(
+        "search_inactive".to_string(),
+        colour("editor.findMatchHighlightBackground", blend(bg, fg, 40)),
+    );
+    Ok((theme, highlights))
+}
+
+pub fn load(path: &Path) -> Result<(String, Theme, HashMap<String, Colour>), String> {
+    // Read a base16 scheme or a VS Code theme, named after its file
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .ok_or_else(|| "The theme needs a file name".to_string())?;
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let (theme, highlights) = match extension.as_deref() {
+        Some("yaml" | "yml") => base16(&name, &text)?,
+        Some("json") => vscode(&name, &text)?,
+        _ => return Err("Themes are read from .yaml and .json files".to_string()),
+    };
+    Ok((name, theme, highlights))
+}
+
+pub fn import(reader: &mut Reader, path: &Path) -> Result<String, String> {
+    // Add a theme from a file to the ones that can be switched to, giving its name
+    let (name, theme, highlights) = load(path)?;
+    reader.themes.insert(name.clone(), theme);
+    reader.highlights.insert(name.clone(), highlights);
+    Ok(name)
+}
+
+pub fn import_dir(reader: &mut Reader, dir: &Path) {
+    // Take in every theme in a directory, leaving the ones the config gives as they are
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let taken = path
+            .file_stem()
+            .is_some_and(|stem| reader.themes.contains_key(&*stem.to_string_lossy()));
+        if taken {
+            continue;
+        }
+        if let Err(error) = import(reader, &path) {
+            log!(error, "Theme", format!("{}: {}", path.display(), error));
+        }
+    }
+}
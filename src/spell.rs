@@ -0,0 +1,118 @@
+// Spell.rs - Finding misspelt words and what might have been meant instead
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use unicode_width::UnicodeWidthStr;
+
+pub struct Dictionary {
+    words: HashSet<String>, // Every known word, in lowercase
+}
+
+impl Dictionary {
+    pub fn open(path: &str) -> io::Result<Self> {
+        // Read a list of words, one on each line
+        let words = fs::read_to_string(path)?
+            .lines()
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        Ok(Self { words })
+    }
+    pub fn knows(&self, word: &str) -> bool {
+        // Check a word, allowing for it being possessive
+        let word = word.to_lowercase();
+        self.words.contains(&word)
+            || word
+                .strip_suffix("'s")
+                .is_some_and(|word| self.words.contains(word))
+    }
+    pub fn mistakes(&self, line: &str) -> Vec<(usize, usize)> {
+        // The display columns of the words in a line that aren't known
+        words(line)
+            .into_iter()
+            .filter(|(_, word)| !self.knows(word))
+            .map(|(start, word)| (start, start + word.width()))
+            .collect()
+    }
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        // The known words closest to a misspelt one, in the same case
+        let lower: Vec<char> = word.to_lowercase().chars().collect();
+        let mut close: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|known| known.chars().count().abs_diff(lower.len()) <= 2)
+            .filter_map(|known| {
+                let distance = distance(&lower, &known.chars().collect::<Vec<char>>());
+                (distance <= 2).then_some((distance, known))
+            })
+            .collect();
+        close.sort();
+        let capital = word.chars().next().is_some_and(char::is_uppercase);
+        close
+            .into_iter()
+            .take(limit)
+            .map(|(_, known)| {
+                if capital {
+                    let mut chars = known.chars();
+                    chars.next().map_or(String::new(), |first| {
+                        first.to_uppercase().chain(chars).collect()
+                    })
+                } else {
+                    known.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn words(line: &str) -> Vec<(usize, &str)> {
+    // The words in a line worth checking and the columns they start at, leaving out
+    // parts of names in code such as snake_case, camelCase, ACRONYMS and numbers
+    let mut found = vec![];
+    let mut column = 0;
+    let mut start = None;
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    for (i, &(index, c)) in chars.iter().enumerate() {
+        let inner = c == '\''
+            && start.is_some()
+            && chars
+                .get(i + 1)
+                .is_some_and(|(_, next)| next.is_alphabetic());
+        if c.is_alphabetic() || inner {
+            start = start.or(Some((index, column)));
+        } else if let Some((begin, at)) = start.take() {
+            found.push((begin, at, index, c));
+        }
+        column += c.to_string().width();
+    }
+    if let Some((begin, at)) = start {
+        found.push((begin, at, line.len(), ' '));
+    }
+    found
+        .into_iter()
+        .filter(|&(begin, _, end, after)| {
+            let word = &line[begin..end];
+            let before = line[..begin].chars().next_back().unwrap_or(' ');
+            let joined = |c: char| c.is_alphanumeric() || c == '_';
+            word.chars().count() > 1
+                && !word.chars().skip(1).any(char::is_uppercase)
+                && !joined(before)
+                && !joined(after)
+        })
+        .map(|(begin, at, end, _)| (at, &line[begin..end]))
+        .collect()
+}
+
+fn distance(a: &[char], b: &[char]) -> usize {
+    // How many characters have to be added, removed or changed to turn one word into another
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let change = previous[j] + usize::from(x != y);
+            current.push(change.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
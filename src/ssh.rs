@@ -0,0 +1,62 @@
+// Ssh.rs - Opening and saving files on other machines, given as user@host:/path/to/file
+use crate::util::tool_error;
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+
+pub fn parse(path: &str) -> Option<(&str, &str)> {
+    // Split a path into the machine and the file on it, when it names another machine
+    // The host can't hold a slash, and one letter before the colon is a Windows drive
+    let (host, file) = path.split_once(':')?;
+    let named = host.contains('@') || file.starts_with('/') || file.starts_with('~');
+    (host.len() > 1 && !host.contains(['/', '\\']) && !file.is_empty() && named)
+        .then_some((host, file))
+}
+
+fn quote(file: &str) -> String {
+    // Quote a file for the shell on the other machine, which starts in the home directory
+    let file = file.strip_prefix("~/").unwrap_or(file);
+    format!("'{}'", file.replace('\'', "'\\''"))
+}
+
+fn ssh(host: &str, command: &str, input: Option<&[u8]>) -> io::Result<Output> {
+    // Run a command on another machine, failing with what ssh said when it didn't work
+    // Nothing can be typed in while Ox has the terminal, so passwords aren't asked for
+    let mut child = Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            host,
+            command,
+        ])
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| io::Error::new(error.kind(), format!("couldn't run ssh: {error}")))?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(output);
+    }
+    Err(io::Error::other(tool_error("ssh", &output)))
+}
+
+pub fn fetch(host: &str, file: &str) -> io::Result<Vec<u8>> {
+    // Read a file from another machine, where one that isn't there yet reads as empty
+    let file = quote(file);
+    let command = format!("test ! -e {file} || cat -- {file}");
+    Ok(ssh(host, &command, None)?.stdout)
+}
+
+pub fn store(host: &str, file: &str, bytes: &[u8]) -> io::Result<()> {
+    // Write a file on another machine
+    ssh(host, &format!("cat > {}", quote(file)), Some(bytes)).map(|_| ())
+}
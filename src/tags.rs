@@ -0,0 +1,108 @@
+// Tags.rs - Looking up where symbols are defined in a tags file made by ctags
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// How a tags file says to find a definition in its file
+#[derive(Debug, Clone)]
+pub enum Address {
+    Line(usize),     // The line, counting from 1
+    Pattern(String), // The text of the line, when it was written as a search
+}
+
+// A place a symbol is defined
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub path: PathBuf,    // The file, found from where the tags file is
+    pub address: Address, // Where in the file it is
+    pub kind: String,     // What sort of thing it is, such as f for a function
+}
+
+impl Tag {
+    pub fn line(&self, rows: &[&str]) -> Option<usize> {
+        // The line the definition is on, counting from 0
+        match &self.address {
+            Address::Line(line) => Some(line.saturating_sub(1)),
+            Address::Pattern(text) => rows
+                .iter()
+                .position(|row| row == text)
+                .or_else(|| rows.iter().position(|row| row.contains(text.as_str()))),
+        }
+    }
+    pub fn describe(&self) -> String {
+        // A line about the tag for picking between definitions
+        let place = match &self.address {
+            Address::Line(line) => line.to_string(),
+            Address::Pattern(text) => text.trim().to_string(),
+        };
+        let path = env::current_dir()
+            .ok()
+            .and_then(|cwd| self.path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| self.path.clone());
+        format!("{} ({}) {}", path.display(), self.kind, place)
+    }
+}
+
+pub fn locate(start: &Path) -> Option<PathBuf> {
+    // Find the tags file of the project, looking up from a directory
+    start
+        .ancestors()
+        .map(|dir| dir.join("tags"))
+        .find(|path| path.is_file())
+}
+
+fn parse(line: &str) -> Option<(&str, &str, Address, String)> {
+    // Read the symbol, file, address and kind of a line of a tags file
+    let mut fields = line.splitn(3, '\t');
+    let name = fields.next()?;
+    let file = fields.next()?;
+    let rest = fields.next()?;
+    // Extra fields come after the address, behind ;"
+    let (address, extra) = match rest.find(";\"") {
+        Some(end) => (&rest[..end], &rest[end + 2..]),
+        None => (rest, ""),
+    };
+    let kind = extra
+        .split('\t')
+        .find(|field| !field.is_empty() && !field.contains(':'))
+        .or_else(|| {
+            extra
+                .split('\t')
+                .find_map(|field| field.strip_prefix("kind:"))
+        })
+        .unwrap_or("")
+        .to_string();
+    let address = if let Ok(line) = address.parse() {
+        Address::Line(line)
+    } else {
+        // Searches are between slashes or question marks, and may be anchored at either end
+        let inner = address.get(1..address.len().checked_sub(1)?)?;
+        let inner = inner.strip_prefix('^').unwrap_or(inner);
+        let inner = inner.strip_suffix('$').unwrap_or(inner);
+        Address::Pattern(
+            inner
+                .replace("\\/", "/")
+                .replace("\\?", "?")
+                .replace("\\\\", "\\"),
+        )
+    };
+    Some((name, file, address, kind))
+}
+
+pub fn find(tags: &Path, symbol: &str) -> io::Result<Vec<Tag>> {
+    // Look up every definition of a symbol in a tags file
+    let contents = fs::read_to_string(tags)?;
+    let root = tags.parent().unwrap_or_else(|| Path::new("."));
+    Ok(contents
+        .lines()
+        .filter(|line| !line.starts_with("!_TAG_"))
+        .filter_map(parse)
+        .filter(|(name, ..)| *name == symbol)
+        .map(|(_, file, address, kind)| Tag {
+            path: root.join(file),
+            address,
+            kind,
+        })
+        .collect())
+}
@@ -1,92 +1,367 @@
 // Terminal.rs - Handling low level terminal operations
+use crate::input::{Input, Reader};
 use crate::util::Exp;
-use crate::Position;
+use crate::{Position, Size};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event as InputEvent, KeyCode, KeyEvent, KeyModifiers,
+};
 use crossterm::terminal;
 use crossterm::{execute, ErrorKind};
 use std::env;
+use std::fmt::Write as _;
 use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use term::terminfo::TermInfo;
 use unicode_width::UnicodeWidthStr;
 
-// Struct to hold size
-pub struct Size {
-    pub width: usize,
-    pub height: usize,
+// Ask the terminal to mark the start and end of pasted text, and stop asking
+const PASTE_ON: &str = "\x1b[?2004h";
+const PASTE_OFF: &str = "\x1b[?2004l";
+
+// Put the cursor back to the shape the terminal is set up to draw it as
+const SHAPE_RESET: &str = "\x1b[0 q";
+
+// Terminals known to take DECSCUSR for the shape of the cursor, by the start of $TERM
+const SHAPED_TERMS: [&str; 10] = [
+    "xterm",
+    "tmux",
+    "screen",
+    "rxvt",
+    "alacritty",
+    "kitty",
+    "foot",
+    "wezterm",
+    "st",
+    "vte",
+];
+
+// Whether the terminal is in raw mode on the alternate screen, so it is only left once
+static ENTERED: AtomicBool = AtomicBool::new(false);
+
+// Whether the shape of the cursor was changed, so it is put back on the way out
+static SHAPED: AtomicBool = AtomicBool::new(false);
+
+// How the text cursor is drawn, which tells what typing will do
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Shape {
+    Block,     // Keys run commands or act on a selection
+    Bar,       // Keys type text in between characters
+    Underline, // Keys type over the characters under the cursor
+}
+
+impl Shape {
+    fn code(self) -> u8 {
+        // The steady DECSCUSR shape
+        match self {
+            Shape::Block => 2,
+            Shape::Underline => 4,
+            Shape::Bar => 6,
+        }
+    }
+}
+
+// What the editor draws on and reads its input from, so it can run without a real terminal
+pub trait Backend {
+    fn size(&self) -> Option<(u16, u16)>; // The width and height, when they can be found
+    fn enter(&mut self); // Take over the terminal to draw on
+    fn exit(&mut self); // Leave the terminal as it was found
+    fn write(&mut self, text: &str); // Draw text and escape sequences
+    fn flush(&mut self); // Show what was drawn
+    fn poll(&mut self, wait: Duration) -> bool; // Whether input comes within a time
+    fn read(&mut self) -> Option<Input>; // Wait for a key, the mouse, a resize or a paste
+    fn colours(&self) -> usize; // How many colours can be shown, 24 meaning any RGB colour
+    fn shapes(&self) -> bool; // Whether the cursor can change shape
+}
+
+// The terminal Ox was started in, driven through crossterm
+pub struct Crossterm {
+    input: Reader, // Where keys and pasted text are read from
+}
+
+impl Crossterm {
+    pub fn new() -> Result<Self, ErrorKind> {
+        // Make sure there is a terminal to draw on
+        terminal::size()?;
+        Ok(Self {
+            input: Reader::new()?,
+        })
+    }
+}
+
+impl Backend for Crossterm {
+    fn size(&self) -> Option<(u16, u16)> {
+        terminal::size().ok()
+    }
+    fn enter(&mut self) {
+        // Switch into raw mode on the alternate screen
+        ENTERED.store(true, Ordering::SeqCst);
+        terminal::enable_raw_mode().unwrap();
+        execute!(stdout(), terminal::EnterAlternateScreen, EnableMouseCapture).unwrap();
+        print!("{PASTE_ON}");
+        self.flush();
+    }
+    fn exit(&mut self) {
+        leave();
+    }
+    fn write(&mut self, text: &str) {
+        print!("{text}");
+    }
+    fn flush(&mut self) {
+        stdout().flush().unwrap();
+    }
+    fn poll(&mut self, wait: Duration) -> bool {
+        self.input.poll(wait)
+    }
+    fn read(&mut self) -> Option<Input> {
+        self.input.read()
+    }
+    fn colours(&self) -> usize {
+        // Work out how many colours the terminal shows
+        let colour = env::var("COLORTERM").unwrap_or_default();
+        if colour == "truecolor" || colour == "24bit" {
+            24
+        } else if let Ok(info) = TermInfo::from_env() {
+            match info.numbers.get("colors") {
+                Some(colours) if *colours >= 256 => 256,
+                _ => 16,
+            }
+        } else {
+            16
+        }
+    }
+    fn shapes(&self) -> bool {
+        // Work out whether the terminal takes DECSCUSR, which terminfo calls Ss
+        if TermInfo::from_env().is_ok_and(|info| info.strings.contains_key("Ss")) {
+            return true;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        SHAPED_TERMS.iter().any(|known| term.starts_with(known))
+            || env::var("TERM_PROGRAM").is_ok()
+            || env::var("VTE_VERSION").is_ok()
+    }
+}
+
+// A terminal that is never shown, for running scripts and tests, where whatever waits
+// for a key is cancelled
+pub struct Headless {
+    size: (u16, u16), // The size it pretends to be
+}
+
+impl Headless {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            size: (width, height),
+        }
+    }
+}
+
+impl Backend for Headless {
+    fn size(&self) -> Option<(u16, u16)> {
+        Some(self.size)
+    }
+    fn enter(&mut self) {}
+    fn exit(&mut self) {}
+    fn write(&mut self, _: &str) {}
+    fn flush(&mut self) {}
+    fn poll(&mut self, _: Duration) -> bool {
+        true
+    }
+    fn read(&mut self) -> Option<Input> {
+        Some(Input::Event(InputEvent::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+        })))
+    }
+    fn colours(&self) -> usize {
+        24
+    }
+    fn shapes(&self) -> bool {
+        false
+    }
+}
+
+pub fn leave() {
+    // Leave the terminal Ox took over as it was found, even when on the way out of a panic
+    if !ENTERED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    print!("{PASTE_OFF}");
+    if SHAPED.swap(false, Ordering::SeqCst) {
+        print!("{SHAPE_RESET}");
+    }
+    let _ = execute!(
+        stdout(),
+        DisableMouseCapture,
+        terminal::LeaveAlternateScreen,
+        crossterm::cursor::Show
+    );
+    let _ = terminal::disable_raw_mode();
+    let _ = stdout().flush();
 }
 
 // The terminal struct
 pub struct Terminal {
-    pub size: Size, // For holding the size of the terminal
-    regex: Exp,     // For holding the regex
+    pub size: Size,               // For holding the size of the terminal
+    pub cursor: Option<Position>, // Where the text cursor is shown, if it is
+    at: Position,                 // Where the cursor was last moved to
+    backend: Box<dyn Backend>,    // What is drawn on and read from
+    regex: Exp,                   // For holding the regex
+    screen: Vec<String>,          // The rows as they were last drawn
+    frame: String,                // Buffer the changes are written into
+    shapes: bool,                 // Whether the terminal can change the shape of the cursor
+    shape: Option<Shape>,         // The shape the cursor was last given
 }
 
 // Implement methods into the terminal struct / class
 impl Terminal {
-    pub fn new() -> Result<Self, ErrorKind> {
-        // Create a new terminal and switch into raw mode
-        let size = terminal::size()?;
-        Terminal::enter();
-        Ok(Self {
+    pub fn new(mut backend: Box<dyn Backend>) -> Self {
+        // Take over a terminal to draw on
+        let size = backend.size().unwrap_or((80, 24));
+        backend.enter();
+        Self {
             size: Size {
                 width: size.0 as usize,
                 height: size.1 as usize,
             },
+            cursor: None,
+            at: Position { x: 0, y: 0 },
+            shapes: backend.shapes(),
+            backend,
             regex: Exp::new(),
-        })
+            screen: vec![],
+            frame: String::new(),
+            shape: None,
+        }
     }
-    pub fn enter() {
-        // Enter the current terminal
-        terminal::enable_raw_mode().unwrap();
-        execute!(stdout(), terminal::EnterAlternateScreen).unwrap();
+    pub fn headless() -> Self {
+        // A terminal that is never switched into, for running scripts without drawing
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        Terminal::new(Box::new(Headless::new(width, height)))
+    }
+    pub fn lend(&mut self) {
+        // Hand the terminal over to something else, such as a program asking for a password
+        self.backend.exit();
+    }
+    pub fn take_back(&mut self) -> (u16, u16) {
+        // Take the terminal back after lending it, giving the size it has now
+        // Whatever had the terminal may have shaped the cursor its own way
+        self.backend.enter();
+        self.shape = None;
+        self.backend
+            .size()
+            .unwrap_or((self.size.width as u16, self.size.height as u16))
+    }
+    #[cfg(unix)]
+    #[allow(clippy::unnecessary_wraps)] // Other systems can't stop, and give back nothing
+    pub fn suspend(&mut self) -> Option<(u16, u16)> {
+        // Hand the terminal back to the shell and stop until the editor is brought back,
+        // then give the size the terminal has now
+        self.lend();
+        // When stopping isn't allowed the signal is ignored and the editor carries on
+        unsafe {
+            libc::raise(libc::SIGTSTP);
+        }
+        Some(self.take_back())
+    }
+    #[cfg(not(unix))]
+    pub fn suspend(&mut self) -> Option<(u16, u16)> {
+        // Only Unix shells can stop a program and carry it on later
+        None
     }
-    pub fn exit() {
-        // Exit the terminal
-        execute!(stdout(), terminal::LeaveAlternateScreen).unwrap();
-        terminal::disable_raw_mode().unwrap();
+    pub fn poll(&mut self, wait: Duration) -> bool {
+        // Wait for input for a while, giving whether any came
+        self.backend.poll(wait)
     }
-    pub fn goto(p: &Position) {
+    pub fn read(&mut self) -> Option<Input> {
+        // Wait for a key, the mouse, the terminal changing size or pasted text
+        self.backend.read()
+    }
+    pub fn colours(&self) -> usize {
+        self.backend.colours()
+    }
+    pub fn goto(&mut self, p: &Position) {
         // Move the cursor to a position
-        execute!(stdout(), crossterm::cursor::MoveTo(p.x as u16, p.y as u16)).unwrap();
+        self.backend
+            .write(&MoveTo(p.x as u16, p.y as u16).to_string());
+        self.at = *p;
     }
-    pub fn flush() {
+    pub fn flush(&mut self) {
         // Flush the screen to prevent weird behaviour
-        stdout().flush().unwrap();
+        self.backend.flush();
     }
-    pub fn hide_cursor() {
+    pub fn write(&mut self, text: &str) {
+        self.backend.write(text);
+    }
+    pub fn hide_cursor(&mut self) {
         // Hide the text cursor
-        execute!(stdout(), crossterm::cursor::Hide).unwrap();
+        self.backend.write(&Hide.to_string());
+        self.cursor = None;
+    }
+    pub fn show_cursor(&mut self) {
+        // Show the text cursor where it was last moved to
+        self.backend.write(&Show.to_string());
+        self.cursor = Some(self.at);
+    }
+    pub fn set_shape(&mut self, shape: Shape) {
+        // Change the shape of the cursor, when the terminal can and it isn't that shape already
+        if self.shapes && self.shape != Some(shape) {
+            self.backend.write(&format!("\x1b[{} q", shape.code()));
+            SHAPED.store(true, Ordering::SeqCst);
+            self.shape = Some(shape);
+        }
+    }
+    pub fn clear(&mut self) {
+        self.backend
+            .write(&terminal::Clear(terminal::ClearType::All).to_string());
+        self.flush();
+    }
+    pub fn draw(&mut self, rows: Vec<String>) {
+        // Write out only the rows that changed since the last draw
+        self.frame.clear();
+        for (y, row) in rows.iter().enumerate() {
+            if self.screen.get(y) != Some(row) {
+                let _ = write!(self.frame, "{}{}", MoveTo(0, y as u16), row);
+            }
+        }
+        self.backend.write(&self.frame);
+        self.screen = rows;
     }
-    pub fn show_cursor() {
-        execute!(stdout(), crossterm::cursor::Show).unwrap();
+    pub fn forget(&mut self) {
+        // Make the next draw write every row, for when something else drew over them
+        self.screen.clear();
     }
-    pub fn clear() {
-        execute!(stdout(), terminal::Clear(terminal::ClearType::All)).unwrap();
+    pub fn forget_rows(&mut self, rows: std::ops::Range<usize>) {
+        // Make the next draw write these rows again
+        for row in rows {
+            if let Some(row) = self.screen.get_mut(row) {
+                row.clear();
+            }
+        }
     }
     pub fn align_break(&self, l: &str, r: &str) -> String {
         // Align two items to the left and right
         let left_length = UnicodeWidthStr::width(l);
         let right_length = UnicodeWidthStr::width(r);
-        let padding = (self.size.width as usize).saturating_sub(left_length + right_length);
-        " ".repeat(padding as usize)
+        let padding = self.size.width.saturating_sub(left_length + right_length);
+        " ".repeat(padding)
     }
     pub fn align_left(&self, text: &str) -> String {
         // Align items to the left
+        self.pad(text, self.size.width)
+    }
+    pub fn pad(&self, text: &str, width: usize) -> String {
+        // Produce the padding that fills the rest of a line of a certain width
         let length = self.regex.ansi_len(text);
-        let padding = (self.size.width as usize).saturating_sub(length);
-        " ".repeat(padding as usize)
+        let padding = width.saturating_sub(length);
+        " ".repeat(padding)
     }
-    pub fn availablility() -> usize {
-        let colour = env::var("COLORTERM");
-        if colour.unwrap_or_else(|_| "".to_string()) == "truecolor" {
-            24
-        } else if let Ok(info) = TermInfo::from_env() {
-            if info.numbers.get("colors").unwrap() == &256 {
-                256
-            } else {
-                16
-            }
-        } else {
-            16
-        }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // Give the terminal back however the editor stops
+        self.backend.exit();
     }
 }
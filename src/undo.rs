@@ -1,6 +1,11 @@
 // Undo.rs - Utilities for undoing, redoing and storing events
+use crate::encoding::Encoding;
 use crate::util::line_offset;
-use crate::{Direction, Position, Row, Variable};
+use crate::{Direction, Position, Row, Transform, Variable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // Enum for the the types of banks
 #[derive(Debug, Clone)]
@@ -12,53 +17,213 @@ pub enum BankType {
 // Event enum to store the types of events that occur
 #[derive(Debug, Clone)]
 pub enum Event {
-    Store(BankType, usize),                         // Store an item in a bank
-    Load(BankType, usize),                          // Load an item from a bank
-    SpliceUp(Position, Position),                   // Delete from start
-    SplitDown(Position, Position),                  // Return from middle of the line
-    InsertLineAbove(Position),                      // Return key in the middle of line
-    InsertLineBelow(Position),                      // Return on the end of line
-    Deletion(Position, char),                       // Delete from middle
-    Insertion(Position, char),                      // Insert character
-    InsertTab(Position),                            // Insert a tab character
-    DeleteTab(Position),                            // Delete a tab character
-    DeleteLine(Position, i128, Box<Row>),           // For deleting a line
-    UpdateLine(Position, i128, Box<Row>, Box<Row>), // For holding entire line updates
-    MoveCursor(i128, Direction),                    // For moving the cursor
-    GotoCursor(Position),                           // For setting the cursor position
-    MoveWord(Direction),                            // Move cursor through words
-    DeleteWord(Position, String),                   // Delete word
-    Theme(String),                                  // Theme change event
-    Search,                                         // Search the document
-    Replace,                                        // Replace certain occurances
-    ReplaceAll,                                     // Replace everything
-    Cmd,                                            // Trigger command mode
-    Home,                                           // Moving cursor to the start of line
-    End,                                            // Moving cursor to the end of line
-    PageUp,                                         // Moving cursor one page up
-    PageDown,                                       // Moving cursor one page down
-    Overwrite(Vec<Row>, Vec<Row>),                  // Overwrite document
-    New,                                            // New document
-    Open(Option<String>),                           // Open document
-    Save(Option<String>, bool),                     // Save document
-    SaveAll,                                        // Save all documents
-    Undo,                                           // Undo event
-    Redo,                                           // Redo event
-    Commit,                                         // Commit undo event
-    Quit(bool),                                     // Quit document
-    QuitAll(bool),                                  // Quit all
-    NextTab,                                        // Next tab
-    PrevTab,                                        // Previous tab
-    ReloadConfig,                                   // Reload the configuration file
-    Shell(String, bool, bool, bool),                // Running a shell command
-    Set(Variable, bool),                            // For updating variables of the document
+    Store(BankType, usize),                           // Store an item in a bank
+    Load(BankType, usize),                            // Load an item from a bank
+    SpliceUp(Position, Position),                     // Delete from start
+    SplitDown(Position, Position),                    // Return from middle of the line
+    InsertLineAbove(Position),                        // Return key in the middle of line
+    InsertLineBelow(Position),                        // Return on the end of line
+    Deletion(Position, String),                       // Delete from middle
+    Insertion(Position, String),                      // Insert characters
+    InsertTab(Position),                              // Insert a tab character
+    DeleteTab(Position),                              // Delete a tab character
+    DeleteLine(Position, i128, Box<Row>),             // For deleting a line
+    UpdateLine(Position, i128, Box<Row>, Box<Row>),   // For holding entire line updates
+    SwapLine(Position, Direction),                    // Swap a line with the one above or below
+    MoveCursor(i128, Direction),                      // For moving the cursor
+    GotoCursor(Position),                             // For setting the cursor position
+    MoveWord(Direction),                              // Move cursor through words
+    MoveParagraph(Direction),                         // Move to the blank line above or below
+    MoveBlock(bool),                // Move to the start or end of the indented block
+    DeleteWord(Position),           // Delete word
+    Theme(String),                  // Theme change event
+    Option(String, Option<String>), // Change a general option
+    Search,                         // Search the document
+    Replace,                        // Replace certain occurances
+    Substitute(String, String, (usize, usize), bool), // Replace the first or every match on lines
+    DeleteLines(usize, usize, Option<char>), // Cut lines into a register or onto the clipboard
+    YankLines(usize, usize, Option<char>), // Copy lines into a register or onto the clipboard
+    ReplaceAll,                     // Replace everything
+    Cmd,                            // Trigger command mode
+    Home,                           // Moving cursor to the start of line
+    End,                            // Moving cursor to the end of line
+    PageUp,                         // Moving cursor one page up
+    PageDown,                       // Moving cursor one page down
+    ToggleWrap,                     // Toggle soft wrapping of long lines
+    ToggleRelative,                 // Toggle relative line numbers
+    ToggleBlame,                    // Toggle the blame of the cursor's line
+    ToggleReadOnly,                 // Refuse or allow edits to the document
+    LineEndings(bool),              // Save with CRLF line endings or with LF
+    Encoding(Encoding),             // Save in a different character encoding
+    Scroll(Direction),              // Scroll the view without moving the cursor
+    Center,                         // Scroll the cursor's line to the middle
+    AddCursor(Direction),           // Add a cursor on the line above or below
+    AddNextCursor,                  // Add a cursor where the word appears next
+    SelectBlock,                    // Start or stop selecting a block of columns
+    Mark(bool, Option<char>),       // Set or go to a mark, asking for its letter
+    Retrace(bool),                  // Go back through the jumps made or forward
+    Fold(bool),                     // Fold the block at the cursor, or all blocks
+    Unfold(bool),                   // Open the fold at the cursor, or all folds
+    Comment,                        // Comment or uncomment the selected lines
+    Suspend,                        // Stop the editor and go back to the shell
+    Statistics,                     // Show word, character and line counts
+    Copy(Option<char>),             // Copy to the clipboard or a register
+    Paste(Option<char>),            // Paste from the clipboard or a register
+    Cut(Option<char>),              // Cut the selection to the clipboard or a register
+    Registers,                      // Pick from what the registers hold to paste
+    Select,                         // Start or stop selecting text
+    MatchBracket,                   // Jump to the bracket paired with this one
+    Record,                         // Start or stop recording keys
+    Replay,                         // Replay the recorded keys
+    Repeat,                         // Type the last run of edits again
+    Overwrite(Vec<Row>, Vec<Row>),  // Overwrite document
+    New,                            // New document
+    Open(Option<String>),           // Open document
+    Recent,                         // Pick a file opened lately to open again
+    Jump(Option<String>),           // Go to a line and column, asking when not given
+    Save(Option<String>, bool),     // Save document
+    SaveAll,                        // Save all documents
+    Undo,                           // Undo event
+    Redo,                           // Redo event
+    RedoBranch(usize),              // Redo along one of the branches of the history
+    History,                        // Browse the history of the document as a tree
+    Commit,                         // Commit undo event
+    Quit(bool),                     // Quit document
+    QuitAll(bool),                  // Quit all
+    NextTab,                        // Next tab
+    PrevTab,                        // Previous tab
+    SplitView(bool),                // Split the screen into two panes
+    Unsplit,                        // Go back to a single pane
+    Explorer,                       // Show, focus or hide the file tree
+    Console,                        // Open a shell below the documents or move focus to and from it
+    Diff,                       // Compare the documents of the two panes, or stop comparing them
+    Export(Option<String>),     // Write the highlighted document to a web page
+    Preview,                    // Show or hide a formatted preview of a Markdown document
+    Build(Option<String>),      // Run the build command of the language, or the one given
+    Problem(bool),              // Go to the next or previous problem the build found
+    Problems,                   // Show or hide the list of problems the build found
+    Messages,                   // Look back over the messages shown on the command line
+    Help,                       // Open a help document listing the keys and commands
+    Find,                       // Pick a file to open by fuzzy matching
+    Grep(Option<String>),       // Search every file under the working directory
+    GrepReplace,                // Replace across the files, going through the changes first
+    Hex(Option<String>),        // Show the bytes of a file
+    Complete,                   // Pick from what the language server suggests
+    Words,                      // Pick from the words in the open documents
+    Spell,                      // Pick a spelling for the word at the cursor
+    Definition(Option<String>), // Go to where a symbol is defined, by the tags file
+    DefinitionBack,             // Go back to where a definition was gone to from
+    Hunk(bool),                 // Go to the next or previous run of changed lines
+    Plugin(String, String),     // Hand a command to a plugin
+    SwitchPane,                 // Move focus to the other pane
+    ReloadConfig,               // Reload the configuration file
+    Shell(String, bool, bool, bool), // Running a shell command
+    Filter(String, bool),       // Insert what a command writes, or pass the text through it
+    Transform(Transform, Option<(usize, usize)>), // Change the selection or the lines given
+    Increment(i128),            // Add to the number under or after the cursor
+    Format,                     // Run the formatter of the file type over the document
+    Set(Variable, bool),        // For updating variables of the document
 }
 
-// A struct for holding all the events taken by the user
+// One state of the document in the history, reached by applying a patch to its parent
+#[derive(Debug)]
+struct Node {
+    parent: usize,        // The state the patch was made in
+    patch: Vec<Event>,    // The events of the patch, newest first
+    children: Vec<usize>, // The states made from this one, oldest first
+    next: Option<usize>,  // The child that redoing goes to
+    made: Instant,        // When the patch was committed
+}
+
+// An edit as it is kept between sessions, with lines kept as their text
+#[derive(Debug, Serialize, Deserialize)]
+enum Kept {
+    SpliceUp(Position, Position),
+    SplitDown(Position, Position),
+    InsertLineAbove(Position),
+    InsertLineBelow(Position),
+    Deletion(Position, String),
+    Insertion(Position, String),
+    InsertTab(Position),
+    DeleteTab(Position),
+    DeleteLine(Position, i64, String),
+    UpdateLine(Position, i64, String, String),
+    SwapLine(Position, Direction),
+    Overwrite(Vec<String>, Vec<String>),
+}
+
+impl Kept {
+    fn from(event: &Event) -> Option<Self> {
+        // Write down an edit, leaving out events that don't change the text
+        Some(match event {
+            Event::SpliceUp(before, after) => Kept::SpliceUp(*before, *after),
+            Event::SplitDown(before, after) => Kept::SplitDown(*before, *after),
+            Event::InsertLineAbove(pos) => Kept::InsertLineAbove(*pos),
+            Event::InsertLineBelow(pos) => Kept::InsertLineBelow(*pos),
+            Event::Deletion(pos, text) => Kept::Deletion(*pos, text.clone()),
+            Event::Insertion(pos, text) => Kept::Insertion(*pos, text.clone()),
+            Event::InsertTab(pos) => Kept::InsertTab(*pos),
+            Event::DeleteTab(pos) => Kept::DeleteTab(*pos),
+            Event::DeleteLine(pos, offset, row) => {
+                Kept::DeleteLine(*pos, i64::try_from(*offset).ok()?, row.string.clone())
+            }
+            Event::UpdateLine(pos, offset, before, after) => Kept::UpdateLine(
+                *pos,
+                i64::try_from(*offset).ok()?,
+                before.string.clone(),
+                after.string.clone(),
+            ),
+            Event::SwapLine(pos, direction) => Kept::SwapLine(*pos, *direction),
+            Event::Overwrite(before, after) => Kept::Overwrite(
+                before.iter().map(|row| row.string.clone()).collect(),
+                after.iter().map(|row| row.string.clone()).collect(),
+            ),
+            _ => return None,
+        })
+    }
+    fn event(self) -> Event {
+        // Turn a kept edit back into the event it was written down from
+        let rows = |lines: Vec<String>| lines.iter().map(|line| Row::from(line.as_str())).collect();
+        match self {
+            Kept::SpliceUp(before, after) => Event::SpliceUp(before, after),
+            Kept::SplitDown(before, after) => Event::SplitDown(before, after),
+            Kept::InsertLineAbove(pos) => Event::InsertLineAbove(pos),
+            Kept::InsertLineBelow(pos) => Event::InsertLineBelow(pos),
+            Kept::Deletion(pos, text) => Event::Deletion(pos, text),
+            Kept::Insertion(pos, text) => Event::Insertion(pos, text),
+            Kept::InsertTab(pos) => Event::InsertTab(pos),
+            Kept::DeleteTab(pos) => Event::DeleteTab(pos),
+            Kept::DeleteLine(pos, offset, row) => {
+                Event::DeleteLine(pos, i128::from(offset), Box::new(Row::from(row.as_str())))
+            }
+            Kept::UpdateLine(pos, offset, before, after) => Event::UpdateLine(
+                pos,
+                i128::from(offset),
+                Box::new(Row::from(before.as_str())),
+                Box::new(Row::from(after.as_str())),
+            ),
+            Kept::SwapLine(pos, direction) => Event::SwapLine(pos, direction),
+            Kept::Overwrite(before, after) => Event::Overwrite(rows(before), rows(after)),
+        }
+    }
+}
+
+// The history of a file as it is kept between sessions, ending at the state it was saved in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct History {
+    fingerprint: u64, // A hash of the text of the file when it was saved
+    current: usize,   // The state the file was saved in
+    nodes: Vec<(usize, Vec<Kept>, Option<usize>, u64)>, // The parent, patch, redo and time of each state
+}
+
+// A struct for holding all the events taken by the user, as a tree so that nothing is lost
+// when something is undone and then changed differently
 #[derive(Debug)]
 pub struct EventStack {
-    history: Vec<Vec<Event>>,  // For storing the history of events
+    nodes: Vec<Node>, // Every state there has been, the first being the opened file
+    current: usize,   // The state the document is in
     current_patch: Vec<Event>, // For storing the current group
+    held: bool,       // Whether the current group is kept open for now
 }
 
 // Methods for the EventStack
@@ -66,8 +231,16 @@ impl EventStack {
     pub fn new() -> Self {
         // Initialise an Event stack
         Self {
-            history: vec![],
+            nodes: vec![Node {
+                parent: 0,
+                patch: vec![],
+                children: vec![],
+                next: None,
+                made: Instant::now(),
+            }],
+            current: 0,
             current_patch: vec![],
+            held: false,
         }
     }
     pub fn push(&mut self, event: Event) {
@@ -75,27 +248,254 @@ impl EventStack {
         self.current_patch.insert(0, event);
     }
     pub fn pop(&mut self) -> Option<Vec<Event>> {
-        // Take a patch off the event stack
-        self.history.pop()
+        // Step back to the state before the current one, giving the patch to reverse
+        if self.current == 0 {
+            return None;
+        }
+        let node = self.current;
+        self.current = self.nodes[node].parent;
+        self.nodes[self.current].next = Some(node);
+        Some(self.nodes[node].patch.clone())
+    }
+    pub fn forward(&mut self) -> Option<Vec<Event>> {
+        // Step on to the state redoing goes to, giving the patch to apply again
+        let next = self.nodes[self.current].next?;
+        self.current = next;
+        Some(self.nodes[next].patch.clone())
+    }
+    pub fn branches(&self) -> usize {
+        // How many different states redoing could go to
+        self.nodes[self.current].children.len()
+    }
+    pub fn choose(&mut self, branch: usize) -> bool {
+        // Pick which of the states after this one redoing goes to
+        if let Some(child) = self.nodes[self.current].children.get(branch) {
+            self.nodes[self.current].next = Some(*child);
+            true
+        } else {
+            false
+        }
+    }
+    pub fn path(&self, mut state: usize) -> Vec<usize> {
+        // The states on the way from the opened file to a state, ending with it
+        let mut path = vec![state];
+        while state != 0 {
+            state = self.nodes[state].parent;
+            path.push(state);
+        }
+        path.reverse();
+        path
+    }
+    pub fn aim(&mut self, state: usize) {
+        // Make redoing from the parent of a state go to it
+        let parent = self.nodes[state].parent;
+        if state != 0 {
+            self.nodes[parent].next = Some(state);
+        }
+    }
+    pub fn state(&self) -> usize {
+        // Which state the document is in, for telling whether it matches the saved one
+        self.current
+    }
+    pub fn tree(&self) -> Vec<(usize, String)> {
+        // Lay the states out as lines, each branch indented under where it split off
+        let mut lines = vec![];
+        let mut stack = vec![(0, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            let age = self.nodes[node].made.elapsed().as_secs();
+            let age = match age {
+                0..=59 => format!("{age}s"),
+                60..=3599 => format!("{}m", age / 60),
+                _ => format!("{}h", age / 3600),
+            };
+            let text = if node == 0 {
+                "opened".to_string()
+            } else {
+                let events = self.nodes[node].patch.len();
+                format!("#{node} {age} ago, {events} changes")
+            };
+            let mark = if node == self.current {
+                '\u{25cf}'
+            } else {
+                '\u{25cb}'
+            };
+            lines.push((node, format!("{}{} {}", "  ".repeat(depth), mark, text)));
+            // The newest branch carries on down the same line, the older ones are indented
+            let children = &self.nodes[node].children;
+            if let Some((last, older)) = children.split_last() {
+                stack.push((*last, depth));
+                stack.extend(older.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+        lines
+    }
+    pub fn keep(&self, state: usize, fingerprint: u64, limit: usize) -> Option<History> {
+        // Write down the history up to a limit of states, keeping those on the way to the
+        // given state first and then the newest of the rest
+        if self.nodes.len() < 2 || limit == 0 {
+            return None;
+        }
+        let route = self.path(state);
+        let start = route.len().saturating_sub(limit + 1);
+        let mut kept: HashSet<usize> = route[start..].iter().copied().collect();
+        for node in (1..self.nodes.len()).rev() {
+            if kept.len() > limit {
+                break;
+            }
+            // Take in a state along with whatever is missing between it and the states kept
+            let mut chain = vec![];
+            let mut at = node;
+            while !kept.contains(&at) && at != 0 {
+                chain.push(at);
+                at = self.nodes[at].parent;
+            }
+            if kept.contains(&at) && kept.len() + chain.len() <= limit + 1 {
+                kept.extend(chain);
+            }
+        }
+        // Number the kept states again, starting from the one everything kept grew from
+        let mut order: Vec<usize> = kept.into_iter().collect();
+        order.sort_unstable();
+        let index = |node: usize| order.binary_search(&node).ok();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut nodes: Vec<_> = order
+            .iter()
+            .map(|node| {
+                let node = &self.nodes[*node];
+                let made = now.saturating_sub(node.made.elapsed()).as_secs();
+                let patch: Vec<Kept> = node.patch.iter().filter_map(Kept::from).collect();
+                (
+                    index(node.parent).unwrap_or(0),
+                    patch,
+                    node.next.and_then(index),
+                    made,
+                )
+            })
+            .collect();
+        nodes[0].0 = 0;
+        nodes[0].1.clear();
+        Some(History {
+            fingerprint,
+            current: index(state)?,
+            nodes,
+        })
     }
-    pub fn append(&mut self, patch: Vec<Event>) {
-        // Append a patch to the stack
-        self.history.push(patch);
+    pub fn resume(history: History, fingerprint: u64) -> Option<Self> {
+        // Pick up a kept history, as long as it was kept for the text the file has now
+        if history.fingerprint != fingerprint || history.current >= history.nodes.len() {
+            return None;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut nodes: Vec<Node> = vec![];
+        for (index, (parent, patch, next, made)) in history.nodes.into_iter().enumerate() {
+            // Each state comes after the one it was made from
+            if index > 0 && parent >= index {
+                return None;
+            }
+            let age = now.saturating_sub(Duration::from_secs(made));
+            nodes.push(Node {
+                parent,
+                patch: patch.into_iter().map(Kept::event).collect(),
+                children: vec![],
+                next,
+                made: Instant::now().checked_sub(age).unwrap_or_else(Instant::now),
+            });
+            if index > 0 {
+                nodes[parent].children.push(index);
+            }
+        }
+        if nodes
+            .iter()
+            .any(|node| node.next.is_some_and(|next| next >= nodes.len()))
+        {
+            return None;
+        }
+        Some(Self {
+            nodes,
+            current: history.current,
+            current_patch: vec![],
+            held: false,
+        })
     }
-    pub fn empty(&mut self) {
-        // Empty the stack
-        self.history.clear();
+    pub fn hold(&mut self, held: bool) {
+        // Keep an edit made in several places together, however it commits along the way
+        self.held = held;
     }
     pub fn commit(&mut self) {
-        // Commit patch to history
-        if !self.current_patch.is_empty() {
-            self.history.push(self.current_patch.clone());
-            self.current_patch.clear();
+        // Commit patch to history, as a new state made from the current one
+        if !self.held && !self.current_patch.is_empty() {
+            let node = self.nodes.len();
+            self.nodes.push(Node {
+                parent: self.current,
+                patch: std::mem::take(&mut self.current_patch),
+                children: vec![],
+                next: None,
+                made: Instant::now(),
+            });
+            self.nodes[self.current].children.push(node);
+            self.nodes[self.current].next = Some(node);
+            self.current = node;
         }
     }
-    pub fn len(&self) -> usize {
-        // Find the length of the undo stack
-        self.history.len()
+}
+
+pub fn will_edit(event: &Event) -> bool {
+    // Whether an event changes the text, which read only documents refuse
+    matches!(
+        event,
+        Event::SpliceUp(_, _)
+            | Event::SplitDown(_, _)
+            | Event::InsertLineAbove(_)
+            | Event::InsertLineBelow(_)
+            | Event::Deletion(_, _)
+            | Event::Insertion(_, _)
+            | Event::InsertTab(_)
+            | Event::DeleteTab(_)
+            | Event::DeleteLine(_, _, _)
+            | Event::UpdateLine(_, _, _, _)
+            | Event::SwapLine(_, _)
+            | Event::ReplaceAll
+            | Event::Replace
+            | Event::Substitute(_, _, _, _)
+            | Event::Overwrite(_, _)
+    )
+}
+
+pub fn shifted(event: Event, lines: usize) -> Event {
+    // Move the lines an event is at down, for when lines have been read in above them
+    let down = |pos: Position| Position {
+        x: pos.x,
+        y: pos.y + lines,
+    };
+    match event {
+        Event::SpliceUp(before, after) => Event::SpliceUp(down(before), down(after)),
+        Event::SplitDown(before, after) => Event::SplitDown(down(before), down(after)),
+        Event::InsertLineAbove(pos) => Event::InsertLineAbove(down(pos)),
+        Event::InsertLineBelow(pos) => Event::InsertLineBelow(down(pos)),
+        Event::Deletion(pos, text) => Event::Deletion(down(pos), text),
+        Event::Insertion(pos, text) => Event::Insertion(down(pos), text),
+        Event::InsertTab(pos) => Event::InsertTab(down(pos)),
+        Event::DeleteTab(pos) => Event::DeleteTab(down(pos)),
+        Event::DeleteLine(pos, offset, row) => Event::DeleteLine(down(pos), offset, row),
+        Event::UpdateLine(pos, offset, before, after) => {
+            Event::UpdateLine(down(pos), offset, before, after)
+        }
+        Event::SwapLine(pos, direction) => Event::SwapLine(down(pos), direction),
+        Event::Substitute(pattern, with, (from, to), global) => {
+            Event::Substitute(pattern, with, (from + lines, to + lines), global)
+        }
+        Event::DeleteLines(from, to, register) => {
+            Event::DeleteLines(from + lines, to + lines, register)
+        }
+        Event::Transform(transform, range) => Event::Transform(
+            transform,
+            range.map(|(from, to)| (from + lines, to + lines)),
+        ),
+        event => event,
     }
 }
 
@@ -107,14 +507,8 @@ pub fn reverse(before: Event, limit: usize) -> Option<Vec<Event>> {
         Event::SplitDown(before, after) => vec![Event::SpliceUp(after, before)],
         Event::InsertLineAbove(pos) => vec![Event::DeleteLine(pos, 0, Box::new(Row::from("")))],
         Event::InsertLineBelow(pos) => vec![Event::DeleteLine(pos, 1, Box::new(Row::from("")))],
-        Event::Deletion(pos, ch) => vec![Event::Insertion(pos, ch)],
-        Event::Insertion(pos, ch) => vec![Event::Deletion(
-            Position {
-                x: pos.x.saturating_add(1),
-                y: pos.y,
-            },
-            ch,
-        )],
+        Event::Deletion(pos, text) => vec![Event::Insertion(pos, text)],
+        Event::Insertion(pos, text) => vec![Event::Deletion(pos, text)],
         Event::DeleteLine(pos, offset, before) => vec![
             Event::InsertLineAbove(Position {
                 x: pos.x,
@@ -126,6 +520,20 @@ pub fn reverse(before: Event, limit: usize) -> Option<Vec<Event>> {
             vec![Event::UpdateLine(pos, offset, after, before)]
         }
         Event::Overwrite(before, after) => vec![Event::Overwrite(after, before)],
+        Event::SwapLine(pos, Direction::Up) => vec![Event::SwapLine(
+            Position {
+                x: pos.x,
+                y: pos.y.saturating_sub(1),
+            },
+            Direction::Down,
+        )],
+        Event::SwapLine(pos, Direction::Down) => vec![Event::SwapLine(
+            Position {
+                x: pos.x,
+                y: pos.y.saturating_add(1),
+            },
+            Direction::Up,
+        )],
         Event::InsertTab(pos) => vec![Event::DeleteTab(pos)],
         Event::DeleteTab(pos) => vec![Event::InsertTab(pos)],
         _ => return None,
@@ -1,7 +1,9 @@
 // Util.rs - Utilities for the rest of the program
+use crate::{Position, Row, Transform};
 use regex::Regex;
+use std::process::Output;
 use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use unicode_width::UnicodeWidthStr;
 
 // For holding general purpose regular expressions
 #[derive(Debug, Clone)]
@@ -20,6 +22,47 @@ impl Exp {
         // Find the length of a string without ANSI values
         UnicodeWidthStr::width(&*self.ansi.replace_all(string, ""))
     }
+    pub fn paint_column(&self, text: &str, column: usize, colour: &str) -> String {
+        // Put a background behind one column of a drawn line, going back to the one before after it
+        let mut result = String::with_capacity(text.len() + 32);
+        let mut background = "\u{1b}[49m".to_string();
+        let mut at = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (plain, code) = match self.ansi.find(rest) {
+                Some(found) => (&rest[..found.start()], Some(found.as_str())),
+                None => (rest, None),
+            };
+            for grapheme in plain.graphemes(true) {
+                if at == column {
+                    result.push_str(colour);
+                    result.push_str(grapheme);
+                    result.push_str(&background);
+                } else {
+                    result.push_str(grapheme);
+                }
+                at += grapheme.width();
+            }
+            if let Some(code) = code {
+                // Later text goes back to the last background set, or the terminal's own
+                if code.starts_with("\u{1b}[48;") || code == "\u{1b}[49m" {
+                    background = code.to_string();
+                } else if code == "\u{1b}[0m" {
+                    background = "\u{1b}[49m".to_string();
+                }
+                result.push_str(code);
+            }
+            rest = &rest[plain.len() + code.map_or(0, str::len)..];
+        }
+        // Lines that end before the column are carried on to it
+        if at <= column {
+            result.push_str(&" ".repeat(column - at));
+            result.push_str(colour);
+            result.push(' ');
+            result.push_str("\u{1b}[49m");
+        }
+        result
+    }
 }
 
 pub fn title(c: &str) -> String {
@@ -29,18 +72,98 @@ pub fn title(c: &str) -> String {
     })
 }
 
+pub fn transform(text: &str, how: Transform) -> String {
+    // Change text in one of the ways there are commands for
+    let lines = || text.split('\n').map(ToString::to_string);
+    let lines: Vec<String> = match how {
+        Transform::Upper => return text.to_uppercase(),
+        Transform::Lower => return text.to_lowercase(),
+        Transform::Title => {
+            // A letter after something that isn't a letter or number starts a word
+            let mut after = ' ';
+            return text
+                .chars()
+                .map(|c| {
+                    let start = !after.is_alphanumeric() && after != '\'';
+                    after = c;
+                    if start {
+                        c.to_uppercase().collect::<String>()
+                    } else {
+                        c.to_lowercase().collect()
+                    }
+                })
+                .collect();
+        }
+        Transform::Sort(unique, reverse) => {
+            let mut sorted: Vec<String> = lines().collect();
+            sorted.sort();
+            if unique {
+                sorted.dedup();
+            }
+            if reverse {
+                sorted.reverse();
+            }
+            sorted
+        }
+        Transform::Reverse => lines().rev().collect(),
+        Transform::Unique => {
+            let mut seen = std::collections::HashSet::new();
+            lines().filter(|line| seen.insert(line.clone())).collect()
+        }
+    };
+    lines.join("\n")
+}
+
+pub fn bump(number: &str, amount: i128) -> Option<String> {
+    // Add to a number written in decimal or 0x hex, keeping its width, case and zero padding
+    if let Some(digits) = number
+        .get(2..)
+        .filter(|_| number[1..].starts_with(['x', 'X']))
+    {
+        // Hex numbers count up from zero, as they stand for bytes more than amounts
+        let value = i128::from_str_radix(digits, 16).ok()?;
+        let value = value.checked_add(amount)?.max(0);
+        let width = digits.len();
+        let hex = if digits.chars().any(|c| c.is_ascii_uppercase()) {
+            format!("{value:0width$X}")
+        } else {
+            format!("{value:0width$x}")
+        };
+        return Some(format!("{}{}", &number[..2], hex));
+    }
+    let value = number.parse::<i128>().ok()?.checked_add(amount)?;
+    let digits = number.trim_start_matches('-');
+    let width = if digits.len() > 1 && digits.starts_with('0') {
+        digits.len()
+    } else {
+        1
+    };
+    let sign = if value < 0 { "-" } else { "" };
+    Some(format!(
+        "{}{:0width$}",
+        sign,
+        value.unsigned_abs(),
+        width = width
+    ))
+}
+
+pub fn line_column(text: &str) -> Option<(usize, Option<usize>)> {
+    // Read a line number with an optional column after it, such as 12:5
+    let mut parts = text.trim().splitn(2, ':');
+    let line = parts.next()?.parse().ok()?;
+    let column = match parts.next() {
+        Some(column) => Some(column.parse().ok()?),
+        None => None,
+    };
+    Some((line, column))
+}
+
 pub fn trim_end(text: &str, end: usize) -> String {
     // Trim a string with unicode in it to fit into a specific length
-    let mut widths = Vec::new();
-    for i in text.chars() {
-        widths.push(UnicodeWidthChar::width(i).map_or(0, |i| i));
-    }
-    let chars: Vec<&str> = text.graphemes(true).collect();
     let mut result = vec![];
     let mut length = 0;
-    for i in 0..chars.len() {
-        let chr = chars[i];
-        let wid = widths[i];
+    for chr in text.graphemes(true) {
+        let wid = UnicodeWidthStr::width(chr);
         if length == end {
             return result.join("");
         } else if length + wid <= end {
@@ -54,6 +177,92 @@ pub fn trim_end(text: &str, end: usize) -> String {
     result.join("")
 }
 
+#[derive(PartialEq)]
+enum Class {
+    Space,  // Whitespace between words
+    Word,   // Letters, digits and underscores in any script
+    Symbol, // Punctuation and everything else
+}
+
+fn class(grapheme: &str) -> Class {
+    // Sort a character into the kind of run it belongs to
+    let first = grapheme.chars().next().unwrap_or(' ');
+    if first.is_whitespace() {
+        Class::Space
+    } else if first.is_alphanumeric() || first == '_' {
+        Class::Word
+    } else {
+        Class::Symbol
+    }
+}
+
+pub fn word_start(chars: &[&str], index: usize) -> usize {
+    // Where the word before a character starts, skipping any space in between
+    let mut index = index.min(chars.len());
+    while index > 0 && class(chars[index - 1]) == Class::Space {
+        index -= 1;
+    }
+    if let Some(kind) = index.checked_sub(1).map(|before| class(chars[before])) {
+        while index > 0 && class(chars[index - 1]) == kind {
+            index -= 1;
+        }
+    }
+    index
+}
+
+pub fn word_end(chars: &[&str], index: usize) -> usize {
+    // Where the word after a character ends, skipping any space in between
+    let mut index = index.min(chars.len());
+    while index < chars.len() && class(chars[index]) == Class::Space {
+        index += 1;
+    }
+    if let Some(kind) = chars.get(index).map(|after| class(after)) {
+        while index < chars.len() && class(chars[index]) == kind {
+            index += 1;
+        }
+    }
+    index
+}
+
+pub fn word_around(row: &Row, x: usize) -> Option<(usize, usize)> {
+    // The columns a word starts and ends at, when a column is in it or just after it
+    let chars = row.chars();
+    let boundaries = row.boundaries();
+    let inside = |index: usize| chars.get(index).is_some_and(|c| class(c) == Class::Word);
+    let index = boundaries.iter().take_while(|&&column| column < x).count();
+    let index = if inside(index) {
+        index
+    } else if index > 0 && inside(index - 1) {
+        index - 1
+    } else {
+        return None;
+    };
+    let start = word_start(&chars, index + 1);
+    let end = word_end(&chars, index);
+    let column = |index: usize| {
+        boundaries
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| row.length())
+    };
+    Some((column(start), column(end)))
+}
+
+pub fn text_between(rows: &[Row], start: Position, end: Position) -> String {
+    // The text from one position to a later one, with newlines where lines end
+    if start.y == end.y {
+        return rows[start.y].slice(start.x, end.x).to_string();
+    }
+    let mut text = rows[start.y].slice(start.x, usize::MAX).to_string();
+    for row in &rows[start.y + 1..end.y] {
+        text.push('\n');
+        text.push_str(&row.string);
+    }
+    text.push('\n');
+    text.push_str(rows[end.y].slice(0, end.x));
+    text
+}
+
 pub fn line_offset(point: usize, offset: i128, limit: usize) -> usize {
     if offset.is_negative() {
         if point as i128 + offset >= 0 {
@@ -92,24 +301,23 @@ pub fn spaces_to_tabs(code: &str, tab_width: usize) -> String {
 }
 
 pub fn tabs_to_spaces(code: &str, tab_width: usize) -> String {
-    // Convert tabs to spaces
+    // Convert tabs to spaces, lining each one up with the next tab stop
+    let tab_width = tab_width.max(1);
     let mut result = vec![];
-    for mut line in code.split('\n') {
-        // Count the number of spaces
-        let mut tabs = 0;
-        for c in line.chars() {
-            if c == '\t' {
-                tabs += 1;
+    for line in code.split('\n') {
+        let mut expanded = String::new();
+        let mut width = 0;
+        for grapheme in line.graphemes(true) {
+            if grapheme == "\t" {
+                let spaces = tab_width - width % tab_width;
+                expanded.push_str(&" ".repeat(spaces));
+                width += spaces;
             } else {
-                break;
+                expanded.push_str(grapheme);
+                width += UnicodeWidthStr::width(grapheme);
             }
         }
-        // Divide by tab width
-        let spaces = tabs * tab_width;
-        // Remove spaces
-        line = &line[tabs..];
-        // Add tabs
-        result.push(format!("{}{}", " ".repeat(spaces), line));
+        result.push(expanded);
     }
     result.join("\n")
 }
@@ -119,14 +327,38 @@ pub fn is_ansi(s: &str, chk: &Regex) -> bool {
 }
 
 pub fn safe_ansi_insert(index: usize, list: &[&str], chk: &Regex) -> Option<usize> {
+    // Find where to insert into a rendered line to land on a screen column
     let mut c = 0;
     for (ac, i) in list.iter().enumerate() {
         if !is_ansi(i, chk) {
-            c += 1;
-        }
-        if c == index {
-            return Some(ac.saturating_add(1));
+            if c >= index {
+                return Some(ac);
+            }
+            c += UnicodeWidthStr::width(*i);
         }
     }
-    None
+    if c >= index {
+        Some(list.len())
+    } else {
+        None
+    }
+}
+
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    // FNV-1a, spelled out so what is kept on disk hashes the same across Rust releases
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn tool_error(program: &str, output: &Output) -> String {
+    // The last thing a failed program said, or how it exited if it said nothing
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map_or_else(
+            || format!("{} exited with {}", program, output.status),
+            str::to_string,
+        )
 }